@@ -0,0 +1,177 @@
+//! Project Time Budgets
+//!
+//! A budget limits the time that should be spent on a project. Either in total or per week/month.
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, TimeZone, Utc};
+#[cfg(feature = "sea-orm")]
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use utoipa::ToSchema;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    Display,
+    EnumIter,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[cfg_attr(feature = "sea-orm", derive(DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "String", db_type = "Text"))]
+pub enum BudgetPeriod {
+    /// The budget is for the lifetime of the project
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Total"))]
+    Total,
+    /// Resets every Monday at 00:00 UTC
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Weekly"))]
+    Weekly,
+    /// Resets on the first of every month at 00:00 UTC
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Monthly"))]
+    Monthly,
+}
+impl BudgetPeriod {
+    /// The start of the period containing `now`. None for [BudgetPeriod::Total]
+    pub fn start_of_period(&self, now: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let today = now.with_timezone(&Utc).date_naive();
+        let start = match self {
+            BudgetPeriod::Total => return None,
+            BudgetPeriod::Weekly => {
+                today - Duration::days(today.weekday().num_days_from_monday() as i64)
+            }
+            BudgetPeriod::Monthly => today.with_day(1)?,
+        };
+        Some(
+            Utc.from_utc_datetime(&start.and_time(NaiveTime::MIN))
+                .into(),
+        )
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct ProjectBudget {
+    pub id: i64,
+    pub project_id: i64,
+    pub period: BudgetPeriod,
+    /// The budgeted time in seconds
+    pub seconds: i64,
+    /// Percentages of the budget that trigger a notification. Such as 80 and 100
+    pub thresholds: Vec<i32>,
+    pub created: DateTime<FixedOffset>,
+}
+impl ProjectBudget {
+    pub fn percent_used(&self, used_seconds: i64) -> f64 {
+        if self.seconds <= 0 {
+            return 0.0;
+        }
+        used_seconds as f64 / self.seconds as f64 * 100.0
+    }
+    /// The thresholds that have been reached with the used time
+    pub fn reached_thresholds(&self, used_seconds: i64) -> impl Iterator<Item = i32> + '_ {
+        let percent = self.percent_used(used_seconds);
+        self.thresholds
+            .iter()
+            .copied()
+            .filter(move |threshold| percent >= *threshold as f64)
+    }
+}
+/// Checks that the thresholds are percentages between 1 and 1000
+pub fn are_thresholds_valid(thresholds: &[i32]) -> bool {
+    thresholds
+        .iter()
+        .all(|threshold| (1..=1000).contains(threshold))
+}
+/// How much of a budget has been used
+#[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
+pub struct BudgetUsage {
+    pub budget: ProjectBudget,
+    /// The start of the current period. None for [BudgetPeriod::Total]
+    pub period_start: Option<DateTime<FixedOffset>>,
+    pub used_seconds: i64,
+    pub remaining_seconds: i64,
+    pub percent_used: f64,
+}
+impl BudgetUsage {
+    pub fn new(
+        budget: ProjectBudget,
+        period_start: Option<DateTime<FixedOffset>>,
+        used_seconds: i64,
+    ) -> Self {
+        Self {
+            period_start,
+            used_seconds,
+            remaining_seconds: (budget.seconds - used_seconds).max(0),
+            percent_used: budget.percent_used(used_seconds),
+            budget,
+        }
+    }
+}
+/// Created when the time spent on a project crosses a threshold of its budget.
+///
+/// A threshold is only crossed once per period
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct BudgetEvent {
+    pub id: i64,
+    pub budget_id: i64,
+    pub project_id: i64,
+    pub threshold: i32,
+    /// The start of the period the threshold was crossed in. None for [BudgetPeriod::Total]
+    pub period_start: Option<DateTime<FixedOffset>>,
+    pub used_seconds: i64,
+    pub budget_seconds: i64,
+    pub created: DateTime<FixedOffset>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset};
+
+    use super::{BudgetPeriod, ProjectBudget};
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+    #[test]
+    pub fn start_of_period() {
+        // A Wednesday
+        let now = time("2023-12-20T15:30:00-05:00");
+        assert_eq!(BudgetPeriod::Total.start_of_period(now), None);
+        assert_eq!(
+            BudgetPeriod::Weekly.start_of_period(now),
+            Some(time("2023-12-18T00:00:00Z"))
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.start_of_period(now),
+            Some(time("2023-12-01T00:00:00Z"))
+        );
+        // Already Monday in UTC
+        let now = time("2023-12-17T22:00:00-05:00");
+        assert_eq!(
+            BudgetPeriod::Weekly.start_of_period(now),
+            Some(time("2023-12-18T00:00:00Z"))
+        );
+    }
+    #[test]
+    pub fn reached_thresholds() {
+        let budget = ProjectBudget {
+            id: 1,
+            project_id: 1,
+            period: BudgetPeriod::Total,
+            seconds: 3600,
+            thresholds: vec![80, 100],
+            created: time("2023-12-01T00:00:00Z"),
+        };
+        assert_eq!(budget.reached_thresholds(2000).count(), 0);
+        assert_eq!(budget.reached_thresholds(2880).collect::<Vec<_>>(), [80]);
+        assert_eq!(
+            budget.reached_thresholds(4000).collect::<Vec<_>>(),
+            [80, 100]
+        );
+    }
+}
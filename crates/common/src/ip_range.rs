@@ -0,0 +1,184 @@
+//! IP Ranges
+//!
+//! Used by the allowlists of API keys and the trusted proxy list.
+use std::{
+    fmt::{self, Display},
+    net::IpAddr,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::{
+    openapi::{schema::Schema, ObjectBuilder, RefOr},
+    ToSchema,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid IP or CIDR range: {0}")]
+pub struct InvalidIpRange(pub String);
+/// A single IP address or a CIDR range.
+///
+/// Example: `203.0.113.7`, `10.0.0.0/8` or `2001:db8::/32`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+impl IpRange {
+    /// If the address is inside the range.
+    ///
+    /// IPv4 mapped IPv6 addresses are compared as IPv4
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, canonical(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        ip => ip,
+    }
+}
+impl FromStr for IpRange {
+    type Err = InvalidIpRange;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidIpRange(value.to_owned());
+        let value = value.trim();
+        let (network, prefix) = match value.split_once('/') {
+            Some((network, prefix)) => (network, Some(prefix)),
+            None => (value, None),
+        };
+        let network = canonical(IpAddr::from_str(network).map_err(|_| invalid())?);
+        let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None => max_prefix,
+        };
+        if prefix > max_prefix {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix })
+    }
+}
+impl TryFrom<String> for IpRange {
+    type Error = InvalidIpRange;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+impl Display for IpRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_prefix = if self.network.is_ipv4() { 32 } else { 128 };
+        if self.prefix == max_prefix {
+            write!(f, "{}", self.network)
+        } else {
+            write!(f, "{}/{}", self.network, self.prefix)
+        }
+    }
+}
+impl From<IpRange> for String {
+    fn from(value: IpRange) -> Self {
+        value.to_string()
+    }
+}
+impl<'s> ToSchema<'s> for IpRange {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "IpRange",
+            ObjectBuilder::new()
+                .schema_type(utoipa::openapi::SchemaType::String)
+                .description(Some("A single IP address or a CIDR range."))
+                .example(Some("10.0.0.0/8".into()))
+                .into(),
+        )
+    }
+}
+/// Finds the address of the client.
+///
+/// If the peer is a trusted proxy. The `X-Forwarded-For` chain is walked from the right.
+/// The first address that is not a trusted proxy is the client.
+/// Anything to the left of it was set by the client and can not be trusted.
+pub fn resolve_client_ip(peer: IpAddr, forwarded_for: &[IpAddr], trusted: &[IpRange]) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|range| range.contains(ip));
+    if !is_trusted(peer) {
+        return peer;
+    }
+    forwarded_for
+        .iter()
+        .rev()
+        .copied()
+        .find(|ip| !is_trusted(*ip))
+        .or_else(|| forwarded_for.first().copied())
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{resolve_client_ip, IpRange};
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+    #[test]
+    pub fn ranges() {
+        let single: IpRange = "203.0.113.7".parse().unwrap();
+        assert!(single.contains(ip("203.0.113.7")));
+        assert!(!single.contains(ip("203.0.113.8")));
+        assert_eq!(single.to_string(), "203.0.113.7");
+
+        let private: IpRange = "10.0.0.0/8".parse().unwrap();
+        assert!(private.contains(ip("10.20.30.40")));
+        assert!(private.contains(ip("::ffff:10.1.1.1")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert_eq!(private.to_string(), "10.0.0.0/8");
+
+        let v6: IpRange = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+        assert!(!v6.contains(ip("10.0.0.1")));
+
+        let any: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(ip("198.51.100.1")));
+
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("not an ip".parse::<IpRange>().is_err());
+        assert!("10.0.0.0/".parse::<IpRange>().is_err());
+    }
+    #[test]
+    pub fn client_ip() {
+        let trusted = vec!["10.0.0.0/8".parse::<IpRange>().unwrap()];
+        // Untrusted peers can not spoof their address
+        assert_eq!(
+            resolve_client_ip(ip("198.51.100.1"), &[ip("203.0.113.7")], &trusted),
+            ip("198.51.100.1")
+        );
+        // The right most untrusted address is the client
+        assert_eq!(
+            resolve_client_ip(
+                ip("10.0.0.1"),
+                &[ip("1.1.1.1"), ip("203.0.113.7"), ip("10.0.0.2")],
+                &trusted
+            ),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.1"), &[], &trusted),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! Project Labels
+//!
+//! Labels are named colored tags owned by a user or team that can be attached to projects.
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct Label {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub name: String,
+    /// A hex color such as `#ff0000`
+    pub color: String,
+    pub created: DateTime<FixedOffset>,
+}
+/// Checks that the color is a hex color in the format `#rrggbb`
+pub fn is_valid_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use utoipa::ToSchema;
 
 #[derive(Debug, Error)]
 pub enum LanguageError {
@@ -96,6 +97,28 @@ impl LanguageDef {
         Ok(())
     }
 }
+/// A language definition stored in the database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Language {
+    pub id: i64,
+    pub name: String,
+    pub color: Option<String>,
+    pub aliases: Vec<String>,
+    pub categories: Vec<String>,
+    pub extensions: Vec<String>,
+    pub file_names: Vec<String>,
+    /// Who added the definition. Example: "FromOrganization"
+    pub source: String,
+}
+/// A language category stored in the database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// Who added the category. Example: "FromOrganization"
+    pub source: String,
+}
 /// Accepts a List or a single element into a Vec
 mod vec_serializer_deserializer {
 
@@ -1,6 +1,9 @@
 pub mod user_types;
 pub use http;
-pub use project::{PartialProjectQuery, Project, ProjectQuery, ProjectSortBy, UserOrTeam};
+pub use project::{
+    PartialProjectQuery, Project, ProjectContributor, ProjectQuery, ProjectSortBy, PublicProject,
+    PublicProjectPage, UserOrTeam,
+};
 pub use query_params::*;
 pub use user_types::{
     api_token::{APIToken, APITokenPermissions},
@@ -11,10 +14,17 @@ pub use user_types::{
     report_intervals::ReportIntervals,
     Email, Username,
 };
+pub mod budget;
 #[cfg(feature = "sea-orm")]
 pub mod database_helpers;
+pub mod ip_range;
+pub mod label;
+pub mod organization;
 pub mod project;
+pub mod project_rules;
 pub mod query_params;
+pub mod stats;
+pub mod team;
 use utoipa::openapi::ComponentsBuilder;
 pub use version_control_ref::*;
 pub mod heartbeat;
@@ -30,6 +40,11 @@ pub fn register_schemas(builder: ComponentsBuilder) -> ComponentsBuilder {
         .schema_from::<Bio>()
         .schema_from::<Pronouns>()
         .schema_from::<APITokenPermissions>()
+        .schema_from::<APIToken>()
+        .schema_from::<user_types::api_token::CreatedAPIToken>()
+        .schema_from::<user_types::api_token::APITokenListing>()
+        .schema_from::<user_types::api_token::FromCLI>()
+        .schema_from::<ip_range::IpRange>()
         .schema_from::<Preferences>()
         .schema_from::<Username>()
         .schema_from::<Email>()
@@ -48,15 +63,79 @@ pub fn register_schemas(builder: ComponentsBuilder) -> ComponentsBuilder {
         .schema_from::<UserOrTeam>()
         .schema_from::<ProjectSortBy>()
         .schema_from::<QueryOrdering>()
+        .schema_from::<Pagination>()
+        .schema_from::<VersionControlProvider>()
+        .schema_from::<PublicProject>()
+        .schema_from::<ProjectContributor>()
+        .schema_from::<PublicProjectPage>()
+        .schema_from::<stats::TimeBreakdown>()
+        .schema_from::<stats::DailyTime>()
+        .schema_from::<stats::TeamMemberTime>()
+        .schema_from::<stats::TeamStats>()
+        .schema_from::<stats::OrganizationStats>()
+        .schema_from::<label::Label>()
+        .schema_from::<language::Language>()
+        .schema_from::<language::Category>()
+        .schema_from::<team::Team>()
+        .schema_from::<team::TeamRole>()
+        .schema_from::<team::TeamMembership>()
+        .schema_from::<team::TeamMember>()
+        .schema_from::<team::TeamInvite>()
+        .schema_from::<team::CreatedTeamInvite>()
+        .schema_from::<team::TeamAPIToken>()
+        .schema_from::<team::CreatedTeamAPIToken>()
+        .schema_from::<organization::Organization>()
+        .schema_from::<organization::OrganizationAdmin>()
+        .schema_from::<budget::BudgetPeriod>()
+        .schema_from::<budget::ProjectBudget>()
+        .schema_from::<budget::BudgetUsage>()
+        .schema_from::<budget::BudgetEvent>()
+        .schema_from::<project_rules::ProjectRule>()
+        .schema_from::<project_rules::RuleField>()
+        .schema_from::<project_rules::RulePattern>()
+        .schema_from::<project_rules::RuleAction>()
 }
 
 /// Accepts either an integer id or a string name.
-#[derive(Debug, Clone, Hash, PartialEq, Eq, EnumIs, ToSchema, Serialize, Deserialize)]
+///
+/// Strings that only contain digits are treated as ids. So ids work in paths and query strings.
+/// Projects and labels can be named like an id. Their lookups try the name before the id
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EnumIs, ToSchema, Serialize)]
 #[serde(untagged)]
 pub enum IdOrName {
     Id(i64),
     Name(String),
 }
+impl<'de> Deserialize<'de> for IdOrName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct IdOrNameVisitor;
+        impl<'de> serde::de::Visitor<'de> for IdOrNameVisitor {
+            type Value = IdOrName;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an integer id or a string name")
+            }
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(IdOrName::Id(v))
+            }
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                i64::try_from(v)
+                    .map(IdOrName::Id)
+                    .map_err(|_| E::custom("id is too large"))
+            }
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                match v.parse::<i64>() {
+                    Ok(id) => Ok(IdOrName::Id(id)),
+                    Err(_) => Ok(IdOrName::Name(v.to_owned())),
+                }
+            }
+        }
+        deserializer.deserialize_any(IdOrNameVisitor)
+    }
+}
 impl From<i64> for IdOrName {
     fn from(id: i64) -> Self {
         Self::Id(id)
@@ -74,3 +153,18 @@ impl From<&str> for IdOrName {
         Self::Name(name.to_string())
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::IdOrName;
+
+    #[test]
+    pub fn id_or_name() {
+        let parse = |value: &str| serde_json::from_str::<IdOrName>(value).unwrap();
+        assert_eq!(parse("5"), IdOrName::Id(5));
+        assert_eq!(parse(r#""5""#), IdOrName::Id(5));
+        assert_eq!(
+            parse(r#""codi-time""#),
+            IdOrName::Name("codi-time".to_owned())
+        );
+    }
+}
@@ -0,0 +1,24 @@
+//! Organization Types
+//!
+//! An organization groups the teams of a company. Its admins manage the organization wide languages and rules.
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::TinyUser;
+
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct Organization {
+    pub id: i64,
+    /// Example: "Codi Time Inc"
+    pub name: String,
+    /// Example: "codi-time-inc"
+    pub id_style_name: String,
+    pub created: DateTime<FixedOffset>,
+}
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct OrganizationAdmin {
+    pub user: TinyUser,
+    pub added: DateTime<FixedOffset>,
+}
@@ -3,7 +3,12 @@ use either::Either;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::{version_control_ref::VersionControlRef, IdOrName, QueryOrdering};
+use crate::{
+    label::Label,
+    stats::TimeBreakdown,
+    version_control_ref::{VersionControlProvider, VersionControlRef},
+    IdOrName, Pagination, QueryOrdering, TinyUser,
+};
 
 #[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
 #[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
@@ -22,6 +27,38 @@ pub struct Project {
     pub last_update: DateTime<FixedOffset>,
     pub created_at: DateTime<FixedOffset>,
 }
+/// The parts of a project that are shown to everyone when the project is public
+#[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
+pub struct PublicProject {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub languages: Vec<String>,
+    pub color: Option<String>,
+    pub version_control_ref: Option<VersionControlRef>,
+    pub last_heartbeat: DateTime<FixedOffset>,
+    pub created: DateTime<FixedOffset>,
+}
+#[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
+pub struct ProjectContributor {
+    pub user: TinyUser,
+    pub seconds: i64,
+    /// None if the user does not share their languages
+    pub languages: Option<Vec<TimeBreakdown>>,
+}
+#[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
+pub struct PublicProjectPage {
+    pub project: PublicProject,
+    pub total_seconds: i64,
+    pub languages: Vec<TimeBreakdown>,
+    /// Empty if the owner does not share their labels
+    pub labels: Vec<Label>,
+    pub contributors: Vec<ProjectContributor>,
+    /// Contributors that do not share their projects.
+    /// Their time is still counted in the totals
+    pub anonymous_contributors: u64,
+}
 #[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
 pub enum UserOrTeam {
     User {
@@ -35,6 +72,24 @@ pub enum ProjectSortBy {
     Name(Option<QueryOrdering>),
     LastUpdate(Option<QueryOrdering>),
     LastHeartbeat(Option<QueryOrdering>),
+    /// The total time tracked in the project
+    TotalTime(Option<QueryOrdering>),
+}
+impl ProjectSortBy {
+    /// Identifies the column and order. Stored in page cursors
+    pub fn cursor_key(&self) -> String {
+        let (column, order) = match self {
+            Self::Name(order) => ("Name", order),
+            Self::LastUpdate(order) => ("LastUpdate", order),
+            Self::LastHeartbeat(order) => ("LastHeartbeat", order),
+            Self::TotalTime(order) => ("TotalTime", order),
+        };
+        let order = match order.clone().unwrap_or_default() {
+            QueryOrdering::Ascending => 'a',
+            QueryOrdering::Descending => 'd',
+        };
+        format!("{}:{}", column, order)
+    }
 }
 /// A Project Query for your own Projects
 #[derive(Clone, Debug, PartialEq, ToSchema, Serialize, Deserialize)]
@@ -44,6 +99,14 @@ pub struct PartialProjectQuery {
     /// Will Query Name and Renames
     pub name: Option<String>,
     pub sort_by: Option<ProjectSortBy>,
+    pub public: Option<bool>,
+    /// Only projects owned by this team
+    pub team: Option<IdOrName>,
+    pub vcs_provider: Option<VersionControlProvider>,
+    /// Only projects with this label
+    pub label: Option<IdOrName>,
+    pub last_heartbeat_after: Option<DateTime<FixedOffset>>,
+    pub last_heartbeat_before: Option<DateTime<FixedOffset>>,
 }
 
 impl Default for PartialProjectQuery {
@@ -52,6 +115,12 @@ impl Default for PartialProjectQuery {
             language: None,
             name: None,
             sort_by: None,
+            public: None,
+            team: None,
+            vcs_provider: None,
+            label: None,
+            last_heartbeat_after: None,
+            last_heartbeat_before: None,
         }
     }
 }
@@ -62,7 +131,8 @@ pub struct ProjectQuery {
     pub owned_by: UserOrTeam,
     #[serde(flatten)]
     pub query_params: PartialProjectQuery,
-    // TODO add pagination
+    #[serde(flatten)]
+    pub pagination: Pagination,
 }
 
 impl From<(i64, PartialProjectQuery)> for ProjectQuery {
@@ -73,6 +143,15 @@ impl From<(i64, PartialProjectQuery)> for ProjectQuery {
                 check_teams: true,
             },
             query_params,
+            pagination: Default::default(),
+        }
+    }
+}
+impl From<(i64, PartialProjectQuery, Pagination)> for ProjectQuery {
+    fn from((id, query_params, pagination): (i64, PartialProjectQuery, Pagination)) -> Self {
+        Self {
+            pagination,
+            ..Self::from((id, query_params))
         }
     }
 }
@@ -80,6 +159,7 @@ impl Default for ProjectQuery {
     fn default() -> Self {
         Self {
             query_params: Default::default(),
+            pagination: Default::default(),
             owned_by: UserOrTeam::User {
                 id_or_name: IdOrName::Id(0),
                 check_teams: true,
@@ -0,0 +1,254 @@
+//! Project Mapping Rules
+//!
+//! Rules are evaluated when a heartbeat is ingested. The first rule that matches decides what happens to the heartbeat.
+use chrono::{DateTime, FixedOffset};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+#[cfg(feature = "sea-orm")]
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+#[derive(Debug, Error)]
+pub enum InvalidRulePattern {
+    #[error("Invalid Glob: {0}")]
+    Glob(#[from] globset::Error),
+    #[error("Invalid Regex: {0}")]
+    Regex(#[from] regex::Error),
+}
+/// The part of the heartbeat a rule is checked against
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    EnumString,
+    Display,
+    EnumIter,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[cfg_attr(feature = "sea-orm", derive(DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "String", db_type = "Text"))]
+pub enum RuleField {
+    /// The path of the file being edited
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Entity"))]
+    Entity,
+    /// The project name sent by the client
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "ProjectName"))]
+    ProjectName,
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Branch"))]
+    Branch,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromJsonQueryResult))]
+#[serde(tag = "type", content = "value")]
+pub enum RulePattern {
+    /// A Glob such as `/tmp/**` or `**/node_modules/**`
+    Glob(String),
+    Regex(String),
+}
+impl RulePattern {
+    pub fn compile(&self) -> Result<RuleMatcher, InvalidRulePattern> {
+        match self {
+            RulePattern::Glob(glob) => {
+                let glob = GlobBuilder::new(glob).literal_separator(true).build()?;
+                Ok(RuleMatcher::Glob(glob.compile_matcher()))
+            }
+            RulePattern::Regex(regex) => Ok(RuleMatcher::Regex(Regex::new(regex)?)),
+        }
+    }
+}
+#[derive(Debug, Clone)]
+pub enum RuleMatcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+impl RuleMatcher {
+    pub fn is_match(&self, value: &str) -> bool {
+        match self {
+            RuleMatcher::Glob(glob) => glob.is_match(value),
+            RuleMatcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromJsonQueryResult))]
+#[serde(tag = "type", content = "value")]
+pub enum RuleAction {
+    /// Assigns the heartbeat to the project with the id
+    AssignProject(i64),
+    /// Replaces the project name sent by the client
+    Rename(String),
+    /// Drops the heartbeat
+    Ignore,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct ProjectRule {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub organization_id: Option<i64>,
+    pub name: String,
+    pub field: RuleField,
+    pub pattern: RulePattern,
+    pub action: RuleAction,
+    /// Higher priorities are checked first
+    pub priority: i32,
+    pub enabled: bool,
+    pub created: DateTime<FixedOffset>,
+}
+/// The values of a heartbeat that rules are checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatTarget<'a> {
+    pub entity: &'a str,
+    pub project: Option<&'a str>,
+    pub branch: Option<&'a str>,
+}
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    pub id: i64,
+    pub field: RuleField,
+    pub matcher: RuleMatcher,
+    pub action: RuleAction,
+}
+impl CompiledRule {
+    pub fn new(
+        id: i64,
+        field: RuleField,
+        pattern: &RulePattern,
+        action: RuleAction,
+    ) -> Result<Self, InvalidRulePattern> {
+        Ok(Self {
+            id,
+            field,
+            matcher: pattern.compile()?,
+            action,
+        })
+    }
+
+    pub fn matches(&self, target: &HeartbeatTarget<'_>) -> bool {
+        match self.field {
+            RuleField::Entity => {
+                // Windows Paths are matched as if they were Unix Paths
+                if target.entity.contains('\\') {
+                    self.matcher.is_match(&target.entity.replace('\\', "/"))
+                } else {
+                    self.matcher.is_match(target.entity)
+                }
+            }
+            RuleField::ProjectName => target
+                .project
+                .map(|project| self.matcher.is_match(project))
+                .unwrap_or(false),
+            RuleField::Branch => target
+                .branch
+                .map(|branch| self.matcher.is_match(branch))
+                .unwrap_or(false),
+        }
+    }
+}
+impl TryFrom<ProjectRule> for CompiledRule {
+    type Error = InvalidRulePattern;
+
+    fn try_from(value: ProjectRule) -> Result<Self, Self::Error> {
+        CompiledRule::new(value.id, value.field, &value.pattern, value.action)
+    }
+}
+/// An ordered set of rules. The first rule to match wins.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub rules: Vec<CompiledRule>,
+}
+impl RuleSet {
+    /// Compiles the rules in the order they are provided.
+    ///
+    /// Disabled rules and rules with an invalid pattern are skipped.
+    pub fn new(rules: impl IntoIterator<Item = ProjectRule>) -> Self {
+        let rules = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| CompiledRule::try_from(rule).ok())
+            .collect();
+        Self { rules }
+    }
+    pub fn evaluate(&self, target: &HeartbeatTarget<'_>) -> Option<&CompiledRule> {
+        self.rules.iter().find(|rule| rule.matches(target))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompiledRule, HeartbeatTarget, RuleAction, RuleField, RulePattern, RuleSet};
+
+    fn target(entity: &str) -> HeartbeatTarget<'_> {
+        HeartbeatTarget {
+            entity,
+            project: Some("codi-time"),
+            branch: Some("feature/rules"),
+        }
+    }
+    #[test]
+    pub fn glob_rules() {
+        let tmp = CompiledRule::new(
+            1,
+            RuleField::Entity,
+            &RulePattern::Glob("/tmp/**".to_owned()),
+            RuleAction::Ignore,
+        )
+        .unwrap();
+        assert!(tmp.matches(&target("/tmp/scratch/main.rs")));
+        assert!(!tmp.matches(&target("/home/user/tmp/main.rs")));
+
+        let node_modules = CompiledRule::new(
+            2,
+            RuleField::Entity,
+            &RulePattern::Glob("**/node_modules/**".to_owned()),
+            RuleAction::Ignore,
+        )
+        .unwrap();
+        assert!(node_modules.matches(&target("/home/user/app/node_modules/vue/index.js")));
+        assert!(node_modules.matches(&target("C:\\app\\node_modules\\vue\\index.js")));
+        assert!(!node_modules.matches(&target("/home/user/app/src/index.js")));
+    }
+    #[test]
+    pub fn first_match_wins() {
+        let rules = RuleSet {
+            rules: vec![
+                CompiledRule::new(
+                    1,
+                    RuleField::Branch,
+                    &RulePattern::Regex("^feature/".to_owned()),
+                    RuleAction::Rename("codi-time-features".to_owned()),
+                )
+                .unwrap(),
+                CompiledRule::new(
+                    2,
+                    RuleField::ProjectName,
+                    &RulePattern::Glob("codi-*".to_owned()),
+                    RuleAction::AssignProject(5),
+                )
+                .unwrap(),
+            ],
+        };
+        let matched = rules.evaluate(&target("/home/user/codi-time/src/main.rs"));
+        assert_eq!(matched.map(|rule| rule.id), Some(1));
+    }
+    #[test]
+    pub fn invalid_patterns() {
+        assert!(RulePattern::Regex("(".to_owned()).compile().is_err());
+        assert!(RulePattern::Glob("[".to_owned()).compile().is_err());
+    }
+}
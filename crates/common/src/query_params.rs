@@ -1,9 +1,17 @@
-use serde::Serialize;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use utoipa::{
     openapi::{ObjectBuilder, RefOr, SchemaType},
     ToSchema,
 };
+/// Header containing the total number of items matching the query
+pub const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+/// Header containing the cursor for the next page
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+pub const MAX_PAGE_SIZE: u64 = 500;
 #[derive(Clone, Debug, PartialEq, Error)]
 #[error("Invalid Ordering. Expected 'a' | 'd' | \"ascending\" | \"descending\" | 0 | 1")]
 pub struct InvalidOrdering;
@@ -68,6 +76,98 @@ impl TryFrom<u8> for QueryOrdering {
         }
     }
 }
+#[derive(Clone, Debug, PartialEq, Error)]
+#[error("Invalid Cursor")]
+pub struct InvalidCursor;
+/// Pagination for list queries.
+///
+/// Supports either a cursor or limit/offset. If a cursor is provided the offset is ignored.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct Pagination {
+    /// The maximum number of items to return. Defaults to 50. At most 500
+    pub limit: Option<u64>,
+    /// The number of items to skip
+    pub offset: Option<u64>,
+    /// The value of the `X-Next-Cursor` header from the previous page
+    pub cursor: Option<String>,
+}
+impl Pagination {
+    pub fn limit(&self) -> u64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+    pub fn offset(&self) -> u64 {
+        if self.cursor.is_some() {
+            0
+        } else {
+            self.offset.unwrap_or_default()
+        }
+    }
+    pub fn decode_cursor(&self) -> Result<Option<PageCursor>, InvalidCursor> {
+        self.cursor.as_deref().map(PageCursor::decode).transpose()
+    }
+    /// Decodes the cursor and checks that it was created for the same sort.
+    ///
+    /// A cursor from another sort would skip or repeat items
+    pub fn decode_cursor_for(&self, sort: &str) -> Result<Option<PageCursor>, InvalidCursor> {
+        match self.decode_cursor()? {
+            Some(cursor) if cursor.sort != sort => Err(InvalidCursor),
+            cursor => Ok(cursor),
+        }
+    }
+}
+/// The value of the sort column for the last item of a page
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum CursorValue {
+    Int(i64),
+    Text(String),
+    Time(DateTime<FixedOffset>),
+    /// The sort column of the item was NULL
+    Null,
+}
+#[cfg(feature = "sea-orm")]
+impl From<CursorValue> for sea_orm::Value {
+    fn from(value: CursorValue) -> Self {
+        match value {
+            CursorValue::Int(value) => value.into(),
+            CursorValue::Text(value) => value.into(),
+            CursorValue::Time(value) => value.into(),
+            CursorValue::Null => sea_orm::Value::String(None),
+        }
+    }
+}
+/// Points to the last item of a page.
+///
+/// The next page starts after the item with the sort value and id.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// The sort the cursor was created for. Example: "Name:a"
+    pub sort: String,
+    pub id: i64,
+    pub value: CursorValue,
+}
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Failed to serialize cursor");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+    pub fn decode(cursor: &str) -> Result<Self, InvalidCursor> {
+        let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| InvalidCursor)?;
+        serde_json::from_slice(&json).map_err(|_| InvalidCursor)
+    }
+}
+/// A page of items.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The total number of items matching the query
+    pub total: u64,
+    /// None if this is the last page
+    pub next_cursor: Option<String>,
+}
 mod _serde {
     use serde::Deserialize;
 
@@ -111,3 +211,47 @@ mod _serde {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CursorValue, InvalidCursor, PageCursor, Pagination};
+
+    #[test]
+    pub fn cursor_round_trip() {
+        let cursor = PageCursor {
+            sort: "Name:a".to_owned(),
+            id: 42,
+            value: CursorValue::Text("codi-time".to_owned()),
+        };
+        let pagination = Pagination {
+            cursor: Some(cursor.encode()),
+            offset: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(pagination.decode_cursor(), Ok(Some(cursor.clone())));
+        assert_eq!(pagination.decode_cursor_for("Name:a"), Ok(Some(cursor)));
+        assert_eq!(pagination.offset(), 0);
+        assert!(PageCursor::decode("not a cursor").is_err());
+    }
+    #[test]
+    pub fn cursor_from_another_sort_is_invalid() {
+        let cursor = PageCursor {
+            sort: "LastHeartbeat:d".to_owned(),
+            id: 42,
+            value: CursorValue::Null,
+        };
+        let pagination = Pagination {
+            cursor: Some(cursor.encode()),
+            ..Default::default()
+        };
+        assert_eq!(
+            pagination.decode_cursor_for("LastHeartbeat:d"),
+            Ok(Some(cursor))
+        );
+        assert_eq!(
+            pagination.decode_cursor_for("LastHeartbeat:a"),
+            Err(InvalidCursor)
+        );
+        assert_eq!(Pagination::default().decode_cursor_for("Name:a"), Ok(None));
+    }
+}
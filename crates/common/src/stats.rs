@@ -0,0 +1,131 @@
+//! Time Tracking Statistics
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::TinyUser;
+
+/// Time spent grouped by a name. Such as a language or a project
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct TimeBreakdown {
+    /// None if the heartbeats did not have a value. Such as an unknown language
+    pub name: Option<String>,
+    pub seconds: i64,
+}
+/// Time spent on a day. Days are in UTC
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct DailyTime {
+    pub date: NaiveDate,
+    pub seconds: i64,
+}
+/// The longest range that can be requested
+pub const MAX_STATS_RANGE_DAYS: i64 = 366;
+/// The range used if `from` is not provided
+pub const DEFAULT_STATS_RANGE_DAYS: i64 = 7;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum InvalidStatsRange {
+    #[error("`from` must be before `to`")]
+    Reversed,
+    #[error("The range can not be longer than {MAX_STATS_RANGE_DAYS} days")]
+    TooLong,
+}
+/// A range of time to calculate statistics over.
+///
+/// `to` defaults to now and `from` defaults to a week before `to`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsRange {
+    pub from: Option<DateTime<FixedOffset>>,
+    pub to: Option<DateTime<FixedOffset>>,
+}
+impl StatsRange {
+    /// Fills in the missing ends of the range and validates it
+    pub fn resolve(
+        &self,
+        now: DateTime<FixedOffset>,
+    ) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), InvalidStatsRange> {
+        let to = self.to.unwrap_or(now);
+        let from = self
+            .from
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_STATS_RANGE_DAYS));
+        if from >= to {
+            return Err(InvalidStatsRange::Reversed);
+        }
+        if to - from > Duration::days(MAX_STATS_RANGE_DAYS) {
+            return Err(InvalidStatsRange::TooLong);
+        }
+        Ok((from, to))
+    }
+}
+/// The time a member of a team spent on the projects of the team
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct TeamMemberTime {
+    pub user: TinyUser,
+    pub seconds: i64,
+    /// None if the member does not share their languages
+    pub languages: Option<Vec<TimeBreakdown>>,
+}
+/// Time spent on the projects of a team over a range
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct TeamStats {
+    pub from: DateTime<FixedOffset>,
+    pub to: DateTime<FixedOffset>,
+    pub total_seconds: i64,
+    pub members: Vec<TeamMemberTime>,
+    /// Members that do not share their projects.
+    /// Their time is still counted in the totals
+    pub anonymous_members: u64,
+    /// Time sent by the service accounts of the team. Grouped by the name of their API key
+    pub service_accounts: Vec<TimeBreakdown>,
+    pub projects: Vec<TimeBreakdown>,
+    pub languages: Vec<TimeBreakdown>,
+    /// Only days with tracked time are included
+    pub days: Vec<DailyTime>,
+}
+/// Time spent on the projects of every team in an organization over a range
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct OrganizationStats {
+    pub from: DateTime<FixedOffset>,
+    pub to: DateTime<FixedOffset>,
+    pub total_seconds: i64,
+    pub teams: Vec<TimeBreakdown>,
+    pub projects: Vec<TimeBreakdown>,
+    pub languages: Vec<TimeBreakdown>,
+    /// Only days with tracked time are included
+    pub days: Vec<DailyTime>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use super::{InvalidStatsRange, StatsRange};
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+    #[test]
+    pub fn resolve_range() {
+        let now = time("2024-01-10T12:00:00Z");
+        assert_eq!(
+            StatsRange::default().resolve(now),
+            Ok((now - Duration::days(7), now))
+        );
+        let range = StatsRange {
+            from: Some(time("2024-01-01T00:00:00Z")),
+            to: None,
+        };
+        assert_eq!(range.resolve(now), Ok((time("2024-01-01T00:00:00Z"), now)));
+        let range = StatsRange {
+            from: Some(now),
+            to: Some(now - Duration::days(1)),
+        };
+        assert_eq!(range.resolve(now), Err(InvalidStatsRange::Reversed));
+        let range = StatsRange {
+            from: Some(time("2022-01-01T00:00:00Z")),
+            to: None,
+        };
+        assert_eq!(range.resolve(now), Err(InvalidStatsRange::TooLong));
+    }
+}
@@ -0,0 +1,212 @@
+//! Team Types
+use chrono::{DateTime, FixedOffset};
+#[cfg(feature = "sea-orm")]
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use utoipa::ToSchema;
+
+use crate::{APITokenPermissions, Email, TinyUser};
+
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct Team {
+    pub id: i64,
+    /// Example: "Codi Time Developers"
+    pub name: String,
+    /// Example: "codi-time-developers"
+    pub id_style_name: String,
+    /// The organization the team belongs to
+    pub organization_id: Option<i64>,
+    pub created: DateTime<FixedOffset>,
+}
+/// The role of a member within a team. Ordered from most to least privileged
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    EnumString,
+    Display,
+    EnumIter,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[cfg_attr(feature = "sea-orm", derive(DeriveActiveEnum))]
+#[cfg_attr(feature = "sea-orm", sea_orm(rs_type = "String", db_type = "Text"))]
+pub enum TeamRole {
+    /// Can delete the team and manage the other owners
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Owner"))]
+    Owner,
+    /// Manages the team, its members and invites
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Admin"))]
+    Admin,
+    /// Manages the projects, rules, labels and budgets of the team
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Maintainer"))]
+    Maintainer,
+    /// Contributes time to the projects of the team
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Member"))]
+    #[default]
+    Member,
+    /// Can see the projects and stats of the team but contributes no time
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "Viewer"))]
+    Viewer,
+}
+impl TeamRole {
+    fn level(&self) -> u8 {
+        match self {
+            TeamRole::Owner => 4,
+            TeamRole::Admin => 3,
+            TeamRole::Maintainer => 2,
+            TeamRole::Member => 1,
+            TeamRole::Viewer => 0,
+        }
+    }
+    /// If this role has every permission of `role`
+    pub fn is_at_least(&self, role: TeamRole) -> bool {
+        self.level() >= role.level()
+    }
+    /// Manages the team, its members and invites
+    pub fn can_manage_team(&self) -> bool {
+        self.is_at_least(TeamRole::Admin)
+    }
+    /// Manages the projects, rules, labels and budgets of the team
+    pub fn can_manage_projects(&self) -> bool {
+        self.is_at_least(TeamRole::Maintainer)
+    }
+    /// If heartbeats of the member are attributed to the projects of the team
+    pub fn contributes_time(&self) -> bool {
+        self.is_at_least(TeamRole::Member)
+    }
+    /// If a member with this role can give `role` to someone or change the role of someone that has `role`.
+    ///
+    /// Owners can assign any role. Admins can assign any role below their own.
+    pub fn can_assign(&self, role: TeamRole) -> bool {
+        match self {
+            TeamRole::Owner => true,
+            TeamRole::Admin => role.level() < self.level(),
+            _ => false,
+        }
+    }
+}
+/// A team you are a member of
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct TeamMembership {
+    pub team: Team,
+    pub role: TeamRole,
+    pub joined: DateTime<FixedOffset>,
+}
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct TeamMember {
+    pub user: TinyUser,
+    pub role: TeamRole,
+    pub joined: DateTime<FixedOffset>,
+}
+/// A pending invitation to join a team
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct TeamInvite {
+    pub id: i64,
+    pub team_id: i64,
+    pub invited_by: i64,
+    /// None if the invite was sent to an email that is not registered yet
+    pub user_id: Option<i64>,
+    pub email: Option<Email>,
+    /// The role the invitee gets when they join
+    pub role: TeamRole,
+    pub expires: DateTime<FixedOffset>,
+    pub created: DateTime<FixedOffset>,
+}
+/// The response to creating an invite.
+///
+/// The token is only returned once. It can be used to accept the invite from a link
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct CreatedTeamInvite {
+    pub invite: TeamInvite,
+    pub token: String,
+}
+/// An API key owned by a team. Used by service accounts such as CI bots.
+///
+/// Heartbeats sent with it are not attributed to a person and can only be assigned to projects of the team
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+#[cfg_attr(feature = "sea-orm", derive(sea_orm::FromQueryResult))]
+pub struct TeamAPIToken {
+    pub id: i64,
+    pub team_id: i64,
+    /// The member that created the key
+    pub created_by: i64,
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<APITokenPermissions>,
+    pub revoked: Option<DateTime<FixedOffset>>,
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    pub created: DateTime<FixedOffset>,
+}
+impl TeamAPIToken {
+    pub fn has_permission(&self, permission: APITokenPermissions) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+/// The response to creating a team API key.
+///
+/// The token is only returned once
+#[derive(Clone, Debug, PartialEq, Eq, ToSchema, Serialize, Deserialize)]
+pub struct CreatedTeamAPIToken {
+    pub key: TeamAPIToken,
+    pub token: String,
+}
+/// Converts a team name into its id style name.
+///
+/// "Codi Time Developers" becomes "codi-time-developers".
+/// Returns None if the name does not contain a non-numeric alphanumeric character.
+/// So it can not be confused with an id.
+pub fn to_id_style_name(name: &str) -> Option<String> {
+    let mut id_style_name = String::with_capacity(name.len());
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() {
+            id_style_name.extend(c.to_lowercase());
+        } else if !id_style_name.is_empty() && !id_style_name.ends_with('-') {
+            id_style_name.push('-');
+        }
+    }
+    let id_style_name = id_style_name.trim_end_matches('-').to_owned();
+    if id_style_name.chars().all(|c| c.is_numeric() || c == '-') {
+        return None;
+    }
+    Some(id_style_name)
+}
+#[cfg(test)]
+mod tests {
+    use super::{to_id_style_name, TeamRole};
+
+    #[test]
+    pub fn id_style_names() {
+        assert_eq!(
+            to_id_style_name("Codi Time Developers").as_deref(),
+            Some("codi-time-developers")
+        );
+        assert_eq!(
+            to_id_style_name("  Rust & Go -- Team 2 ").as_deref(),
+            Some("rust-go-team-2")
+        );
+        assert_eq!(to_id_style_name("1234"), None);
+        assert_eq!(to_id_style_name("!!!"), None);
+    }
+    #[test]
+    pub fn team_roles() {
+        assert!(TeamRole::Owner.is_at_least(TeamRole::Admin));
+        assert!(!TeamRole::Maintainer.can_manage_team());
+        assert!(TeamRole::Maintainer.can_manage_projects());
+        assert!(TeamRole::Member.contributes_time());
+        assert!(!TeamRole::Viewer.contributes_time());
+
+        assert!(TeamRole::Owner.can_assign(TeamRole::Owner));
+        assert!(TeamRole::Admin.can_assign(TeamRole::Maintainer));
+        assert!(!TeamRole::Admin.can_assign(TeamRole::Admin));
+        assert!(!TeamRole::Maintainer.can_assign(TeamRole::Viewer));
+    }
+}
@@ -1,9 +1,14 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, FixedOffset};
 use digestible::Digestible;
 #[cfg(feature = "sea-orm")]
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 use utoipa::ToSchema;
+
+use crate::ip_range::IpRange;
 /// Permissions/Scopes for API Keys
 #[derive(
     Debug,
@@ -40,6 +45,24 @@ pub enum APITokenPermissions {
     /// Required for the the editor plugins to work
     #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "ReadUsage"))]
     ReadUsage,
+    /// Read your profile and API keys
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "ReadProfile"))]
+    ReadProfile,
+    /// Update your preferences, report intervals and API keys
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "WriteProfile"))]
+    WriteProfile,
+    /// Read your projects, rules, labels and budgets
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "ReadProjects"))]
+    ReadProjects,
+    /// Manage your projects, rules, labels and budgets
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "WriteProjects"))]
+    WriteProjects,
+    /// Read your teams and organizations
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "ReadTeams"))]
+    ReadTeams,
+    /// Manage your teams and organizations
+    #[cfg_attr(feature = "sea-orm", sea_orm(string_value = "WriteTeams"))]
+    WriteTeams,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema, Digestible)]
@@ -59,10 +82,130 @@ pub struct APIToken {
     pub id: i64,
     /// Has One relation to users::id
     pub user_id: i64,
+    pub name: String,
+    pub description: String,
     pub permissions: Vec<APITokenPermissions>,
     pub from_cli: Option<FromCLI>,
     /// Key is invalid. Invalid keys are kept for a short period for warning and logging purposes.
     pub revoked: Option<DateTimeWithTimeZone>,
     pub expires_at: Option<DateTimeWithTimeZone>,
+    /// None if the key was never used
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+    pub last_ip: Option<String>,
+    pub last_user_agent: Option<String>,
+    /// Requests made with the key since it was created
+    pub request_count: i64,
+    /// IPs or CIDR ranges the key can be used from. Empty allows any address
+    pub allowed_ips: Vec<String>,
     pub created: DateTimeWithTimeZone,
 }
+/// Keys unused for this many days are flagged as stale by default
+pub const DEFAULT_STALE_API_KEY_DAYS: i64 = 30;
+/// The longest window for stale keys and recent requests. Older daily requests are removed
+pub const MAX_STALE_API_KEY_DAYS: i64 = 365;
+impl APIToken {
+    pub fn has_permission(&self, permission: APITokenPermissions) -> bool {
+        self.permissions.contains(&permission)
+    }
+    /// If the key can be used from the address.
+    ///
+    /// Keys without an allowlist can be used from anywhere.
+    /// Entries that fail to parse never match. So a broken allowlist fails closed
+    pub fn allows_ip(&self, ip: Option<IpAddr>) -> bool {
+        if self.allowed_ips.is_empty() {
+            return true;
+        }
+        let Some(ip) = ip else {
+            return false;
+        };
+        self.allowed_ips
+            .iter()
+            .filter_map(|range| range.parse::<IpRange>().ok())
+            .any(|range| range.contains(ip))
+    }
+    /// If the key has not been used in `days`. Keys that were never used count from their creation.
+    ///
+    /// Revoked keys are never stale
+    pub fn is_stale(&self, now: DateTime<FixedOffset>, days: i64) -> bool {
+        if self.revoked.is_some() {
+            return false;
+        }
+        let last_activity = self.last_used_at.unwrap_or(self.created);
+        now - last_activity > Duration::days(days)
+    }
+}
+/// An API key in the listing of your keys
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct APITokenListing {
+    #[serde(flatten)]
+    pub key: APIToken,
+    /// The key has not been used recently. The machine it was created for might be gone
+    pub stale: bool,
+    /// Requests made with the key in the last `stale_after_days` days. Counted per UTC day
+    pub recent_requests: i64,
+}
+/// The response to creating an API key.
+///
+/// The token is only returned once
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CreatedAPIToken {
+    pub key: APIToken,
+    pub token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use super::{APIToken, APITokenPermissions, DEFAULT_STALE_API_KEY_DAYS};
+
+    fn time(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+    fn token(created: &str, last_used_at: Option<&str>) -> APIToken {
+        APIToken {
+            id: 1,
+            user_id: 1,
+            name: "Laptop".to_owned(),
+            description: String::new(),
+            permissions: vec![APITokenPermissions::WriteHeartbeat],
+            from_cli: None,
+            revoked: None,
+            expires_at: None,
+            last_used_at: last_used_at.map(time),
+            last_ip: None,
+            last_user_agent: None,
+            request_count: 0,
+            allowed_ips: vec![],
+            created: time(created),
+        }
+    }
+    #[test]
+    pub fn stale_keys() {
+        let now = time("2024-03-01T00:00:00Z");
+        let days = DEFAULT_STALE_API_KEY_DAYS;
+        assert!(!token("2024-02-20T00:00:00Z", None).is_stale(now, days));
+        assert!(token("2024-01-01T00:00:00Z", None).is_stale(now, days));
+        assert!(!token("2024-01-01T00:00:00Z", Some("2024-02-25T00:00:00Z")).is_stale(now, days));
+        assert!(token("2023-01-01T00:00:00Z", Some("2024-01-15T00:00:00Z")).is_stale(now, days));
+
+        let mut revoked = token("2023-01-01T00:00:00Z", None);
+        revoked.revoked = Some(now - Duration::days(1));
+        assert!(!revoked.is_stale(now, days));
+    }
+    #[test]
+    pub fn allowed_ips() {
+        let mut key = token("2024-01-01T00:00:00Z", None);
+        assert!(key.allows_ip(None));
+        assert!(key.allows_ip(Some("198.51.100.1".parse().unwrap())));
+
+        key.allowed_ips = vec!["203.0.113.7".to_owned(), "10.0.0.0/8".to_owned()];
+        assert!(key.allows_ip(Some("203.0.113.7".parse().unwrap())));
+        assert!(key.allows_ip(Some("10.1.2.3".parse().unwrap())));
+        assert!(!key.allows_ip(Some("198.51.100.1".parse().unwrap())));
+        assert!(!key.allows_ip(None));
+
+        key.allowed_ips = vec!["not an ip".to_owned()];
+        assert!(!key.allows_ip(Some("198.51.100.1".parse().unwrap())));
+    }
+}
@@ -21,6 +21,9 @@ pub struct User {
     pub group: Group,
     pub receive_email_notifications: bool,
     pub require_password_change: bool,
+    /// If set. Logging in requires a TOTP code
+    #[digestible(digest_with = digest_with_hash)]
+    pub totp_enabled_at: Option<DateTime<FixedOffset>>,
     #[digestible(digest_with = digest_with_hash)]
     pub password_changed_at: DateTime<FixedOffset>,
     pub location: Location,
@@ -45,7 +48,7 @@ pub struct PublicUser {
     pub show_on_leader_board: bool,
     pub preferences: Preferences,
     pub banned: bool,
-    #[sea_orm(skip)]
+    /// Selected with an `EXISTS` on the connections of the user
     pub is_connected_to_github: bool,
     #[digestible(digest_with = digest_with_hash)]
     pub last_logged_in: DateTime<FixedOffset>,
@@ -7,6 +7,20 @@ use utoipa::ToSchema;
 pub enum VersionControlRef {
     Github(GithubVersionControlRef),
 }
+impl VersionControlRef {
+    pub fn provider(&self) -> VersionControlProvider {
+        match self {
+            VersionControlRef::Github(_) => VersionControlProvider::Github,
+        }
+    }
+}
+/// The service hosting the repository. Matches the `type` of [VersionControlRef]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize, ToSchema,
+)]
+pub enum VersionControlProvider {
+    Github,
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", content = "value")]
@@ -0,0 +1,31 @@
+use sea_orm::entity::prelude::*;
+
+/// Requests made with an API key per UTC day. Gives the recent requests of a key
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "api_key_daily_requests")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub api_key_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub day: Date,
+    pub requests: i64,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::api_keys::Entity",
+        from = "Column::ApiKeyId",
+        to = "crate::api_keys::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    APIKey,
+}
+
+impl Related<crate::api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::APIKey.def()
+    }
+}
@@ -1,5 +1,6 @@
 use common::{user_types::api_token::FromCLI, APITokenPermissions};
 use sea_orm::entity::prelude::*;
+pub mod daily_requests;
 mod utils;
 use sea_orm_exports::SeaORMExports;
 pub use utils::*;
@@ -23,6 +24,16 @@ pub struct Model {
     pub revoked: Option<DateTimeWithTimeZone>,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     pub expires_at: Option<DateTimeWithTimeZone>,
+    /// Usage is recorded in memory and flushed periodically. So it can lag behind by a minute
+    pub last_used_at: Option<DateTimeWithTimeZone>,
+    pub last_ip: Option<String>,
+    pub last_user_agent: Option<String>,
+    /// Requests made with the key since it was created. Recent requests are in `api_key_daily_requests`
+    #[sea_orm(default_value = "0")]
+    pub request_count: i64,
+    /// IPs or CIDR ranges the key can be used from. Empty allows any address
+    #[sea_orm(default_value = "{}")]
+    pub allowed_ips: Vec<String>,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     pub created: DateTimeWithTimeZone,
 }
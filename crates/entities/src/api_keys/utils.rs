@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
 use common::{APIToken, User};
-use sea_orm::entity::prelude::*;
+use sea_orm::{entity::prelude::*, QueryOrder, QuerySelect};
 use tracing::warn;
 
-use crate::{APIKeyColumn, APIKeyEntity, UserEntity};
+use crate::{
+    APIKeyColumn, APIKeyDailyRequestsColumn, APIKeyDailyRequestsEntity, APIKeyEntity, APIKeyModel,
+    UserEntity,
+};
 pub async fn get_user_and_token(
     token: &str,
     database: &impl ConnectionTrait,
@@ -36,3 +42,60 @@ pub async fn get_user_and_token(
         }
     }
 }
+/// Gets the API keys of a user. Including revoked keys. Newest first
+pub async fn get_api_keys_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<APIToken>, DbErr> {
+    APIKeyEntity::find()
+        .filter(APIKeyColumn::UserId.eq(user_id))
+        .order_by_desc(APIKeyColumn::Created)
+        .into_model()
+        .all(database)
+        .await
+}
+/// Requests made with each key of the user since the day. Keys without requests are left out
+pub async fn get_recent_requests_for_user(
+    user_id: i64,
+    since: NaiveDate,
+    database: &impl ConnectionTrait,
+) -> Result<HashMap<i64, i64>, DbErr> {
+    let requests: Vec<(i64, i64)> = APIKeyDailyRequestsEntity::find()
+        .select_only()
+        .column(APIKeyDailyRequestsColumn::ApiKeyId)
+        .column_as(
+            Expr::cust(r#"CAST(SUM("api_key_daily_requests"."requests") AS BIGINT)"#),
+            "requests",
+        )
+        .inner_join(APIKeyEntity)
+        .filter(
+            APIKeyColumn::UserId
+                .eq(user_id)
+                .and(APIKeyDailyRequestsColumn::Day.gte(since)),
+        )
+        .group_by(APIKeyDailyRequestsColumn::ApiKeyId)
+        .into_tuple()
+        .all(database)
+        .await?;
+    Ok(requests.into_iter().collect())
+}
+impl From<APIKeyModel> for APIToken {
+    fn from(value: APIKeyModel) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            name: value.name,
+            description: value.description,
+            permissions: value.permissions,
+            from_cli: value.from_cli,
+            revoked: value.revoked,
+            expires_at: value.expires_at,
+            last_used_at: value.last_used_at,
+            last_ip: value.last_ip,
+            last_user_agent: value.last_user_agent,
+            request_count: value.request_count,
+            allowed_ips: value.allowed_ips,
+            created: value.created,
+        }
+    }
+}
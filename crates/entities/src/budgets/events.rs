@@ -0,0 +1,50 @@
+use sea_orm::entity::prelude::*;
+
+/// Created when a threshold of a budget is crossed
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "budget_events")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub budget_id: i64,
+    pub project_id: i64,
+    pub threshold: i32,
+    /// The start of the period the threshold was crossed in. None for total budgets
+    pub period_start: Option<DateTimeWithTimeZone>,
+    pub used_seconds: i64,
+    pub budget_seconds: i64,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::budgets::Entity",
+        from = "Column::BudgetId",
+        to = "crate::budgets::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Budget,
+    #[sea_orm(
+        belongs_to = "crate::projects::Entity",
+        from = "Column::ProjectId",
+        to = "crate::projects::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Project,
+}
+
+impl Related<crate::budgets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Budget.def()
+    }
+}
+impl Related<crate::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Project.def()
+    }
+}
@@ -0,0 +1,48 @@
+use common::budget::BudgetPeriod;
+use sea_orm::entity::prelude::*;
+pub mod events;
+mod utils;
+pub use utils::*;
+/// A time budget for a project. A project can only have one budget.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "project_budgets")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub project_id: i64,
+    pub period: BudgetPeriod,
+    /// The budgeted time in seconds
+    pub seconds: i64,
+    /// Percentages of the budget that trigger a notification
+    #[sea_orm(default_value = "{80,100}")]
+    pub thresholds: Vec<i32>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::projects::Entity",
+        from = "Column::ProjectId",
+        to = "crate::projects::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Project,
+    #[sea_orm(has_many = "crate::budgets::events::Entity")]
+    Events,
+}
+
+impl Related<crate::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Project.def()
+    }
+}
+impl Related<crate::budgets::events::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Events.def()
+    }
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, FixedOffset};
+use common::budget::{BudgetEvent, BudgetUsage, ProjectBudget};
+use sea_orm::{entity::prelude::*, QueryOrder, QuerySelect};
+
+use crate::{
+    heartbeats::get_total_time, BudgetEventColumn, BudgetEventEntity, BudgetEventModel,
+    HeartbeatColumn, ProjectBudgetColumn, ProjectBudgetEntity, ProjectBudgetModel,
+};
+
+pub async fn get_budget_for_project(
+    project_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Option<ProjectBudgetModel>, DbErr> {
+    ProjectBudgetEntity::find()
+        .filter(ProjectBudgetColumn::ProjectId.eq(project_id))
+        .one(database)
+        .await
+}
+/// Calculates how much of the budget has been used in the period containing `now`
+pub async fn get_budget_usage(
+    budget: ProjectBudget,
+    now: DateTime<FixedOffset>,
+    database: &impl ConnectionTrait,
+) -> Result<BudgetUsage, DbErr> {
+    let period_start = budget.period.start_of_period(now);
+    let mut filter = HeartbeatColumn::Project.eq(budget.project_id);
+    if let Some(period_start) = period_start {
+        filter = filter.and(HeartbeatColumn::StartTime.gte(period_start));
+    }
+    let used_seconds = get_total_time(filter, database).await?;
+    Ok(BudgetUsage::new(budget, period_start, used_seconds))
+}
+/// The budget events of a project. Newest first
+pub async fn get_budget_events(
+    project_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<BudgetEvent>, DbErr> {
+    BudgetEventEntity::find()
+        .filter(BudgetEventColumn::ProjectId.eq(project_id))
+        .order_by_desc(BudgetEventColumn::Created)
+        .into_model()
+        .all(database)
+        .await
+}
+/// The thresholds that have already been crossed in the period
+pub async fn get_crossed_thresholds(
+    budget_id: i64,
+    period_start: Option<DateTime<FixedOffset>>,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<i32>, DbErr> {
+    let period = match period_start {
+        Some(period_start) => BudgetEventColumn::PeriodStart.eq(period_start),
+        None => BudgetEventColumn::PeriodStart.is_null(),
+    };
+    BudgetEventEntity::find()
+        .select_only()
+        .column(BudgetEventColumn::Threshold)
+        .filter(BudgetEventColumn::BudgetId.eq(budget_id).and(period))
+        .into_tuple()
+        .all(database)
+        .await
+}
+impl From<ProjectBudgetModel> for ProjectBudget {
+    fn from(value: ProjectBudgetModel) -> Self {
+        Self {
+            id: value.id,
+            project_id: value.project_id,
+            period: value.period,
+            seconds: value.seconds,
+            thresholds: value.thresholds,
+            created: value.created,
+        }
+    }
+}
+impl From<BudgetEventModel> for BudgetEvent {
+    fn from(value: BudgetEventModel) -> Self {
+        Self {
+            id: value.id,
+            budget_id: value.budget_id,
+            project_id: value.project_id,
+            threshold: value.threshold,
+            period_start: value.period_start,
+            used_seconds: value.used_seconds,
+            budget_seconds: value.budget_seconds,
+            created: value.created,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! CLI logins stored in the database. So every replica of the server can answer them
+use common::user_types::api_token::FromCLI;
+use sea_orm::entity::prelude::*;
+use sea_orm_exports::SeaORMExports;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumIter, EnumString};
+
+#[derive(
+    DeriveActiveEnum,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Deserialize,
+    Serialize,
+    EnumString,
+    Display,
+    EnumIter,
+)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+pub enum CLIAccessState {
+    /// Waiting for the user
+    #[sea_orm(string_value = "Pending")]
+    Pending,
+    /// The user is approving or denying it. The CLI should keep waiting
+    #[sea_orm(string_value = "Answering")]
+    Answering,
+    /// Approved. Waiting for the CLI to retrieve the token
+    #[sea_orm(string_value = "Unclaimed")]
+    Unclaimed,
+    /// The CLI retrieved the token
+    #[sea_orm(string_value = "Claimed")]
+    Claimed,
+    #[sea_orm(string_value = "Denied")]
+    Denied,
+    #[sea_orm(string_value = "Expired")]
+    Expired,
+}
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, SeaORMExports)]
+#[sea_orm(table_name = "cli_access_requests")]
+#[exports(CLIAccessRequest, has_relation)]
+pub struct Model {
+    /// SHA-256 of the key the CLI polls with
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    pub from_cli: FromCLI,
+    pub username: Option<String>,
+    pub ip_address: String,
+    pub state: CLIAccessState,
+    /// Set once the request is approved
+    pub api_key_id: Option<i64>,
+    /// The token encrypted with a key derived from the key the CLI polls with.
+    /// Cleared once the CLI retrieves it or the request expires
+    pub token: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    /// When the request was approved, denied or expired
+    pub completed_at: Option<DateTimeWithTimeZone>,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::api_keys::Entity",
+        from = "Column::ApiKeyId",
+        to = "crate::api_keys::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    APIKey,
+}
+
+impl Related<crate::api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::APIKey.def()
+    }
+}
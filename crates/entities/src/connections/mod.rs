@@ -25,6 +25,9 @@ pub enum Application {
     Github,
     #[sea_orm(string_value = "WakaTime")]
     WakaTime,
+    /// Single sign on through an OpenID Connect provider
+    #[sea_orm(string_value = "OpenIDConnect")]
+    OpenIDConnect,
 }
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, SeaORMExports)]
 #[sea_orm(table_name = "connections")]
@@ -41,6 +44,10 @@ pub struct Model {
     /// Used to store other data provided by the application that should not be public
     pub other_data_private: Option<JsonValue>,
     pub application: Application,
+    /// The id of the provider in the config. For applications with more than one provider
+    pub provider: Option<String>,
+    /// The id of the account in the application. For OpenID Connect this is the `sub` claim
+    pub account_id: Option<String>,
     /// For Apps other than WakAPI this is the refresh token
     pub token: String,
     /// Not Available for WakAPI
@@ -29,6 +29,8 @@ impl UserConnections {
 #[sea_orm(entity = "ConnectionEntity")]
 struct RestOfConnection {
     other_data_private: Option<JsonValue>,
+    provider: Option<String>,
+    account_id: Option<String>,
     token: String,
     expires_at: Option<DateTimeWithTimeZone>,
 }
@@ -57,6 +59,8 @@ impl PubConnection {
 
         let Some(RestOfConnection {
             other_data_private,
+            provider,
+            account_id,
             token,
             expires_at,
             ..
@@ -71,6 +75,8 @@ impl PubConnection {
             other_data,
             other_data_private,
             application,
+            provider,
+            account_id,
             token,
             expires_at,
             created,
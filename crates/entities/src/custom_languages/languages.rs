@@ -8,6 +8,7 @@ pub struct Model {
     pub id: i64,
     pub user_id: Option<i64>,
     pub team_id: Option<i64>,
+    pub organization_id: Option<i64>,
     pub name: String,
     pub color: Option<String>,
     #[sea_orm(default_value = "{}")]
@@ -44,6 +45,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(
+        belongs_to = "crate::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "crate::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Organization,
 }
 
 impl Related<crate::users::Entity> for Entity {
@@ -56,3 +65,8 @@ impl Related<crate::teams::Entity> for Entity {
         Relation::Team.def()
     }
 }
+impl Related<crate::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
@@ -3,14 +3,17 @@
 ///
 /// A User can add a new Language or Category that is only available to them
 /// A Team can add a new Language or Category that is available to all members of the Team
+/// An Organization can add a new Language or Category that is available to all members of its Teams
 ///
 /// An Admin can add a new Language or Category that is available to all Users
 use sea_orm::DeriveActiveEnum;
 
 pub mod categories;
 pub mod languages;
+mod utils;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIter, EnumString};
+pub use utils::*;
 #[derive(
     DeriveActiveEnum,
     Clone,
@@ -39,7 +42,24 @@ pub enum Source {
     /// Added by a Team for their own use
     #[sea_orm(string_value = "FromTeam")]
     FromTeam,
+    /// Added by an Organization for the members of its Teams
+    #[sea_orm(string_value = "FromOrganization")]
+    FromOrganization,
     /// Public Definitions Added by Admin
     #[sea_orm(string_value = "FromAdmin")]
     FromAdmin,
 }
+impl Source {
+    /// When multiple definitions share a name the one with the lowest precedence is used.
+    ///
+    /// User -> Team -> Organization -> Admin -> Default
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Source::FromUser => 0,
+            Source::FromTeam => 1,
+            Source::FromOrganization => 2,
+            Source::FromAdmin | Source::ModifiedDefault => 3,
+            Source::FromDefault => 4,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use ahash::{HashSet, HashSetExt};
+use common::language::{Category, Language};
+use sea_orm::{entity::prelude::*, sea_query::SimpleExpr, QueryOrder};
+
+use super::Source;
+use crate::{
+    organizations::get_organization_ids_for_teams, teams::get_team_ids_for_user,
+    LanguageCategoryColumn, LanguageCategoryEntity, LanguageCategoryModel, LanguageColumn,
+    LanguageEntity, LanguageModel,
+};
+
+/// The owners whose definitions are available to a user
+struct DefinitionOwners {
+    user_id: i64,
+    teams: Vec<i64>,
+    organizations: Vec<i64>,
+}
+impl DefinitionOwners {
+    async fn for_user(user_id: i64, database: &impl ConnectionTrait) -> Result<Self, DbErr> {
+        let teams = get_team_ids_for_user(user_id, database).await?;
+        let organizations = get_organization_ids_for_teams(teams.clone(), database).await?;
+        Ok(Self {
+            user_id,
+            teams,
+            organizations,
+        })
+    }
+    fn filter<C: ColumnTrait>(&self, user: C, team: C, organization: C) -> SimpleExpr {
+        let public = user
+            .is_null()
+            .and(team.is_null())
+            .and(organization.is_null());
+        user.eq(self.user_id)
+            .or(team.is_in(self.teams.clone()))
+            .or(organization.is_in(self.organizations.clone()))
+            .or(public)
+    }
+}
+/// Keeps the definition with the lowest [Source::precedence] for each name. Names are compared ignoring case
+fn resolve_by_precedence<T>(mut definitions: Vec<T>, key: impl Fn(&T) -> (&str, Source)) -> Vec<T> {
+    definitions.sort_by_key(|definition| key(definition).1.precedence());
+    let mut seen = HashSet::with_capacity(definitions.len());
+    definitions.retain(|definition| seen.insert(key(definition).0.to_lowercase()));
+    definitions
+}
+/// Gets the languages available to the user.
+///
+/// If multiple definitions share a name. The user's own is used first followed by their teams, their organizations, the admin and the default definitions
+pub async fn get_languages_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageModel>, DbErr> {
+    let owners = DefinitionOwners::for_user(user_id, database).await?;
+    let languages = LanguageEntity::find()
+        .filter(owners.filter(
+            LanguageColumn::UserId,
+            LanguageColumn::TeamId,
+            LanguageColumn::OrganizationId,
+        ))
+        .order_by_asc(LanguageColumn::Id)
+        .all(database)
+        .await?;
+    Ok(resolve_by_precedence(languages, |language| {
+        (language.name.as_str(), language.source)
+    }))
+}
+/// Gets the language categories available to the user. Using the same precedence as [get_languages_for_user]
+pub async fn get_language_categories_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageCategoryModel>, DbErr> {
+    let owners = DefinitionOwners::for_user(user_id, database).await?;
+    let categories = LanguageCategoryEntity::find()
+        .filter(owners.filter(
+            LanguageCategoryColumn::UserId,
+            LanguageCategoryColumn::TeamId,
+            LanguageCategoryColumn::OrganizationId,
+        ))
+        .order_by_asc(LanguageCategoryColumn::Id)
+        .all(database)
+        .await?;
+    Ok(resolve_by_precedence(categories, |category| {
+        (category.name.as_str(), category.source)
+    }))
+}
+/// Gets the languages added by the team
+pub async fn get_languages_for_team(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageModel>, DbErr> {
+    LanguageEntity::find()
+        .filter(LanguageColumn::TeamId.eq(team_id))
+        .filter(LanguageColumn::Source.eq(Source::FromTeam))
+        .order_by_asc(LanguageColumn::Name)
+        .all(database)
+        .await
+}
+/// Gets the language categories added by the team
+pub async fn get_language_categories_for_team(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageCategoryModel>, DbErr> {
+    LanguageCategoryEntity::find()
+        .filter(LanguageCategoryColumn::TeamId.eq(team_id))
+        .filter(LanguageCategoryColumn::Source.eq(Source::FromTeam))
+        .order_by_asc(LanguageCategoryColumn::Name)
+        .all(database)
+        .await
+}
+/// Gets the languages added by the organization
+pub async fn get_languages_for_organization(
+    organization_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageModel>, DbErr> {
+    LanguageEntity::find()
+        .filter(LanguageColumn::OrganizationId.eq(organization_id))
+        .filter(LanguageColumn::Source.eq(Source::FromOrganization))
+        .order_by_asc(LanguageColumn::Name)
+        .all(database)
+        .await
+}
+/// Gets the language categories added by the organization
+pub async fn get_language_categories_for_organization(
+    organization_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<LanguageCategoryModel>, DbErr> {
+    LanguageCategoryEntity::find()
+        .filter(LanguageCategoryColumn::OrganizationId.eq(organization_id))
+        .filter(LanguageCategoryColumn::Source.eq(Source::FromOrganization))
+        .order_by_asc(LanguageCategoryColumn::Name)
+        .all(database)
+        .await
+}
+impl From<LanguageModel> for Language {
+    fn from(value: LanguageModel) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            color: value.color,
+            aliases: value.aliases,
+            categories: value.categories,
+            extensions: value.extensions,
+            file_names: value.file_names,
+            source: value.source.to_string(),
+        }
+    }
+}
+impl From<LanguageCategoryModel> for Category {
+    fn from(value: LanguageCategoryModel) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            description: value.description,
+            source: value.source.to_string(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{resolve_by_precedence, Source};
+
+    #[test]
+    fn resolve_by_precedence_prefers_the_closest_owner() {
+        let definitions = vec![
+            ("Rust", Source::FromDefault, 1),
+            ("rust", Source::FromOrganization, 2),
+            ("RUST", Source::FromTeam, 3),
+            ("Go", Source::FromDefault, 4),
+            ("Go", Source::FromAdmin, 5),
+            ("Zig", Source::FromOrganization, 6),
+            ("Zig", Source::FromUser, 7),
+            ("Odin", Source::FromOrganization, 8),
+        ];
+        let mut resolved: Vec<i32> =
+            resolve_by_precedence(definitions, |(name, source, _)| (*name, *source))
+                .into_iter()
+                .map(|(_, _, id)| id)
+                .collect();
+        resolved.sort();
+        assert_eq!(resolved, vec![3, 5, 7, 8]);
+    }
+
+    #[test]
+    fn resolve_by_precedence_orders_the_sources() {
+        let sources = [
+            Source::FromUser,
+            Source::FromTeam,
+            Source::FromOrganization,
+            Source::FromAdmin,
+            Source::FromDefault,
+        ];
+        for pair in sources.windows(2) {
+            assert!(pair[0].precedence() < pair[1].precedence());
+        }
+        assert_eq!(
+            Source::ModifiedDefault.precedence(),
+            Source::FromAdmin.precedence()
+        );
+    }
+}
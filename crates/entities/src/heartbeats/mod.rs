@@ -1,13 +1,17 @@
 use common::heartbeat::{CodeChanges, HeartbeatCategory, HeartbeatType};
 use sea_orm::entity::prelude::*;
+mod utils;
+pub use utils::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "heartbeats")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
-    /// Foreign Key to User::id
-    pub user_id: i64,
+    /// Foreign Key to User::id. None if the heartbeat was sent by a service account
+    pub user_id: Option<i64>,
+    /// Foreign Key to TeamAPIKey::id. Set if the heartbeat was sent by a service account
+    pub team_api_key_id: Option<i64>,
     /// The Path to the file that was being edited.
     pub entity: String,
     #[sea_orm(rename = "type")]
@@ -62,6 +66,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Project,
+    #[sea_orm(
+        belongs_to = "crate::teams::team_api_keys::Entity",
+        from = "Column::TeamApiKeyId",
+        to = "crate::teams::team_api_keys::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    TeamAPIKey,
 }
 
 impl Related<crate::users::Entity> for Entity {
@@ -74,3 +86,8 @@ impl Related<crate::projects::Entity> for Entity {
         Relation::Project.def()
     }
 }
+impl Related<crate::teams::team_api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamAPIKey.def()
+    }
+}
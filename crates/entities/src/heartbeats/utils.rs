@@ -0,0 +1,202 @@
+use chrono::NaiveDate;
+use common::stats::{DailyTime, TimeBreakdown};
+use sea_orm::{entity::prelude::*, sea_query::SimpleExpr, JoinType, QueryOrder, QuerySelect};
+
+use crate::{
+    HeartbeatColumn, HeartbeatEntity, HeartbeatRelation, LabelColumn, ProjectColumn,
+    ProjectLabelColumn, ProjectLabelEntity, ProjectLabelRelation, ProjectRelation,
+    TeamAPIKeyColumn, TeamColumn,
+};
+
+/// The sum of the time between start_time and end_time in seconds
+pub fn sum_of_seconds() -> SimpleExpr {
+    Expr::cust(
+        r#"COALESCE(CAST(SUM(EXTRACT(EPOCH FROM ("heartbeats"."end_time" - "heartbeats"."start_time"))) AS BIGINT), 0)"#,
+    )
+}
+/// The total time in seconds tracked in the project of the current `projects` row.
+///
+/// Used for sorting projects by their tracked time
+pub fn project_total_seconds() -> SimpleExpr {
+    Expr::cust(
+        r#"(SELECT COALESCE(CAST(SUM(EXTRACT(EPOCH FROM ("heartbeats"."end_time" - "heartbeats"."start_time"))) AS BIGINT), 0) FROM "heartbeats" WHERE "heartbeats"."project" = "projects"."id")"#,
+    )
+}
+/// The total time in seconds of the heartbeats matching the filter
+pub async fn get_total_time(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<i64, DbErr> {
+    let total: Option<i64> = HeartbeatEntity::find()
+        .select_only()
+        .column_as(sum_of_seconds(), "seconds")
+        .filter(filter)
+        .into_tuple()
+        .one(database)
+        .await?;
+    Ok(total.unwrap_or_default())
+}
+/// Time spent per language. Ordered by the most time spent
+pub async fn get_time_by_language(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TimeBreakdown>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column_as(HeartbeatColumn::Language, "name")
+        .column_as(sum_of_seconds(), "seconds")
+        .filter(filter)
+        .group_by(HeartbeatColumn::Language)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_model()
+        .all(database)
+        .await
+}
+/// Time spent per user. Returns a tuple of (user_id, seconds)
+///
+/// Heartbeats sent by service accounts are not included
+pub async fn get_time_by_user(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<(i64, i64)>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column(HeartbeatColumn::UserId)
+        .column_as(sum_of_seconds(), "seconds")
+        .filter(filter.and(HeartbeatColumn::UserId.is_not_null()))
+        .group_by(HeartbeatColumn::UserId)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_tuple()
+        .all(database)
+        .await
+}
+/// Time spent per user per language. Returns a tuple of (user_id, language, seconds)
+///
+/// Heartbeats sent by service accounts are not included
+pub async fn get_time_by_user_and_language(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<(i64, Option<String>, i64)>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column(HeartbeatColumn::UserId)
+        .column(HeartbeatColumn::Language)
+        .column_as(sum_of_seconds(), "seconds")
+        .filter(filter.and(HeartbeatColumn::UserId.is_not_null()))
+        .group_by(HeartbeatColumn::UserId)
+        .group_by(HeartbeatColumn::Language)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_tuple()
+        .all(database)
+        .await
+}
+/// Time spent per project. Ordered by the most time spent
+///
+/// Heartbeats without a project are not included
+pub async fn get_time_by_project(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TimeBreakdown>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column_as(ProjectColumn::Name, "name")
+        .column_as(sum_of_seconds(), "seconds")
+        .join(JoinType::InnerJoin, HeartbeatRelation::Project.def())
+        .filter(filter)
+        .group_by(ProjectColumn::Id)
+        .group_by(ProjectColumn::Name)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_model()
+        .all(database)
+        .await
+}
+/// Time spent per team. Ordered by the most time spent
+///
+/// Heartbeats without a project or on projects owned by a user are not included
+pub async fn get_time_by_team(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TimeBreakdown>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column_as(TeamColumn::Name, "name")
+        .column_as(sum_of_seconds(), "seconds")
+        .join(JoinType::InnerJoin, HeartbeatRelation::Project.def())
+        .join(JoinType::InnerJoin, ProjectRelation::Team.def())
+        .filter(filter)
+        .group_by(TeamColumn::Id)
+        .group_by(TeamColumn::Name)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_model()
+        .all(database)
+        .await
+}
+/// Time spent per team API key. Ordered by the most time spent
+///
+/// Only heartbeats sent by service accounts are included
+pub async fn get_time_by_team_api_key(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TimeBreakdown>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column_as(TeamAPIKeyColumn::Name, "name")
+        .column_as(sum_of_seconds(), "seconds")
+        .join(JoinType::InnerJoin, HeartbeatRelation::TeamAPIKey.def())
+        .filter(filter)
+        .group_by(TeamAPIKeyColumn::Id)
+        .group_by(TeamAPIKeyColumn::Name)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_model()
+        .all(database)
+        .await
+}
+/// Time spent per day in UTC. Ordered by the day
+pub async fn get_time_by_day(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<DailyTime>, DbErr> {
+    let day = || Expr::cust(r#"CAST(("heartbeats"."start_time" AT TIME ZONE 'UTC') AS DATE)"#);
+    let days: Vec<(NaiveDate, i64)> = HeartbeatEntity::find()
+        .select_only()
+        .column_as(day(), "day")
+        .column_as(sum_of_seconds(), "seconds")
+        .filter(filter)
+        .group_by(day())
+        .order_by_asc(day())
+        .into_tuple()
+        .all(database)
+        .await?;
+    Ok(days
+        .into_iter()
+        .map(|(date, seconds)| DailyTime { date, seconds })
+        .collect())
+}
+/// Time spent per label. Ordered by the most time spent
+///
+/// Heartbeats of a project with multiple labels count towards each label.
+/// The filter can reference the columns of `labels`
+pub async fn get_time_by_label(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TimeBreakdown>, DbErr> {
+    HeartbeatEntity::find()
+        .select_only()
+        .column_as(LabelColumn::Name, "name")
+        .column_as(sum_of_seconds(), "seconds")
+        .join(
+            JoinType::InnerJoin,
+            HeartbeatEntity::belongs_to(ProjectLabelEntity)
+                .from(HeartbeatColumn::Project)
+                .to(ProjectLabelColumn::ProjectId)
+                .into(),
+        )
+        .join(JoinType::InnerJoin, ProjectLabelRelation::Label.def())
+        .filter(filter)
+        .group_by(LabelColumn::Id)
+        .group_by(LabelColumn::Name)
+        .order_by_desc(Expr::cust(r#""seconds""#))
+        .into_model()
+        .all(database)
+        .await
+}
@@ -0,0 +1,66 @@
+use sea_orm::entity::prelude::*;
+pub mod project_labels;
+mod utils;
+pub use utils::*;
+/// A named colored tag that can be attached to projects.
+///
+/// Owned by either a User or a Team.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "labels")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub name: String,
+    pub color: String,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "crate::teams::Entity",
+        from = "Column::TeamId",
+        to = "crate::teams::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Team,
+    #[sea_orm(has_many = "crate::labels::project_labels::Entity")]
+    ProjectLabels,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+impl Related<crate::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Team.def()
+    }
+}
+impl Related<crate::labels::project_labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectLabels.def()
+    }
+}
+impl Related<crate::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        crate::labels::project_labels::Relation::Project.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(crate::labels::project_labels::Relation::Label.def().rev())
+    }
+}
@@ -0,0 +1,44 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "project_labels")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub project_id: i64,
+    pub label_id: i64,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::projects::Entity",
+        from = "Column::ProjectId",
+        to = "crate::projects::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Project,
+    #[sea_orm(
+        belongs_to = "crate::labels::Entity",
+        from = "Column::LabelId",
+        to = "crate::labels::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Label,
+}
+
+impl Related<crate::projects::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Project.def()
+    }
+}
+impl Related<crate::labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Label.def()
+    }
+}
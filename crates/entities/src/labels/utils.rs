@@ -0,0 +1,112 @@
+use common::label::Label;
+use sea_orm::{
+    entity::prelude::*,
+    sea_query::{OnConflict, SimpleExpr},
+    ActiveValue, QueryOrder,
+};
+
+use crate::{
+    teams::get_team_ids_for_user, LabelColumn, LabelEntity, LabelModel, ProjectLabelActiveModel,
+    ProjectLabelColumn, ProjectLabelEntity,
+};
+
+async fn get_labels(
+    filter: SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<Label>, DbErr> {
+    LabelEntity::find()
+        .filter(filter)
+        .order_by_asc(LabelColumn::Name)
+        .into_model()
+        .all(database)
+        .await
+}
+pub async fn get_labels_for_team(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<Label>, DbErr> {
+    get_labels(LabelColumn::TeamId.eq(team_id), database).await
+}
+pub async fn get_labels_owned_by_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<Label>, DbErr> {
+    get_labels(LabelColumn::UserId.eq(user_id), database).await
+}
+/// Gets the labels owned by the user and the labels of the teams they are a member of
+pub async fn get_labels_available_to_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<Label>, DbErr> {
+    let teams = get_team_ids_for_user(user_id, database).await?;
+    let filter = if teams.is_empty() {
+        LabelColumn::UserId.eq(user_id)
+    } else {
+        LabelColumn::UserId
+            .eq(user_id)
+            .or(LabelColumn::TeamId.is_in(teams))
+    };
+    get_labels(filter, database).await
+}
+/// Gets the labels attached to a project
+pub async fn get_labels_for_project(
+    project_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<Label>, DbErr> {
+    LabelEntity::find()
+        .inner_join(ProjectLabelEntity)
+        .filter(ProjectLabelColumn::ProjectId.eq(project_id))
+        .order_by_asc(LabelColumn::Name)
+        .into_model()
+        .all(database)
+        .await
+}
+/// Checks if the label is already attached to the project
+/// Attaches the label to the project. Returns false if the label was already attached
+pub async fn attach_label(
+    project_id: i64,
+    label_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let inserted = ProjectLabelEntity::insert(ProjectLabelActiveModel {
+        project_id: ActiveValue::Set(project_id),
+        label_id: ActiveValue::Set(label_id),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([ProjectLabelColumn::ProjectId, ProjectLabelColumn::LabelId])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(database)
+    .await?;
+    Ok(inserted > 0)
+}
+/// Removes the label from the project. Returns false if the label was not attached
+pub async fn detach_label(
+    project_id: i64,
+    label_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let result = ProjectLabelEntity::delete_many()
+        .filter(
+            ProjectLabelColumn::ProjectId
+                .eq(project_id)
+                .and(ProjectLabelColumn::LabelId.eq(label_id)),
+        )
+        .exec(database)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+impl From<LabelModel> for Label {
+    fn from(value: LabelModel) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            team_id: value.team_id,
+            name: value.name,
+            color: value.color,
+            created: value.created,
+        }
+    }
+}
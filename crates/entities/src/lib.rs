@@ -2,26 +2,55 @@
 #![allow(async_fn_in_trait)]
 pub mod api_keys;
 pub mod avatar;
+pub mod budgets;
+pub mod cli_access_requests;
 pub mod connections;
 pub mod custom_languages;
 pub mod gravatar;
 pub mod heartbeats;
+pub mod labels;
+pub mod oauth_states;
+pub mod organizations;
+pub mod project_rules;
 pub mod projects;
 pub mod teams;
 pub mod users;
+pub mod webauthn_ceremonies;
 pub use avatar::Source;
 pub use connections::Application;
 use sea_orm_exports::export_module;
 
 export_module!(users, User, has_relation);
+export_module!(users::email_verifications, EmailVerification, has_relation);
+export_module!(users::password_resets, PasswordReset, has_relation);
+export_module!(users::recovery_codes, RecoveryCode, has_relation);
+export_module!(users::login_challenges, LoginChallenge, has_relation);
+export_module!(users::passkeys, Passkey, has_relation);
 export_module!(avatar, Avatar, has_relation);
 export_module!(connections, Connection, has_relation);
 export_module!(api_keys, APIKey, has_relation);
+export_module!(api_keys::daily_requests, APIKeyDailyRequests, has_relation);
+export_module!(cli_access_requests, CLIAccessRequest, has_relation);
+export_module!(webauthn_ceremonies, WebAuthnCeremony, has_relation);
+export_module!(oauth_states, OAuthState, has_relation);
 
 export_module!(projects, Project, has_relation);
 export_module!(heartbeats, Heartbeat, has_relation);
 export_module!(teams, Team, has_relation);
 export_module!(teams::team_members, TeamMember, has_relation);
+export_module!(teams::team_invites, TeamInvite, has_relation);
+export_module!(teams::team_api_keys, TeamAPIKey, has_relation);
+export_module!(organizations, Organization, has_relation);
+export_module!(
+    organizations::organization_admins,
+    OrganizationAdmin,
+    has_relation
+);
 export_module!(custom_languages::languages, Language, has_relation);
 export_module!(custom_languages::categories, LanguageCategory, has_relation);
+export_module!(project_rules, ProjectRule, has_relation);
+export_module!(labels, Label, has_relation);
+export_module!(labels::project_labels, ProjectLabel, has_relation);
+export_module!(budgets, ProjectBudget, has_relation);
+export_module!(budgets::events, BudgetEvent, has_relation);
 pub static COLLATE_IGNORE_CASE: &str = "COLLATE ignoreCase";
@@ -0,0 +1,41 @@
+//! A login through an external provider that is waiting for the provider to redirect back
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "oauth_states")]
+pub struct Model {
+    /// The `state` parameter sent to the provider
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub state: String,
+    /// The id of the provider in the config
+    pub provider: String,
+    /// PKCE code verifier
+    pub code_verifier: String,
+    /// OpenID Connect nonce. None for plain OAuth providers
+    pub nonce: Option<String>,
+    /// Set if a logged in user is linking the provider to their account
+    pub user_id: Option<i64>,
+    pub expires: DateTimeWithTimeZone,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
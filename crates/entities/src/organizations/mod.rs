@@ -0,0 +1,64 @@
+pub mod organization_admins;
+use common::database_helpers::{BasicTableTrait, HasNameColumn};
+use helper_macros::DatabaseHelpers;
+use sea_orm::entity::prelude::*;
+mod utils;
+pub use utils::*;
+/// A group of teams. Such as a company
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, DatabaseHelpers)]
+#[sea_orm(table_name = "organizations")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    #[column(id)]
+    pub id: i64,
+    /// Example: "Codi Time Inc"
+    pub name: String,
+    /// Example: "codi-time-inc"
+    #[column(name)]
+    pub id_style_name: String,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[column(created)]
+    pub created: DateTimeWithTimeZone,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "crate::organizations::organization_admins::Entity")]
+    OrganizationAdmins,
+    #[sea_orm(has_many = "crate::teams::Entity")]
+    Teams,
+    #[sea_orm(has_many = "crate::custom_languages::languages::Entity")]
+    CustomLanguages,
+    #[sea_orm(has_many = "crate::custom_languages::categories::Entity")]
+    CustomLanguageCategories,
+    #[sea_orm(has_many = "crate::project_rules::Entity")]
+    ProjectRules,
+}
+
+impl Related<crate::organizations::organization_admins::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrganizationAdmins.def()
+    }
+}
+impl Related<crate::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Teams.def()
+    }
+}
+impl Related<crate::custom_languages::languages::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustomLanguages.def()
+    }
+}
+impl Related<crate::custom_languages::categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CustomLanguageCategories.def()
+    }
+}
+impl Related<crate::project_rules::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectRules.def()
+    }
+}
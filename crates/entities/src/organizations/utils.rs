@@ -0,0 +1,114 @@
+use common::organization::Organization;
+use sea_orm::{entity::prelude::*, PaginatorTrait, QueryOrder, QuerySelect};
+
+use crate::{
+    OrganizationAdminColumn, OrganizationAdminEntity, OrganizationColumn, OrganizationEntity,
+    OrganizationModel, TeamColumn, TeamEntity, TeamMemberColumn, TeamMemberEntity, TeamModel,
+};
+
+/// Gets the organizations the user is an admin of
+pub async fn get_organizations_for_admin(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<OrganizationModel>, DbErr> {
+    OrganizationEntity::find()
+        .inner_join(OrganizationAdminEntity)
+        .filter(OrganizationAdminColumn::UserId.eq(user_id))
+        .order_by_asc(OrganizationColumn::Name)
+        .all(database)
+        .await
+}
+pub async fn is_organization_admin(
+    organization_id: i64,
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let count = OrganizationAdminEntity::find()
+        .filter(
+            OrganizationAdminColumn::OrganizationId
+                .eq(organization_id)
+                .and(OrganizationAdminColumn::UserId.eq(user_id)),
+        )
+        .count(database)
+        .await?;
+    Ok(count > 0)
+}
+/// Checks if the user is a member of any team in the organization
+pub async fn is_member_of_organization(
+    organization_id: i64,
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let count = TeamMemberEntity::find()
+        .inner_join(TeamEntity)
+        .filter(
+            TeamColumn::OrganizationId
+                .eq(organization_id)
+                .and(TeamMemberColumn::UserId.eq(user_id)),
+        )
+        .count(database)
+        .await?;
+    Ok(count > 0)
+}
+pub async fn count_organization_admins(
+    organization_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<u64, DbErr> {
+    OrganizationAdminEntity::find()
+        .filter(OrganizationAdminColumn::OrganizationId.eq(organization_id))
+        .count(database)
+        .await
+}
+/// Gets the ids of the organizations that own any of the teams
+pub async fn get_organization_ids_for_teams(
+    team_ids: Vec<i64>,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<i64>, DbErr> {
+    if team_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let organizations: Vec<Option<i64>> = TeamEntity::find()
+        .select_only()
+        .column(TeamColumn::OrganizationId)
+        .distinct()
+        .filter(
+            TeamColumn::Id
+                .is_in(team_ids)
+                .and(TeamColumn::OrganizationId.is_not_null()),
+        )
+        .into_tuple()
+        .all(database)
+        .await?;
+    Ok(organizations.into_iter().flatten().collect())
+}
+/// Gets the teams of an organization. Ordered by name
+pub async fn get_organization_teams(
+    organization_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TeamModel>, DbErr> {
+    TeamEntity::find()
+        .filter(TeamColumn::OrganizationId.eq(organization_id))
+        .order_by_asc(TeamColumn::Name)
+        .all(database)
+        .await
+}
+pub async fn does_organization_id_style_name_exist(
+    id_style_name: &str,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let count = OrganizationEntity::find()
+        .filter(OrganizationColumn::IdStyleName.eq(id_style_name))
+        .count(database)
+        .await?;
+    Ok(count > 0)
+}
+impl From<OrganizationModel> for Organization {
+    fn from(value: OrganizationModel) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            id_style_name: value.id_style_name,
+            created: value.created,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use common::project_rules::{RuleAction, RuleField, RulePattern};
+use sea_orm::entity::prelude::*;
+mod utils;
+pub use utils::*;
+/// A rule that is evaluated against incoming heartbeats.
+///
+/// Owned by either a User, a Team or an Organization.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "project_rules")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub team_id: Option<i64>,
+    pub organization_id: Option<i64>,
+    pub name: String,
+    pub field: RuleField,
+    pub pattern: RulePattern,
+    pub action: RuleAction,
+    /// Higher priorities are checked first
+    #[sea_orm(default_value = "0")]
+    pub priority: i32,
+    #[sea_orm(default_value = "true")]
+    pub enabled: bool,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "crate::teams::Entity",
+        from = "Column::TeamId",
+        to = "crate::teams::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Team,
+    #[sea_orm(
+        belongs_to = "crate::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "crate::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Organization,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+impl Related<crate::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Team.def()
+    }
+}
+impl Related<crate::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
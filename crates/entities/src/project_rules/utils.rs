@@ -0,0 +1,102 @@
+use common::project_rules::ProjectRule;
+use sea_orm::{entity::prelude::*, QueryOrder};
+
+use crate::{
+    organizations::get_organization_ids_for_teams, teams::get_contributing_team_ids_for_user,
+    ProjectRuleColumn, ProjectRuleEntity, ProjectRuleModel, TeamEntity,
+};
+
+/// Rules are ordered by priority. Ties are broken by the oldest rule.
+async fn get_rules(
+    filter: sea_orm::sea_query::SimpleExpr,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    ProjectRuleEntity::find()
+        .filter(filter)
+        .order_by_desc(ProjectRuleColumn::Priority)
+        .order_by_asc(ProjectRuleColumn::Id)
+        .into_model()
+        .all(database)
+        .await
+}
+pub async fn get_rules_for_team(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    get_rules(ProjectRuleColumn::TeamId.eq(team_id), database).await
+}
+pub async fn get_rules_for_organization(
+    organization_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    get_rules(
+        ProjectRuleColumn::OrganizationId.eq(organization_id),
+        database,
+    )
+    .await
+}
+pub async fn get_rules_owned_by_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    get_rules(ProjectRuleColumn::UserId.eq(user_id), database).await
+}
+/// Gets every rule that applies to the heartbeats of a user.
+///
+/// The user's own rules come first followed by the rules of the teams they contribute time to.
+/// Then the rules of the organizations of those teams.
+pub async fn get_rules_applied_to_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    let mut rules = get_rules_owned_by_user(user_id, database).await?;
+    let teams = get_contributing_team_ids_for_user(user_id, database).await?;
+    if !teams.is_empty() {
+        rules.extend(get_rules(ProjectRuleColumn::TeamId.is_in(teams.clone()), database).await?);
+    }
+    let organizations = get_organization_ids_for_teams(teams, database).await?;
+    if !organizations.is_empty() {
+        rules.extend(
+            get_rules(
+                ProjectRuleColumn::OrganizationId.is_in(organizations),
+                database,
+            )
+            .await?,
+        );
+    }
+    Ok(rules)
+}
+/// Gets every rule that applies to the heartbeats of the team's service accounts.
+///
+/// The team's rules come first followed by the rules of its organization.
+pub async fn get_rules_applied_to_team(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<ProjectRule>, DbErr> {
+    let mut rules = get_rules_for_team(team_id, database).await?;
+    let organization = TeamEntity::find_by_id(team_id)
+        .one(database)
+        .await?
+        .and_then(|team| team.organization_id);
+    if let Some(organization_id) = organization {
+        rules.extend(get_rules_for_organization(organization_id, database).await?);
+    }
+    Ok(rules)
+}
+impl From<ProjectRuleModel> for ProjectRule {
+    fn from(value: ProjectRuleModel) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            team_id: value.team_id,
+            organization_id: value.organization_id,
+            name: value.name,
+            field: value.field,
+            pattern: value.pattern,
+            action: value.action,
+            priority: value.priority,
+            enabled: value.enabled,
+            created: value.created,
+        }
+    }
+}
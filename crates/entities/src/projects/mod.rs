@@ -40,6 +40,10 @@ impl ActiveModelBehavior for ActiveModel {}
 pub enum Relation {
     #[sea_orm(has_many = "crate::heartbeats::Entity")]
     Heartbeats,
+    #[sea_orm(has_many = "crate::labels::project_labels::Entity")]
+    ProjectLabels,
+    #[sea_orm(has_one = "crate::budgets::Entity")]
+    Budget,
     #[sea_orm(
         belongs_to = "crate::users::Entity",
         from = "Column::UserId",
@@ -63,6 +67,24 @@ impl Related<crate::connections::Entity> for Entity {
         Relation::Heartbeats.def()
     }
 }
+impl Related<crate::labels::project_labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectLabels.def()
+    }
+}
+impl Related<crate::budgets::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Budget.def()
+    }
+}
+impl Related<crate::labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        crate::labels::project_labels::Relation::Label.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(crate::labels::project_labels::Relation::Project.def().rev())
+    }
+}
 impl Related<crate::users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -1,13 +1,20 @@
 use common::{
     project::{PartialProjectQuery, ProjectQuery, UserOrTeam},
-    Project, ProjectSortBy,
+    CursorValue, IdOrName, InvalidCursor, Page, PageCursor, Project, ProjectSortBy, PublicProject,
 };
 use sea_orm::{
-    entity::prelude::*, ConnectionTrait, FromQueryResult, Order, QueryOrder, QuerySelect,
+    entity::prelude::*,
+    sea_query::{Query, SimpleExpr},
+    ConnectionTrait, FromQueryResult, Order, PaginatorTrait, QueryOrder, QuerySelect,
 };
+use thiserror::Error;
 
 use crate::{
-    users::UserEntity, ProjectColumn, ProjectEntity, TeamEntity, TeamMemberColumn, TeamMemberEntity,
+    heartbeats::{get_total_time, project_total_seconds},
+    teams::get_team_member,
+    users::UserEntity,
+    HeartbeatColumn, LabelColumn, LabelEntity, ProjectColumn, ProjectEntity, ProjectLabelColumn,
+    ProjectLabelEntity, ProjectModel, TeamEntity, TeamMemberColumn, TeamMemberEntity,
 };
 
 pub async fn get_projects_user_has_access_to(
@@ -30,23 +37,58 @@ pub async fn get_projects_user_has_access_to(
             .or(ProjectColumn::TeamId.is_in(teams))
     };
 
-    let projects: Vec<Project> = ProjectEntity::find()
-        .filter(query)
-        .into_model()
-        .all(database)
-        .await?;
-    Ok(projects)
+    let projects = ProjectEntity::find().filter(query).all(database).await?;
+    Ok(projects.into_iter().map(Project::from).collect())
 }
 
-pub async fn query_projects<M: FromQueryResult>(
+#[derive(Debug, Error)]
+pub enum ProjectQueryError {
+    #[error(transparent)]
+    Database(#[from] DbErr),
+    /// The cursor could not be decoded or was created for another sort
+    #[error(transparent)]
+    InvalidCursor(#[from] InvalidCursor),
+}
+/// Limits the query to the items after the cursor.
+///
+/// Postgres sorts NULLs last in ascending and first in descending order.
+fn after_cursor(sort_expr: SimpleExpr, sort_order: &Order, cursor: PageCursor) -> SimpleExpr {
+    let sort = || Expr::expr(sort_expr.clone());
+    match (sort_order, cursor.value) {
+        (Order::Desc, CursorValue::Null) => sort()
+            .is_null()
+            .and(ProjectColumn::Id.lt(cursor.id))
+            .or(sort().is_not_null()),
+        (_, CursorValue::Null) => sort().is_null().and(ProjectColumn::Id.gt(cursor.id)),
+        (Order::Desc, value) => {
+            let value: sea_orm::Value = value.into();
+            sort()
+                .lt(value.clone())
+                .or(sort().eq(value).and(ProjectColumn::Id.lt(cursor.id)))
+        }
+        (_, value) => {
+            let value: sea_orm::Value = value.into();
+            sort()
+                .gt(value.clone())
+                .or(sort().eq(value).and(ProjectColumn::Id.gt(cursor.id)))
+                .or(sort().is_null())
+        }
+    }
+}
+/// Queries projects with the filters, sorting and pagination of the query.
+///
+/// Returns None if the user or team does not exist
+pub async fn query_projects(
     query: ProjectQuery,
     database: &impl ConnectionTrait,
-) -> Result<Option<Vec<M>>, DbErr> {
+) -> Result<Option<Page<Project>>, ProjectQueryError> {
     let ProjectQuery {
         owned_by,
         query_params,
+        pagination,
     } = query;
-    let base_query = match owned_by {
+    // Label names are only unique per owner. So names are resolved among the labels of the owner
+    let (base_query, label_owner) = match owned_by {
         UserOrTeam::User {
             id_or_name,
             check_teams,
@@ -64,21 +106,26 @@ pub async fn query_projects<M: FromQueryResult>(
                     .await?;
                 // TODO condense this into one query
                 if teams.is_empty() {
-                    ProjectColumn::UserId.eq(id)
+                    (ProjectColumn::UserId.eq(id), LabelColumn::UserId.eq(id))
                 } else {
-                    ProjectColumn::UserId
-                        .eq(id)
-                        .or(ProjectColumn::TeamId.is_in(teams))
+                    (
+                        ProjectColumn::UserId
+                            .eq(id)
+                            .or(ProjectColumn::TeamId.is_in(teams.clone())),
+                        LabelColumn::UserId
+                            .eq(id)
+                            .or(LabelColumn::TeamId.is_in(teams)),
+                    )
                 }
             } else {
-                ProjectColumn::UserId.eq(id)
+                (ProjectColumn::UserId.eq(id), LabelColumn::UserId.eq(id))
             }
         }
         UserOrTeam::Team(id_or_name) => {
             let Some(id) = id_or_name.get_id::<TeamEntity>(database).await? else {
                 return Ok(None);
             };
-            ProjectColumn::TeamId.eq(id)
+            (ProjectColumn::TeamId.eq(id), LabelColumn::TeamId.eq(id))
         }
     };
 
@@ -86,41 +133,300 @@ pub async fn query_projects<M: FromQueryResult>(
         language,
         name,
         sort_by,
+        public,
+        team,
+        vcs_provider,
+        label,
+        last_heartbeat_after,
+        last_heartbeat_before,
     } = query_params;
-    let sql_query = if let Some(language) = language {
-        base_query.and(ProjectColumn::Languages.contains(language))
-    } else {
-        base_query
-    };
-    let (sort_column, sort_order) = if let Some(sort_by) = sort_by {
-        match sort_by {
-            ProjectSortBy::Name(order) => (ProjectColumn::Name, order.unwrap_or_default().into()),
-            ProjectSortBy::LastUpdate(order) => {
-                (ProjectColumn::LastUpdate, order.unwrap_or_default().into())
-            }
-            ProjectSortBy::LastHeartbeat(order) => (
-                ProjectColumn::LastHeartbeat,
-                order.unwrap_or_default().into(),
-            ),
-        }
-    } else {
-        (ProjectColumn::Name, Order::Asc)
-    };
-    let sql_query = if let Some(name) = name {
-        sql_query.and(
+    let mut sql_query = base_query;
+    if let Some(language) = language {
+        sql_query = sql_query.and(ProjectColumn::Languages.contains(language));
+    }
+    if let Some(name) = name {
+        sql_query = sql_query.and(
             ProjectColumn::Name
                 .eq(name.clone())
                 .or(ProjectColumn::Renames.contains(name)),
-        )
-    } else {
-        sql_query
+        );
+    }
+    if let Some(public) = public {
+        sql_query = sql_query.and(ProjectColumn::Public.eq(public));
+    }
+    if let Some(team) = team {
+        let Some(team_id) = team.get_id::<TeamEntity>(database).await? else {
+            return Ok(None);
+        };
+        sql_query = sql_query.and(ProjectColumn::TeamId.eq(team_id));
+    }
+    if let Some(vcs_provider) = vcs_provider {
+        sql_query = sql_query.and(Expr::cust_with_values(
+            r#""projects"."version_control_ref"->>'type' = $1"#,
+            [vcs_provider.to_string()],
+        ));
+    }
+    if let Some(label) = label {
+        // A label can be named like an id. So its name is tried before the id
+        let label = match label {
+            IdOrName::Id(id) => {
+                let named: Option<i64> = LabelEntity::find()
+                    .select_only()
+                    .column(LabelColumn::Id)
+                    .filter(LabelColumn::Name.eq(id.to_string()))
+                    .filter(label_owner.clone())
+                    .into_tuple()
+                    .one(database)
+                    .await?;
+                IdOrName::Id(named.unwrap_or(id))
+            }
+            name => name,
+        };
+        let labels = match label {
+            IdOrName::Id(id) => ProjectLabelColumn::LabelId.eq(id),
+            IdOrName::Name(name) => ProjectLabelColumn::LabelId.in_subquery(
+                Query::select()
+                    .column(LabelColumn::Id)
+                    .from(LabelEntity)
+                    .and_where(LabelColumn::Name.eq(name))
+                    .and_where(label_owner)
+                    .to_owned(),
+            ),
+        };
+        sql_query = sql_query.and(
+            ProjectColumn::Id.in_subquery(
+                Query::select()
+                    .column(ProjectLabelColumn::ProjectId)
+                    .from(ProjectLabelEntity)
+                    .and_where(labels)
+                    .to_owned(),
+            ),
+        );
+    }
+    if let Some(after) = last_heartbeat_after {
+        sql_query = sql_query.and(ProjectColumn::LastHeartbeat.gte(after));
+    }
+    if let Some(before) = last_heartbeat_before {
+        sql_query = sql_query.and(ProjectColumn::LastHeartbeat.lt(before));
+    }
+
+    let sort_by = sort_by.unwrap_or(ProjectSortBy::Name(None));
+    let (sort_expr, sort_order): (SimpleExpr, Order) = match &sort_by {
+        ProjectSortBy::Name(order) => (
+            ProjectColumn::Name.into_simple_expr(),
+            order.clone().unwrap_or_default().into(),
+        ),
+        ProjectSortBy::LastUpdate(order) => (
+            ProjectColumn::LastUpdate.into_simple_expr(),
+            order.clone().unwrap_or_default().into(),
+        ),
+        ProjectSortBy::LastHeartbeat(order) => (
+            ProjectColumn::LastHeartbeat.into_simple_expr(),
+            order.clone().unwrap_or_default().into(),
+        ),
+        ProjectSortBy::TotalTime(order) => (
+            project_total_seconds(),
+            order.clone().unwrap_or_default().into(),
+        ),
     };
 
-    let projects: Vec<M> = ProjectEntity::find()
-        .order_by(sort_column, sort_order)
+    let sort_key = sort_by.cursor_key();
+    let cursor = pagination.decode_cursor_for(&sort_key)?;
+
+    let total = ProjectEntity::find()
+        .filter(sql_query.clone())
+        .count(database)
+        .await?;
+    if let Some(cursor) = cursor {
+        sql_query = sql_query.and(after_cursor(sort_expr.clone(), &sort_order, cursor));
+    }
+
+    let limit = pagination.limit();
+    let projects: Vec<ProjectModel> = ProjectEntity::find()
         .filter(sql_query)
+        .order_by(sort_expr, sort_order.clone())
+        .order_by(ProjectColumn::Id, sort_order)
+        .offset(pagination.offset())
+        .limit(limit)
+        .all(database)
+        .await?;
+
+    let next_cursor = match projects.last() {
+        Some(last) if projects.len() as u64 == limit => {
+            let value = match sort_by {
+                ProjectSortBy::Name(_) => CursorValue::Text(last.name.clone()),
+                ProjectSortBy::LastUpdate(_) => CursorValue::Time(last.last_update),
+                ProjectSortBy::LastHeartbeat(_) => CursorValue::Time(last.last_heartbeat),
+                ProjectSortBy::TotalTime(_) => CursorValue::Int(
+                    get_total_time(HeartbeatColumn::Project.eq(last.id), database).await?,
+                ),
+            };
+            Some(
+                PageCursor {
+                    sort: sort_key,
+                    id: last.id,
+                    value,
+                }
+                .encode(),
+            )
+        }
+        _ => None,
+    };
+    Ok(Some(Page {
+        items: projects.into_iter().map(Project::from).collect(),
+        total,
+        next_cursor,
+    }))
+}
+/// Checks if the user owns the project or is a member of the team that owns it.
+pub async fn does_user_have_access_to_project(
+    user: i64,
+    project: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let owner: Option<(Option<i64>, Option<i64>)> = ProjectEntity::find_by_id(project)
+        .select_only()
+        .column(ProjectColumn::UserId)
+        .column(ProjectColumn::TeamId)
+        .into_tuple()
+        .one(database)
+        .await?;
+    match owner {
+        Some((Some(user_id), _)) => Ok(user_id == user),
+        Some((None, Some(team_id))) => {
+            Ok(get_team_member(team_id, user, database).await?.is_some())
+        }
+        _ => Ok(false),
+    }
+}
+/// Checks if heartbeats of the user can be attributed to the project.
+///
+/// Same as [does_user_have_access_to_project] except that viewers of a team can not contribute to its projects.
+pub async fn can_user_contribute_to_project(
+    user: i64,
+    project: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let owner: Option<(Option<i64>, Option<i64>)> = ProjectEntity::find_by_id(project)
+        .select_only()
+        .column(ProjectColumn::UserId)
+        .column(ProjectColumn::TeamId)
+        .into_tuple()
+        .one(database)
+        .await?;
+    match owner {
+        Some((Some(user_id), _)) => Ok(user_id == user),
+        Some((None, Some(team_id))) => Ok(get_team_member(team_id, user, database)
+            .await?
+            .is_some_and(|member| member.role.contributes_time())),
+        _ => Ok(false),
+    }
+}
+/// Finds a project owned by the user by its name or one of its renames
+pub async fn get_user_project_by_name<M: FromQueryResult>(
+    user: i64,
+    name: &str,
+    database: &impl ConnectionTrait,
+) -> Result<Option<M>, DbErr> {
+    ProjectEntity::find()
+        .filter(
+            ProjectColumn::UserId.eq(user).and(
+                ProjectColumn::Name
+                    .eq(name)
+                    .or(ProjectColumn::Renames.contains(name)),
+            ),
+        )
+        .into_model()
+        .one(database)
+        .await
+}
+/// Finds a project owned by the team by its name or one of its renames
+pub async fn get_team_project_by_name<M: FromQueryResult>(
+    team: i64,
+    name: &str,
+    database: &impl ConnectionTrait,
+) -> Result<Option<M>, DbErr> {
+    ProjectEntity::find()
+        .filter(
+            ProjectColumn::TeamId.eq(team).and(
+                ProjectColumn::Name
+                    .eq(name)
+                    .or(ProjectColumn::Renames.contains(name)),
+            ),
+        )
         .into_model()
+        .one(database)
+        .await
+}
+/// Finds a project owned by the user by its id, name or one of its renames
+///
+/// A project can be named like an id. So its name is tried before the id
+pub async fn get_user_project<M: FromQueryResult>(
+    user: i64,
+    project: IdOrName,
+    database: &impl ConnectionTrait,
+) -> Result<Option<M>, DbErr> {
+    match project {
+        IdOrName::Id(id) => {
+            if let Some(project) = get_user_project_by_name(user, &id.to_string(), database).await?
+            {
+                return Ok(Some(project));
+            }
+            ProjectEntity::find()
+                .filter(ProjectColumn::Id.eq(id).and(ProjectColumn::UserId.eq(user)))
+                .into_model()
+                .one(database)
+                .await
+        }
+        IdOrName::Name(name) => get_user_project_by_name(user, &name, database).await,
+    }
+}
+/// Gets the projects owned by the user that are marked public
+pub async fn get_public_projects_for_user(
+    user: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<PublicProject>, DbErr> {
+    let projects = ProjectEntity::find()
+        .filter(
+            ProjectColumn::UserId
+                .eq(user)
+                .and(ProjectColumn::Public.eq(true)),
+        )
+        .order_by_desc(ProjectColumn::LastHeartbeat)
         .all(database)
         .await?;
-    Ok(Some(projects))
+    Ok(projects.into_iter().map(PublicProject::from).collect())
+}
+impl From<ProjectModel> for PublicProject {
+    fn from(value: ProjectModel) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            team_id: value.team_id,
+            name: value.name,
+            languages: value.languages,
+            color: value.color,
+            version_control_ref: value.version_control_ref,
+            last_heartbeat: value.last_heartbeat,
+            created: value.created,
+        }
+    }
+}
+impl From<ProjectModel> for Project {
+    fn from(value: ProjectModel) -> Self {
+        Self {
+            id: value.id,
+            user_id: value.user_id,
+            team_id: value.team_id,
+            name: value.name,
+            renames: value.renames,
+            languages: value.languages,
+            color: value.color,
+            version_control_ref: value.version_control_ref,
+            public: value.public,
+            last_heartbeat: value.last_heartbeat,
+            last_update: value.last_update,
+            created_at: value.created,
+        }
+    }
 }
@@ -1,8 +1,11 @@
+pub mod team_api_keys;
+pub mod team_invites;
 pub mod team_members;
 use common::database_helpers::{BasicTableTrait, HasNameColumn};
 use helper_macros::DatabaseHelpers;
 use sea_orm::entity::prelude::*;
 mod utils;
+pub use utils::*;
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, DatabaseHelpers)]
 #[sea_orm(table_name = "teams")]
 pub struct Model {
@@ -14,6 +17,8 @@ pub struct Model {
     /// Example: "codi-time-developers"
     #[column(name)]
     pub id_style_name: String,
+    /// The organization the team belongs to
+    pub organization_id: Option<i64>,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     #[column(created)]
     pub created: DateTimeWithTimeZone,
@@ -23,6 +28,14 @@ impl ActiveModelBehavior for ActiveModel {}
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::organizations::Entity",
+        from = "Column::OrganizationId",
+        to = "crate::organizations::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    Organization,
     #[sea_orm(has_many = "crate::teams::team_members::Entity")]
     TeamMembers,
     #[sea_orm(has_many = "crate::projects::Entity")]
@@ -31,6 +44,14 @@ pub enum Relation {
     CustomLanguages,
     #[sea_orm(has_many = "crate::custom_languages::categories::Entity")]
     CustomLanguageCategories,
+    #[sea_orm(has_many = "crate::project_rules::Entity")]
+    ProjectRules,
+    #[sea_orm(has_many = "crate::labels::Entity")]
+    Labels,
+    #[sea_orm(has_many = "crate::teams::team_invites::Entity")]
+    TeamInvites,
+    #[sea_orm(has_many = "crate::teams::team_api_keys::Entity")]
+    TeamAPIKeys,
 }
 
 impl Related<crate::teams::team_members::Entity> for Entity {
@@ -54,3 +75,28 @@ impl Related<crate::custom_languages::categories::Entity> for Entity {
         Relation::CustomLanguageCategories.def()
     }
 }
+impl Related<crate::project_rules::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectRules.def()
+    }
+}
+impl Related<crate::teams::team_invites::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamInvites.def()
+    }
+}
+impl Related<crate::teams::team_api_keys::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TeamAPIKeys.def()
+    }
+}
+impl Related<crate::labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Labels.def()
+    }
+}
+impl Related<crate::organizations::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
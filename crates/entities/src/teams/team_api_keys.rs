@@ -0,0 +1,58 @@
+use common::APITokenPermissions;
+use sea_orm::entity::prelude::*;
+
+/// An API key owned by a team instead of a user. Used by service accounts
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "team_api_keys")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub team_id: i64,
+    /// The member that created the key
+    pub created_by: i64,
+    pub name: String,
+    #[sea_orm(default_value = "")]
+    pub description: String,
+    /// Sha256 hash of the token
+    pub token: String,
+    pub permissions: Vec<APITokenPermissions>,
+    /// Key is invalid. Revoked keys are kept so the heartbeats sent with them are not lost
+    pub revoked: Option<DateTimeWithTimeZone>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::teams::Entity",
+        from = "Column::TeamId",
+        to = "crate::teams::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Team,
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::CreatedBy",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    CreatedBy,
+    #[sea_orm(has_many = "crate::heartbeats::Entity")]
+    Heartbeats,
+}
+
+impl Related<crate::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Team.def()
+    }
+}
+impl Related<crate::heartbeats::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Heartbeats.def()
+    }
+}
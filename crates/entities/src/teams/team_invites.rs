@@ -0,0 +1,64 @@
+use common::{team::TeamRole, Email};
+use sea_orm::entity::prelude::*;
+
+/// An invitation to join a team. Deleted once it is accepted, declined or revoked.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "team_invites")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub team_id: i64,
+    /// The admin that created the invite
+    pub invited_by: i64,
+    /// The invited user. None if the invite was sent to an email that is not registered yet
+    pub user_id: Option<i64>,
+    pub email: Option<Email>,
+    /// The role the invitee gets when they join
+    #[sea_orm(default_value = "Member")]
+    pub role: TeamRole,
+    /// Sha256 hash of the token
+    pub token: String,
+    pub expires: DateTimeWithTimeZone,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::teams::Entity",
+        from = "Column::TeamId",
+        to = "crate::teams::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Team,
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::InvitedBy",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    InvitedBy,
+}
+
+impl Related<crate::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Team.def()
+    }
+}
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
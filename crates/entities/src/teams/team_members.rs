@@ -1,3 +1,4 @@
+use common::team::TeamRole;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
@@ -7,7 +8,8 @@ pub struct Model {
     pub id: i64,
     pub team_id: i64,
     pub user_id: i64,
-    pub admin: bool,
+    #[sea_orm(default_value = "Member")]
+    pub role: TeamRole,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     pub created: DateTimeWithTimeZone,
 }
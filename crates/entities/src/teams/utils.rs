@@ -1,6 +1,267 @@
-use common::IdOrName;
-use sea_orm::{
-    entity::prelude::*, sea_query::SimpleExpr, ActiveValue, IntoActiveModel, QuerySelect,
+use chrono::{DateTime, FixedOffset};
+use common::{
+    team::{Team, TeamAPIToken, TeamInvite, TeamRole},
+    Email,
 };
+use sea_orm::{entity::prelude::*, PaginatorTrait, QueryOrder, QuerySelect};
 
-use crate::{TeamColumn, TeamEntity};
+use crate::{
+    TeamAPIKeyColumn, TeamAPIKeyEntity, TeamAPIKeyModel, TeamColumn, TeamEntity, TeamInviteColumn,
+    TeamInviteEntity, TeamInviteModel, TeamMemberColumn, TeamMemberEntity, TeamMemberModel,
+    TeamModel,
+};
+
+/// Gets the ids of every team the user is a member of
+pub async fn get_team_ids_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<i64>, DbErr> {
+    TeamMemberEntity::find()
+        .select_only()
+        .column(TeamMemberColumn::TeamId)
+        .filter(TeamMemberColumn::UserId.eq(user_id))
+        .into_tuple()
+        .all(database)
+        .await
+}
+/// Gets the membership of a user in a team. None if they are not a member
+pub async fn get_team_member(
+    team_id: i64,
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Option<TeamMemberModel>, DbErr> {
+    TeamMemberEntity::find()
+        .filter(
+            TeamMemberColumn::TeamId
+                .eq(team_id)
+                .and(TeamMemberColumn::UserId.eq(user_id)),
+        )
+        .one(database)
+        .await
+}
+/// Gets the teams the user is a member of along with their membership
+pub async fn get_teams_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<(TeamMemberModel, TeamModel)>, DbErr> {
+    let teams = TeamMemberEntity::find()
+        .find_also_related(TeamEntity)
+        .filter(TeamMemberColumn::UserId.eq(user_id))
+        .order_by_asc(TeamColumn::Name)
+        .all(database)
+        .await?;
+    Ok(teams
+        .into_iter()
+        .filter_map(|(member, team)| team.map(|team| (member, team)))
+        .collect())
+}
+/// Gets the members of a team. Oldest members first
+pub async fn get_team_members(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TeamMemberModel>, DbErr> {
+    TeamMemberEntity::find()
+        .filter(TeamMemberColumn::TeamId.eq(team_id))
+        .order_by_asc(TeamMemberColumn::Created)
+        .all(database)
+        .await
+}
+/// Gets the ids of every team the user contributes time to. Teams where the user is a [TeamRole::Viewer] are excluded
+pub async fn get_contributing_team_ids_for_user(
+    user_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<i64>, DbErr> {
+    TeamMemberEntity::find()
+        .select_only()
+        .column(TeamMemberColumn::TeamId)
+        .filter(
+            TeamMemberColumn::UserId
+                .eq(user_id)
+                .and(TeamMemberColumn::Role.ne(TeamRole::Viewer)),
+        )
+        .into_tuple()
+        .all(database)
+        .await
+}
+/// Counts the members of the team that have one of the roles
+pub async fn count_team_members_with_role(
+    team_id: i64,
+    roles: &[TeamRole],
+    database: &impl ConnectionTrait,
+) -> Result<u64, DbErr> {
+    TeamMemberEntity::find()
+        .filter(
+            TeamMemberColumn::TeamId
+                .eq(team_id)
+                .and(TeamMemberColumn::Role.is_in(roles.iter().copied())),
+        )
+        .count(database)
+        .await
+}
+pub async fn does_id_style_name_exist(
+    id_style_name: &str,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let count = TeamEntity::find()
+        .filter(TeamColumn::IdStyleName.eq(id_style_name))
+        .count(database)
+        .await?;
+    Ok(count > 0)
+}
+impl From<TeamModel> for Team {
+    fn from(value: TeamModel) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            id_style_name: value.id_style_name,
+            organization_id: value.organization_id,
+            created: value.created,
+        }
+    }
+}
+/// Gets the invites of a team that have not expired
+pub async fn get_pending_invites_for_team(
+    team_id: i64,
+    now: DateTime<FixedOffset>,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TeamInvite>, DbErr> {
+    TeamInviteEntity::find()
+        .filter(
+            TeamInviteColumn::TeamId
+                .eq(team_id)
+                .and(TeamInviteColumn::Expires.gt(now)),
+        )
+        .order_by_desc(TeamInviteColumn::Created)
+        .into_model()
+        .all(database)
+        .await
+}
+/// Gets the invites sent to the user that have not expired
+pub async fn get_pending_invites_for_user(
+    user_id: i64,
+    now: DateTime<FixedOffset>,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TeamInvite>, DbErr> {
+    TeamInviteEntity::find()
+        .filter(
+            TeamInviteColumn::UserId
+                .eq(user_id)
+                .and(TeamInviteColumn::Expires.gt(now)),
+        )
+        .order_by_desc(TeamInviteColumn::Created)
+        .into_model()
+        .all(database)
+        .await
+}
+/// Finds an invite by the sha256 hash of its token
+pub async fn get_invite_by_token(
+    token_hash: &str,
+    database: &impl ConnectionTrait,
+) -> Result<Option<TeamInviteModel>, DbErr> {
+    TeamInviteEntity::find()
+        .filter(TeamInviteColumn::Token.eq(token_hash))
+        .one(database)
+        .await
+}
+/// Checks if the user or email already has an invite to the team that has not expired
+pub async fn has_pending_invite(
+    team_id: i64,
+    user_id: Option<i64>,
+    email: Option<&Email>,
+    now: DateTime<FixedOffset>,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let recipient = match (user_id, email) {
+        (Some(user_id), _) => TeamInviteColumn::UserId.eq(user_id),
+        (None, Some(email)) => TeamInviteColumn::Email.eq(email.clone()),
+        (None, None) => return Ok(false),
+    };
+    let count = TeamInviteEntity::find()
+        .filter(
+            TeamInviteColumn::TeamId
+                .eq(team_id)
+                .and(TeamInviteColumn::Expires.gt(now))
+                .and(recipient),
+        )
+        .count(database)
+        .await?;
+    Ok(count > 0)
+}
+/// Assigns the invites sent to the email to the user that verified it
+pub async fn claim_invites_for_email(
+    user_id: i64,
+    email: Email,
+    database: &impl ConnectionTrait,
+) -> Result<u64, DbErr> {
+    let result = TeamInviteEntity::update_many()
+        .filter(
+            TeamInviteColumn::Email
+                .eq(email)
+                .and(TeamInviteColumn::UserId.is_null()),
+        )
+        .col_expr(TeamInviteColumn::UserId, Expr::value(user_id))
+        .exec(database)
+        .await?;
+    Ok(result.rows_affected)
+}
+impl From<TeamInviteModel> for TeamInvite {
+    fn from(value: TeamInviteModel) -> Self {
+        Self {
+            id: value.id,
+            team_id: value.team_id,
+            invited_by: value.invited_by,
+            user_id: value.user_id,
+            email: value.email,
+            role: value.role,
+            expires: value.expires,
+            created: value.created,
+        }
+    }
+}
+/// Finds a team API key that has not been revoked or expired by the sha256 hash of its token
+pub async fn get_team_api_key_by_token(
+    token_hash: &str,
+    database: &impl ConnectionTrait,
+) -> Result<Option<TeamAPIToken>, DbErr> {
+    TeamAPIKeyEntity::find()
+        .filter(
+            TeamAPIKeyColumn::Token
+                .eq(token_hash)
+                .and(TeamAPIKeyColumn::Revoked.is_null())
+                .and(
+                    TeamAPIKeyColumn::ExpiresAt
+                        .is_null()
+                        .or(TeamAPIKeyColumn::ExpiresAt.gte(chrono::Utc::now())),
+                ),
+        )
+        .into_model()
+        .one(database)
+        .await
+}
+/// Gets the API keys of a team. Including revoked keys. Newest first
+pub async fn get_team_api_keys(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<TeamAPIToken>, DbErr> {
+    TeamAPIKeyEntity::find()
+        .filter(TeamAPIKeyColumn::TeamId.eq(team_id))
+        .order_by_desc(TeamAPIKeyColumn::Created)
+        .into_model()
+        .all(database)
+        .await
+}
+impl From<TeamAPIKeyModel> for TeamAPIToken {
+    fn from(value: TeamAPIKeyModel) -> Self {
+        Self {
+            id: value.id,
+            team_id: value.team_id,
+            created_by: value.created_by,
+            name: value.name,
+            description: value.description,
+            permissions: value.permissions,
+            revoked: value.revoked,
+            expires_at: value.expires_at,
+            created: value.created,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm::entity::prelude::*;
+
+/// Issued by login when the password is correct but a second factor is required.
+/// Exchanged for a session with a TOTP or recovery code.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "login_challenges")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub user_id: i64,
+    /// Sha256 hash of the challenge token
+    pub token: String,
+    /// Wrong codes entered for this challenge
+    #[sea_orm(default_value = "0")]
+    pub failed_attempts: i32,
+    pub expires: DateTimeWithTimeZone,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
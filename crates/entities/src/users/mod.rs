@@ -1,4 +1,9 @@
+pub mod email_verifications;
+pub mod login_challenges;
+pub mod passkeys;
+pub mod password_resets;
 pub mod pub_user;
+pub mod recovery_codes;
 
 mod utils;
 use common::{
@@ -35,6 +40,20 @@ pub struct Model {
     #[serde(skip_serializing)]
     pub password: String,
     pub require_password_change: bool,
+    /// Base32 TOTP secret. Set during enrollment. Only in use once `totp_enabled_at` is set
+    #[serde(skip_serializing)]
+    pub totp_secret: Option<String>,
+    pub totp_enabled_at: Option<DateTimeWithTimeZone>,
+    /// The time step of the last accepted TOTP code. Codes of it and earlier steps are rejected
+    #[serde(skip_serializing)]
+    pub totp_last_step: Option<i64>,
+    /// Wrong two factor codes since the last accepted one
+    #[serde(skip_serializing)]
+    #[sea_orm(default_value = "0")]
+    pub two_factor_failed_attempts: i32,
+    /// Two factor codes are not checked before this time
+    #[serde(skip_serializing)]
+    pub two_factor_locked_until: Option<DateTimeWithTimeZone>,
     #[sea_orm(default_expr = "Expr::current_timestamp()")]
     pub password_changed_at: DateTimeWithTimeZone,
     #[sea_orm(default_value = "Etc/UTC")]
@@ -73,6 +92,10 @@ pub enum Relation {
     CustomLanguages,
     #[sea_orm(has_many = "crate::custom_languages::categories::Entity")]
     CustomLanguageCategories,
+    #[sea_orm(has_many = "crate::project_rules::Entity")]
+    ProjectRules,
+    #[sea_orm(has_many = "crate::labels::Entity")]
+    Labels,
 }
 
 impl Related<crate::connections::Entity> for Entity {
@@ -112,3 +135,13 @@ impl Related<crate::custom_languages::categories::Entity> for Entity {
         Relation::CustomLanguageCategories.def()
     }
 }
+impl Related<crate::project_rules::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProjectRules.def()
+    }
+}
+impl Related<crate::labels::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Labels.def()
+    }
+}
@@ -0,0 +1,39 @@
+use sea_orm::{entity::prelude::*, JsonValue};
+
+/// A WebAuthn credential. Used for passwordless login or as a second factor.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "passkeys")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = true)]
+    pub id: i64,
+    pub user_id: i64,
+    /// Chosen by the user. So they can tell their passkeys apart
+    pub name: String,
+    /// Base64 URL safe credential id. Used to find the passkey during a passwordless login
+    #[sea_orm(unique)]
+    pub credential_id: String,
+    /// The serialized `webauthn_rs::prelude::Passkey`. Updated as the signature counter changes
+    pub passkey: JsonValue,
+    pub last_used: Option<DateTimeWithTimeZone>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
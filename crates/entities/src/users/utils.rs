@@ -1,6 +1,8 @@
 use common::{user_types::group::Group, Email, IdOrName, PublicUser, User, Username};
 use sea_orm::{
-    entity::prelude::*, sea_query::SimpleExpr, ActiveValue, IntoActiveModel, QuerySelect,
+    entity::prelude::*,
+    sea_query::{Query, SimpleExpr},
+    ActiveValue, IntoActiveModel, QuerySelect,
 };
 
 pub async fn id_or_name_to_id(
@@ -22,7 +24,7 @@ pub async fn id_or_name_to_id(
     }
 }
 use super::UserModel;
-use crate::{UserColumn, UserEntity};
+use crate::{Application, ConnectionColumn, ConnectionEntity, UserColumn, UserEntity};
 pub async fn does_email_exist(
     email: Email,
     connection: &impl ConnectionTrait,
@@ -209,6 +211,20 @@ impl UserType for UserModel {
         UserEntity::find().filter(filter).all(connection).await
     }
 }
+/// True if the user has a connection to the application
+fn is_connected_to(application: Application) -> SimpleExpr {
+    Expr::exists(
+        Query::select()
+            .expr(Expr::val(1))
+            .from(ConnectionEntity)
+            .and_where(
+                Expr::col((ConnectionEntity, ConnectionColumn::UserId))
+                    .equals((UserEntity, UserColumn::Id)),
+            )
+            .and_where(Expr::col((ConnectionEntity, ConnectionColumn::Application)).eq(application))
+            .to_owned(),
+    )
+}
 impl UserType for PublicUser {
     fn id(&self) -> i64 {
         self.id
@@ -222,6 +238,10 @@ impl UserType for PublicUser {
         Self: Sized,
     {
         let user = UserEntity::find()
+            .column_as(
+                is_connected_to(Application::Github),
+                "is_connected_to_github",
+            )
             .filter(filter)
             .into_model()
             .one(connection)
@@ -237,6 +257,10 @@ impl UserType for PublicUser {
         Self: Sized,
     {
         let users = UserEntity::find()
+            .column_as(
+                is_connected_to(Application::Github),
+                "is_connected_to_github",
+            )
             .filter(filter)
             .into_model()
             .all(connection)
@@ -293,6 +317,7 @@ impl From<UserModel> for User {
             email_verified_at: value.email_verified_at,
             receive_email_notifications: value.receive_email_notifications,
             require_password_change: value.require_password_change,
+            totp_enabled_at: value.totp_enabled_at,
             password_changed_at: value.password_changed_at,
             location: value.location,
             show_on_leader_board: value.show_on_leader_board,
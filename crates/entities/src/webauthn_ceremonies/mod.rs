@@ -0,0 +1,37 @@
+//! The server side state of a WebAuthn ceremony. Kept between the start and finish requests
+use sea_orm::{entity::prelude::*, JsonValue};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "webauthn_ceremonies")]
+pub struct Model {
+    /// The key the client finishes the ceremony with
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub key: String,
+    /// The user registering a passkey. None for passwordless logins
+    pub user_id: Option<i64>,
+    /// The serialized ceremony state
+    pub state: JsonValue,
+    pub expires: DateTimeWithTimeZone,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    pub created: DateTimeWithTimeZone,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::users::Entity",
+        from = "Column::UserId",
+        to = "crate::users::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<crate::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
@@ -2,6 +2,23 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20230822_185310_init;
 mod m20231204_154044_create_table;
+mod m20231220_181201_project_rules;
+mod m20231222_140512_labels;
+mod m20231227_093418_project_budgets;
+mod m20240103_171205_team_invites;
+mod m20240108_102733_team_roles;
+mod m20240112_153047_team_api_keys;
+mod m20240116_110524_organizations;
+mod m20240119_094212_api_key_usage;
+mod m20240123_141507_api_key_allowed_ips;
+mod m20240125_103822_cli_access_requests;
+mod m20240129_160341_email_verifications;
+mod m20240131_092817_password_resets;
+mod m20240202_134455_two_factor;
+mod m20240206_101533_passkeys;
+mod m20240209_151202_oauth;
+mod m20240212_103045_two_factor_lockout;
+mod m20240214_090512_api_key_daily_requests;
 pub mod utils;
 pub struct Migrator;
 
@@ -11,6 +28,23 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20230822_185310_init::Migration),
             Box::new(m20231204_154044_create_table::Migration),
+            Box::new(m20231220_181201_project_rules::Migration),
+            Box::new(m20231222_140512_labels::Migration),
+            Box::new(m20231227_093418_project_budgets::Migration),
+            Box::new(m20240103_171205_team_invites::Migration),
+            Box::new(m20240108_102733_team_roles::Migration),
+            Box::new(m20240112_153047_team_api_keys::Migration),
+            Box::new(m20240116_110524_organizations::Migration),
+            Box::new(m20240119_094212_api_key_usage::Migration),
+            Box::new(m20240123_141507_api_key_allowed_ips::Migration),
+            Box::new(m20240125_103822_cli_access_requests::Migration),
+            Box::new(m20240129_160341_email_verifications::Migration),
+            Box::new(m20240131_092817_password_resets::Migration),
+            Box::new(m20240202_134455_two_factor::Migration),
+            Box::new(m20240206_101533_passkeys::Migration),
+            Box::new(m20240209_151202_oauth::Migration),
+            Box::new(m20240212_103045_two_factor_lockout::Migration),
+            Box::new(m20240214_090512_api_key_daily_requests::Migration),
         ]
     }
 }
@@ -1,4 +1,17 @@
-use sea_orm_migration::{prelude::*, sea_orm::Schema};
+//! The tables as they were when this migration was written.
+//!
+//! Columns are listed explicitly instead of being built from the entities.
+//! Later migrations change these tables and would fail on a fresh database if the current entities were used.
+use common::user_types::{bio::Bio, preferences::Preferences};
+use entities::{
+    APIKeyColumn, APIKeyEntity, AvatarColumn, AvatarEntity, ConnectionColumn, ConnectionEntity,
+    HeartbeatColumn, HeartbeatEntity, LanguageCategoryColumn, LanguageCategoryEntity,
+    LanguageColumn, LanguageEntity, ProjectColumn, ProjectEntity, TeamColumn, TeamEntity,
+    TeamMemberColumn, TeamMemberEntity, UserColumn, UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id, string_array};
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -6,22 +19,394 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let schema = Schema::new(manager.get_database_backend());
         // TODO User needs a custom create for the `ignoreCase` collation
-        crate::utils::entities!(
-            schema,
-            manager,
-            entities::UserEntity,
-            entities::AvatarEntity,
-            entities::ConnectionEntity,
-            entities::APIKeyEntity,
-            entities::TeamEntity,
-            entities::TeamMemberEntity,
-            entities::ProjectEntity,
-            entities::HeartbeatEntity,
-            entities::LanguageEntity,
-            entities::LanguageCategoryEntity
-        );
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserEntity)
+                    .col(&mut id(UserColumn::Id))
+                    .col(
+                        ColumnDef::new(UserColumn::Name)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(ColumnDef::new(UserColumn::Username).text().not_null())
+                    .col(
+                        ColumnDef::new(UserColumn::Bio)
+                            .json()
+                            .not_null()
+                            .default(Bio::default()),
+                    )
+                    .col(ColumnDef::new(UserColumn::Email).text().not_null())
+                    .col(
+                        ColumnDef::new(UserColumn::EmailVerifiedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(UserColumn::Group).text().not_null())
+                    .col(
+                        ColumnDef::new(UserColumn::ReceiveEmailNotifications)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UserColumn::Password).string().not_null())
+                    .col(
+                        ColumnDef::new(UserColumn::RequirePasswordChange)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(UserColumn::PasswordChangedAt))
+                    .col(
+                        ColumnDef::new(UserColumn::Location)
+                            .text()
+                            .not_null()
+                            .default("Etc/UTC"),
+                    )
+                    .col(
+                        ColumnDef::new(UserColumn::ShowOnLeaderBoard)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(UserColumn::ReportInterval)
+                            .array(ColumnType::Text)
+                            .not_null()
+                            .default("{}"),
+                    )
+                    .col(
+                        ColumnDef::new(UserColumn::Preferences)
+                            .json()
+                            .not_null()
+                            .default(Preferences::default()),
+                    )
+                    .col(
+                        ColumnDef::new(UserColumn::Banned)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(&mut current_timestamp(UserColumn::LastLoggedIn))
+                    .col(&mut current_timestamp(UserColumn::Created))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(AvatarEntity)
+                    .col(&mut id(AvatarColumn::Id))
+                    .col(
+                        ColumnDef::new(AvatarColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AvatarColumn::Hash).string().not_null())
+                    .col(ColumnDef::new(AvatarColumn::Source).json().not_null())
+                    .col(&mut current_timestamp(AvatarColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        AvatarEntity,
+                        AvatarColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConnectionEntity)
+                    .col(&mut id(ConnectionColumn::Id))
+                    .col(
+                        ColumnDef::new(ConnectionColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ConnectionColumn::OtherData).json().null())
+                    .col(
+                        ColumnDef::new(ConnectionColumn::OtherDataPrivate)
+                            .json()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ConnectionColumn::Application)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ConnectionColumn::Token).string().not_null())
+                    .col(
+                        ColumnDef::new(ConnectionColumn::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(&mut current_timestamp(ConnectionColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        ConnectionEntity,
+                        ConnectionColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(APIKeyEntity)
+                    .col(&mut id(APIKeyColumn::Id))
+                    .col(
+                        ColumnDef::new(APIKeyColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(APIKeyColumn::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(APIKeyColumn::Description)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(ColumnDef::new(APIKeyColumn::Token).string().not_null())
+                    .col(
+                        ColumnDef::new(APIKeyColumn::Permissions)
+                            .array(ColumnType::Text)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(APIKeyColumn::FromCli).json().null())
+                    .col(
+                        ColumnDef::new(APIKeyColumn::Revoked)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(APIKeyColumn::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(&mut current_timestamp(APIKeyColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        APIKeyEntity,
+                        APIKeyColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamEntity)
+                    .col(&mut id(TeamColumn::Id))
+                    .col(ColumnDef::new(TeamColumn::Name).string().not_null())
+                    .col(ColumnDef::new(TeamColumn::IdStyleName).string().not_null())
+                    .col(&mut current_timestamp(TeamColumn::Created))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamMemberEntity)
+                    .col(&mut id(TeamMemberColumn::Id))
+                    .col(
+                        ColumnDef::new(TeamMemberColumn::TeamId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamMemberColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    // Replaced by the role column in m20240108_102733_team_roles
+                    .col(ColumnDef::new(Alias::new("admin")).boolean().not_null())
+                    .col(&mut current_timestamp(TeamMemberColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        TeamMemberEntity,
+                        TeamMemberColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        TeamMemberEntity,
+                        TeamMemberColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectEntity)
+                    .col(&mut id(ProjectColumn::Id))
+                    .col(ColumnDef::new(ProjectColumn::UserId).big_integer().null())
+                    .col(ColumnDef::new(ProjectColumn::TeamId).big_integer().null())
+                    .col(ColumnDef::new(ProjectColumn::Name).string().not_null())
+                    .col(&mut string_array(ProjectColumn::Renames))
+                    .col(&mut string_array(ProjectColumn::Languages))
+                    .col(ColumnDef::new(ProjectColumn::Color).string().null())
+                    .col(
+                        ColumnDef::new(ProjectColumn::VersionControlRef)
+                            .json()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ProjectColumn::Public).boolean().not_null())
+                    .col(&mut current_timestamp(ProjectColumn::LastHeartbeat))
+                    .col(&mut current_timestamp(ProjectColumn::LastUpdate))
+                    .col(&mut current_timestamp(ProjectColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        ProjectEntity,
+                        ProjectColumn::UserId,
+                        UserEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        ProjectEntity,
+                        ProjectColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(HeartbeatEntity)
+                    .col(&mut id(HeartbeatColumn::Id))
+                    // Made nullable in m20240112_153047_team_api_keys
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(HeartbeatColumn::Entity).string().not_null())
+                    .col(ColumnDef::new(HeartbeatColumn::Type).text().not_null())
+                    .col(ColumnDef::new(HeartbeatColumn::Category).text().not_null())
+                    .col(ColumnDef::new(HeartbeatColumn::CodeChange).json().null())
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::Project)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(HeartbeatColumn::Branch).string().null())
+                    .col(ColumnDef::new(HeartbeatColumn::Language).string().null())
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::IsWrite)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(HeartbeatColumn::Editor).string().null())
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::OperatingSystem)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::MachineNameId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(HeartbeatColumn::UserAgent)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(HeartbeatColumn::StartTime))
+                    .col(&mut current_timestamp(HeartbeatColumn::EndTime))
+                    .col(ColumnDef::new(HeartbeatColumn::Closed).boolean().not_null())
+                    .col(&mut current_timestamp(HeartbeatColumn::CreatedAt))
+                    .foreign_key(&mut foreign_key(
+                        HeartbeatEntity,
+                        HeartbeatColumn::UserId,
+                        UserEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        HeartbeatEntity,
+                        HeartbeatColumn::Project,
+                        ProjectEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(LanguageEntity)
+                    .col(&mut id(LanguageColumn::Id))
+                    .col(ColumnDef::new(LanguageColumn::UserId).big_integer().null())
+                    .col(ColumnDef::new(LanguageColumn::TeamId).big_integer().null())
+                    .col(ColumnDef::new(LanguageColumn::Name).string().not_null())
+                    .col(ColumnDef::new(LanguageColumn::Color).string().null())
+                    .col(&mut string_array(LanguageColumn::Aliases))
+                    .col(&mut string_array(LanguageColumn::Categories))
+                    .col(&mut string_array(LanguageColumn::Extensions))
+                    .col(&mut string_array(LanguageColumn::FileNames))
+                    .col(
+                        ColumnDef::new(LanguageColumn::Source)
+                            .text()
+                            .not_null()
+                            .default("FromAdmin"),
+                    )
+                    .col(&mut current_timestamp(LanguageColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        LanguageEntity,
+                        LanguageColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        LanguageEntity,
+                        LanguageColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(LanguageCategoryEntity)
+                    .col(&mut id(LanguageCategoryColumn::Id))
+                    .col(
+                        ColumnDef::new(LanguageCategoryColumn::UserId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(LanguageCategoryColumn::TeamId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(LanguageCategoryColumn::Name)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LanguageCategoryColumn::Description)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(LanguageCategoryColumn::Source)
+                            .text()
+                            .not_null()
+                            .default("FromAdmin"),
+                    )
+                    .col(&mut current_timestamp(LanguageCategoryColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        LanguageCategoryEntity,
+                        LanguageCategoryColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        LanguageCategoryEntity,
+                        LanguageCategoryColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
         Ok(())
     }
 
@@ -0,0 +1,87 @@
+//! Columns are listed explicitly. See [crate::m20231204_154044_create_table]
+use entities::{
+    ProjectRuleColumn, ProjectRuleEntity, TeamColumn, TeamEntity, UserColumn, UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectRuleEntity)
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::UserId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::TeamId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(ProjectRuleColumn::Name).string().not_null())
+                    .col(ColumnDef::new(ProjectRuleColumn::Field).text().not_null())
+                    .col(ColumnDef::new(ProjectRuleColumn::Pattern).json().not_null())
+                    .col(ColumnDef::new(ProjectRuleColumn::Action).json().not_null())
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::Priority)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectRuleColumn::Created)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-project_rules-user_id")
+                            .from(ProjectRuleEntity, ProjectRuleColumn::UserId)
+                            .to(UserEntity, UserColumn::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-project_rules-team_id")
+                            .from(ProjectRuleEntity, ProjectRuleColumn::TeamId)
+                            .to(TeamEntity, TeamColumn::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::ProjectRuleEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
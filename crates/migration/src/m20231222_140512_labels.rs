@@ -0,0 +1,102 @@
+use entities::{
+    LabelColumn, LabelEntity, ProjectEntity, ProjectLabelColumn, ProjectLabelEntity, TeamEntity,
+    UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const PROJECT_LABEL_INDEX: &str = "project_labels_project_id_label_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LabelEntity)
+                    .col(&mut id(LabelColumn::Id))
+                    .col(ColumnDef::new(LabelColumn::UserId).big_integer().null())
+                    .col(ColumnDef::new(LabelColumn::TeamId).big_integer().null())
+                    .col(ColumnDef::new(LabelColumn::Name).string().not_null())
+                    .col(ColumnDef::new(LabelColumn::Color).string().not_null())
+                    .col(&mut current_timestamp(LabelColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        LabelEntity,
+                        LabelColumn::UserId,
+                        UserEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        LabelEntity,
+                        LabelColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectLabelEntity)
+                    .col(&mut id(ProjectLabelColumn::Id))
+                    .col(
+                        ColumnDef::new(ProjectLabelColumn::ProjectId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectLabelColumn::LabelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(ProjectLabelColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        ProjectLabelEntity,
+                        ProjectLabelColumn::ProjectId,
+                        ProjectEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        ProjectLabelEntity,
+                        ProjectLabelColumn::LabelId,
+                        LabelEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        // A label is attached to a project once
+        manager
+            .create_index(
+                Index::create()
+                    .name(PROJECT_LABEL_INDEX)
+                    .table(ProjectLabelEntity)
+                    .col(ProjectLabelColumn::ProjectId)
+                    .col(ProjectLabelColumn::LabelId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::ProjectLabelEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::LabelEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
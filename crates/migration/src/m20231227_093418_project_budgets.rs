@@ -0,0 +1,120 @@
+use entities::{
+    BudgetEventColumn, BudgetEventEntity, ProjectBudgetColumn, ProjectBudgetEntity, ProjectEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProjectBudgetEntity)
+                    .col(&mut id(ProjectBudgetColumn::Id))
+                    .col(
+                        ColumnDef::new(ProjectBudgetColumn::ProjectId)
+                            .big_integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectBudgetColumn::Period)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectBudgetColumn::Seconds)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProjectBudgetColumn::Thresholds)
+                            .array(ColumnType::Integer)
+                            .not_null()
+                            .default("{80,100}"),
+                    )
+                    .col(&mut current_timestamp(ProjectBudgetColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        ProjectBudgetEntity,
+                        ProjectBudgetColumn::ProjectId,
+                        ProjectEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(BudgetEventEntity)
+                    .col(&mut id(BudgetEventColumn::Id))
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::BudgetId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::ProjectId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::Threshold)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::PeriodStart)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::UsedSeconds)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetEventColumn::BudgetSeconds)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(BudgetEventColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        BudgetEventEntity,
+                        BudgetEventColumn::BudgetId,
+                        ProjectBudgetEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        BudgetEventEntity,
+                        BudgetEventColumn::ProjectId,
+                        ProjectEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::BudgetEventEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::ProjectBudgetEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
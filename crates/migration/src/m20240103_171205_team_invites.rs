@@ -0,0 +1,71 @@
+use entities::{TeamEntity, TeamInviteColumn, TeamInviteEntity, UserEntity};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamInviteEntity)
+                    .col(&mut id(TeamInviteColumn::Id))
+                    .col(
+                        ColumnDef::new(TeamInviteColumn::TeamId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamInviteColumn::InvitedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamInviteColumn::UserId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(TeamInviteColumn::Email).text().null())
+                    .col(ColumnDef::new(TeamInviteColumn::Token).string().not_null())
+                    .col(
+                        ColumnDef::new(TeamInviteColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(TeamInviteColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        TeamInviteEntity,
+                        TeamInviteColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        TeamInviteEntity,
+                        TeamInviteColumn::UserId,
+                        UserEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        TeamInviteEntity,
+                        TeamInviteColumn::InvitedBy,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::TeamInviteEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
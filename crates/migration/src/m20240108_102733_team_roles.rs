@@ -0,0 +1,96 @@
+use entities::{TeamInviteColumn, TeamInviteEntity, TeamMemberColumn, TeamMemberEntity};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+/// The boolean column replaced by [TeamMemberColumn::Role]
+fn admin_column() -> Alias {
+    Alias::new("admin")
+}
+fn role_column<C: IntoIden>(column: C) -> ColumnDef {
+    ColumnDef::new(column)
+        .text()
+        .not_null()
+        .default("Member")
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamMemberEntity)
+                    .add_column(role_column(TeamMemberColumn::Role))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(TeamMemberEntity)
+                    .value(TeamMemberColumn::Role, "Admin")
+                    .and_where(Expr::col(admin_column()).eq(true))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamMemberEntity)
+                    .drop_column(admin_column())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamInviteEntity)
+                    .add_column(role_column(TeamInviteColumn::Role))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamInviteEntity)
+                    .drop_column(TeamInviteColumn::Role)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamMemberEntity)
+                    .add_column(
+                        ColumnDef::new(admin_column())
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(TeamMemberEntity)
+                    .value(admin_column(), true)
+                    .and_where(Expr::col(TeamMemberColumn::Role).is_in(["Owner", "Admin"]))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TeamMemberEntity)
+                    .drop_column(TeamMemberColumn::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
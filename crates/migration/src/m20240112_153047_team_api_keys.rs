@@ -0,0 +1,132 @@
+use entities::{
+    HeartbeatColumn, HeartbeatEntity, TeamAPIKeyColumn, TeamAPIKeyEntity, TeamEntity, UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+const FOREIGN_KEY: &str = "fk-heartbeats-team_api_key_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamAPIKeyEntity)
+                    .col(&mut id(TeamAPIKeyColumn::Id))
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::TeamId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::CreatedBy)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(TeamAPIKeyColumn::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::Description)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .col(ColumnDef::new(TeamAPIKeyColumn::Token).string().not_null())
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::Permissions)
+                            .array(ColumnType::Text)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::Revoked)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(TeamAPIKeyColumn::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(&mut current_timestamp(TeamAPIKeyColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        TeamAPIKeyEntity,
+                        TeamAPIKeyColumn::TeamId,
+                        TeamEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        TeamAPIKeyEntity,
+                        TeamAPIKeyColumn::CreatedBy,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        // Heartbeats of service accounts do not belong to a user
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(HeartbeatEntity)
+                    .modify_column(ColumnDef::new(HeartbeatColumn::UserId).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(HeartbeatEntity)
+                    .add_column(
+                        ColumnDef::new(HeartbeatColumn::TeamApiKeyId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name(FOREIGN_KEY)
+                            .from_tbl(HeartbeatEntity)
+                            .from_col(HeartbeatColumn::TeamApiKeyId)
+                            .to_tbl(TeamAPIKeyEntity)
+                            .to_col(TeamAPIKeyColumn::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(HeartbeatEntity)
+                    .and_where(Expr::col(HeartbeatColumn::UserId).is_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(HeartbeatEntity)
+                    .drop_foreign_key(Alias::new(FOREIGN_KEY))
+                    .drop_column(HeartbeatColumn::TeamApiKeyId)
+                    .modify_column(
+                        ColumnDef::new(HeartbeatColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::TeamAPIKeyEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,179 @@
+use entities::{
+    LanguageCategoryColumn, LanguageCategoryEntity, LanguageColumn, LanguageEntity,
+    OrganizationAdminColumn, OrganizationAdminEntity, OrganizationColumn, OrganizationEntity,
+    ProjectRuleColumn, ProjectRuleEntity, TeamColumn, TeamEntity, UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+const TEAMS_FOREIGN_KEY: &str = "fk-teams-organization_id";
+const LANGUAGES_FOREIGN_KEY: &str = "fk-languages-organization_id";
+const CATEGORIES_FOREIGN_KEY: &str = "fk-categories-organization_id";
+const PROJECT_RULES_FOREIGN_KEY: &str = "fk-project_rules-organization_id";
+
+/// Adds the nullable organization_id column to a table
+fn add_organization_column<T: Iden + Copy + 'static, C: Iden + Copy + 'static>(
+    table: T,
+    column: C,
+    foreign_key: &str,
+    on_delete: ForeignKeyAction,
+) -> TableAlterStatement {
+    Table::alter()
+        .table(table)
+        .add_column(ColumnDef::new(column).big_integer().null())
+        .add_foreign_key(
+            TableForeignKey::new()
+                .name(foreign_key)
+                .from_tbl(table)
+                .from_col(column)
+                .to_tbl(OrganizationEntity)
+                .to_col(OrganizationColumn::Id)
+                .on_update(ForeignKeyAction::Cascade)
+                .on_delete(on_delete),
+        )
+        .to_owned()
+}
+fn drop_organization_column<T: Iden + 'static, C: Iden + 'static>(
+    table: T,
+    column: C,
+    foreign_key: &str,
+) -> TableAlterStatement {
+    Table::alter()
+        .table(table)
+        .drop_foreign_key(Alias::new(foreign_key))
+        .drop_column(column)
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrganizationEntity)
+                    .col(&mut id(OrganizationColumn::Id))
+                    .col(ColumnDef::new(OrganizationColumn::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(OrganizationColumn::IdStyleName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(OrganizationColumn::Created))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrganizationAdminEntity)
+                    .col(&mut id(OrganizationAdminColumn::Id))
+                    .col(
+                        ColumnDef::new(OrganizationAdminColumn::OrganizationId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrganizationAdminColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(OrganizationAdminColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        OrganizationAdminEntity,
+                        OrganizationAdminColumn::OrganizationId,
+                        OrganizationEntity,
+                    ))
+                    .foreign_key(&mut foreign_key(
+                        OrganizationAdminEntity,
+                        OrganizationAdminColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        // Teams are kept when their organization is deleted
+        manager
+            .alter_table(add_organization_column(
+                TeamEntity,
+                TeamColumn::OrganizationId,
+                TEAMS_FOREIGN_KEY,
+                ForeignKeyAction::SetNull,
+            ))
+            .await?;
+        manager
+            .alter_table(add_organization_column(
+                LanguageEntity,
+                LanguageColumn::OrganizationId,
+                LANGUAGES_FOREIGN_KEY,
+                ForeignKeyAction::Cascade,
+            ))
+            .await?;
+        manager
+            .alter_table(add_organization_column(
+                LanguageCategoryEntity,
+                LanguageCategoryColumn::OrganizationId,
+                CATEGORIES_FOREIGN_KEY,
+                ForeignKeyAction::Cascade,
+            ))
+            .await?;
+        manager
+            .alter_table(add_organization_column(
+                ProjectRuleEntity,
+                ProjectRuleColumn::OrganizationId,
+                PROJECT_RULES_FOREIGN_KEY,
+                ForeignKeyAction::Cascade,
+            ))
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(drop_organization_column(
+                ProjectRuleEntity,
+                ProjectRuleColumn::OrganizationId,
+                PROJECT_RULES_FOREIGN_KEY,
+            ))
+            .await?;
+        manager
+            .alter_table(drop_organization_column(
+                LanguageCategoryEntity,
+                LanguageCategoryColumn::OrganizationId,
+                CATEGORIES_FOREIGN_KEY,
+            ))
+            .await?;
+        manager
+            .alter_table(drop_organization_column(
+                LanguageEntity,
+                LanguageColumn::OrganizationId,
+                LANGUAGES_FOREIGN_KEY,
+            ))
+            .await?;
+        manager
+            .alter_table(drop_organization_column(
+                TeamEntity,
+                TeamColumn::OrganizationId,
+                TEAMS_FOREIGN_KEY,
+            ))
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::OrganizationAdminEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::OrganizationEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,45 @@
+use entities::{APIKeyColumn, APIKeyEntity};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(APIKeyEntity)
+                    .add_column(
+                        ColumnDef::new(APIKeyColumn::LastUsedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .add_column(ColumnDef::new(APIKeyColumn::LastIp).text().null())
+                    .add_column(ColumnDef::new(APIKeyColumn::LastUserAgent).text().null())
+                    .add_column(
+                        ColumnDef::new(APIKeyColumn::RequestCount)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(APIKeyEntity)
+                    .drop_column(APIKeyColumn::LastUsedAt)
+                    .drop_column(APIKeyColumn::LastIp)
+                    .drop_column(APIKeyColumn::LastUserAgent)
+                    .drop_column(APIKeyColumn::RequestCount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,35 @@
+use entities::{APIKeyColumn, APIKeyEntity};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(APIKeyEntity)
+                    .add_column(
+                        ColumnDef::new(APIKeyColumn::AllowedIps)
+                            .array(ColumnType::Text)
+                            .not_null()
+                            .default(Expr::cust("'{}'")),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(APIKeyEntity)
+                    .drop_column(APIKeyColumn::AllowedIps)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,83 @@
+use entities::{APIKeyEntity, CLIAccessRequestColumn, CLIAccessRequestEntity};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::foreign_key;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CLIAccessRequestEntity)
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::FromCli)
+                            .json()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::Username)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::IpAddress)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::State)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::ApiKeyId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::Token)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CLIAccessRequestColumn::CompletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(&mut foreign_key(
+                        CLIAccessRequestEntity,
+                        CLIAccessRequestColumn::ApiKeyId,
+                        APIKeyEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::CLIAccessRequestEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
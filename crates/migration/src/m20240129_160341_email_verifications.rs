@@ -0,0 +1,59 @@
+use entities::{EmailVerificationColumn, EmailVerificationEntity, UserEntity};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailVerificationEntity)
+                    .col(&mut id(EmailVerificationColumn::Id))
+                    .col(
+                        ColumnDef::new(EmailVerificationColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerificationColumn::Email)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerificationColumn::Token)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EmailVerificationColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(EmailVerificationColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        EmailVerificationEntity,
+                        EmailVerificationColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::EmailVerificationEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
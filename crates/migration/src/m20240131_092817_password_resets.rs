@@ -0,0 +1,54 @@
+use entities::{PasswordResetColumn, PasswordResetEntity, UserEntity};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasswordResetEntity)
+                    .col(&mut id(PasswordResetColumn::Id))
+                    .col(
+                        ColumnDef::new(PasswordResetColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasswordResetColumn::Token)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PasswordResetColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(PasswordResetColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        PasswordResetEntity,
+                        PasswordResetColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::PasswordResetEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
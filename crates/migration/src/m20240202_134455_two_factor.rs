@@ -0,0 +1,106 @@
+use entities::{
+    LoginChallengeColumn, LoginChallengeEntity, RecoveryCodeColumn, RecoveryCodeEntity, UserColumn,
+    UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserEntity)
+                    .add_column(ColumnDef::new(UserColumn::TotpSecret).text().null())
+                    .add_column(
+                        ColumnDef::new(UserColumn::TotpEnabledAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCodeEntity)
+                    .col(&mut id(RecoveryCodeColumn::Id))
+                    .col(
+                        ColumnDef::new(RecoveryCodeColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecoveryCodeColumn::Code).string().not_null())
+                    .col(&mut current_timestamp(RecoveryCodeColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        RecoveryCodeEntity,
+                        RecoveryCodeColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoginChallengeEntity)
+                    .col(&mut id(LoginChallengeColumn::Id))
+                    .col(
+                        ColumnDef::new(LoginChallengeColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginChallengeColumn::Token)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(LoginChallengeColumn::FailedAttempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(LoginChallengeColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(LoginChallengeColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        LoginChallengeEntity,
+                        LoginChallengeColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::LoginChallengeEntity)
+                    .table(entities::RecoveryCodeEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserEntity)
+                    .drop_column(UserColumn::TotpSecret)
+                    .drop_column(UserColumn::TotpEnabledAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,94 @@
+use entities::{
+    PasskeyColumn, PasskeyEntity, UserEntity, WebAuthnCeremonyColumn, WebAuthnCeremonyEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key, id};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PasskeyEntity)
+                    .col(&mut id(PasskeyColumn::Id))
+                    .col(
+                        ColumnDef::new(PasskeyColumn::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PasskeyColumn::Name).string().not_null())
+                    .col(
+                        ColumnDef::new(PasskeyColumn::CredentialId)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(PasskeyColumn::Passkey).json().not_null())
+                    .col(
+                        ColumnDef::new(PasskeyColumn::LastUsed)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(&mut current_timestamp(PasskeyColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        PasskeyEntity,
+                        PasskeyColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebAuthnCeremonyEntity)
+                    .col(
+                        ColumnDef::new(WebAuthnCeremonyColumn::Key)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebAuthnCeremonyColumn::UserId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebAuthnCeremonyColumn::State)
+                            .json()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebAuthnCeremonyColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(WebAuthnCeremonyColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        WebAuthnCeremonyEntity,
+                        WebAuthnCeremonyColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::WebAuthnCeremonyEntity)
+                    .table(entities::PasskeyEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,107 @@
+use entities::{
+    ConnectionColumn, ConnectionEntity, OAuthStateColumn, OAuthStateEntity, UserEntity,
+};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::{current_timestamp, foreign_key};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const ACCOUNT_INDEX: &str = "connections_application_provider_account_id";
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionEntity)
+                    .add_column(ColumnDef::new(ConnectionColumn::Provider).text().null())
+                    .add_column(ColumnDef::new(ConnectionColumn::AccountId).text().null())
+                    .to_owned(),
+            )
+            .await?;
+        // An external account can only be linked to one user
+        manager
+            .create_index(
+                Index::create()
+                    .name(ACCOUNT_INDEX)
+                    .table(ConnectionEntity)
+                    .col(ConnectionColumn::Application)
+                    .col(ConnectionColumn::Provider)
+                    .col(ConnectionColumn::AccountId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(OAuthStateEntity)
+                    .col(
+                        ColumnDef::new(OAuthStateColumn::State)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OAuthStateColumn::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OAuthStateColumn::CodeVerifier)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OAuthStateColumn::Nonce).string().null())
+                    .col(
+                        ColumnDef::new(OAuthStateColumn::UserId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(OAuthStateColumn::Expires)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(&mut current_timestamp(OAuthStateColumn::Created))
+                    .foreign_key(&mut foreign_key(
+                        OAuthStateEntity,
+                        OAuthStateColumn::UserId,
+                        UserEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::OAuthStateEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(ACCOUNT_INDEX)
+                    .table(ConnectionEntity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionEntity)
+                    .drop_column(ConnectionColumn::Provider)
+                    .drop_column(ConnectionColumn::AccountId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use entities::{UserColumn, UserEntity};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserEntity)
+                    .add_column(
+                        ColumnDef::new(UserColumn::TotpLastStep)
+                            .big_integer()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserColumn::TwoFactorFailedAttempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(UserColumn::TwoFactorLockedUntil)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UserEntity)
+                    .drop_column(UserColumn::TotpLastStep)
+                    .drop_column(UserColumn::TwoFactorFailedAttempts)
+                    .drop_column(UserColumn::TwoFactorLockedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
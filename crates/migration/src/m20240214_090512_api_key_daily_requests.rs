@@ -0,0 +1,57 @@
+use entities::{APIKeyDailyRequestsColumn, APIKeyDailyRequestsEntity, APIKeyEntity};
+use sea_orm_migration::prelude::*;
+
+use crate::utils::foreign_key;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(APIKeyDailyRequestsEntity)
+                    .col(
+                        ColumnDef::new(APIKeyDailyRequestsColumn::ApiKeyId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(APIKeyDailyRequestsColumn::Day)
+                            .date()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(APIKeyDailyRequestsColumn::Requests)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(APIKeyDailyRequestsColumn::ApiKeyId)
+                            .col(APIKeyDailyRequestsColumn::Day),
+                    )
+                    .foreign_key(&mut foreign_key(
+                        APIKeyDailyRequestsEntity,
+                        APIKeyDailyRequestsColumn::ApiKeyId,
+                        APIKeyEntity,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entities::APIKeyDailyRequestsEntity)
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
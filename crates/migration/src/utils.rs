@@ -1,11 +1,46 @@
-macro_rules! entities {
-    ($schema:ident,$manager:ident, $($entity_type:path),*) => {
-        $(
-        {
-            let statement = $schema.create_table_from_entity($entity_type);
-            $manager.create_table(statement).await?;
-        }
-        )*
-    };
-}
-pub(crate) use entities;
+//! Column definitions shared by the migrations.
+//!
+//! Tables are created from explicit columns instead of the entities, so a fresh database ends with the same schema as an upgraded one.
+use sea_orm_migration::prelude::*;
+
+/// Auto incrementing big integer primary key
+pub(crate) fn id<C: IntoIden>(column: C) -> ColumnDef {
+    ColumnDef::new(column)
+        .big_integer()
+        .not_null()
+        .auto_increment()
+        .primary_key()
+        .to_owned()
+}
+/// Timestamp that defaults to the time the row was inserted
+pub(crate) fn current_timestamp<C: IntoIden>(column: C) -> ColumnDef {
+    ColumnDef::new(column)
+        .timestamp_with_time_zone()
+        .not_null()
+        .default(Expr::current_timestamp())
+        .to_owned()
+}
+/// Text array that defaults to an empty array
+pub(crate) fn string_array<C: IntoIden>(column: C) -> ColumnDef {
+    ColumnDef::new(column)
+        .array(ColumnType::String(None))
+        .not_null()
+        .default("{}")
+        .to_owned()
+}
+/// Foreign key to the id of another table. Named `fk-{table}-{column}`
+pub(crate) fn foreign_key<T, C, R>(table: T, column: C, to_table: R) -> ForeignKeyCreateStatement
+where
+    T: Iden + 'static,
+    C: Iden + 'static,
+    R: Iden + 'static,
+{
+    let name = format!("fk-{}-{}", table.to_string(), column.to_string());
+    ForeignKey::create()
+        .name(name)
+        .from(table, column)
+        .to(to_table, Alias::new("id"))
+        .on_update(ForeignKeyAction::Cascade)
+        .on_delete(ForeignKeyAction::Cascade)
+        .to_owned()
+}
@@ -0,0 +1,214 @@
+//! API Key Usage Tracking
+//!
+//! Updating the key on every request would slow down authentication.
+//! So usage is collected in memory and written to the database every [FLUSH_INTERVAL].
+//! Usage that could not be written is kept for the next flush.
+//! Usage that was not flushed before the server stops is lost.
+//!
+//! Besides the lifetime total on the key, requests are counted per UTC day in `api_key_daily_requests`.
+//! The key listing sums the recent days. Days older than [MAX_STALE_API_KEY_DAYS] are removed.
+use std::{net::IpAddr, time::Duration};
+
+use actix_web::{http::header, HttpRequest};
+use ahash::{HashMap, HashMapExt};
+use chrono::{DateTime, Duration as ChronoDuration, FixedOffset};
+use common::user_types::api_token::MAX_STALE_API_KEY_DAYS;
+use entities::{
+    APIKeyColumn, APIKeyDailyRequestsActiveModel, APIKeyDailyRequestsColumn,
+    APIKeyDailyRequestsEntity, APIKeyEntity,
+};
+use parking_lot::Mutex;
+use sea_orm::{
+    entity::prelude::*, sea_query::OnConflict, ActiveValue, DatabaseConnection, TransactionTrait,
+};
+use tracing::{debug, error};
+
+use crate::utils::time_utils;
+
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// Details of the request that used the key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestDetails {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+impl RequestDetails {
+    /// `client_ip` should be resolved with [TrustedProxies](crate::client_ip::TrustedProxies)
+    pub fn new(request: &HttpRequest, client_ip: Option<IpAddr>) -> Self {
+        Self {
+            ip_address: client_ip.map(|ip| ip.to_string()),
+            user_agent: request
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned),
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PendingUsage {
+    last_used_at: DateTime<FixedOffset>,
+    details: RequestDetails,
+    requests: i64,
+}
+#[derive(Debug, Default)]
+pub struct APIKeyUsage {
+    pending: Mutex<HashMap<i64, PendingUsage>>,
+}
+
+impl APIKeyUsage {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Records a request made with the key
+    pub fn record(&self, key_id: i64, details: RequestDetails) {
+        let now = time_utils::get_current_time();
+        let mut pending = self.pending.lock();
+        pending
+            .entry(key_id)
+            .and_modify(|usage| {
+                usage.last_used_at = now;
+                usage.details = details.clone();
+                usage.requests += 1;
+            })
+            .or_insert(PendingUsage {
+                last_used_at: now,
+                details,
+                requests: 1,
+            });
+    }
+    /// Puts back usage that could not be written.
+    ///
+    /// Requests recorded in the meantime are newer. They keep their details and the unwritten requests are added to their count
+    fn merge_back(&self, unwritten: impl Iterator<Item = (i64, PendingUsage)>) {
+        let mut pending = self.pending.lock();
+        for (key_id, usage) in unwritten {
+            pending
+                .entry(key_id)
+                .and_modify(|newer| newer.requests += usage.requests)
+                .or_insert(usage);
+        }
+    }
+    /// Writes the collected usage to the database
+    ///
+    /// On an error the usage that was not written is kept for the next flush
+    pub async fn flush(&self, database: &DatabaseConnection) -> Result<(), DbErr> {
+        let pending = std::mem::take(&mut *self.pending.lock());
+        if pending.is_empty() {
+            return Ok(());
+        }
+        debug!("Flushing the usage of {} API keys", pending.len());
+        let mut pending = pending.into_iter();
+        while let Some((key_id, usage)) = pending.next() {
+            if let Err(err) = write_usage(key_id, &usage, database).await {
+                self.merge_back(std::iter::once((key_id, usage)).chain(pending));
+                return Err(err);
+            }
+        }
+        let oldest_day = time_utils::get_current_time().naive_utc().date()
+            - ChronoDuration::days(MAX_STALE_API_KEY_DAYS);
+        APIKeyDailyRequestsEntity::delete_many()
+            .filter(APIKeyDailyRequestsColumn::Day.lt(oldest_day))
+            .exec(database)
+            .await?;
+        Ok(())
+    }
+    #[cfg(test)]
+    fn pending_requests(&self, key_id: i64) -> Option<i64> {
+        self.pending.lock().get(&key_id).map(|usage| usage.requests)
+    }
+    /// Flushes the usage every [FLUSH_INTERVAL] until the server stops
+    pub async fn flush_periodically(&self, database: &DatabaseConnection) {
+        let mut interval = actix_web::rt::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = self.flush(database).await {
+                error!("Failed to flush API key usage: {}", err);
+            }
+        }
+    }
+}
+/// Adds the usage to the key and its day. Both or neither are written
+async fn write_usage(
+    key_id: i64,
+    usage: &PendingUsage,
+    database: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let transaction = database.begin().await?;
+    let updated = APIKeyEntity::update_many()
+        .filter(APIKeyColumn::Id.eq(key_id))
+        .col_expr(APIKeyColumn::LastUsedAt, Expr::value(usage.last_used_at))
+        .col_expr(
+            APIKeyColumn::LastIp,
+            Expr::value(usage.details.ip_address.clone()),
+        )
+        .col_expr(
+            APIKeyColumn::LastUserAgent,
+            Expr::value(usage.details.user_agent.clone()),
+        )
+        .col_expr(
+            APIKeyColumn::RequestCount,
+            Expr::col(APIKeyColumn::RequestCount).add(usage.requests),
+        )
+        .exec(&transaction)
+        .await?;
+    // The key was deleted since the requests
+    if updated.rows_affected == 0 {
+        return transaction.commit().await;
+    }
+    APIKeyDailyRequestsEntity::insert(APIKeyDailyRequestsActiveModel {
+        api_key_id: ActiveValue::Set(key_id),
+        day: ActiveValue::Set(usage.last_used_at.naive_utc().date()),
+        requests: ActiveValue::Set(usage.requests),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            APIKeyDailyRequestsColumn::ApiKeyId,
+            APIKeyDailyRequestsColumn::Day,
+        ])
+        .value(
+            APIKeyDailyRequestsColumn::Requests,
+            Expr::cust(r#""api_key_daily_requests"."requests" + "excluded"."requests""#),
+        )
+        .to_owned(),
+    )
+    .exec(&transaction)
+    .await?;
+    transaction.commit().await
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details() -> RequestDetails {
+        RequestDetails {
+            ip_address: Some("127.0.0.1".to_owned()),
+            user_agent: Some("wakatime/1.0".to_owned()),
+        }
+    }
+
+    #[actix_web::test]
+    async fn failed_flushes_keep_the_usage() {
+        let usage = APIKeyUsage::new();
+        usage.record(1, details());
+        usage.record(1, details());
+        usage.record(2, details());
+
+        assert!(usage
+            .flush(&DatabaseConnection::Disconnected)
+            .await
+            .is_err());
+        assert_eq!(usage.pending_requests(1), Some(2));
+        assert_eq!(usage.pending_requests(2), Some(1));
+
+        // Requests made after the failure are added to the kept usage
+        usage.record(1, details());
+        assert!(usage
+            .flush(&DatabaseConnection::Disconnected)
+            .await
+            .is_err());
+        assert_eq!(usage.pending_requests(1), Some(3));
+    }
+}
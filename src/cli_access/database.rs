@@ -0,0 +1,401 @@
+//! CLI logins in the `cli_access_requests` table
+//!
+//! Rows are found by the SHA-256 of the key the CLI polls with. The token is encrypted with a key derived from it.
+//! So the table alone does not give away a token. The token is cleared as soon as the CLI retrieves it.
+use actix_web::web::Data;
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Nonce,
+};
+use chrono::{DateTime, Duration, FixedOffset, Local};
+use common::{user_types::api_token::FromCLI, APIToken};
+use entities::{
+    cli_access_requests::CLIAccessState, APIKeyEntity, CLIAccessRequestActiveModel,
+    CLIAccessRequestColumn, CLIAccessRequestEntity, CLIAccessRequestModel,
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseConnection, DbBackend, Statement, TransactionTrait,
+};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use super::{
+    CLIAccessError, CLIAccessStorage, CompletedRequest, FinishedRequest, NewCLIRequest,
+    RequestStatus,
+};
+use crate::{
+    config::CLIAccessConfig,
+    utils::{base64_utils, sha256, time_utils},
+};
+
+/// The primary key of the request. The key itself is never stored
+fn row_key(key: &str) -> String {
+    sha256::encode_to_string(key)
+}
+fn token_cipher(key: &str) -> ChaCha20Poly1305 {
+    let secret = Sha256::new()
+        .chain_update(b"cli-access-token:")
+        .chain_update(key)
+        .finalize();
+    ChaCha20Poly1305::new(&secret)
+}
+/// Encrypts the token with the key of the request. The nonce is stored in front of it
+fn seal_token(key: &str, token: &str) -> String {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let sealed = token_cipher(key)
+        .encrypt(&nonce, token.as_bytes())
+        .expect("Encrypting a token can not fail");
+    base64_utils::encode([nonce.as_slice(), &sealed].concat())
+}
+/// None if the token was not sealed with the key
+fn open_token(key: &str, sealed: &str) -> Option<String> {
+    let sealed = base64_utils::decode(sealed).ok()?;
+    if sealed.len() < 12 {
+        return None;
+    }
+    let (nonce, sealed) = sealed.split_at(12);
+    let token = token_cipher(key)
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .ok()?;
+    String::from_utf8(token).ok()
+}
+/// Stores CLI logins in the `cli_access_requests` table. So every replica can answer them
+#[derive(Debug)]
+pub struct DatabaseCLIAccess {
+    database: Data<DatabaseConnection>,
+    pending_lifetime: Duration,
+    unclaimed_lifetime: Duration,
+    max_pending_per_ip: usize,
+}
+impl DatabaseCLIAccess {
+    pub fn new(config: &CLIAccessConfig, database: Data<DatabaseConnection>) -> Self {
+        Self {
+            database,
+            pending_lifetime: config.pending_lifetime.duration,
+            unclaimed_lifetime: config.unclaimed_lifetime.duration,
+            max_pending_per_ip: config.max_pending_per_ip,
+        }
+    }
+    /// Requests created before this are expired
+    fn pending_cutoff(&self) -> DateTime<FixedOffset> {
+        time_utils::get_current_time() - self.pending_lifetime
+    }
+    /// Requests completed before this are expired
+    fn unclaimed_cutoff(&self) -> DateTime<FixedOffset> {
+        time_utils::get_current_time() - self.unclaimed_lifetime
+    }
+    /// Moves the request from one state to another.
+    ///
+    /// Returns false if another replica moved it first
+    async fn transition(
+        &self,
+        key: &str,
+        from: CLIAccessState,
+        to: CLIAccessState,
+    ) -> Result<bool, DbErr> {
+        let result = CLIAccessRequestEntity::update_many()
+            .filter(
+                CLIAccessRequestColumn::Key
+                    .eq(row_key(key))
+                    .and(CLIAccessRequestColumn::State.eq(from)),
+            )
+            .col_expr(CLIAccessRequestColumn::State, Expr::value(to))
+            .exec(self.database.as_ref())
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+}
+fn into_pending(model: CLIAccessRequestModel) -> NewCLIRequest {
+    NewCLIRequest {
+        from_cli: model.from_cli,
+        username: model.username,
+        ip_address: model.ip_address,
+        created_at: model.created_at.with_timezone(&Local),
+    }
+}
+impl CLIAccessStorage for DatabaseCLIAccess {
+    async fn create_new_pending_access(
+        &self,
+        from_cli: FromCLI,
+        username: Option<String>,
+        ip_address: String,
+    ) -> Result<String, CLIAccessError> {
+        let transaction = self.database.begin().await?;
+        // Serializes the count and the insert across replicas. Released with the transaction
+        transaction
+            .execute(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_advisory_xact_lock(hashtext($1))",
+                [format!("cli_access:{}", ip_address).into()],
+            ))
+            .await?;
+        let pending_from_ip = CLIAccessRequestEntity::find()
+            .filter(
+                CLIAccessRequestColumn::IpAddress
+                    .eq(&ip_address)
+                    .and(CLIAccessRequestColumn::State.eq(CLIAccessState::Pending))
+                    .and(CLIAccessRequestColumn::CreatedAt.gt(self.pending_cutoff())),
+            )
+            .count(&transaction)
+            .await?;
+        if pending_from_ip >= self.max_pending_per_ip as u64 {
+            return Err(CLIAccessError::TooManyPendingRequests(ip_address));
+        }
+        let key = loop {
+            let key = super::generate_key();
+            let exists = CLIAccessRequestEntity::find_by_id(row_key(&key))
+                .count(&transaction)
+                .await?;
+            if exists == 0 {
+                break key;
+            }
+        };
+        CLIAccessRequestActiveModel {
+            key: ActiveValue::Set(row_key(&key)),
+            from_cli: ActiveValue::Set(from_cli),
+            username: ActiveValue::Set(username),
+            ip_address: ActiveValue::Set(ip_address),
+            state: ActiveValue::Set(CLIAccessState::Pending),
+            api_key_id: ActiveValue::Set(None),
+            token: ActiveValue::Set(None),
+            created_at: ActiveValue::Set(time_utils::get_current_time()),
+            completed_at: ActiveValue::Set(None),
+        }
+        .insert(&transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(key)
+    }
+
+    async fn get_pending_access(&self, key: &str) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        let request = CLIAccessRequestEntity::find_by_id(row_key(key))
+            .filter(
+                CLIAccessRequestColumn::State
+                    .eq(CLIAccessState::Pending)
+                    .and(CLIAccessRequestColumn::CreatedAt.gt(self.pending_cutoff())),
+            )
+            .one(self.database.as_ref())
+            .await?;
+        Ok(request.map(into_pending))
+    }
+    async fn remove_pending_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        let Some(request) = self.get_pending_access(key).await? else {
+            return Ok(None);
+        };
+        if !self
+            .transition(key, CLIAccessState::Pending, CLIAccessState::Answering)
+            .await?
+        {
+            return Ok(None);
+        }
+        Ok(Some(request))
+    }
+    async fn deny_access(&self, key: &str) -> Result<bool, CLIAccessError> {
+        let result = CLIAccessRequestEntity::update_many()
+            .filter(
+                CLIAccessRequestColumn::Key
+                    .eq(row_key(key))
+                    .and(CLIAccessRequestColumn::State.eq(CLIAccessState::Answering)),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::State,
+                Expr::value(CLIAccessState::Denied),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::CompletedAt,
+                Expr::value(time_utils::get_current_time()),
+            )
+            .exec(self.database.as_ref())
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    async fn complete_access(
+        &self,
+        key: &str,
+        _: NewCLIRequest,
+        api_token: APIToken,
+        token: String,
+    ) -> Result<bool, CLIAccessError> {
+        let result = CLIAccessRequestEntity::update_many()
+            .filter(
+                CLIAccessRequestColumn::Key
+                    .eq(row_key(key))
+                    .and(CLIAccessRequestColumn::State.eq(CLIAccessState::Answering)),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::State,
+                Expr::value(CLIAccessState::Unclaimed),
+            )
+            .col_expr(CLIAccessRequestColumn::ApiKeyId, Expr::value(api_token.id))
+            .col_expr(
+                CLIAccessRequestColumn::Token,
+                Expr::value(seal_token(key, &token)),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::CompletedAt,
+                Expr::value(time_utils::get_current_time()),
+            )
+            .exec(self.database.as_ref())
+            .await?;
+        Ok(result.rows_affected == 1)
+    }
+    async fn status(&self, key: &str) -> Result<RequestStatus, CLIAccessError> {
+        let Some(request) = CLIAccessRequestEntity::find_by_id(row_key(key))
+            .one(self.database.as_ref())
+            .await?
+        else {
+            return Ok(RequestStatus::Unknown);
+        };
+        let status = match request.state {
+            CLIAccessState::Pending if request.created_at <= self.pending_cutoff() => {
+                RequestStatus::Finished(FinishedRequest::Expired)
+            }
+            CLIAccessState::Pending | CLIAccessState::Answering => RequestStatus::Pending,
+            CLIAccessState::Unclaimed
+                if request
+                    .completed_at
+                    .is_some_and(|completed_at| completed_at <= self.unclaimed_cutoff()) =>
+            {
+                RequestStatus::Finished(FinishedRequest::Expired)
+            }
+            // Another replica is handing it out. The CLI will find out on its next poll
+            CLIAccessState::Unclaimed => RequestStatus::Pending,
+            CLIAccessState::Denied => RequestStatus::Finished(FinishedRequest::Denied),
+            CLIAccessState::Expired => RequestStatus::Finished(FinishedRequest::Expired),
+            CLIAccessState::Claimed => RequestStatus::Unknown,
+        };
+        Ok(status)
+    }
+
+    async fn get_unclaimed_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<CompletedRequest>, CLIAccessError> {
+        let Some((request, Some(api_key))) = CLIAccessRequestEntity::find_by_id(row_key(key))
+            .filter(
+                CLIAccessRequestColumn::State
+                    .eq(CLIAccessState::Unclaimed)
+                    .and(CLIAccessRequestColumn::CompletedAt.gt(self.unclaimed_cutoff())),
+            )
+            .find_also_related(APIKeyEntity)
+            .one(self.database.as_ref())
+            .await?
+        else {
+            return Ok(None);
+        };
+        let (Some(sealed), Some(completed_at)) = (request.token, request.completed_at) else {
+            return Ok(None);
+        };
+        // The token is cleared in the same update that claims it. So only one replica hands it out
+        let claimed = CLIAccessRequestEntity::update_many()
+            .filter(
+                CLIAccessRequestColumn::Key
+                    .eq(row_key(key))
+                    .and(CLIAccessRequestColumn::State.eq(CLIAccessState::Unclaimed)),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::State,
+                Expr::value(CLIAccessState::Claimed),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::Token,
+                Expr::value(Option::<String>::None),
+            )
+            .exec(self.database.as_ref())
+            .await?;
+        if claimed.rows_affected != 1 {
+            return Ok(None);
+        }
+        let Some(token) = open_token(key, &sealed) else {
+            warn!("The token of a CLI login could not be decrypted");
+            return Ok(None);
+        };
+        Ok(Some(CompletedRequest {
+            from_cli: request.from_cli,
+            username: request.username,
+            api_token: APIToken::from(api_key),
+            token,
+            ip_address: request.ip_address,
+            created_at: request.created_at.with_timezone(&Local),
+            completed_at: completed_at.with_timezone(&Local),
+        }))
+    }
+    async fn sweep(&self) -> Result<Vec<i64>, CLIAccessError> {
+        let database = self.database.as_ref();
+        let now = time_utils::get_current_time();
+        // Claimed requests have nothing left to tell the CLI
+        CLIAccessRequestEntity::delete_many()
+            .filter(
+                CLIAccessRequestColumn::State
+                    .eq(CLIAccessState::Claimed)
+                    .or(CLIAccessRequestColumn::State
+                        .is_in([CLIAccessState::Denied, CLIAccessState::Expired])
+                        .and(CLIAccessRequestColumn::CompletedAt.lte(self.unclaimed_cutoff()))),
+            )
+            .exec(database)
+            .await?;
+        CLIAccessRequestEntity::update_many()
+            .filter(
+                CLIAccessRequestColumn::State
+                    .is_in([CLIAccessState::Pending, CLIAccessState::Answering])
+                    .and(CLIAccessRequestColumn::CreatedAt.lte(self.pending_cutoff())),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::State,
+                Expr::value(CLIAccessState::Expired),
+            )
+            .col_expr(CLIAccessRequestColumn::CompletedAt, Expr::value(now))
+            .exec(database)
+            .await?;
+
+        let expired_unclaimed = CLIAccessRequestColumn::State
+            .eq(CLIAccessState::Unclaimed)
+            .and(CLIAccessRequestColumn::CompletedAt.lte(self.unclaimed_cutoff()));
+        let unused_keys: Vec<i64> = CLIAccessRequestEntity::find()
+            .filter(expired_unclaimed.clone())
+            .all(database)
+            .await?
+            .into_iter()
+            .filter_map(|request| request.api_key_id)
+            .collect();
+        CLIAccessRequestEntity::update_many()
+            .filter(expired_unclaimed)
+            .col_expr(
+                CLIAccessRequestColumn::State,
+                Expr::value(CLIAccessState::Expired),
+            )
+            .col_expr(
+                CLIAccessRequestColumn::Token,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(CLIAccessRequestColumn::CompletedAt, Expr::value(now))
+            .exec(database)
+            .await?;
+        Ok(unused_keys)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_only_open_with_their_key() {
+        let sealed = seal_token("abcdefgh12345678", "secret-token");
+        assert!(!sealed.contains("secret-token"));
+        assert_eq!(
+            open_token("abcdefgh12345678", &sealed).as_deref(),
+            Some("secret-token")
+        );
+        assert_eq!(open_token("abcdefgh12345679", &sealed), None);
+        assert_eq!(open_token("abcdefgh12345678", "AAAA"), None);
+    }
+    #[test]
+    fn rows_do_not_store_the_key() {
+        assert_ne!(row_key("abcdefgh12345678"), "abcdefgh12345678");
+        assert_eq!(row_key("abcdefgh12345678"), row_key("abcdefgh12345678"));
+    }
+}
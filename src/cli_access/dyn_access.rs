@@ -0,0 +1,114 @@
+use actix_web::web::Data;
+use common::{user_types::api_token::FromCLI, APIToken};
+use sea_orm::DatabaseConnection;
+
+use super::{
+    database::DatabaseCLIAccess, memory::MemoryCLIAccess, CLIAccessError, CLIAccessStorage,
+    CompletedRequest, NewCLIRequest, RequestStatus,
+};
+use crate::config::{CLIAccessConfig, CLIAccessStorageConfig};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DynCLIAccess {
+    Memory(MemoryCLIAccess),
+    Database(DatabaseCLIAccess),
+}
+impl DynCLIAccess {
+    pub fn new(config: CLIAccessConfig, database: Data<DatabaseConnection>) -> Self {
+        match config.storage {
+            CLIAccessStorageConfig::Memory => DynCLIAccess::Memory(MemoryCLIAccess::new(&config)),
+            CLIAccessStorageConfig::Database => {
+                DynCLIAccess::Database(DatabaseCLIAccess::new(&config, database))
+            }
+        }
+    }
+}
+
+impl CLIAccessStorage for DynCLIAccess {
+    #[inline]
+    async fn create_new_pending_access(
+        &self,
+        from_cli: FromCLI,
+        username: Option<String>,
+        ip_address: String,
+    ) -> Result<String, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => {
+                access
+                    .create_new_pending_access(from_cli, username, ip_address)
+                    .await
+            }
+            DynCLIAccess::Database(access) => {
+                access
+                    .create_new_pending_access(from_cli, username, ip_address)
+                    .await
+            }
+        }
+    }
+    #[inline]
+    async fn get_pending_access(&self, key: &str) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.get_pending_access(key).await,
+            DynCLIAccess::Database(access) => access.get_pending_access(key).await,
+        }
+    }
+    #[inline]
+    async fn remove_pending_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.remove_pending_access(key).await,
+            DynCLIAccess::Database(access) => access.remove_pending_access(key).await,
+        }
+    }
+    #[inline]
+    async fn deny_access(&self, key: &str) -> Result<bool, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.deny_access(key).await,
+            DynCLIAccess::Database(access) => access.deny_access(key).await,
+        }
+    }
+    #[inline]
+    async fn complete_access(
+        &self,
+        key: &str,
+        request: NewCLIRequest,
+        api_token: APIToken,
+        token: String,
+    ) -> Result<bool, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => {
+                access.complete_access(key, request, api_token, token).await
+            }
+            DynCLIAccess::Database(access) => {
+                access.complete_access(key, request, api_token, token).await
+            }
+        }
+    }
+    #[inline]
+    async fn status(&self, key: &str) -> Result<RequestStatus, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.status(key).await,
+            DynCLIAccess::Database(access) => access.status(key).await,
+        }
+    }
+    #[inline]
+    async fn get_unclaimed_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<CompletedRequest>, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.get_unclaimed_access(key).await,
+            DynCLIAccess::Database(access) => access.get_unclaimed_access(key).await,
+        }
+    }
+    #[inline]
+    async fn sweep(&self) -> Result<Vec<i64>, CLIAccessError> {
+        match self {
+            DynCLIAccess::Memory(access) => access.sweep().await,
+            DynCLIAccess::Database(access) => access.sweep().await,
+        }
+    }
+}
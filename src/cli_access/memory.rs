@@ -0,0 +1,331 @@
+use ahash::{HashMap, HashMapExt};
+use chrono::{DateTime, Duration, Local};
+use common::{user_types::api_token::FromCLI, APIToken};
+use parking_lot::RwLock;
+
+use super::{
+    CLIAccessError, CLIAccessStorage, CompletedRequest, FinishedRequest, NewCLIRequest,
+    RequestStatus,
+};
+use crate::config::CLIAccessConfig;
+
+#[derive(Debug)]
+pub struct MemoryCLIAccess {
+    pending_accesses: RwLock<HashMap<String, NewCLIRequest>>,
+    unclaimed_accesses: RwLock<HashMap<String, CompletedRequest>>,
+    finished_requests: RwLock<HashMap<String, (FinishedRequest, DateTime<Local>)>>,
+    pending_lifetime: Duration,
+    unclaimed_lifetime: Duration,
+    max_pending_per_ip: usize,
+}
+
+impl MemoryCLIAccess {
+    pub fn new(config: &CLIAccessConfig) -> Self {
+        Self {
+            pending_accesses: RwLock::new(HashMap::new()),
+            unclaimed_accesses: RwLock::new(HashMap::new()),
+            finished_requests: RwLock::new(HashMap::new()),
+            pending_lifetime: config.pending_lifetime.duration,
+            unclaimed_lifetime: config.unclaimed_lifetime.duration,
+            max_pending_per_ip: config.max_pending_per_ip,
+        }
+    }
+    fn is_pending_expired(&self, request: &NewCLIRequest, now: DateTime<Local>) -> bool {
+        now - request.created_at > self.pending_lifetime
+    }
+    fn is_unclaimed_expired(&self, request: &CompletedRequest, now: DateTime<Local>) -> bool {
+        now - request.completed_at > self.unclaimed_lifetime
+    }
+}
+impl CLIAccessStorage for MemoryCLIAccess {
+    async fn create_new_pending_access(
+        &self,
+        from_cli: FromCLI,
+        username: Option<String>,
+        ip_address: String,
+    ) -> Result<String, CLIAccessError> {
+        let now = Local::now();
+        let mut pending_accesses = self.pending_accesses.write();
+        let pending_from_ip = pending_accesses
+            .values()
+            .filter(|request| {
+                request.ip_address == ip_address && !self.is_pending_expired(request, now)
+            })
+            .count();
+        if pending_from_ip >= self.max_pending_per_ip {
+            return Err(CLIAccessError::TooManyPendingRequests(ip_address));
+        }
+        let key = loop {
+            let key = super::generate_key();
+            if !pending_accesses.contains_key(&key) {
+                break key;
+            }
+        };
+        let new_access = NewCLIRequest {
+            from_cli,
+            username,
+            ip_address,
+            created_at: now,
+        };
+        pending_accesses.insert(key.clone(), new_access);
+        Ok(key)
+    }
+
+    async fn get_pending_access(&self, key: &str) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        let pending_accesses = self.pending_accesses.read();
+        Ok(pending_accesses
+            .get(key)
+            .filter(|request| !self.is_pending_expired(request, Local::now()))
+            .cloned())
+    }
+    async fn remove_pending_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<NewCLIRequest>, CLIAccessError> {
+        let Some(request) = self.pending_accesses.write().remove(key) else {
+            return Ok(None);
+        };
+        let now = Local::now();
+        if self.is_pending_expired(&request, now) {
+            self.finished_requests
+                .write()
+                .insert(key.to_owned(), (FinishedRequest::Expired, now));
+            return Ok(None);
+        }
+        Ok(Some(request))
+    }
+    async fn deny_access(&self, key: &str) -> Result<bool, CLIAccessError> {
+        self.finished_requests
+            .write()
+            .insert(key.to_owned(), (FinishedRequest::Denied, Local::now()));
+        Ok(true)
+    }
+
+    async fn complete_access(
+        &self,
+        key: &str,
+        request: NewCLIRequest,
+        api_token: APIToken,
+        token: String,
+    ) -> Result<bool, CLIAccessError> {
+        let NewCLIRequest {
+            from_cli,
+            username,
+            ip_address,
+            created_at,
+        } = request;
+        let completed = CompletedRequest {
+            from_cli,
+            username,
+            api_token,
+            token,
+            ip_address,
+            created_at,
+            completed_at: Local::now(),
+        };
+        self.unclaimed_accesses
+            .write()
+            .insert(key.to_owned(), completed);
+        Ok(true)
+    }
+    async fn status(&self, key: &str) -> Result<RequestStatus, CLIAccessError> {
+        if let Some(request) = self.pending_accesses.read().get(key) {
+            if self.is_pending_expired(request, Local::now()) {
+                return Ok(RequestStatus::Finished(FinishedRequest::Expired));
+            }
+            return Ok(RequestStatus::Pending);
+        }
+        if let Some((finished, _)) = self.finished_requests.read().get(key) {
+            return Ok(RequestStatus::Finished(*finished));
+        }
+        Ok(RequestStatus::Unknown)
+    }
+
+    async fn get_unclaimed_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<CompletedRequest>, CLIAccessError> {
+        let mut unclaimed_accesses = self.unclaimed_accesses.write();
+        let Some(request) = unclaimed_accesses.remove(key) else {
+            return Ok(None);
+        };
+        let now = Local::now();
+        if self.is_unclaimed_expired(&request, now) {
+            // Put it back so the sweeper can revoke its key
+            unclaimed_accesses.insert(key.to_owned(), request);
+            self.finished_requests
+                .write()
+                .insert(key.to_owned(), (FinishedRequest::Expired, now));
+            return Ok(None);
+        }
+        Ok(Some(request))
+    }
+    async fn sweep(&self) -> Result<Vec<i64>, CLIAccessError> {
+        let now = Local::now();
+        let mut expired = Vec::new();
+        self.pending_accesses.write().retain(|key, request| {
+            let keep = !self.is_pending_expired(request, now);
+            if !keep {
+                expired.push(key.clone());
+            }
+            keep
+        });
+        let mut unused_keys = Vec::new();
+        self.unclaimed_accesses.write().retain(|key, request| {
+            let keep = !self.is_unclaimed_expired(request, now);
+            if !keep {
+                expired.push(key.clone());
+                unused_keys.push(request.api_token.id);
+            }
+            keep
+        });
+        let mut finished_requests = self.finished_requests.write();
+        finished_requests
+            .retain(|_, (_, finished_at)| now - *finished_at <= self.unclaimed_lifetime);
+        for key in expired {
+            finished_requests.insert(key, (FinishedRequest::Expired, now));
+        }
+        Ok(unused_keys)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+    use common::{user_types::api_token::FromCLI, APIToken};
+
+    use super::MemoryCLIAccess;
+    use crate::cli_access::{CLIAccessError, CLIAccessStorage, FinishedRequest, RequestStatus};
+
+    fn access(
+        pending_lifetime: Duration,
+        unclaimed_lifetime: Duration,
+        max_pending_per_ip: usize,
+    ) -> MemoryCLIAccess {
+        MemoryCLIAccess {
+            pending_accesses: Default::default(),
+            unclaimed_accesses: Default::default(),
+            finished_requests: Default::default(),
+            pending_lifetime,
+            unclaimed_lifetime,
+            max_pending_per_ip,
+        }
+    }
+    fn api_token(id: i64) -> APIToken {
+        APIToken {
+            id,
+            user_id: 1,
+            name: "CLI".to_owned(),
+            description: String::new(),
+            permissions: vec![],
+            from_cli: None,
+            revoked: None,
+            expires_at: None,
+            last_used_at: None,
+            last_ip: None,
+            last_user_agent: None,
+            request_count: 0,
+            allowed_ips: vec![],
+            created: Local::now().into(),
+        }
+    }
+    async fn new_request(
+        access: &MemoryCLIAccess,
+        ip_address: &str,
+    ) -> Result<String, CLIAccessError> {
+        access
+            .create_new_pending_access(FromCLI::default(), None, ip_address.to_owned())
+            .await
+    }
+    /// Approves the request like the complete access route does
+    async fn approve(access: &MemoryCLIAccess, key: &str, api_key_id: i64) {
+        let request = access.remove_pending_access(key).await.unwrap().unwrap();
+        assert!(access
+            .complete_access(key, request, api_token(api_key_id), "token".to_owned())
+            .await
+            .unwrap());
+    }
+
+    #[actix_web::test]
+    async fn limits_pending_requests_per_ip() {
+        let access = access(Duration::minutes(10), Duration::minutes(5), 2);
+        new_request(&access, "10.0.0.1").await.unwrap();
+        new_request(&access, "10.0.0.1").await.unwrap();
+        assert!(matches!(
+            new_request(&access, "10.0.0.1").await,
+            Err(CLIAccessError::TooManyPendingRequests(_))
+        ));
+        new_request(&access, "10.0.0.2").await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn expired_pending_requests_do_not_count_towards_the_limit() {
+        let access = access(Duration::seconds(-1), Duration::minutes(5), 1);
+        new_request(&access, "10.0.0.1").await.unwrap();
+        new_request(&access, "10.0.0.1").await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn pending_requests_expire() {
+        let access = access(Duration::seconds(-1), Duration::minutes(5), 5);
+        let key = new_request(&access, "10.0.0.1").await.unwrap();
+        assert_eq!(access.get_pending_access(&key).await.unwrap(), None);
+        assert_eq!(
+            access.status(&key).await.unwrap(),
+            RequestStatus::Finished(FinishedRequest::Expired)
+        );
+        assert_eq!(access.remove_pending_access(&key).await.unwrap(), None);
+        assert_eq!(
+            access.status(&key).await.unwrap(),
+            RequestStatus::Finished(FinishedRequest::Expired)
+        );
+    }
+
+    #[actix_web::test]
+    async fn unclaimed_requests_are_handed_out_once() {
+        let access = access(Duration::minutes(10), Duration::minutes(5), 5);
+        let key = new_request(&access, "10.0.0.1").await.unwrap();
+        assert_eq!(access.status(&key).await.unwrap(), RequestStatus::Pending);
+        approve(&access, &key, 7).await;
+        let completed = access.get_unclaimed_access(&key).await.unwrap().unwrap();
+        assert_eq!(completed.api_token.id, 7);
+        assert_eq!(completed.ip_address, "10.0.0.1");
+        assert_eq!(access.get_unclaimed_access(&key).await.unwrap(), None);
+        assert_eq!(access.sweep().await.unwrap(), Vec::<i64>::new());
+    }
+
+    #[actix_web::test]
+    async fn expired_unclaimed_requests_report_expired_before_the_sweep() {
+        let access = access(Duration::minutes(10), Duration::seconds(-1), 5);
+        let key = new_request(&access, "10.0.0.1").await.unwrap();
+        approve(&access, &key, 7).await;
+        assert_eq!(access.get_unclaimed_access(&key).await.unwrap(), None);
+        assert_eq!(
+            access.status(&key).await.unwrap(),
+            RequestStatus::Finished(FinishedRequest::Expired)
+        );
+        // The key was never handed out so the sweep revokes it
+        assert_eq!(access.sweep().await.unwrap(), vec![7]);
+        assert_eq!(access.get_unclaimed_access(&key).await.unwrap(), None);
+    }
+
+    #[actix_web::test]
+    async fn sweep_keeps_the_outcome_of_expired_requests() {
+        let access = access(Duration::seconds(-1), Duration::minutes(5), 5);
+        let key = new_request(&access, "10.0.0.1").await.unwrap();
+        assert!(access.sweep().await.unwrap().is_empty());
+        assert!(access.pending_accesses.read().is_empty());
+        assert_eq!(
+            access.status(&key).await.unwrap(),
+            RequestStatus::Finished(FinishedRequest::Expired)
+        );
+        assert!(access.deny_access("denied").await.unwrap());
+        assert_eq!(
+            access.status("denied").await.unwrap(),
+            RequestStatus::Finished(FinishedRequest::Denied)
+        );
+        assert_eq!(
+            access.status("unknown").await.unwrap(),
+            RequestStatus::Unknown
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Pending CLI Logins
+//!
+//! Requests expire if the user does not answer them in time.
+//! Approved requests expire if the CLI does not retrieve the token in time.
+//! The outcome of a finished request is kept for a while. So the CLI can tell why it failed.
+//!
+//! The memory storage only works with a single server.
+//! Use the database storage if the server is behind a load balancer.
+pub mod database;
+mod dyn_access;
+pub mod memory;
+
+use std::{fmt::Debug, time::Duration as StdDuration};
+
+use chrono::{DateTime, Local};
+use common::{user_types::api_token::FromCLI, APIToken};
+pub use dyn_access::DynCLIAccess;
+use entities::{APIKeyColumn, APIKeyEntity};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sea_orm::{entity::prelude::*, DatabaseConnection};
+use this_actix_error::ActixError;
+use thiserror::Error;
+use tracing::{debug, error};
+
+use crate::utils::time_utils;
+
+pub type CLIAccess = DynCLIAccess;
+
+pub const SWEEP_INTERVAL: StdDuration = StdDuration::from_secs(60);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewCLIRequest {
+    pub from_cli: FromCLI,
+    pub username: Option<String>,
+    pub ip_address: String,
+    pub created_at: DateTime<Local>,
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompletedRequest {
+    pub from_cli: FromCLI,
+    pub username: Option<String>,
+    pub api_token: APIToken,
+    /// The raw token. Only handed back to the CLI once
+    pub token: String,
+    pub ip_address: String,
+    pub created_at: DateTime<Local>,
+    pub completed_at: DateTime<Local>,
+}
+/// Why a request is no longer pending or unclaimed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FinishedRequest {
+    Denied,
+    Expired,
+}
+/// The state of a request that has not been retrieved
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestStatus {
+    Pending,
+    Finished(FinishedRequest),
+    /// Never existed or the outcome was already swept
+    Unknown,
+}
+#[derive(Debug, Error, ActixError)]
+pub enum CLIAccessError {
+    #[status_code(TOO_MANY_REQUESTS)]
+    #[error("Too many pending CLI requests from {0}")]
+    TooManyPendingRequests(String),
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    #[error("Database Error")]
+    DatabaseError(#[from] DbErr),
+}
+
+/// Storage for CLI logins
+///
+/// Implementations treat expired requests as missing.
+#[allow(async_fn_in_trait)]
+pub trait CLIAccessStorage: Debug {
+    /// Returns the key the CLI polls with
+    async fn create_new_pending_access(
+        &self,
+        from_cli: FromCLI,
+        username: Option<String>,
+        ip_address: String,
+    ) -> Result<String, CLIAccessError>;
+
+    async fn get_pending_access(&self, key: &str) -> Result<Option<NewCLIRequest>, CLIAccessError>;
+    /// Takes the request so it can be answered. Only one caller gets it.
+    ///
+    /// Follow up with [Self::deny_access] or [Self::complete_access]
+    async fn remove_pending_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<NewCLIRequest>, CLIAccessError>;
+    /// Records that the user denied the request.
+    ///
+    /// Returns false if the request expired while it was being answered
+    async fn deny_access(&self, key: &str) -> Result<bool, CLIAccessError>;
+    /// Stores the approved request. So the CLI can retrieve the token
+    ///
+    /// Returns false if the request expired while it was being answered. The token will never be retrieved
+    async fn complete_access(
+        &self,
+        key: &str,
+        request: NewCLIRequest,
+        api_token: APIToken,
+        token: String,
+    ) -> Result<bool, CLIAccessError>;
+    /// The state of a request that is not waiting to be retrieved
+    async fn status(&self, key: &str) -> Result<RequestStatus, CLIAccessError>;
+    /// Takes the approved request. It is only handed out once
+    async fn get_unclaimed_access(
+        &self,
+        key: &str,
+    ) -> Result<Option<CompletedRequest>, CLIAccessError>;
+    /// Removes expired requests. Their outcome is kept for the unclaimed lifetime.
+    ///
+    /// Returns the ids of API keys that were approved but never retrieved
+    async fn sweep(&self) -> Result<Vec<i64>, CLIAccessError>;
+}
+pub(crate) fn generate_key() -> String {
+    StdRng::from_entropy()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+/// Sweeps every [SWEEP_INTERVAL] until the server stops.
+///
+/// API keys that were never retrieved are revoked. Nobody has their token
+pub async fn sweep_periodically(access: &impl CLIAccessStorage, database: &DatabaseConnection) {
+    let mut interval = actix_web::rt::time::interval(SWEEP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let unused_keys = match access.sweep().await {
+            Ok(unused_keys) => unused_keys,
+            Err(err) => {
+                error!("Failed to sweep CLI access requests: {}", err);
+                continue;
+            }
+        };
+        if unused_keys.is_empty() {
+            continue;
+        }
+        debug!("Revoking {} unclaimed CLI API keys", unused_keys.len());
+        let result = APIKeyEntity::update_many()
+            .filter(
+                APIKeyColumn::Id
+                    .is_in(unused_keys)
+                    .and(APIKeyColumn::Revoked.is_null()),
+            )
+            .col_expr(
+                APIKeyColumn::Revoked,
+                Expr::value(time_utils::get_current_time()),
+            )
+            .exec(database)
+            .await;
+        if let Err(err) = result {
+            error!("Failed to revoke unclaimed CLI API keys: {}", err);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Client IP Resolution
+//!
+//! `X-Forwarded-For` is only honoured when the request comes from a trusted proxy.
+//! Otherwise any client could claim to be anywhere.
+use std::net::IpAddr;
+
+use actix_web::{web::Data, HttpRequest};
+use common::ip_range::{resolve_client_ip, IpRange};
+
+const X_FORWARDED_FOR: &str = "X-Forwarded-For";
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<IpRange>,
+}
+impl TrustedProxies {
+    pub fn new(proxies: Vec<IpRange>) -> Self {
+        Self { proxies }
+    }
+    /// The address of the client that made the request
+    ///
+    /// None if the peer address is unknown. Such as in tests
+    pub fn client_ip(&self, request: &HttpRequest) -> Option<IpAddr> {
+        let peer = request.peer_addr()?.ip();
+        let forwarded_for: Vec<IpAddr> = request
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|value| value.trim().parse().ok())
+            .collect();
+        Some(resolve_client_ip(peer, &forwarded_for, &self.proxies))
+    }
+    /// Resolves the client address using the trusted proxies of the app.
+    ///
+    /// No proxies are trusted if they were never registered
+    pub fn client_ip_of(request: &HttpRequest) -> Option<IpAddr> {
+        match request.app_data::<Data<TrustedProxies>>() {
+            Some(proxies) => proxies.client_ip(request),
+            None => TrustedProxies::default().client_ip(request),
+        }
+    }
+}
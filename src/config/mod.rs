@@ -3,12 +3,17 @@ pub mod tracing;
 use std::path::PathBuf;
 
 use chrono::Duration;
+use common::ip_range::IpRange;
 use config_types::chrono_types::duration::ConfigDuration;
 use digestible::Digestible;
 use sea_orm::ConnectOptions;
 use serde::{Deserialize, Serialize};
 
-use crate::recaptcha::GoogleRecaptcha;
+use crate::{
+    email::EmailConfig,
+    oauth::{github::GithubConfig, oidc::OIDCProviderConfig},
+    recaptcha::GoogleRecaptcha,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
@@ -22,6 +27,19 @@ pub struct ServerConfig {
     pub tracing: tracing::TracingConfiguration,
     pub public_registration: bool,
     pub recaptcha: Option<GoogleRecaptcha>,
+    /// Proxies allowed to set `X-Forwarded-For`. Such as your load balancer
+    ///
+    /// Example: `["10.0.0.0/8", "127.0.0.1"]`
+    pub trusted_proxies: Vec<IpRange>,
+    pub cli_access: CLIAccessConfig,
+    /// Emails are not sent if not provided
+    pub email: Option<EmailConfig>,
+    /// If false. Only admins can log in with a password. Everyone else must use single sign on
+    pub password_login: bool,
+    /// OpenID Connect providers for single sign on. Requires `home_url`
+    pub oidc: Vec<OIDCProviderConfig>,
+    /// Lets users link their GitHub account. Requires `home_url`
+    pub github: Option<GithubConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -42,6 +60,12 @@ impl Default for ServerConfig {
             tracing: Default::default(),
             public_registration: true,
             recaptcha: None,
+            trusted_proxies: vec![],
+            cli_access: CLIAccessConfig::default(),
+            email: None,
+            password_login: true,
+            oidc: vec![],
+            github: None,
         }
     }
 }
@@ -65,6 +89,43 @@ impl Default for SessionConfig {
         }
     }
 }
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct CLIAccessConfig {
+    /// How long the user has to approve a request
+    pub pending_lifetime: ConfigDuration,
+    /// How long the CLI has to retrieve the token once the request is approved
+    pub unclaimed_lifetime: ConfigDuration,
+    /// Pending requests allowed from a single IP address
+    pub max_pending_per_ip: usize,
+    pub storage: CLIAccessStorageConfig,
+}
+/// Where pending CLI logins are stored
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default)]
+#[serde(tag = "type")]
+pub enum CLIAccessStorageConfig {
+    /// Only works with a single server
+    #[default]
+    Memory,
+    /// Required if you run more than one replica of the server
+    Database,
+}
+impl Default for CLIAccessConfig {
+    fn default() -> Self {
+        Self {
+            pending_lifetime: ConfigDuration {
+                duration: Duration::minutes(10),
+                unit: config_types::chrono_types::duration::Unit::Minutes,
+            },
+            unclaimed_lifetime: ConfigDuration {
+                duration: Duration::minutes(5),
+                unit: config_types::chrono_types::duration::Unit::Minutes,
+            },
+            max_pending_per_ip: 5,
+            storage: CLIAccessStorageConfig::default(),
+        }
+    }
+}
 // TODO. Add SessionCleaner, and session life.
 #[derive(Debug, Deserialize, Serialize, Default)]
 #[serde(default)]
@@ -0,0 +1,81 @@
+//! The emails the server sends
+use serde::Serialize;
+
+use super::Email;
+
+/// Sent after the password of an account is changed
+#[derive(Debug, Serialize)]
+pub struct PasswordChanged {
+    pub name: String,
+    pub username: String,
+    /// Formatted for humans
+    pub changed_at: String,
+}
+impl Email for PasswordChanged {
+    const TEMPLATE: &'static str = "password_changed";
+
+    fn subject(&self) -> String {
+        "Your password was changed".to_owned()
+    }
+}
+/// Sent at registration and after the email is changed
+#[derive(Debug, Serialize)]
+pub struct VerifyEmail {
+    pub name: String,
+    pub token: String,
+    pub expires_in_hours: i64,
+}
+impl Email for VerifyEmail {
+    const TEMPLATE: &'static str = "verify_email";
+
+    fn subject(&self) -> String {
+        "Verify your email".to_owned()
+    }
+}
+/// Sent to the old address after the email of an account is changed
+#[derive(Debug, Serialize)]
+pub struct EmailChanged {
+    pub name: String,
+    pub username: String,
+    pub new_email: String,
+}
+impl Email for EmailChanged {
+    const TEMPLATE: &'static str = "email_changed";
+
+    fn subject(&self) -> String {
+        "Your email was changed".to_owned()
+    }
+}
+/// Sent when someone asks to reset the password of an account
+#[derive(Debug, Serialize)]
+pub struct PasswordReset {
+    pub name: String,
+    pub username: String,
+    pub token: String,
+    pub expires_in_minutes: i64,
+}
+impl Email for PasswordReset {
+    const TEMPLATE: &'static str = "password_reset";
+
+    fn subject(&self) -> String {
+        "Reset your password".to_owned()
+    }
+}
+/// Sent to the user or the email that was invited to a team
+#[derive(Debug, Serialize)]
+pub struct TeamInvitation {
+    /// Empty if the email does not have an account yet
+    pub name: String,
+    pub team: String,
+    pub invited_by: String,
+    pub role: String,
+    pub token: String,
+    pub expires_in_days: i64,
+}
+impl Email for TeamInvitation {
+    const TEMPLATE: &'static str = "team_invitation";
+
+    fn subject(&self) -> String {
+        format!("You were invited to join {}", self.team)
+    }
+}
@@ -0,0 +1,196 @@
+//! Email Delivery
+//!
+//! Emails are rendered when they are queued and sent by a background task.
+//! Failed sends are retried with a growing delay. Emails still failing after [EmailConfig::max_retries] are dropped.
+//!
+//! If no email config is provided. Emails are logged and dropped.
+pub mod emails;
+pub mod templates;
+pub mod transport;
+
+use std::time::Duration as StdDuration;
+
+use lettre::{
+    address::AddressError,
+    message::{Mailbox, MultiPart},
+    Message,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info, warn};
+
+use self::transport::EmailTransport;
+pub use self::{templates::EmailTemplates, transport::EmailTransportConfig};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    /// Example: `Codi Time <noreply@example.com>`
+    pub from: String,
+    pub transport: EmailTransportConfig,
+    /// Attempts after the first failure
+    pub max_retries: u32,
+    /// The delay before the first retry. Doubled after every failure
+    pub retry_delay_seconds: u64,
+}
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            from: "Codi Time <noreply@localhost>".to_owned(),
+            transport: EmailTransportConfig::default(),
+            max_retries: 5,
+            retry_delay_seconds: 30,
+        }
+    }
+}
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("Invalid email address: {0}")]
+    InvalidAddress(#[from] AddressError),
+    #[error("Failed to render the email: {0}")]
+    RenderError(#[from] handlebars::RenderError),
+    #[error("Failed to build the email: {0}")]
+    BuildError(#[from] lettre::error::Error),
+    #[error("SMTP Error: {0}")]
+    SmtpError(#[from] lettre::transport::smtp::Error),
+    #[error("File Transport Error: {0}")]
+    FileError(#[from] lettre::transport::file::Error),
+}
+/// An email that can be sent.
+///
+/// The email is the context of its templates. `{TEMPLATE}.html.hbs` and `{TEMPLATE}.txt.hbs`
+pub trait Email: Serialize {
+    const TEMPLATE: &'static str;
+    fn subject(&self) -> String;
+}
+/// Who the email is sent to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipient {
+    pub name: String,
+    pub email: String,
+}
+impl Recipient {
+    pub fn new(name: impl Into<String>, email: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            email: email.into(),
+        }
+    }
+}
+impl From<&common::User> for Recipient {
+    fn from(user: &common::User) -> Self {
+        Self::new(user.name.clone(), user.email.as_ref())
+    }
+}
+#[derive(Debug)]
+struct QueuedEmail {
+    message: Message,
+    attempts: u32,
+}
+#[derive(Debug)]
+struct EmailSender {
+    from: Mailbox,
+    templates: EmailTemplates,
+    queue: UnboundedSender<QueuedEmail>,
+}
+#[derive(Debug)]
+pub struct EmailAccess {
+    sender: Option<EmailSender>,
+}
+impl EmailAccess {
+    /// Starts the send queue. Must be called inside the actix runtime
+    pub fn new(config: Option<EmailConfig>, home_url: Option<String>) -> anyhow::Result<Self> {
+        let Some(config) = config else {
+            info!("No email config provided. Emails will not be sent");
+            return Ok(Self { sender: None });
+        };
+        let from: Mailbox = config.from.parse()?;
+        let transport = EmailTransport::new(config.transport)?;
+        let templates = EmailTemplates::new(home_url)?;
+        let (queue, receiver) = mpsc::unbounded_channel();
+        actix_web::rt::spawn(process_queue(
+            transport,
+            receiver,
+            queue.clone(),
+            config.max_retries,
+            StdDuration::from_secs(config.retry_delay_seconds),
+        ));
+        Ok(Self {
+            sender: Some(EmailSender {
+                from,
+                templates,
+                queue,
+            }),
+        })
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.sender.is_some()
+    }
+    /// Renders the email and queues it
+    pub fn send<E: Email>(&self, to: impl Into<Recipient>, email: E) -> Result<(), EmailError> {
+        let to = to.into();
+        let Some(sender) = self.sender.as_ref() else {
+            debug!(
+                "Emails are disabled. Not sending {} to {}",
+                E::TEMPLATE,
+                to.email
+            );
+            return Ok(());
+        };
+        let (html, text) = sender.templates.render(&email)?;
+        let message = Message::builder()
+            .from(sender.from.clone())
+            .to(Mailbox::new(Some(to.name), to.email.parse()?))
+            .subject(email.subject())
+            .multipart(MultiPart::alternative_plain_html(text, html))?;
+        if sender
+            .queue
+            .send(QueuedEmail {
+                message,
+                attempts: 0,
+            })
+            .is_err()
+        {
+            error!("The email queue is closed. {} was dropped", E::TEMPLATE);
+        }
+        Ok(())
+    }
+    /// Same as [Self::send] but logs the error instead of returning it.
+    ///
+    /// For notifications that should not fail the request
+    pub fn send_or_log<E: Email>(&self, to: impl Into<Recipient>, email: E) {
+        if let Err(err) = self.send(to, email) {
+            error!("Failed to queue {} email: {}", E::TEMPLATE, err);
+        }
+    }
+}
+async fn process_queue(
+    transport: EmailTransport,
+    mut receiver: UnboundedReceiver<QueuedEmail>,
+    retry_queue: UnboundedSender<QueuedEmail>,
+    max_retries: u32,
+    retry_delay: StdDuration,
+) {
+    while let Some(mut email) = receiver.recv().await {
+        let Err(err) = transport.send(email.message.clone()).await else {
+            continue;
+        };
+        if email.attempts >= max_retries {
+            error!(
+                "Giving up on an email after {} attempts: {}",
+                email.attempts + 1,
+                err
+            );
+            continue;
+        }
+        let delay = retry_delay * 2u32.saturating_pow(email.attempts);
+        warn!("Failed to send an email. Retrying in {:?}: {}", delay, err);
+        email.attempts += 1;
+        let retry_queue = retry_queue.clone();
+        actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(delay).await;
+            let _ = retry_queue.send(email);
+        });
+    }
+}
@@ -0,0 +1,74 @@
+//! Email Templates
+//!
+//! Every email has an HTML and a plain text template.
+//! HTML templates are wrapped in the `layout` partial.
+//!
+//! Templates are given the email and `home_url`.
+use handlebars::Handlebars;
+use serde::Serialize;
+
+use super::{Email, EmailError};
+
+macro_rules! templates {
+    ($($name:literal),*) => {
+        &[
+            $(
+                (
+                    concat!($name, ".html"),
+                    include_str!(concat!("templates/", $name, ".html.hbs")),
+                ),
+                (
+                    concat!($name, ".txt"),
+                    include_str!(concat!("templates/", $name, ".txt.hbs")),
+                ),
+            )*
+        ]
+    };
+}
+/// (name, template)
+const TEMPLATES: &[(&str, &str)] = templates!(
+    "password_changed",
+    "verify_email",
+    "email_changed",
+    "password_reset",
+    "team_invitation"
+);
+const LAYOUT: &str = include_str!("templates/layout.html.hbs");
+
+#[derive(Debug, Serialize)]
+struct EmailContext<'a, E> {
+    home_url: Option<&'a str>,
+    #[serde(flatten)]
+    email: &'a E,
+}
+
+#[derive(Debug)]
+pub struct EmailTemplates {
+    registry: Handlebars<'static>,
+    home_url: Option<String>,
+}
+impl EmailTemplates {
+    pub fn new(home_url: Option<String>) -> Result<Self, handlebars::TemplateError> {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+        registry.register_partial("layout", LAYOUT)?;
+        for (name, template) in TEMPLATES {
+            registry.register_template_string(name, template)?;
+        }
+        Ok(Self { registry, home_url })
+    }
+    /// Returns (html, text)
+    pub fn render<E: Email>(&self, email: &E) -> Result<(String, String), EmailError> {
+        let context = EmailContext {
+            home_url: self.home_url.as_deref(),
+            email,
+        };
+        let html = self
+            .registry
+            .render(&format!("{}.html", E::TEMPLATE), &context)?;
+        let text = self
+            .registry
+            .render(&format!("{}.txt", E::TEMPLATE), &context)?;
+        Ok((html, text))
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+
+use lettre::{
+    transport::smtp::authentication::Credentials, AsyncFileTransport, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+
+use super::EmailError;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub enum SmtpEncryption {
+    /// Only for local mail servers. Credentials are sent in plain text
+    None,
+    #[default]
+    StartTLS,
+    TLS,
+}
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", content = "settings")]
+pub enum EmailTransportConfig {
+    SMTP {
+        host: String,
+        /// Defaults to the port of the encryption
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+        #[serde(default)]
+        encryption: SmtpEncryption,
+    },
+    /// Writes every email to `{directory}/{id}.eml`. For development and tests
+    File { directory: PathBuf },
+}
+impl Default for EmailTransportConfig {
+    fn default() -> Self {
+        Self::File {
+            directory: PathBuf::from("emails"),
+        }
+    }
+}
+#[derive(Debug)]
+pub enum EmailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+}
+impl EmailTransport {
+    pub fn new(config: EmailTransportConfig) -> anyhow::Result<Self> {
+        match config {
+            EmailTransportConfig::SMTP {
+                host,
+                port,
+                username,
+                password,
+                encryption,
+            } => {
+                let mut builder = match encryption {
+                    SmtpEncryption::None => {
+                        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host)
+                    }
+                    SmtpEncryption::StartTLS => {
+                        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?
+                    }
+                    SmtpEncryption::TLS => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+                };
+                if let Some(port) = port {
+                    builder = builder.port(port);
+                }
+                if let (Some(username), Some(password)) = (username, password) {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+                Ok(Self::Smtp(builder.build()))
+            }
+            EmailTransportConfig::File { directory } => {
+                std::fs::create_dir_all(&directory)?;
+                Ok(Self::File(AsyncFileTransport::new(directory)))
+            }
+        }
+    }
+    pub async fn send(&self, message: Message) -> Result<(), EmailError> {
+        match self {
+            EmailTransport::Smtp(transport) => {
+                transport.send(message).await?;
+            }
+            EmailTransport::File(transport) => {
+                transport.send(message).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,7 +5,12 @@ use serde::{ser::SerializeStruct, Serialize};
 use this_actix_error::ActixError;
 use thiserror::Error;
 
-use crate::user::session::SessionError;
+use crate::{
+    cli_access::CLIAccessError,
+    oauth::OAuthError,
+    user::{session::SessionError, two_factor::TwoFactorError},
+    webauthn::PasskeyError,
+};
 
 #[derive(Debug, Error, ActixError)]
 pub enum WebsiteError {
@@ -26,6 +31,18 @@ pub enum WebsiteError {
     #[error("Session Error")]
     #[status_code(INTERNAL_SERVER_ERROR)]
     SessionError(#[from] SessionError),
+    #[error("CLI Access Error")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    CLIAccessError(#[from] CLIAccessError),
+    #[error("Two Factor Error")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    TwoFactorError(#[from] TwoFactorError),
+    #[error("Passkey Error: {0}")]
+    #[status_code(BAD_REQUEST)]
+    PasskeyError(#[from] PasskeyError),
+    #[error("Single Sign On Error: {0}")]
+    #[status_code(BAD_REQUEST)]
+    OAuthError(#[from] OAuthError),
     #[error("Not Found")]
     #[status_code(NOT_FOUND)]
     NotFound,
@@ -0,0 +1,543 @@
+//! Heartbeat Ingestion
+//!
+//! Every heartbeat that is received goes through [ingest_heartbeat] before it is stored.
+use chrono::{Duration, Utc};
+use common::{
+    budget::{BudgetEvent, ProjectBudget},
+    heartbeat::{CodeChanges, HeartbeatCategory, HeartbeatType},
+    project_rules::{HeartbeatTarget, RuleAction, RuleSet},
+    team::TeamAPIToken,
+};
+use entities::{
+    budgets::{get_budget_for_project, get_budget_usage, get_crossed_thresholds},
+    project_rules::{get_rules_applied_to_team, get_rules_applied_to_user},
+    projects::{
+        can_user_contribute_to_project, get_team_project_by_name, get_user_project_by_name,
+    },
+    BudgetEventActiveModel, HeartbeatActiveModel, HeartbeatColumn, HeartbeatEntity, HeartbeatModel,
+    ProjectActiveModel, ProjectColumn, ProjectEntity, ProjectModel,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, QueryOrder};
+use tracing::{debug, info, instrument, warn};
+
+use crate::utils::time_utils;
+
+/// A heartbeat as it was sent by the client
+#[derive(Debug, Clone)]
+pub struct NewHeartbeat {
+    pub entity: String,
+    pub type_: HeartbeatType,
+    pub category: HeartbeatCategory,
+    pub code_change: Option<CodeChanges>,
+    /// The project name determined by the client
+    pub project: Option<String>,
+    pub branch: Option<String>,
+    pub language: Option<String>,
+    pub is_write: bool,
+    pub editor: Option<String>,
+    pub operating_system: Option<String>,
+    pub machine_name_id: String,
+    pub user_agent: String,
+    pub start_time: Option<DateTimeWithTimeZone>,
+}
+impl NewHeartbeat {
+    pub fn target(&self) -> HeartbeatTarget<'_> {
+        HeartbeatTarget {
+            entity: &self.entity,
+            project: self.project.as_deref(),
+            branch: self.branch.as_deref(),
+        }
+    }
+}
+#[derive(Debug, Clone)]
+pub enum IngestResult {
+    /// A rule with the action [RuleAction::Ignore] matched the heartbeat
+    Ignored { rule_id: i64 },
+    Stored {
+        heartbeat: HeartbeatModel,
+        /// Budget thresholds of the project that were crossed
+        budget_events: Vec<BudgetEvent>,
+    },
+}
+/// Loads the project rules that apply to the user
+pub async fn load_rules(user_id: i64, database: &impl ConnectionTrait) -> Result<RuleSet, DbErr> {
+    let rules = get_rules_applied_to_user(user_id, database).await?;
+    Ok(RuleSet::new(rules))
+}
+
+/// Applies the project rules to the heartbeat, resolves the project and stores the heartbeat.
+///
+/// The budget of the project is checked after the heartbeat is stored.
+#[instrument(skip(rules, database))]
+pub async fn ingest_heartbeat(
+    user_id: i64,
+    heartbeat: NewHeartbeat,
+    rules: &RuleSet,
+    database: &impl ConnectionTrait,
+) -> Result<IngestResult, DbErr> {
+    let project = match rules.evaluate(&heartbeat.target()) {
+        Some(rule) => match &rule.action {
+            RuleAction::Ignore => {
+                debug!("Heartbeat ignored by rule {}", rule.id);
+                return Ok(IngestResult::Ignored { rule_id: rule.id });
+            }
+            RuleAction::AssignProject(project) => {
+                if can_user_contribute_to_project(user_id, *project, database).await? {
+                    Some(*project)
+                } else {
+                    warn!(
+                        "Rule {} assigns to project {} which user {} can not contribute to",
+                        rule.id, project, user_id
+                    );
+                    resolve_project(user_id, heartbeat.project.as_deref(), database).await?
+                }
+            }
+            RuleAction::Rename(name) => resolve_project(user_id, Some(name), database).await?,
+        },
+        None => resolve_project(user_id, heartbeat.project.as_deref(), database).await?,
+    };
+    store_heartbeat(Some(user_id), None, project, heartbeat, database).await
+}
+/// Loads the project rules of the team and its organization. They apply to the heartbeats of its service accounts
+pub async fn load_team_rules(
+    team_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<RuleSet, DbErr> {
+    let rules = get_rules_applied_to_team(team_id, database).await?;
+    Ok(RuleSet::new(rules))
+}
+/// Applies the team's rules to a heartbeat sent by a service account and stores it.
+///
+/// The heartbeat can only be assigned to a project of the team that owns the key.
+#[instrument(skip(key, rules, database), fields(key = key.id, team = key.team_id))]
+pub async fn ingest_service_heartbeat(
+    key: &TeamAPIToken,
+    heartbeat: NewHeartbeat,
+    rules: &RuleSet,
+    database: &impl ConnectionTrait,
+) -> Result<IngestResult, DbErr> {
+    let team_id = key.team_id;
+    let project = match rules.evaluate(&heartbeat.target()) {
+        Some(rule) => match &rule.action {
+            RuleAction::Ignore => {
+                debug!("Heartbeat ignored by rule {}", rule.id);
+                return Ok(IngestResult::Ignored { rule_id: rule.id });
+            }
+            RuleAction::AssignProject(project) => {
+                if is_team_project(team_id, *project, database).await? {
+                    Some(*project)
+                } else {
+                    warn!(
+                        "Rule {} assigns to project {} which is not a project of team {}",
+                        rule.id, project, team_id
+                    );
+                    resolve_team_project(team_id, heartbeat.project.as_deref(), database).await?
+                }
+            }
+            RuleAction::Rename(name) => resolve_team_project(team_id, Some(name), database).await?,
+        },
+        None => resolve_team_project(team_id, heartbeat.project.as_deref(), database).await?,
+    };
+    store_heartbeat(None, Some(key.id), project, heartbeat, database).await
+}
+/// Heartbeats further apart than this are not continuous work. Two intervals of wakatime-cli
+fn heartbeat_timeout() -> Duration {
+    Duration::minutes(4)
+}
+/// Stores the heartbeat and checks the budget of its project
+async fn store_heartbeat(
+    user_id: Option<i64>,
+    team_api_key_id: Option<i64>,
+    project: Option<i64>,
+    heartbeat: NewHeartbeat,
+    database: &impl ConnectionTrait,
+) -> Result<IngestResult, DbErr> {
+    let start_time = heartbeat
+        .start_time
+        .unwrap_or_else(time_utils::get_current_time);
+    let continued = continue_open_heartbeat(
+        user_id,
+        team_api_key_id,
+        project,
+        &heartbeat,
+        start_time,
+        database,
+    )
+    .await?;
+    let heartbeat = match continued {
+        Some(heartbeat) => heartbeat,
+        None => {
+            insert_heartbeat(
+                user_id,
+                team_api_key_id,
+                project,
+                heartbeat,
+                start_time,
+                database,
+            )
+            .await?
+        }
+    };
+    let mut budget_events = Vec::new();
+    if let Some(project) = project {
+        ProjectEntity::update_many()
+            .filter(ProjectColumn::Id.eq(project))
+            .col_expr(ProjectColumn::LastHeartbeat, Expr::value(start_time))
+            .exec(database)
+            .await?;
+        budget_events = check_budget(project, database).await?;
+    }
+    Ok(IngestResult::Stored {
+        heartbeat,
+        budget_events,
+    })
+}
+/// Extends the open heartbeat of the sender's machine up to `start_time` if the new heartbeat continues it.
+///
+/// Returns the open heartbeat if the new one is for the same location. Otherwise the open heartbeat is closed
+/// and None is returned so the new heartbeat is stored as a new entry.
+async fn continue_open_heartbeat(
+    user_id: Option<i64>,
+    team_api_key_id: Option<i64>,
+    project: Option<i64>,
+    heartbeat: &NewHeartbeat,
+    start_time: DateTimeWithTimeZone,
+    database: &impl ConnectionTrait,
+) -> Result<Option<HeartbeatModel>, DbErr> {
+    let sender = match (user_id, team_api_key_id) {
+        (Some(user_id), _) => HeartbeatColumn::UserId.eq(user_id),
+        (None, Some(key_id)) => HeartbeatColumn::TeamApiKeyId.eq(key_id),
+        (None, None) => return Ok(None),
+    };
+    let Some(open) = HeartbeatEntity::find()
+        .filter(sender)
+        .filter(HeartbeatColumn::MachineNameId.eq(heartbeat.machine_name_id.as_str()))
+        .filter(HeartbeatColumn::Closed.eq(false))
+        .order_by_desc(HeartbeatColumn::EndTime)
+        .one(database)
+        .await?
+    else {
+        return Ok(None);
+    };
+    // Heartbeats sent late by an offline client do not touch the open heartbeat
+    if start_time < open.end_time {
+        return Ok(None);
+    }
+    // An entry never spans multiple days
+    let continues = start_time - open.end_time <= heartbeat_timeout()
+        && start_time.with_timezone(&Utc).date_naive()
+            == open.start_time.with_timezone(&Utc).date_naive();
+    let same_location = open.entity == heartbeat.entity
+        && open.project == project
+        && open.branch == heartbeat.branch;
+    let is_write = open.is_write || heartbeat.is_write;
+    let mut active: HeartbeatActiveModel = open.into();
+    if !continues {
+        active.closed = ActiveValue::Set(true);
+        active.update(database).await?;
+        return Ok(None);
+    }
+    active.end_time = ActiveValue::Set(start_time);
+    if same_location {
+        active.is_write = ActiveValue::Set(is_write);
+        return active.update(database).await.map(Some);
+    }
+    active.closed = ActiveValue::Set(true);
+    active.update(database).await?;
+    Ok(None)
+}
+async fn insert_heartbeat(
+    user_id: Option<i64>,
+    team_api_key_id: Option<i64>,
+    project: Option<i64>,
+    heartbeat: NewHeartbeat,
+    start_time: DateTimeWithTimeZone,
+    database: &impl ConnectionTrait,
+) -> Result<HeartbeatModel, DbErr> {
+    let NewHeartbeat {
+        entity,
+        type_,
+        category,
+        code_change,
+        branch,
+        language,
+        is_write,
+        editor,
+        operating_system,
+        machine_name_id,
+        user_agent,
+        ..
+    } = heartbeat;
+    HeartbeatActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        team_api_key_id: ActiveValue::Set(team_api_key_id),
+        entity: ActiveValue::Set(entity),
+        type_: ActiveValue::Set(type_),
+        category: ActiveValue::Set(category),
+        code_change: ActiveValue::Set(code_change),
+        project: ActiveValue::Set(project),
+        branch: ActiveValue::Set(branch),
+        language: ActiveValue::Set(language),
+        is_write: ActiveValue::Set(is_write),
+        editor: ActiveValue::Set(editor),
+        operating_system: ActiveValue::Set(operating_system),
+        machine_name_id: ActiveValue::Set(machine_name_id),
+        user_agent: ActiveValue::Set(user_agent),
+        start_time: ActiveValue::Set(start_time),
+        end_time: ActiveValue::Set(start_time),
+        closed: ActiveValue::Set(false),
+        ..Default::default()
+    }
+    .insert(database)
+    .await
+}
+/// Creates a [BudgetEvent] for every threshold of the project's budget that has been reached
+/// and has not already been crossed in the current period.
+///
+/// The usage of the period is only summed up while the budget has thresholds left to cross.
+pub async fn check_budget(
+    project_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<Vec<BudgetEvent>, DbErr> {
+    let Some(budget) = get_budget_for_project(project_id, database).await? else {
+        return Ok(Vec::new());
+    };
+    let budget = ProjectBudget::from(budget);
+    let now = time_utils::get_current_time();
+    let crossed =
+        get_crossed_thresholds(budget.id, budget.period.start_of_period(now), database).await?;
+    if budget
+        .thresholds
+        .iter()
+        .all(|threshold| crossed.contains(threshold))
+    {
+        return Ok(Vec::new());
+    }
+    let usage = get_budget_usage(budget, now, database).await?;
+    let mut events = Vec::new();
+    for threshold in usage.budget.reached_thresholds(usage.used_seconds) {
+        if crossed.contains(&threshold) {
+            continue;
+        }
+        let event = BudgetEventActiveModel {
+            budget_id: ActiveValue::Set(usage.budget.id),
+            project_id: ActiveValue::Set(project_id),
+            threshold: ActiveValue::Set(threshold),
+            period_start: ActiveValue::Set(usage.period_start),
+            used_seconds: ActiveValue::Set(usage.used_seconds),
+            budget_seconds: ActiveValue::Set(usage.budget.seconds),
+            ..Default::default()
+        }
+        .insert(database)
+        .await?;
+        info!(
+            "Project {} crossed {}% of its budget. {} of {} seconds used",
+            project_id, threshold, usage.used_seconds, usage.budget.seconds
+        );
+        events.push(BudgetEvent::from(event));
+    }
+    Ok(events)
+}
+/// Finds the project of the user with the name. If one does not exist it is created
+async fn resolve_project(
+    user_id: i64,
+    name: Option<&str>,
+    database: &impl ConnectionTrait,
+) -> Result<Option<i64>, DbErr> {
+    let Some(name) = name.filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+    if let Some(project) = get_user_project_by_name::<ProjectModel>(user_id, name, database).await?
+    {
+        return Ok(Some(project.id));
+    }
+    let project = ProjectActiveModel {
+        user_id: ActiveValue::Set(Some(user_id)),
+        name: ActiveValue::Set(name.to_owned()),
+        public: ActiveValue::Set(false),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    Ok(Some(project.id))
+}
+async fn is_team_project(
+    team_id: i64,
+    project_id: i64,
+    database: &impl ConnectionTrait,
+) -> Result<bool, DbErr> {
+    let project = ProjectEntity::find_by_id(project_id).one(database).await?;
+    Ok(project.is_some_and(|project| project.team_id == Some(team_id)))
+}
+/// Finds the project of the team with the name. If one does not exist it is created
+async fn resolve_team_project(
+    team_id: i64,
+    name: Option<&str>,
+    database: &impl ConnectionTrait,
+) -> Result<Option<i64>, DbErr> {
+    let Some(name) = name.filter(|name| !name.is_empty()) else {
+        return Ok(None);
+    };
+    if let Some(project) = get_team_project_by_name::<ProjectModel>(team_id, name, database).await?
+    {
+        return Ok(Some(project.id));
+    }
+    let project = ProjectActiveModel {
+        team_id: ActiveValue::Set(Some(team_id)),
+        name: ActiveValue::Set(name.to_owned()),
+        public: ActiveValue::Set(false),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    Ok(Some(project.id))
+}
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use common::budget::BudgetPeriod;
+    use entities::{budgets::get_budget_events, ProjectBudgetActiveModel};
+
+    use super::*;
+    use crate::test_utils;
+
+    fn heartbeat(project: &str, start_time: DateTimeWithTimeZone) -> NewHeartbeat {
+        NewHeartbeat {
+            entity: "/home/me/src/main.rs".to_owned(),
+            type_: HeartbeatType::File,
+            category: HeartbeatCategory::Coding,
+            code_change: None,
+            project: Some(project.to_owned()),
+            branch: Some("main".to_owned()),
+            language: Some("Rust".to_owned()),
+            is_write: false,
+            editor: None,
+            operating_system: None,
+            machine_name_id: "laptop".to_owned(),
+            user_agent: "test".to_owned(),
+            start_time: Some(start_time),
+        }
+    }
+
+    async fn store(
+        user_id: i64,
+        heartbeat: NewHeartbeat,
+        database: &DatabaseConnection,
+    ) -> HeartbeatModel {
+        let rules = RuleSet::new(Vec::new());
+        match ingest_heartbeat(user_id, heartbeat, &rules, database)
+            .await
+            .unwrap()
+        {
+            IngestResult::Stored { heartbeat, .. } => heartbeat,
+            IngestResult::Ignored { .. } => panic!("The heartbeat was ignored"),
+        }
+    }
+    async fn reload(heartbeat: &HeartbeatModel, database: &DatabaseConnection) -> HeartbeatModel {
+        HeartbeatEntity::find_by_id(heartbeat.id)
+            .one(database)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn heartbeats_within_the_timeout_extend_the_open_heartbeat() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let start = DateTime::parse_from_rfc3339("2024-01-10T12:00:00Z").unwrap();
+
+        let first = store(user.id, heartbeat("merged", start), &database).await;
+        let continued = store(
+            user.id,
+            heartbeat("merged", start + heartbeat_timeout()),
+            &database,
+        )
+        .await;
+        assert_eq!(continued.id, first.id);
+        assert_eq!(continued.end_time, start + heartbeat_timeout());
+        assert!(!continued.closed);
+
+        // One second past the timeout starts a new entry
+        let late = start + heartbeat_timeout() * 2 + Duration::seconds(1);
+        let after_gap = store(user.id, heartbeat("merged", late), &database).await;
+        assert_ne!(after_gap.id, first.id);
+        assert_eq!(after_gap.start_time, late);
+        let first = reload(&first, &database).await;
+        assert!(first.closed);
+        assert_eq!(first.end_time, start + heartbeat_timeout());
+    }
+
+    #[actix_web::test]
+    async fn heartbeats_do_not_continue_past_midnight() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let evening = DateTime::parse_from_rfc3339("2024-01-10T23:58:00Z").unwrap();
+        let morning = DateTime::parse_from_rfc3339("2024-01-11T00:01:00Z").unwrap();
+
+        let first = store(user.id, heartbeat("midnight", evening), &database).await;
+        let next_day = store(user.id, heartbeat("midnight", morning), &database).await;
+        assert_ne!(next_day.id, first.id);
+        assert_eq!(next_day.start_time, morning);
+        let first = reload(&first, &database).await;
+        assert!(first.closed);
+        assert_eq!(first.end_time, evening);
+    }
+
+    #[actix_web::test]
+    async fn budget_thresholds_are_recorded_once_per_period() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let project = ProjectActiveModel {
+            user_id: ActiveValue::Set(Some(user.id)),
+            name: ActiveValue::Set("budgeted".to_owned()),
+            public: ActiveValue::Set(false),
+            ..Default::default()
+        }
+        .insert(&database)
+        .await
+        .unwrap();
+        ProjectBudgetActiveModel {
+            project_id: ActiveValue::Set(project.id),
+            period: ActiveValue::Set(BudgetPeriod::Total),
+            seconds: ActiveValue::Set(120),
+            thresholds: ActiveValue::Set(vec![50, 100]),
+            ..Default::default()
+        }
+        .insert(&database)
+        .await
+        .unwrap();
+        let rules = RuleSet::new(Vec::new());
+        let start = DateTime::parse_from_rfc3339("2024-01-10T12:00:00Z").unwrap();
+
+        let mut crossed = Vec::new();
+        // One heartbeat a minute. The budget is two minutes
+        for minute in 0..5 {
+            let heartbeat = heartbeat("budgeted", start + Duration::minutes(minute));
+            let IngestResult::Stored { budget_events, .. } =
+                ingest_heartbeat(user.id, heartbeat, &rules, &database)
+                    .await
+                    .unwrap()
+            else {
+                panic!("The heartbeat was ignored");
+            };
+            crossed.push(
+                budget_events
+                    .iter()
+                    .map(|event| event.threshold)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(crossed, [vec![], vec![50], vec![100], vec![], vec![]]);
+
+        let events = get_budget_events(project.id, &database).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| event.period_start.is_none()));
+    }
+}
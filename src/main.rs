@@ -1,4 +1,5 @@
 pub mod config;
+pub mod email;
 pub mod error;
 pub mod open_api;
 pub mod state;
@@ -6,22 +7,34 @@ pub mod tracing_setup;
 pub mod utils;
 use std::sync::atomic::AtomicBool;
 pub use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
+pub mod heartbeats;
+pub mod oauth;
+pub mod organizations;
 pub mod projects;
 pub mod recaptcha;
+pub mod teams;
 pub mod user;
 pub mod waka_time;
 use actix_cors::Cors;
 use actix_web::{get, web::Data, App, HttpServer, Scope};
 use clap::Parser;
 use config::{ServerConfig, SessionConfigFull};
+use email::EmailAccess;
 use entities::users::does_first_user_exist;
 use migration::{Migrator, MigratorTrait};
+use oauth::OAuthAccess;
 use open_api::ApiDoc;
 use recaptcha::RecaptchaAccess;
 use rustls::{Certificate, PrivateKey, ServerConfig as RustlsServerConfig};
 use rustls_pemfile::{certs, pkcs8_private_keys};
 use sea_orm::Database;
+use webauthn::WebAuthnAccess;
+pub mod api_key_usage;
 pub mod cli_access;
+pub mod client_ip;
+#[cfg(test)]
+pub mod test_utils;
+pub mod webauthn;
 use human_panic::setup_panic;
 use state::State;
 use tracing_actix_web::TracingLogger;
@@ -55,6 +68,12 @@ async fn main() -> std::io::Result<()> {
         tracing,
         public_registration,
         recaptcha,
+        trusted_proxies,
+        cli_access,
+        email,
+        password_login,
+        oidc,
+        github,
     } = if !args.config.exists() {
         let config = ServerConfig::default();
         let config = toml::to_string(&config)
@@ -93,6 +112,30 @@ async fn main() -> std::io::Result<()> {
                 format!("Failed to create recaptcha access: {}", e),
             )
         })?;
+    let email_access = EmailAccess::new(email, home_url.clone())
+        .map(Data::new)
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to create email access: {}", e),
+            )
+        })?;
+    let webauthn_access = WebAuthnAccess::new(home_url.as_deref())
+        .map(Data::new)
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to create webauthn access: {}", e),
+            )
+        })?;
+    let oauth_access = OAuthAccess::new(oidc, github, home_url.clone())
+        .map(Data::new)
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("Failed to create single sign on access: {}", e),
+            )
+        })?;
     let first_user = does_first_user_exist(&database)
         .await
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
@@ -102,6 +145,9 @@ async fn main() -> std::io::Result<()> {
         home_url,
         public_registration,
         recaptcha_config: recaptcha_access.state_value(),
+        passkeys: webauthn_access.is_enabled(),
+        password_login,
+        github_connections: oauth_access.github_enabled(),
         ..Default::default()
     });
     let session = SessionManagerType::new(manager, session_config.clone()).map_err(|e| {
@@ -112,7 +158,23 @@ async fn main() -> std::io::Result<()> {
     })?;
     let database = Data::new(database);
     let session = Data::new(session);
-    let cli_access = Data::new(cli_access::CLIAccess::new());
+    let cli_access = Data::new(cli_access::CLIAccess::new(cli_access, database.clone()));
+    {
+        let cli_access = cli_access.clone();
+        let database = database.clone();
+        actix_web::rt::spawn(async move {
+            cli_access::sweep_periodically(cli_access.as_ref(), database.as_ref()).await;
+        });
+    }
+    let api_key_usage = Data::new(api_key_usage::APIKeyUsage::new());
+    let trusted_proxies = Data::new(client_ip::TrustedProxies::new(trusted_proxies));
+    {
+        let api_key_usage = api_key_usage.clone();
+        let database = database.clone();
+        actix_web::rt::spawn(async move {
+            api_key_usage.flush_periodically(database.as_ref()).await;
+        });
+    }
     let openapi = Data::new(ApiDoc::openapi());
 
     let server = HttpServer::new(move || {
@@ -127,8 +189,13 @@ async fn main() -> std::io::Result<()> {
             .app_data(session.clone())
             .app_data(state.clone())
             .app_data(recaptcha_access.clone())
+            .app_data(email_access.clone())
+            .app_data(webauthn_access.clone())
+            .app_data(oauth_access.clone())
             .app_data(openapi.clone())
             .app_data(cli_access.clone())
+            .app_data(api_key_usage.clone())
+            .app_data(trusted_proxies.clone())
             .wrap(TracingLogger::default())
             .wrap(cors)
             .service(openapi_json)
@@ -140,9 +207,18 @@ async fn main() -> std::io::Result<()> {
                     })
                     .configure(user::routes::init)
                     .configure(user::update_routes::init)
+                    .configure(user::api_keys::init)
                     .configure(user::cli::init)
+                    .configure(user::email_verification::init)
+                    .configure(user::password_reset::init)
+                    .configure(user::two_factor::init)
+                    .configure(user::passkeys::init)
+                    .configure(oauth::init)
                     .configure(projects::init)
-                    .service(Scope::new("/admin")),
+                    .configure(teams::init)
+                    .configure(organizations::init)
+                    .configure(waka_time::init)
+                    .service(Scope::new("/admin").configure(user::email_verification::init_admin)),
             )
     });
     let server = if let Some(workers) = workers {
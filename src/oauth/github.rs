@@ -0,0 +1,487 @@
+//! Connecting a GitHub account
+//!
+//! GitHub is not a login provider. A logged in user links their account and it is shown on their profile.
+//!
+//! The connection stores the GitHub user id as the account id and the login as `other_data.username`.
+//! Its token is the only token kept. The refresh token for apps with expiring tokens, otherwise the access token.
+use actix_web::{
+    delete, get,
+    http::header::{ACCEPT, LOCATION},
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use chrono::Duration;
+use common::Group;
+use entities::{Application, ConnectionColumn, ConnectionEntity, ConnectionModel};
+use reqwest::Url;
+use sea_orm::{entity::prelude::*, Condition, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+use super::{
+    check_linking_session, clear_state_cookie, create_state, login_or_provision, state_cookie,
+    take_state, ExternalIdentity, OAuthAccess, OAuthError,
+};
+use crate::{
+    email::EmailAccess, error::WebsiteError, state::State, user::SessionAuthentication,
+    utils::time_utils,
+};
+
+/// The provider id used for the `oauth_states` of GitHub
+const STATE_PROVIDER: &str = "github";
+/// A GitHub OAuth App
+///
+/// The callback URL to register with the app is `{home_url}/api/connections/github/callback`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GithubConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Where users authorize the app. Change it to point at a local stand-in
+    pub oauth_url: String,
+    /// The base URL of the REST API. Change it to point at a local stand-in
+    pub api_url: String,
+}
+impl Default for GithubConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::default(),
+            client_secret: String::default(),
+            oauth_url: "https://github.com".to_owned(),
+            api_url: "https://api.github.com".to_owned(),
+        }
+    }
+}
+impl GithubConfig {
+    pub fn redirect_uri(&self, home_url: &str) -> String {
+        format!("{}/api/connections/github/callback", home_url)
+    }
+    fn oauth_url(&self) -> &str {
+        self.oauth_url.trim_end_matches('/')
+    }
+    fn api_url(&self) -> &str {
+        self.api_url.trim_end_matches('/')
+    }
+}
+#[utoipa::path(get,
+    impl_for=link,
+    path = "/api/connections/github/link",
+    responses(
+        (status = 302, description = "Redirect to GitHub"),
+        (status = 401, description = "You are not logged in"),
+        (status = 404, description = "GitHub is not configured"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[get("/connections/github/link")]
+pub async fn link(
+    auth: SessionAuthentication,
+    oauth: Data<OAuthAccess>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let home_url = oauth.home_url()?;
+    let github = oauth.github()?;
+    let pending =
+        create_state(STATE_PROVIDER, false, Some(auth.user.id), database.as_ref()).await?;
+    let url = Url::parse_with_params(
+        &format!("{}/login/oauth/authorize", github.oauth_url()),
+        &[
+            ("client_id", github.client_id.as_str()),
+            ("redirect_uri", github.redirect_uri(home_url).as_str()),
+            ("scope", "read:user"),
+            ("state", pending.state.as_str()),
+            ("code_challenge", pending.code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+            ("allow_signup", "false"),
+        ],
+    )
+    .map_err(|_| OAuthError::InvalidResponse("Invalid oauth_url"))?;
+    Ok(HttpResponse::Found()
+        .cookie(state_cookie(&pending.state))
+        .insert_header((LOCATION, url.to_string()))
+        .finish())
+}
+/// Which token is stored as the token of the connection. Kept in `other_data_private.token_type`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StoredToken {
+    /// The access token of an app with tokens that do not expire
+    #[default]
+    AccessToken,
+    /// The refresh token of an app with expiring tokens. An access token is requested when needed
+    RefreshToken,
+}
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct PrivateData {
+    token_type: StoredToken,
+}
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+/// GitHub responds with 200 even if the exchange failed
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    /// Only for apps with expiring tokens
+    refresh_token: Option<String>,
+    refresh_token_expires_in: Option<i64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    id: i64,
+    login: String,
+    name: Option<String>,
+}
+#[utoipa::path(get,
+    impl_for=callback,
+    path = "/api/connections/github/callback",
+    params(
+        ("state" = String, Query, description = "The state sent to GitHub"),
+        ("code" = Option<String>, Query, description = "The authorization code"),
+        ("error" = Option<String>, Query, description = "Set if the user denied access"),
+    ),
+    responses(
+        (status = 302, description = "Linked. Redirect to the home page"),
+        (status = 400, description = "The link failed, was started in another browser or finished in the session of another user"),
+        (status = 409, description = "The GitHub account is linked to another user"),
+    ),
+)]
+#[get("/connections/github/callback")]
+pub async fn callback(
+    query: web::Query<CallbackQuery>,
+    request: HttpRequest,
+    auth: Option<SessionAuthentication>,
+    oauth: Data<OAuthAccess>,
+    database: Data<DatabaseConnection>,
+    state: Data<State>,
+    email_access: Data<EmailAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let query = query.into_inner();
+    let home_url = oauth.home_url()?;
+    let github = oauth.github()?;
+    let login = take_state(&request, &query.state, STATE_PROVIDER, database.as_ref()).await?;
+    // GitHub is only linked. So the state always belongs to a user
+    let Some(user_id) = login.user_id else {
+        return Err(OAuthError::InvalidState.into());
+    };
+    check_linking_session(&login, auth.as_ref())?;
+    if let Some(error) = query.error {
+        return Err(OAuthError::ProviderError(query.error_description.unwrap_or(error)).into());
+    }
+    let Some(code) = query.code else {
+        return Err(OAuthError::ProviderError("No code was returned".to_owned()).into());
+    };
+    let redirect_uri = github.redirect_uri(home_url);
+    let tokens: TokenResponse = oauth
+        .http_client
+        .post(format!("{}/login/oauth/access_token", github.oauth_url()))
+        .header(ACCEPT, "application/json")
+        .form(&[
+            ("client_id", github.client_id.as_str()),
+            ("client_secret", github.client_secret.as_str()),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_verifier", login.code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(OAuthError::from)?
+        .json()
+        .await
+        .map_err(OAuthError::from)?;
+    if let Some(error) = tokens.error {
+        return Err(OAuthError::ProviderError(tokens.error_description.unwrap_or(error)).into());
+    }
+    let access_token = tokens
+        .access_token
+        .ok_or(OAuthError::InvalidResponse("No access token"))?;
+    let github_user: GithubUser = oauth
+        .http_client
+        .get(format!("{}/user", github.api_url()))
+        .header(ACCEPT, "application/vnd.github+json")
+        .bearer_auth(&access_token)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(OAuthError::from)?
+        .json()
+        .await
+        .map_err(OAuthError::from)?;
+    let account_id = github_user.id.to_string();
+    let (token, token_type, expires_at) = match tokens.refresh_token {
+        Some(refresh_token) => (
+            refresh_token,
+            StoredToken::RefreshToken,
+            tokens
+                .refresh_token_expires_in
+                .map(|seconds| time_utils::get_current_time() + Duration::seconds(seconds)),
+        ),
+        None => (access_token, StoredToken::AccessToken, None),
+    };
+    let identity = ExternalIdentity {
+        application: Application::Github,
+        provider: None,
+        account_id: account_id.clone(),
+        email: None,
+        email_verified: false,
+        username: Some(github_user.login.clone()),
+        name: github_user.name,
+        group: Group::User,
+        token: Some(token),
+        expires_at,
+        other_data: Some(json!({ "username": github_user.login })),
+        other_data_private: Some(json!(PrivateData { token_type })),
+    };
+    login_or_provision(
+        identity,
+        Some(user_id),
+        false,
+        &state,
+        &email_access,
+        database.as_ref(),
+    )
+    .await?;
+    // A user has one GitHub account. Linking another one replaces it
+    ConnectionEntity::delete_many()
+        .filter(ConnectionColumn::UserId.eq(user_id))
+        .filter(ConnectionColumn::Application.eq(Application::Github))
+        .filter(
+            Condition::any()
+                .add(ConnectionColumn::AccountId.ne(account_id))
+                .add(ConnectionColumn::AccountId.is_null()),
+        )
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::Found()
+        .cookie(clear_state_cookie())
+        .insert_header((LOCATION, home_url.to_owned()))
+        .finish())
+}
+#[utoipa::path(delete,
+    impl_for=unlink,
+    path = "/api/me/connections/github",
+    responses(
+        (status = 204, description = "Unlinked"),
+        (status = 401, description = "You are not logged in"),
+        (status = 404, description = "No GitHub account is linked"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[delete("/me/connections/github")]
+pub async fn unlink(
+    auth: SessionAuthentication,
+    oauth: Data<OAuthAccess>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let connections = ConnectionEntity::find()
+        .filter(ConnectionColumn::UserId.eq(auth.user.id))
+        .filter(ConnectionColumn::Application.eq(Application::Github))
+        .all(database.as_ref())
+        .await?;
+    if connections.is_empty() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    for connection in connections {
+        if let Ok(github) = oauth.github() {
+            // Unlinking still works if GitHub can not be reached
+            if let Err(error) = revoke_connection(&oauth, github, &connection).await {
+                warn!(
+                    "Failed to revoke the GitHub grant of user {}: {}",
+                    auth.user.id, error
+                );
+            }
+        }
+        ConnectionEntity::delete_by_id(connection.id)
+            .exec(database.as_ref())
+            .await?;
+    }
+    info!("User {} unlinked their GitHub account", auth.user.id);
+    Ok(HttpResponse::NoContent().finish())
+}
+/// Revokes the grant of the connection. Refreshes the access token first if only the refresh token is stored
+async fn revoke_connection(
+    oauth: &OAuthAccess,
+    github: &GithubConfig,
+    connection: &ConnectionModel,
+) -> Result<(), OAuthError> {
+    let data: PrivateData = connection
+        .other_data_private
+        .clone()
+        .and_then(|data| serde_json::from_value(data).ok())
+        .unwrap_or_default();
+    let access_token = match data.token_type {
+        StoredToken::AccessToken => connection.token.clone(),
+        StoredToken::RefreshToken => refresh_access_token(oauth, github, &connection.token).await?,
+    };
+    revoke_grant(oauth, github, &access_token).await?;
+    Ok(())
+}
+/// Exchanges the refresh token for a new access token
+async fn refresh_access_token(
+    oauth: &OAuthAccess,
+    github: &GithubConfig,
+    refresh_token: &str,
+) -> Result<String, OAuthError> {
+    let tokens: TokenResponse = oauth
+        .http_client
+        .post(format!("{}/login/oauth/access_token", github.oauth_url()))
+        .header(ACCEPT, "application/json")
+        .form(&[
+            ("client_id", github.client_id.as_str()),
+            ("client_secret", github.client_secret.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    if let Some(error) = tokens.error {
+        return Err(OAuthError::ProviderError(
+            tokens.error_description.unwrap_or(error),
+        ));
+    }
+    tokens
+        .access_token
+        .ok_or(OAuthError::InvalidResponse("No access token"))
+}
+/// Removes the app from the authorized apps of the GitHub user
+async fn revoke_grant(
+    oauth: &OAuthAccess,
+    github: &GithubConfig,
+    access_token: &str,
+) -> Result<(), reqwest::Error> {
+    oauth
+        .http_client
+        .delete(format!(
+            "{}/applications/{}/grant",
+            github.api_url(),
+            github.client_id
+        ))
+        .header(ACCEPT, "application/vnd.github+json")
+        .basic_auth(&github.client_id, Some(&github.client_secret))
+        .json(&json!({ "access_token": access_token }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use actix_web::{App, HttpServer};
+    use reqwest::Client;
+
+    use super::*;
+
+    /// The access tokens the stand-in revoked
+    type Revoked = Arc<Mutex<Vec<String>>>;
+
+    /// Only hands out an access token for the refresh token `refresh`
+    async fn token(form: web::Form<Vec<(String, String)>>) -> HttpResponse {
+        let refreshed = form
+            .iter()
+            .any(|(key, value)| key == "grant_type" && value == "refresh_token")
+            && form
+                .iter()
+                .any(|(key, value)| key == "refresh_token" && value == "refresh");
+        if !refreshed {
+            return HttpResponse::Ok().json(json!({ "error": "bad_refresh_token" }));
+        }
+        HttpResponse::Ok().json(json!({ "access_token": "fresh", "refresh_token": "next" }))
+    }
+    async fn revoke(revoked: Data<Revoked>, body: web::Json<serde_json::Value>) -> HttpResponse {
+        let access_token = body["access_token"].as_str().unwrap_or_default().to_owned();
+        revoked.lock().unwrap().push(access_token);
+        HttpResponse::NoContent().finish()
+    }
+    /// Starts a stand-in for GitHub on a random local port
+    fn start_stand_in() -> (GithubConfig, Revoked) {
+        let revoked = Revoked::default();
+        let data = Data::new(revoked.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/login/oauth/access_token", web::post().to(token))
+                .route("/applications/{client_id}/grant", web::delete().to(revoke))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        let github = GithubConfig {
+            client_id: "codi-time".to_owned(),
+            client_secret: "secret".to_owned(),
+            oauth_url: url.clone(),
+            api_url: url,
+        };
+        (github, revoked)
+    }
+    fn connection(token: &str, token_type: StoredToken) -> ConnectionModel {
+        ConnectionModel {
+            id: 1,
+            user_id: 1,
+            other_data: None,
+            other_data_private: Some(json!(PrivateData { token_type })),
+            application: Application::Github,
+            provider: None,
+            account_id: Some("1".to_owned()),
+            token: token.to_owned(),
+            expires_at: None,
+            created: time_utils::get_current_time(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn unlinking_revokes_the_stored_or_a_refreshed_access_token() {
+        let (github, revoked) = start_stand_in();
+        let oauth = OAuthAccess {
+            http_client: Client::new(),
+            home_url: None,
+            oidc: vec![],
+            github: None,
+        };
+
+        revoke_connection(
+            &oauth,
+            &github,
+            &connection("access", StoredToken::AccessToken),
+        )
+        .await
+        .unwrap();
+        revoke_connection(
+            &oauth,
+            &github,
+            &connection("refresh", StoredToken::RefreshToken),
+        )
+        .await
+        .unwrap();
+        assert_eq!(*revoked.lock().unwrap(), ["access", "fresh"]);
+
+        // Nothing is revoked if the refresh fails
+        let refused = revoke_connection(
+            &oauth,
+            &github,
+            &connection("expired", StoredToken::RefreshToken),
+        )
+        .await;
+        assert!(matches!(refused, Err(OAuthError::ProviderError(_))));
+        assert_eq!(revoked.lock().unwrap().len(), 2);
+    }
+}
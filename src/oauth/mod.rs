@@ -0,0 +1,503 @@
+//! Logging in through external providers
+//!
+//! Every provider uses the authorization code flow with PKCE.
+//! The login route stores an `oauth_states` row, sets the state cookie and redirects to the provider.
+//! The provider redirects back to the callback route which takes the row so it can only be used once.
+//! The callback only accepts the state if the browser sends back the matching state cookie.
+//! Linking a provider is bound to the user that started it through the state.
+//! The session cookie is Strict. So it is not needed when the provider redirects back.
+//!
+//! Accounts are linked through the `connections` table by the provider and the id of the account in the provider.
+pub mod github;
+pub mod oidc;
+
+use actix_web::{
+    cookie::{time::Duration as CookieDuration, Cookie, CookieBuilder, SameSite},
+    get, web,
+    web::Data,
+    HttpRequest, HttpResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Duration;
+use common::{Email, Group, Username};
+use entities::{
+    teams::claim_invites_for_email,
+    users::{does_email_exist, does_username_exist},
+    Application, ConnectionActiveModel, ConnectionColumn, ConnectionEntity, OAuthStateActiveModel,
+    OAuthStateColumn, OAuthStateEntity, OAuthStateModel, UserActiveModel, UserEntity, UserModel,
+};
+use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, SeedableRng};
+use reqwest::Client;
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, JsonValue};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use this_actix_error::ActixError;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+use self::{
+    github::GithubConfig,
+    oidc::{OIDCProvider, OIDCProviderConfig},
+};
+use crate::{
+    email::EmailAccess,
+    error::WebsiteError,
+    recaptcha::new_client,
+    state::State,
+    user::{email_verification::send_verification, SessionAuthentication},
+    utils::{password, time_utils},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_providers)
+        .service(oidc::login)
+        .service(oidc::callback)
+        .service(github::link)
+        .service(github::callback)
+        .service(github::unlink);
+}
+/// How long the user has to finish logging in with the provider
+pub fn state_lifetime() -> Duration {
+    Duration::minutes(10)
+}
+/// Binds a login to the browser that started it
+const STATE_COOKIE: &str = "oauth_state";
+/// Set when redirecting to the provider.
+///
+/// Lax so it is sent when the provider redirects back to the callback
+pub fn state_cookie(state: &str) -> Cookie<'static> {
+    CookieBuilder::new(STATE_COOKIE, state.to_owned())
+        .path("/api")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(state_lifetime().num_seconds()))
+        .finish()
+}
+/// Removes the state cookie once the callback used it
+pub fn clear_state_cookie() -> Cookie<'static> {
+    let mut cookie = state_cookie("");
+    cookie.make_removal();
+    cookie
+}
+#[derive(Debug, Error, ActixError)]
+pub enum OAuthError {
+    #[error("Unknown provider")]
+    #[status_code(NOT_FOUND)]
+    UnknownProvider,
+    #[error("Single sign on requires home_url to be set")]
+    #[status_code(NOT_FOUND)]
+    NoHomeUrl,
+    #[error("The login expired or was already used")]
+    #[status_code(BAD_REQUEST)]
+    InvalidState,
+    #[error("The provider returned an error: {0}")]
+    #[status_code(BAD_REQUEST)]
+    ProviderError(String),
+    #[error("Failed to contact the provider: {0}")]
+    #[status_code(BAD_GATEWAY)]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid response from the provider: {0}")]
+    #[status_code(BAD_GATEWAY)]
+    InvalidResponse(&'static str),
+    #[error("Invalid ID token: {0}")]
+    #[status_code(BAD_REQUEST)]
+    InvalidIdToken(&'static str),
+    #[error("The provider did not give a valid email address")]
+    #[status_code(BAD_REQUEST)]
+    MissingEmail,
+    #[error("Accounts can not be created through this provider")]
+    #[status_code(FORBIDDEN)]
+    ProvisioningDisabled,
+    #[error("An account with this email already exists. Log in and link the provider instead")]
+    #[status_code(CONFLICT)]
+    EmailInUse,
+    #[error("The account is already linked to another user")]
+    #[status_code(CONFLICT)]
+    AlreadyLinked,
+}
+/// The configured providers
+#[derive(Debug)]
+pub struct OAuthAccess {
+    pub http_client: Client,
+    /// The redirect URIs are built from this. None disables every provider
+    pub home_url: Option<String>,
+    pub oidc: Vec<OIDCProvider>,
+    pub github: Option<GithubConfig>,
+}
+impl OAuthAccess {
+    pub fn new(
+        oidc: Vec<OIDCProviderConfig>,
+        github: Option<GithubConfig>,
+        home_url: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let home_url = home_url.map(|url| url.trim_end_matches('/').to_owned());
+        if home_url.is_none() && !oidc.is_empty() {
+            info!("No home_url provided. Single sign on is disabled");
+        }
+        let github = match github {
+            Some(github) if github.client_id.is_empty() || github.client_secret.is_empty() => {
+                warn!("GitHub client id or secret is empty, disabling GitHub connections");
+                None
+            }
+            github => github,
+        };
+        Ok(Self {
+            http_client: new_client()?,
+            oidc: oidc
+                .into_iter()
+                .map(OIDCProvider::new)
+                .collect::<anyhow::Result<_>>()?,
+            github,
+            home_url,
+        })
+    }
+    /// True if users can link their GitHub account
+    pub fn github_enabled(&self) -> bool {
+        self.home_url.is_some() && self.github.is_some()
+    }
+    pub fn github(&self) -> Result<&GithubConfig, OAuthError> {
+        self.github.as_ref().ok_or(OAuthError::UnknownProvider)
+    }
+    pub fn home_url(&self) -> Result<&str, OAuthError> {
+        self.home_url.as_deref().ok_or(OAuthError::NoHomeUrl)
+    }
+    pub fn get_oidc(&self, id: &str) -> Result<&OIDCProvider, OAuthError> {
+        self.oidc
+            .iter()
+            .find(|provider| provider.config.id == id)
+            .ok_or(OAuthError::UnknownProvider)
+    }
+    /// The providers shown on the login page
+    pub fn providers(&self) -> Vec<SSOProvider> {
+        if self.home_url.is_none() {
+            return vec![];
+        }
+        self.oidc
+            .iter()
+            .map(|provider| SSOProvider {
+                id: provider.config.id.clone(),
+                name: provider.config.name.clone(),
+                application: Application::OpenIDConnect.to_string(),
+                login_url: format!("/api/sso/oidc/{}/login", provider.config.id),
+            })
+            .collect()
+    }
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SSOProvider {
+    pub id: String,
+    pub name: String,
+    pub application: String,
+    /// Send the browser here to log in. Add `?link=true` to link the provider to the logged in user
+    pub login_url: String,
+}
+#[utoipa::path(get,
+    impl_for=list_providers,
+    path = "/api/sso/providers",
+    responses(
+        (status = 200, description = "Single sign on providers", body = [SSOProvider]),
+    ),
+)]
+#[get("/sso/providers")]
+pub async fn list_providers(oauth: Data<OAuthAccess>) -> HttpResponse {
+    HttpResponse::Ok().json(oauth.providers())
+}
+
+fn random_string(length: usize) -> String {
+    StdRng::from_entropy()
+        .sample_iter(Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+/// The S256 code challenge for a PKCE code verifier
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+/// A login that is waiting for the provider
+#[derive(Debug)]
+pub struct PendingLogin {
+    pub state: String,
+    pub code_challenge: String,
+    pub nonce: Option<String>,
+}
+/// Stores the state of a login. Returns the parameters to send to the provider
+///
+/// `user_id` is set if the provider is being linked to a logged in user
+pub async fn create_state(
+    provider: &str,
+    with_nonce: bool,
+    user_id: Option<i64>,
+    database: &DatabaseConnection,
+) -> Result<PendingLogin, WebsiteError> {
+    let now = time_utils::get_current_time();
+    // Abandoned logins are cleaned up here
+    OAuthStateEntity::delete_many()
+        .filter(OAuthStateColumn::Expires.lte(now))
+        .exec(database)
+        .await?;
+    let state = random_string(32);
+    // RFC 7636 requires 43 to 128 characters
+    let code_verifier = random_string(64);
+    let nonce = with_nonce.then(|| random_string(32));
+    OAuthStateActiveModel {
+        state: ActiveValue::Set(state.clone()),
+        provider: ActiveValue::Set(provider.to_owned()),
+        code_verifier: ActiveValue::Set(code_verifier.clone()),
+        nonce: ActiveValue::Set(nonce.clone()),
+        user_id: ActiveValue::Set(user_id),
+        expires: ActiveValue::Set(now + state_lifetime()),
+        created: ActiveValue::Set(now),
+    }
+    .insert(database)
+    .await?;
+    Ok(PendingLogin {
+        state,
+        code_challenge: pkce_challenge(&code_verifier),
+        nonce,
+    })
+}
+/// Removes the state so it can only be used once.
+///
+/// The state must match the state cookie. So a login can not be finished in a browser other than the one that started it.
+pub async fn take_state(
+    request: &HttpRequest,
+    state: &str,
+    provider: &str,
+    database: &DatabaseConnection,
+) -> Result<OAuthStateModel, WebsiteError> {
+    if request.cookie(STATE_COOKIE).as_ref().map(Cookie::value) != Some(state) {
+        warn!(
+            "The state of a {} login does not match the state cookie",
+            provider
+        );
+        return Err(OAuthError::InvalidState.into());
+    }
+    let Some(model) = OAuthStateEntity::find_by_id(state.to_owned())
+        .filter(OAuthStateColumn::Provider.eq(provider))
+        .filter(OAuthStateColumn::Expires.gt(time_utils::get_current_time()))
+        .one(database)
+        .await?
+    else {
+        return Err(OAuthError::InvalidState.into());
+    };
+    let deleted = OAuthStateEntity::delete_by_id(state.to_owned())
+        .exec(database)
+        .await?;
+    if deleted.rows_affected == 0 {
+        return Err(OAuthError::InvalidState.into());
+    }
+    Ok(model)
+}
+/// A login that links the provider to a user is bound to them through the state.
+///
+/// The session is usually not sent with the redirect from the provider. If it is, it must be the same user
+pub fn check_linking_session(
+    login: &OAuthStateModel,
+    auth: Option<&SessionAuthentication>,
+) -> Result<(), OAuthError> {
+    let Some(user_id) = login.user_id else {
+        return Ok(());
+    };
+    if auth.is_some_and(|auth| auth.user.id != user_id) {
+        warn!(
+            "User {} started linking {} but it was finished in the session of another user",
+            user_id, login.provider
+        );
+        return Err(OAuthError::InvalidState);
+    }
+    Ok(())
+}
+/// The account as described by the provider
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    pub application: Application,
+    /// The id of the provider in the config
+    pub provider: Option<String>,
+    /// The id of the account in the provider. Never changes
+    pub account_id: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+    pub name: Option<String>,
+    /// The group given to the user if they are created
+    pub group: Group,
+    /// Stored as the token of the connection
+    pub token: Option<String>,
+    /// When the token expires
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub other_data: Option<JsonValue>,
+    pub other_data_private: Option<JsonValue>,
+}
+/// Finds the user linked to the identity.
+///
+/// If `linking_user` is set the identity is linked to them.
+/// Otherwise if `auto_provision` is true a user is created.
+pub async fn login_or_provision(
+    identity: ExternalIdentity,
+    linking_user: Option<i64>,
+    auto_provision: bool,
+    state: &State,
+    email_access: &EmailAccess,
+    database: &DatabaseConnection,
+) -> Result<UserModel, WebsiteError> {
+    let existing = ConnectionEntity::find()
+        .filter(ConnectionColumn::Application.eq(identity.application))
+        .filter(match identity.provider.as_deref() {
+            Some(provider) => ConnectionColumn::Provider.eq(provider),
+            None => ConnectionColumn::Provider.is_null(),
+        })
+        .filter(ConnectionColumn::AccountId.eq(identity.account_id.as_str()))
+        .one(database)
+        .await?;
+    if let Some(connection) = existing {
+        if linking_user.is_some_and(|user| user != connection.user_id) {
+            return Err(OAuthError::AlreadyLinked.into());
+        }
+        let user_id = connection.user_id;
+        let mut connection: ConnectionActiveModel = connection.into();
+        if let Some(token) = identity.token {
+            connection.token = ActiveValue::Set(token);
+            connection.expires_at = ActiveValue::Set(identity.expires_at);
+        }
+        if identity.other_data.is_some() {
+            connection.other_data = ActiveValue::Set(identity.other_data);
+        }
+        if identity.other_data_private.is_some() {
+            connection.other_data_private = ActiveValue::Set(identity.other_data_private);
+        }
+        connection.update(database).await?;
+        return UserEntity::find_by_id(user_id)
+            .one(database)
+            .await?
+            .ok_or(WebsiteError::NotFound);
+    }
+    if let Some(user_id) = linking_user {
+        let user = UserEntity::find_by_id(user_id)
+            .one(database)
+            .await?
+            .ok_or(WebsiteError::NotFound)?;
+        link(&identity, user.id, database).await?;
+        info!(
+            "Linked {} account {} to user {}",
+            identity.application, identity.account_id, user.id
+        );
+        return Ok(user);
+    }
+    if !auto_provision {
+        return Err(OAuthError::ProvisioningDisabled.into());
+    }
+    let email = identity
+        .email
+        .as_deref()
+        .and_then(|email| Email::new(email).ok())
+        .ok_or(OAuthError::MissingEmail)?;
+    if does_email_exist(email.clone(), database).await? {
+        // Linking by email would let the provider take over local accounts
+        return Err(OAuthError::EmailInUse.into());
+    }
+    let username = available_username(&identity, &email, database).await?;
+    let first_user = state.is_first_user();
+    let group = if first_user {
+        Group::Admin
+    } else {
+        identity.group.clone()
+    };
+    let user = UserActiveModel {
+        name: ActiveValue::Set(identity.name.clone().unwrap_or_default()),
+        username: ActiveValue::Set(username),
+        email: ActiveValue::Set(email.clone()),
+        // Nobody knows this password. It can be replaced with a password reset
+        password: ActiveValue::Set(password::encrypt_password(&random_string(32))?),
+        group: ActiveValue::Set(group),
+        email_verified_at: ActiveValue::Set(
+            identity.email_verified.then(time_utils::get_current_time),
+        ),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    if first_user {
+        state.created_first_user();
+    }
+    link(&identity, user.id, database).await?;
+    info!(
+        "Created user {} from {} account {}",
+        user.id, identity.application, identity.account_id
+    );
+    // Unverified emails claim their invites once they are verified
+    if identity.email_verified {
+        let claimed = claim_invites_for_email(user.id, email, database).await?;
+        if claimed > 0 {
+            debug!("User {} claimed {} team invites", user.id, claimed);
+        }
+    } else {
+        send_verification(
+            user.id,
+            &user.name,
+            user.email.clone(),
+            email_access,
+            database,
+        )
+        .await?;
+    }
+    Ok(user)
+}
+async fn link(
+    identity: &ExternalIdentity,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    ConnectionActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        application: ActiveValue::Set(identity.application),
+        provider: ActiveValue::Set(identity.provider.clone()),
+        account_id: ActiveValue::Set(Some(identity.account_id.clone())),
+        token: ActiveValue::Set(identity.token.clone().unwrap_or_default()),
+        other_data: ActiveValue::Set(identity.other_data.clone()),
+        other_data_private: ActiveValue::Set(identity.other_data_private.clone()),
+        expires_at: ActiveValue::Set(identity.expires_at),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    Ok(())
+}
+/// The preferred username of the provider or the start of the email.
+///
+/// Invalid characters are removed and digits are added until it is unique
+async fn available_username(
+    identity: &ExternalIdentity,
+    email: &Email,
+    database: &DatabaseConnection,
+) -> Result<Username, WebsiteError> {
+    let source = identity
+        .username
+        .clone()
+        .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_owned());
+    let mut base: String = source
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(12)
+        .collect();
+    if base.len() < 3 || base.chars().all(|c| c.is_ascii_digit()) {
+        base = format!("user{}", base);
+        base.truncate(12);
+    }
+    if let Ok(username) = Username::new(&base) {
+        if !does_username_exist(username.clone(), database).await? {
+            return Ok(username);
+        }
+    }
+    let mut rng = StdRng::from_entropy();
+    loop {
+        let candidate = format!("{}{:04}", base, rng.gen_range(0..10000));
+        if let Ok(username) = Username::new(&candidate) {
+            if !does_username_exist(username.clone(), database).await? {
+                return Ok(username);
+            }
+        }
+    }
+}
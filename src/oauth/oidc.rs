@@ -0,0 +1,742 @@
+//! OpenID Connect single sign on
+//!
+//! The endpoints of a provider are found through discovery at `{issuer_url}/.well-known/openid-configuration`.
+//!
+//! The ID token is received directly from the token endpoint over TLS.
+//! Per OpenID Connect Core 3.1.3.7 the TLS server validation may be used in place of checking the token signature.
+//! So the issuer and the token endpoint must use https unless `allow_insecure_issuer` is set.
+//! The claims `iss`, `aud`, `exp` and `nonce` are still validated.
+use actix_web::{
+    get,
+    http::header::LOCATION,
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use common::Group;
+use entities::Application;
+use reqwest::{Client, Url};
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use super::{
+    check_linking_session, clear_state_cookie, create_state, login_or_provision, state_cookie,
+    take_state, ExternalIdentity, OAuthAccess, OAuthError,
+};
+use crate::{
+    email::EmailAccess,
+    error::WebsiteError,
+    state::State,
+    user::{
+        routes::{email_verified, session_cookie},
+        session::{DynSessionManager, SessionManager},
+        SessionAuthentication,
+    },
+    utils::time_utils,
+};
+
+/// An OpenID Connect provider
+///
+/// Example for a local mock IdP
+/// ```toml
+/// [[oidc]]
+/// id = "mock"
+/// name = "Mock IdP"
+/// issuer_url = "http://localhost:8080/default"
+/// allow_insecure_issuer = true
+/// client_id = "codi-time"
+/// group_claim = "groups"
+/// group_mapping = [{ claim_value = "codi-admins", group = "Admin" }]
+/// ```
+///
+/// The redirect URI to register with the provider is `{home_url}/api/sso/oidc/{id}/callback`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OIDCProviderConfig {
+    /// Used in the URLs and to link accounts. Changing it unlinks every account
+    pub id: String,
+    /// Shown on the login page
+    pub name: String,
+    /// Must use https
+    pub issuer_url: String,
+    /// Accept an http issuer. The ID token is not signature checked so this is only for local development
+    #[serde(default)]
+    pub allow_insecure_issuer: bool,
+    pub client_id: String,
+    /// Not required for public clients
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    /// Create users on their first login
+    #[serde(default = "default_true")]
+    pub auto_provision: bool,
+    /// The claim that contains the groups of the user. Can be a string or an array of strings
+    #[serde(default)]
+    pub group_claim: Option<String>,
+    /// The group given to users created through this provider. The first match wins. Everyone else is a User
+    #[serde(default)]
+    pub group_mapping: Vec<GroupMapping>,
+}
+fn default_scopes() -> Vec<String> {
+    vec![
+        "openid".to_owned(),
+        "email".to_owned(),
+        "profile".to_owned(),
+    ]
+}
+fn default_true() -> bool {
+    true
+}
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GroupMapping {
+    pub claim_value: String,
+    pub group: Group,
+}
+/// The parts of the discovery document that are used
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+}
+#[derive(Debug)]
+pub struct OIDCProvider {
+    pub config: OIDCProviderConfig,
+    /// Fetched on the first login
+    metadata: OnceCell<ProviderMetadata>,
+}
+impl OIDCProvider {
+    pub fn new(config: OIDCProviderConfig) -> anyhow::Result<Self> {
+        if !config.allow_insecure_issuer && !is_https(&config.issuer_url) {
+            anyhow::bail!(
+                "The issuer_url of OpenID Connect provider {} must use https. Set allow_insecure_issuer for local development",
+                config.id
+            );
+        }
+        if config.allow_insecure_issuer {
+            warn!(
+                "OpenID Connect provider {} may use http. Do not use this in production",
+                config.id
+            );
+        }
+        Ok(Self {
+            config,
+            metadata: OnceCell::new(),
+        })
+    }
+    pub async fn metadata(&self, client: &Client) -> Result<&ProviderMetadata, OAuthError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let issuer = self.config.issuer_url.trim_end_matches('/');
+                let metadata: ProviderMetadata = client
+                    .get(format!("{}/.well-known/openid-configuration", issuer))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                if metadata.issuer.trim_end_matches('/') != issuer {
+                    warn!(
+                        "Provider {} claims to be {} not {}",
+                        self.config.id, metadata.issuer, issuer
+                    );
+                    return Err(OAuthError::InvalidResponse("Issuer does not match"));
+                }
+                if !self.config.allow_insecure_issuer && !is_https(&metadata.token_endpoint) {
+                    return Err(OAuthError::InvalidResponse(
+                        "The token endpoint does not use https",
+                    ));
+                }
+                info!("Discovered OpenID Connect provider {}", self.config.id);
+                Ok(metadata)
+            })
+            .await
+    }
+    pub fn redirect_uri(&self, home_url: &str) -> String {
+        format!("{}/api/sso/oidc/{}/callback", home_url, self.config.id)
+    }
+    /// Maps the group claim to a group
+    pub fn group(&self, claims: &Map<String, Value>) -> Group {
+        let Some(claim) = self.config.group_claim.as_deref() else {
+            return Group::User;
+        };
+        let values: Vec<&str> = match claims.get(claim) {
+            Some(Value::String(value)) => vec![value.as_str()],
+            Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        self.config
+            .group_mapping
+            .iter()
+            .find(|mapping| values.contains(&mapping.claim_value.as_str()))
+            .map(|mapping| mapping.group.clone())
+            .unwrap_or_default()
+    }
+}
+fn is_https(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.scheme() == "https")
+}
+#[derive(Debug, Deserialize)]
+pub struct SSOLoginQuery {
+    /// Link the provider to the logged in user instead of logging in
+    #[serde(default)]
+    pub link: bool,
+}
+#[utoipa::path(get,
+    impl_for=login,
+    path = "/api/sso/oidc/{provider}/login",
+    params(
+        ("provider" = String, Path, description = "The id of the provider"),
+        ("link" = Option<bool>, Query, description = "Link the provider to the logged in user instead of logging in"),
+    ),
+    responses(
+        (status = 302, description = "Redirect to the provider"),
+        (status = 401, description = "Linking requires a session"),
+        (status = 404, description = "Unknown provider"),
+    ),
+)]
+#[get("/sso/oidc/{provider}/login")]
+pub async fn login(
+    provider: web::Path<String>,
+    query: web::Query<SSOLoginQuery>,
+    auth: Option<SessionAuthentication>,
+    oauth: Data<OAuthAccess>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let home_url = oauth.home_url()?;
+    let provider = oauth.get_oidc(&provider)?;
+    let user_id = if query.link {
+        let Some(auth) = auth else {
+            return Ok(HttpResponse::Unauthorized().finish());
+        };
+        Some(auth.user.id)
+    } else {
+        None
+    };
+    let metadata = provider.metadata(&oauth.http_client).await?;
+    let pending = create_state(&provider.config.id, true, user_id, database.as_ref()).await?;
+    let url = Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", provider.config.client_id.as_str()),
+            ("redirect_uri", provider.redirect_uri(home_url).as_str()),
+            ("scope", provider.config.scopes.join(" ").as_str()),
+            ("state", pending.state.as_str()),
+            ("nonce", pending.nonce.as_deref().unwrap_or_default()),
+            ("code_challenge", pending.code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|_| OAuthError::InvalidResponse("Invalid authorization endpoint"))?;
+    Ok(HttpResponse::Found()
+        .cookie(state_cookie(&pending.state))
+        .insert_header((LOCATION, url.to_string()))
+        .finish())
+}
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub state: String,
+    pub code: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    refresh_token: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(audience) => audience == client_id,
+            Audience::Many(audiences) => audiences.iter().any(|audience| audience == client_id),
+        }
+    }
+}
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    email: Option<String>,
+    /// Some providers send a string
+    email_verified: Option<Value>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    #[serde(flatten)]
+    other: Map<String, Value>,
+}
+/// Decodes the payload of the ID token. See the module docs for why the signature is not checked
+fn decode_id_token(id_token: &str) -> Result<IdTokenClaims, OAuthError> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or(OAuthError::InvalidIdToken("Not a JWT"))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .map_err(|_| OAuthError::InvalidIdToken("Invalid base64"))?;
+    serde_json::from_slice(&payload).map_err(|_| OAuthError::InvalidIdToken("Invalid claims"))
+}
+#[utoipa::path(get,
+    impl_for=callback,
+    path = "/api/sso/oidc/{provider}/callback",
+    params(
+        ("provider" = String, Path, description = "The id of the provider"),
+        ("state" = String, Query, description = "The state sent to the provider"),
+        ("code" = Option<String>, Query, description = "The authorization code"),
+        ("error" = Option<String>, Query, description = "Set if the login failed"),
+    ),
+    responses(
+        (status = 302, description = "Logged in. Redirect to the home page"),
+        (status = 400, description = "The login failed, was started in another browser or linking was finished outside of the user's session"),
+        (status = 401, description = "The email is not verified"),
+        (status = 409, description = "The email or account is in use"),
+    ),
+)]
+#[get("/sso/oidc/{provider}/callback")]
+pub async fn callback(
+    provider: web::Path<String>,
+    query: web::Query<CallbackQuery>,
+    request: HttpRequest,
+    auth: Option<SessionAuthentication>,
+    oauth: Data<OAuthAccess>,
+    database: Data<DatabaseConnection>,
+    session: Data<DynSessionManager>,
+    state: Data<State>,
+    email_access: Data<EmailAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let query = query.into_inner();
+    let home_url = oauth.home_url()?;
+    let provider = oauth.get_oidc(&provider)?;
+    let login = take_state(
+        &request,
+        &query.state,
+        &provider.config.id,
+        database.as_ref(),
+    )
+    .await?;
+    check_linking_session(&login, auth.as_ref())?;
+    if let Some(error) = query.error {
+        return Err(OAuthError::ProviderError(query.error_description.unwrap_or(error)).into());
+    }
+    let Some(code) = query.code else {
+        return Err(OAuthError::ProviderError("No code was returned".to_owned()).into());
+    };
+    let metadata = provider.metadata(&oauth.http_client).await?;
+    let redirect_uri = provider.redirect_uri(home_url);
+    let mut form = vec![
+        ("grant_type", "authorization_code"),
+        ("code", code.as_str()),
+        ("redirect_uri", redirect_uri.as_str()),
+        ("client_id", provider.config.client_id.as_str()),
+        ("code_verifier", login.code_verifier.as_str()),
+    ];
+    if let Some(secret) = provider.config.client_secret.as_deref() {
+        form.push(("client_secret", secret));
+    }
+    let response = oauth
+        .http_client
+        .post(&metadata.token_endpoint)
+        .form(&form)
+        .send()
+        .await
+        .map_err(OAuthError::from)?;
+    if !response.status().is_success() {
+        warn!(
+            "Token exchange with {} failed with {}",
+            provider.config.id,
+            response.status()
+        );
+        return Err(OAuthError::InvalidResponse("Token exchange failed").into());
+    }
+    let tokens: TokenResponse = response.json().await.map_err(OAuthError::from)?;
+    let id_token = tokens
+        .id_token
+        .ok_or(OAuthError::InvalidResponse("No ID token"))?;
+    let claims = decode_id_token(&id_token)?;
+    if claims.iss.trim_end_matches('/') != metadata.issuer.trim_end_matches('/') {
+        return Err(OAuthError::InvalidIdToken("Wrong issuer").into());
+    }
+    if !claims.aud.contains(&provider.config.client_id) {
+        return Err(OAuthError::InvalidIdToken("Wrong audience").into());
+    }
+    if claims.exp <= time_utils::get_current_time().timestamp() {
+        return Err(OAuthError::InvalidIdToken("Expired").into());
+    }
+    if claims.nonce.is_none() || claims.nonce != login.nonce {
+        return Err(OAuthError::InvalidIdToken("Wrong nonce").into());
+    }
+    let email_verified = matches!(claims.email_verified.as_ref(), Some(Value::Bool(true)))
+        || matches!(claims.email_verified.as_ref(), Some(Value::String(value)) if value == "true");
+    let identity = ExternalIdentity {
+        application: Application::OpenIDConnect,
+        provider: Some(provider.config.id.clone()),
+        group: provider.group(&claims.other),
+        account_id: claims.sub,
+        email: claims.email,
+        email_verified,
+        username: claims.preferred_username,
+        name: claims.name,
+        token: tokens.refresh_token,
+        expires_at: None,
+        other_data: None,
+        other_data_private: None,
+    };
+    let linking = login.user_id.is_some();
+    let user = login_or_provision(
+        identity,
+        login.user_id,
+        provider.config.auto_provision,
+        &state,
+        &email_access,
+        database.as_ref(),
+    )
+    .await?;
+    if linking {
+        return Ok(HttpResponse::Found()
+            .cookie(clear_state_cookie())
+            .insert_header((LOCATION, home_url.to_owned()))
+            .finish());
+    }
+    if !email_verified(&user) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    // The provider is trusted to handle its own multi factor authentication
+    let session = session.create_session(user.id)?;
+    Ok(HttpResponse::Found()
+        .cookie(session_cookie(&session))
+        .cookie(clear_state_cookie())
+        .insert_header((LOCATION, home_url.to_owned()))
+        .finish())
+}
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App, HttpServer, Scope,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        oauth::{init, pkce_challenge},
+        test_utils,
+        user::middleware::HandleSession,
+    };
+
+    const CLIENT_ID: &str = "codi-time";
+
+    /// What the stand-in provider knows about the login in progress
+    #[derive(Debug, Default)]
+    struct StandInState {
+        issuer: String,
+        code_challenge: Option<String>,
+        claims: Value,
+    }
+    type StandIn = Arc<Mutex<StandInState>>;
+
+    async fn discovery(stand_in: Data<StandIn>) -> HttpResponse {
+        let issuer = stand_in.lock().unwrap().issuer.clone();
+        HttpResponse::Ok().json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+        }))
+    }
+    /// Only hands out the ID token for the verifier of the challenge sent to the authorization endpoint
+    async fn token(
+        stand_in: Data<StandIn>,
+        form: web::Form<HashMap<String, String>>,
+    ) -> HttpResponse {
+        let stand_in = stand_in.lock().unwrap();
+        let verified = form
+            .get("code_verifier")
+            .map(|verifier| pkce_challenge(verifier));
+        if form.get("code").map(String::as_str) != Some("code")
+            || form.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+            || verified.is_none()
+            || verified != stand_in.code_challenge
+        {
+            return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+        }
+        let id_token = format!(
+            "e30.{}.",
+            URL_SAFE_NO_PAD.encode(stand_in.claims.to_string())
+        );
+        HttpResponse::Ok().json(json!({ "id_token": id_token, "token_type": "Bearer" }))
+    }
+    /// Starts a provider on a random local port
+    fn start_stand_in() -> StandIn {
+        let stand_in = StandIn::default();
+        let data = Data::new(stand_in.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(discovery),
+                )
+                .route("/token", web::post().to(token))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        stand_in.lock().unwrap().issuer = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        stand_in
+    }
+    fn config(issuer_url: &str, allow_insecure_issuer: bool) -> OIDCProviderConfig {
+        OIDCProviderConfig {
+            id: "mock".to_owned(),
+            name: "Mock IdP".to_owned(),
+            issuer_url: issuer_url.to_owned(),
+            allow_insecure_issuer,
+            client_id: CLIENT_ID.to_owned(),
+            client_secret: None,
+            scopes: default_scopes(),
+            auto_provision: true,
+            group_claim: None,
+            group_mapping: vec![],
+        }
+    }
+
+    #[test]
+    fn the_issuer_must_use_https() {
+        assert!(OIDCProvider::new(config("https://idp.example", false)).is_ok());
+        assert!(OIDCProvider::new(config("http://idp.example", false)).is_err());
+        assert!(OIDCProvider::new(config("idp.example", false)).is_err());
+        assert!(OIDCProvider::new(config("http://localhost:8080", true)).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn discovery_checks_the_issuer() {
+        let stand_in = start_stand_in();
+        let issuer = stand_in.lock().unwrap().issuer.clone();
+        let client = Client::new();
+
+        let provider = OIDCProvider::new(config(&issuer, true)).unwrap();
+        let metadata = provider.metadata(&client).await.unwrap();
+        assert_eq!(metadata.token_endpoint, format!("{}/token", issuer));
+
+        stand_in.lock().unwrap().issuer = "http://idp.example".to_owned();
+        let provider = OIDCProvider::new(config(&issuer, true)).unwrap();
+        assert!(matches!(
+            provider.metadata(&client).await,
+            Err(OAuthError::InvalidResponse(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn discovery_requires_an_https_token_endpoint() {
+        let stand_in = start_stand_in();
+        let issuer = stand_in.lock().unwrap().issuer.clone();
+        // Stands in for an https issuer that advertises an http token endpoint
+        let mut provider = OIDCProvider::new(config(&issuer, true)).unwrap();
+        provider.config.allow_insecure_issuer = false;
+        assert!(matches!(
+            provider.metadata(&Client::new()).await,
+            Err(OAuthError::InvalidResponse(_))
+        ));
+    }
+
+    async fn app(
+        database: &DatabaseConnection,
+        stand_in: &StandIn,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        let issuer = stand_in.lock().unwrap().issuer.clone();
+        let oauth = OAuthAccess::new(
+            vec![config(&issuer, true)],
+            None,
+            Some("https://codi.example".to_owned()),
+        )
+        .unwrap();
+        let sessions = test_utils::session_manager();
+        init_service(
+            App::new()
+                .app_data(Data::new(database.clone()))
+                .app_data(Data::new(oauth))
+                .app_data(Data::new(State::default()))
+                .app_data(Data::new(EmailAccess::new(None, None).unwrap()))
+                .app_data(sessions.clone())
+                .service(
+                    Scope::new("/api")
+                        .wrap(HandleSession {
+                            session_manager: sessions.into_inner(),
+                        })
+                        .configure(init),
+                ),
+        )
+        .await
+    }
+    /// Claims for a new user that are valid for the login
+    fn valid_claims(issuer: &str, nonce: &str) -> Value {
+        let name = test_utils::unique_name();
+        json!({
+            "iss": issuer,
+            "sub": name,
+            "aud": CLIENT_ID,
+            "exp": time_utils::get_current_time().timestamp() + 300,
+            "nonce": nonce,
+            "email": format!("{}@example.com", name),
+            "email_verified": true,
+            "preferred_username": name,
+        })
+    }
+    /// Starts a login. Returns the parameters sent to the authorization endpoint
+    async fn start_login(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    ) -> HashMap<String, String> {
+        let response = call_service(
+            app,
+            TestRequest::get()
+                .uri("/api/sso/oidc/mock/login")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        let location = response.headers().get(LOCATION).unwrap().to_str().unwrap();
+        let parameters: HashMap<String, String> = Url::parse(location)
+            .unwrap()
+            .query_pairs()
+            .into_owned()
+            .collect();
+        assert_eq!(parameters["code_challenge_method"], "S256");
+        parameters
+    }
+    /// Returns to the callback like the provider does after the user logged in
+    async fn finish_login(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+        parameters: &HashMap<String, String>,
+    ) -> ServiceResponse {
+        call_service(
+            app,
+            TestRequest::get()
+                .uri(&format!(
+                    "/api/sso/oidc/mock/callback?state={}&code=code",
+                    parameters["state"]
+                ))
+                .cookie(Cookie::new("oauth_state", parameters["state"].clone()))
+                .to_request(),
+        )
+        .await
+    }
+    /// Goes through the login. `claims` changes the claims of the ID token
+    async fn log_in(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+        stand_in: &StandIn,
+        claims: impl FnOnce(&mut Map<String, Value>),
+    ) -> ServiceResponse {
+        let parameters = start_login(app).await;
+        {
+            let mut stand_in = stand_in.lock().unwrap();
+            let mut valid = valid_claims(&stand_in.issuer, &parameters["nonce"]);
+            claims(valid.as_object_mut().unwrap());
+            stand_in.claims = valid;
+            stand_in.code_challenge = Some(parameters["code_challenge"].clone());
+        }
+        finish_login(app, &parameters).await
+    }
+
+    #[actix_web::test]
+    async fn log_in_with_the_code_verifier() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let stand_in = start_stand_in();
+        let app = app(&database, &stand_in).await;
+
+        let response = log_in(&app, &stand_in, |_| {}).await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert!(response
+            .response()
+            .cookies()
+            .any(|cookie| cookie.name() == "session"));
+
+        // The stand-in refuses the code when the verifier does not match the challenge
+        let parameters = start_login(&app).await;
+        {
+            let mut stand_in = stand_in.lock().unwrap();
+            stand_in.claims = valid_claims(&stand_in.issuer, &parameters["nonce"]);
+            stand_in.code_challenge = Some(pkce_challenge("another verifier"));
+        }
+        let response = finish_login(&app, &parameters).await;
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[actix_web::test]
+    async fn invalid_id_tokens_are_rejected() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let stand_in = start_stand_in();
+        let app = app(&database, &stand_in).await;
+        let changes: Vec<(&str, Box<dyn FnOnce(&mut Map<String, Value>)>)> = vec![
+            (
+                "another nonce",
+                Box::new(|claims| {
+                    claims.insert("nonce".to_owned(), json!("another nonce"));
+                }),
+            ),
+            (
+                "no nonce",
+                Box::new(|claims| {
+                    claims.remove("nonce");
+                }),
+            ),
+            (
+                "another audience",
+                Box::new(|claims| {
+                    claims.insert("aud".to_owned(), json!(["another-client"]));
+                }),
+            ),
+            (
+                "an expired exp",
+                Box::new(|claims| {
+                    let expired = time_utils::get_current_time().timestamp() - 1;
+                    claims.insert("exp".to_owned(), json!(expired));
+                }),
+            ),
+            (
+                "another issuer",
+                Box::new(|claims| {
+                    claims.insert("iss".to_owned(), json!("http://idp.example"));
+                }),
+            ),
+        ];
+        for (change, claims) in changes {
+            let response = log_in(&app, &stand_in, claims).await;
+            assert_eq!(
+                response.status(),
+                StatusCode::BAD_REQUEST,
+                "Accepted a token with {}",
+                change
+            );
+        }
+        let response = log_in(&app, &stand_in, |claims| {
+            claims.insert("aud".to_owned(), json!(["another-client", CLIENT_ID]));
+        })
+        .await;
+        assert_eq!(response.status(), StatusCode::FOUND);
+    }
+}
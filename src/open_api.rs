@@ -27,6 +27,52 @@ impl ApiDoc {
             .schema_from::<crate::user::update_routes::UpdatePassword>()
             .schema_from::<crate::user::update_routes::UpdatePasswordResponse>()
             .schema_from::<crate::user::update_routes::UpdatePreferences>()
+            .schema_from::<crate::user::api_keys::NewAPIKey>()
+            .schema_from::<crate::user::api_keys::UpdateAPIKey>()
+            .schema_from::<crate::user::cli::NewCLIRequest>()
+            .schema_from::<crate::user::cli::InitSessionResponse>()
+            .schema_from::<crate::user::cli::PendingCLIAccess>()
+            .schema_from::<crate::user::cli::CLIAccessDecision>()
+            .schema_from::<crate::user::email_verification::VerifyEmailRequest>()
+            .schema_from::<crate::user::email_verification::ResendVerification>()
+            .schema_from::<crate::user::password_reset::ForgotPassword>()
+            .schema_from::<crate::user::password_reset::ResetPassword>()
+            .schema_from::<crate::user::LoginResponse>()
+            .schema_from::<crate::user::two_factor::TwoFactorChallenge>()
+            .schema_from::<crate::user::two_factor::TwoFactorLogin>()
+            .schema_from::<crate::user::two_factor::TotpEnrollment>()
+            .schema_from::<crate::user::two_factor::ConfirmTotp>()
+            .schema_from::<crate::user::two_factor::RecoveryCodes>()
+            .schema_from::<crate::user::two_factor::PasswordConfirmation>()
+            .schema_from::<crate::user::passkeys::PasskeyInfo>()
+            .schema_from::<crate::user::passkeys::PasskeyRegistrationStart>()
+            .schema_from::<crate::user::passkeys::PasskeyAuthenticationStart>()
+            .schema_from::<crate::user::passkeys::StartPasskeyRegistration>()
+            .schema_from::<crate::user::passkeys::FinishPasskeyRegistration>()
+            .schema_from::<crate::user::passkeys::FinishPasskeyLogin>()
+            .schema_from::<crate::user::passkeys::StartPasskeySecondFactor>()
+            .schema_from::<crate::user::passkeys::FinishPasskeySecondFactor>()
+            .schema_from::<crate::oauth::SSOProvider>()
+            .schema_from::<crate::projects::rules::RuleDefinition>()
+            .schema_from::<crate::projects::rules::NewProjectRule>()
+            .schema_from::<crate::projects::rules::UpdateProjectRule>()
+            .schema_from::<crate::projects::rules::TestRulesRequest>()
+            .schema_from::<crate::projects::rules::RuleTestResult>()
+            .schema_from::<crate::projects::labels::NewLabel>()
+            .schema_from::<crate::projects::labels::UpdateLabel>()
+            .schema_from::<crate::projects::budgets::SetBudget>()
+            .schema_from::<crate::teams::NewTeam>()
+            .schema_from::<crate::teams::RenameTeam>()
+            .schema_from::<crate::teams::SetMemberRole>()
+            .schema_from::<crate::teams::invites::NewTeamInvite>()
+            .schema_from::<crate::teams::invites::AcceptInviteByToken>()
+            .schema_from::<crate::teams::api_keys::NewTeamAPIKey>()
+            .schema_from::<crate::teams::languages::NewTeamLanguage>()
+            .schema_from::<crate::teams::languages::NewTeamCategory>()
+            .schema_from::<crate::organizations::NewOrganization>()
+            .schema_from::<crate::organizations::languages::NewOrganizationLanguage>()
+            .schema_from::<crate::organizations::languages::NewOrganizationCategory>()
+            .schema_from::<crate::waka_time::WakaTimeHeartbeat>()
             .schema_from::<crate::recaptcha::PublicRecaptcha>()
             .schema_from::<crate::state::State>();
         builder.build()
@@ -35,11 +81,112 @@ impl ApiDoc {
         PathsBuilder::new()
             .path_from::<crate::user::routes::get_user>()
             .path_from::<crate::user::routes::me>()
+            .path_from::<crate::user::routes::my_languages>()
+            .path_from::<crate::user::routes::my_language_categories>()
             .path_from::<crate::user::update_routes::update_bio>()
             .path_from::<crate::user::update_routes::update_core>()
             .path_from::<crate::user::update_routes::update_password>()
             .path_from::<crate::user::update_routes::update_report_intervals>()
             .path_from::<crate::user::update_routes::update_preferences>()
+            .path_from::<crate::user::api_keys::list_api_keys>()
+            .path_from::<crate::user::api_keys::create_api_key>()
+            .path_from::<crate::user::api_keys::update_api_key>()
+            .path_from::<crate::user::api_keys::revoke_api_key>()
+            .path_from::<crate::user::cli::init_session>()
+            .path_from::<crate::user::cli::retrieve_result>()
+            .path_from::<crate::user::cli::pending_access>()
+            .path_from::<crate::user::cli::complete_access>()
+            .path_from::<crate::user::email_verification::verify_email>()
+            .path_from::<crate::user::email_verification::resend_verification>()
+            .path_from::<crate::user::email_verification::mark_verified>()
+            .path_from::<crate::user::password_reset::forgot_password>()
+            .path_from::<crate::user::password_reset::reset_password>()
+            .path_from::<crate::user::two_factor::login_two_factor>()
+            .path_from::<crate::user::two_factor::enroll>()
+            .path_from::<crate::user::two_factor::confirm>()
+            .path_from::<crate::user::two_factor::regenerate_recovery_codes>()
+            .path_from::<crate::user::two_factor::disable>()
+            .path_from::<crate::user::passkeys::list_passkeys>()
+            .path_from::<crate::user::passkeys::start_registration>()
+            .path_from::<crate::user::passkeys::finish_registration>()
+            .path_from::<crate::user::passkeys::delete_passkey>()
+            .path_from::<crate::user::passkeys::start_login>()
+            .path_from::<crate::user::passkeys::finish_login>()
+            .path_from::<crate::user::passkeys::start_second_factor>()
+            .path_from::<crate::user::passkeys::finish_second_factor>()
+            .path_from::<crate::oauth::list_providers>()
+            .path_from::<crate::oauth::oidc::login>()
+            .path_from::<crate::oauth::oidc::callback>()
+            .path_from::<crate::oauth::github::link>()
+            .path_from::<crate::oauth::github::callback>()
+            .path_from::<crate::oauth::github::unlink>()
+            .path_from::<crate::projects::projects_list>()
+            .path_from::<crate::projects::rules::list_rules>()
+            .path_from::<crate::projects::rules::create_rule>()
+            .path_from::<crate::projects::rules::update_rule>()
+            .path_from::<crate::projects::rules::delete_rule>()
+            .path_from::<crate::projects::rules::test_rules>()
+            .path_from::<crate::projects::public::get_public_project>()
+            .path_from::<crate::projects::public::get_user_public_projects>()
+            .path_from::<crate::projects::public::get_user_project_page>()
+            .path_from::<crate::projects::public::get_user_label_stats>()
+            .path_from::<crate::projects::labels::list_labels>()
+            .path_from::<crate::projects::labels::create_label>()
+            .path_from::<crate::projects::labels::update_label>()
+            .path_from::<crate::projects::labels::delete_label>()
+            .path_from::<crate::projects::labels::label_stats>()
+            .path_from::<crate::projects::labels::attach_label>()
+            .path_from::<crate::projects::labels::remove_label>()
+            .path_from::<crate::projects::budgets::list_budgets>()
+            .path_from::<crate::projects::budgets::get_budget>()
+            .path_from::<crate::projects::budgets::set_budget>()
+            .path_from::<crate::projects::budgets::delete_budget>()
+            .path_from::<crate::projects::budgets::list_budget_events>()
+            .path_from::<crate::teams::my_teams>()
+            .path_from::<crate::teams::create_team>()
+            .path_from::<crate::teams::get_team>()
+            .path_from::<crate::teams::rename_team>()
+            .path_from::<crate::teams::delete_team>()
+            .path_from::<crate::teams::list_members>()
+            .path_from::<crate::teams::set_member_role>()
+            .path_from::<crate::teams::remove_member>()
+            .path_from::<crate::teams::leave_team>()
+            .path_from::<crate::teams::invites::my_invites>()
+            .path_from::<crate::teams::invites::accept_invite_by_token>()
+            .path_from::<crate::teams::invites::accept_invite>()
+            .path_from::<crate::teams::invites::decline_invite>()
+            .path_from::<crate::teams::invites::create_invite>()
+            .path_from::<crate::teams::invites::list_team_invites>()
+            .path_from::<crate::teams::invites::revoke_invite>()
+            .path_from::<crate::teams::stats::team_stats>()
+            .path_from::<crate::teams::api_keys::list_api_keys>()
+            .path_from::<crate::teams::api_keys::create_api_key>()
+            .path_from::<crate::teams::api_keys::revoke_api_key>()
+            .path_from::<crate::teams::languages::list_languages>()
+            .path_from::<crate::teams::languages::create_language>()
+            .path_from::<crate::teams::languages::delete_language>()
+            .path_from::<crate::teams::languages::list_categories>()
+            .path_from::<crate::teams::languages::create_category>()
+            .path_from::<crate::teams::languages::delete_category>()
+            .path_from::<crate::organizations::my_organizations>()
+            .path_from::<crate::organizations::create_organization>()
+            .path_from::<crate::organizations::get_organization>()
+            .path_from::<crate::organizations::rename_organization>()
+            .path_from::<crate::organizations::delete_organization>()
+            .path_from::<crate::organizations::list_admins>()
+            .path_from::<crate::organizations::add_admin>()
+            .path_from::<crate::organizations::remove_admin>()
+            .path_from::<crate::organizations::list_teams>()
+            .path_from::<crate::organizations::add_team>()
+            .path_from::<crate::organizations::remove_team>()
+            .path_from::<crate::organizations::languages::list_languages>()
+            .path_from::<crate::organizations::languages::create_language>()
+            .path_from::<crate::organizations::languages::delete_language>()
+            .path_from::<crate::organizations::languages::list_categories>()
+            .path_from::<crate::organizations::languages::create_category>()
+            .path_from::<crate::organizations::languages::delete_category>()
+            .path_from::<crate::organizations::stats::organization_stats>()
+            .path_from::<crate::waka_time::heartbeat>()
             .path_from::<crate::get_state>()
             .build()
     }
@@ -0,0 +1,304 @@
+//! Organization wide languages and categories
+//!
+//! They are available to every member of the organization's teams.
+//! A definition with the same name added by the member or their team takes precedence
+use actix_web::{
+    delete, get, post,
+    web::{self, Data},
+    HttpResponse,
+};
+use common::{
+    language::{Category, Language, LanguageDef},
+    IdOrName,
+};
+use entities::{
+    custom_languages::{
+        get_language_categories_for_organization, get_languages_for_organization, Source,
+    },
+    LanguageActiveModel, LanguageCategoryActiveModel, LanguageCategoryColumn,
+    LanguageCategoryEntity, LanguageColumn, LanguageEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{get_organization_as_admin, get_organization_as_member};
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_languages)
+        .service(create_language)
+        .service(delete_language)
+        .service(list_categories)
+        .service(create_category)
+        .service(delete_category);
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewOrganizationLanguage {
+    /// Example: "Rust"
+    pub name: String,
+    /// Example: "#dea584"
+    pub color: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Names of the categories of the language
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Example: ["rs"]
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub file_names: Vec<String>,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewOrganizationCategory {
+    /// Example: "Programming"
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[utoipa::path(get,
+    impl_for=list_languages,
+    path = "/api/organizations/{organization}/languages",
+    responses(
+        (status = 200, description = "The languages added by the organization", body = Vec<Language>),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}/languages")]
+pub async fn list_languages(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let languages: Vec<Language> =
+        get_languages_for_organization(organization_id, database.as_ref())
+            .await?
+            .into_iter()
+            .map(Language::from)
+            .collect();
+    Ok(HttpResponse::Ok().json(languages))
+}
+
+#[utoipa::path(post,
+    impl_for=create_language,
+    path = "/api/organizations/{organization}/languages",
+    request_body(content = NewOrganizationLanguage, description = "The Language to add", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Language Added", body = Language),
+        (status = 400, description = "Invalid Name or no file names or extensions were provided"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+        (status = 409, description = "The organization already has a language with that name"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/organizations/{organization}/languages")]
+pub async fn create_language(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    language: web::Json<NewOrganizationLanguage>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_admin(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let NewOrganizationLanguage {
+        name,
+        color,
+        aliases,
+        categories,
+        extensions,
+        file_names,
+    } = language.into_inner();
+    let definition = LanguageDef {
+        name: name.trim().to_owned(),
+        default_color: color,
+        categories,
+        aliases,
+        extensions,
+        file_names,
+    };
+    if let Err(error) = definition.is_valid() {
+        return Ok(HttpResponse::BadRequest().body(error.to_string()));
+    }
+    let existing = get_languages_for_organization(organization_id, database.as_ref()).await?;
+    if existing
+        .iter()
+        .any(|language| language.name.eq_ignore_ascii_case(&definition.name))
+    {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let language = LanguageActiveModel {
+        organization_id: ActiveValue::Set(Some(organization_id)),
+        name: ActiveValue::Set(definition.name),
+        color: ActiveValue::Set(definition.default_color),
+        aliases: ActiveValue::Set(definition.aliases),
+        categories: ActiveValue::Set(definition.categories),
+        extensions: ActiveValue::Set(definition.extensions),
+        file_names: ActiveValue::Set(definition.file_names),
+        source: ActiveValue::Set(Source::FromOrganization),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(Language::from(language)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_language,
+    path = "/api/organizations/{organization}/languages/{language}",
+    responses(
+        (status = 204, description = "Language Removed"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization or language not found"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/organizations/{organization}/languages/{language}")]
+pub async fn delete_language(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, language_id) = path.into_inner();
+    let organization_id =
+        get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+    let result = LanguageEntity::delete_many()
+        .filter(LanguageColumn::Id.eq(language_id))
+        .filter(LanguageColumn::OrganizationId.eq(organization_id))
+        .filter(LanguageColumn::Source.eq(Source::FromOrganization))
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=list_categories,
+    path = "/api/organizations/{organization}/categories",
+    responses(
+        (status = 200, description = "The language categories added by the organization", body = Vec<Category>),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}/categories")]
+pub async fn list_categories(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let categories: Vec<Category> =
+        get_language_categories_for_organization(organization_id, database.as_ref())
+            .await?
+            .into_iter()
+            .map(Category::from)
+            .collect();
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+#[utoipa::path(post,
+    impl_for=create_category,
+    path = "/api/organizations/{organization}/categories",
+    request_body(content = NewOrganizationCategory, description = "The Category to add", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Category Added", body = Category),
+        (status = 400, description = "Invalid Name"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+        (status = 409, description = "The organization already has a category with that name"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/organizations/{organization}/categories")]
+pub async fn create_category(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    category: web::Json<NewOrganizationCategory>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_admin(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let NewOrganizationCategory { name, description } = category.into_inner();
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("Invalid Name"));
+    }
+    let existing =
+        get_language_categories_for_organization(organization_id, database.as_ref()).await?;
+    if existing
+        .iter()
+        .any(|category| category.name.eq_ignore_ascii_case(&name))
+    {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let category = LanguageCategoryActiveModel {
+        organization_id: ActiveValue::Set(Some(organization_id)),
+        name: ActiveValue::Set(name),
+        description: ActiveValue::Set(description),
+        source: ActiveValue::Set(Source::FromOrganization),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(Category::from(category)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_category,
+    path = "/api/organizations/{organization}/categories/{category}",
+    responses(
+        (status = 204, description = "Category Removed"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization or category not found"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/organizations/{organization}/categories/{category}")]
+pub async fn delete_category(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, category_id) = path.into_inner();
+    let organization_id =
+        get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+    let result = LanguageCategoryEntity::delete_many()
+        .filter(LanguageCategoryColumn::Id.eq(category_id))
+        .filter(LanguageCategoryColumn::OrganizationId.eq(organization_id))
+        .filter(LanguageCategoryColumn::Source.eq(Source::FromOrganization))
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,514 @@
+//! Organization Management
+//!
+//! Base Route /api/organizations
+//!
+//! An organization groups the teams of a company.
+//! Its rules and languages apply to every member of its teams.
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Data},
+    HttpResponse,
+};
+use common::{
+    organization::{Organization, OrganizationAdmin},
+    team::{to_id_style_name, Team, TeamRole},
+    IdOrName, TinyUser,
+};
+use entities::{
+    organizations::{
+        count_organization_admins, does_organization_id_style_name_exist, get_organization_teams,
+        get_organizations_for_admin, is_member_of_organization, is_organization_admin,
+    },
+    OrganizationActiveModel, OrganizationAdminActiveModel, OrganizationAdminColumn,
+    OrganizationAdminEntity, OrganizationEntity, TeamEntity, UserColumn, UserEntity,
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel, QueryOrder,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::WebsiteError,
+    teams::get_team_with_role,
+    user::scopes::{self, Scoped},
+};
+pub mod languages;
+pub mod stats;
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(my_organizations)
+        .service(create_organization)
+        .service(get_organization)
+        .service(rename_organization)
+        .service(delete_organization)
+        .service(list_admins)
+        .service(add_admin)
+        .service(remove_admin)
+        .service(list_teams)
+        .service(add_team)
+        .service(remove_team)
+        .configure(languages::init)
+        .configure(stats::init);
+}
+/// Resolves the organization and checks that the user is an admin of it.
+///
+/// Members of its teams get [WebsiteError::Forbidden]. Everyone else gets [WebsiteError::NotFound]
+pub(crate) async fn get_organization_as_admin(
+    organization: IdOrName,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<i64, WebsiteError> {
+    let organization_id = get_organization_as_member(organization, user_id, database).await?;
+    if !is_organization_admin(organization_id, user_id, database).await? {
+        return Err(WebsiteError::Forbidden);
+    }
+    Ok(organization_id)
+}
+/// Resolves the organization and checks that the user is an admin of it or a member of one of its teams
+pub(crate) async fn get_organization_as_member(
+    organization: IdOrName,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<i64, WebsiteError> {
+    let Some(organization_id) = organization.get_id::<OrganizationEntity>(database).await? else {
+        return Err(WebsiteError::NotFound);
+    };
+    if is_organization_admin(organization_id, user_id, database).await?
+        || is_member_of_organization(organization_id, user_id, database).await?
+    {
+        Ok(organization_id)
+    } else {
+        Err(WebsiteError::NotFound)
+    }
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewOrganization {
+    /// Example: "Codi Time Inc". The id style name is generated from it
+    pub name: String,
+}
+
+#[utoipa::path(get,
+    impl_for=my_organizations,
+    path = "/api/organizations",
+    responses(
+        (status = 200, description = "The organizations you are an admin of", body = Vec<Organization>),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations")]
+pub async fn my_organizations(
+    auth: Scoped<scopes::ReadTeams>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organizations: Vec<Organization> =
+        get_organizations_for_admin(auth.id(), database.as_ref())
+            .await?
+            .into_iter()
+            .map(Organization::from)
+            .collect();
+    Ok(HttpResponse::Ok().json(organizations))
+}
+
+#[utoipa::path(post,
+    impl_for=create_organization,
+    path = "/api/organizations",
+    request_body(content = NewOrganization, description = "The Organization to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Organization Created. You are its admin", body = Organization),
+        (status = 400, description = "Invalid Name"),
+        (status = 409, description = "An organization with that id style name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/organizations")]
+pub async fn create_organization(
+    auth: Scoped<scopes::WriteTeams>,
+    organization: web::Json<NewOrganization>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let name = organization.into_inner().name.trim().to_owned();
+    let Some(id_style_name) = to_id_style_name(&name) else {
+        return Ok(HttpResponse::BadRequest()
+            .body("Organization name must contain at least one non-numeric character."));
+    };
+    if does_organization_id_style_name_exist(&id_style_name, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let transaction = database.begin().await?;
+    let organization = OrganizationActiveModel {
+        name: ActiveValue::Set(name),
+        id_style_name: ActiveValue::Set(id_style_name),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+    OrganizationAdminActiveModel {
+        organization_id: ActiveValue::Set(organization.id),
+        user_id: ActiveValue::Set(auth.id()),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(HttpResponse::Created().json(Organization::from(organization)))
+}
+
+#[utoipa::path(get,
+    impl_for=get_organization,
+    path = "/api/organizations/{organization}",
+    responses(
+        (status = 200, description = "The Organization", body = Organization),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}")]
+pub async fn get_organization(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let Some(organization) = OrganizationEntity::find_by_id(organization_id)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    Ok(HttpResponse::Ok().json(Organization::from(organization)))
+}
+
+#[utoipa::path(put,
+    impl_for=rename_organization,
+    path = "/api/organizations/{organization}",
+    request_body(content = NewOrganization, description = "The new name", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Organization Renamed", body = Organization),
+        (status = 400, description = "Invalid Name"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+        (status = 409, description = "An organization with that id style name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[put("/organizations/{organization}")]
+pub async fn rename_organization(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    rename: web::Json<NewOrganization>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_admin(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let Some(organization) = OrganizationEntity::find_by_id(organization_id)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let name = rename.into_inner().name.trim().to_owned();
+    let Some(id_style_name) = to_id_style_name(&name) else {
+        return Ok(HttpResponse::BadRequest()
+            .body("Organization name must contain at least one non-numeric character."));
+    };
+    if id_style_name != organization.id_style_name
+        && does_organization_id_style_name_exist(&id_style_name, database.as_ref()).await?
+    {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let mut organization = organization.into_active_model();
+    organization.name = ActiveValue::Set(name);
+    organization.id_style_name = ActiveValue::Set(id_style_name);
+    let organization = organization.update(database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(Organization::from(organization)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_organization,
+    path = "/api/organizations/{organization}",
+    responses(
+        (status = 204, description = "Organization Deleted. Its teams are kept. Its rules and languages are deleted"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/organizations/{organization}")]
+pub async fn delete_organization(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_admin(path.into_inner(), auth.id(), database.as_ref()).await?;
+    OrganizationEntity::delete_by_id(organization_id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=list_admins,
+    path = "/api/organizations/{organization}/admins",
+    responses(
+        (status = 200, description = "The admins of the organization", body = Vec<OrganizationAdmin>),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}/admins")]
+pub async fn list_admins(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let admins = OrganizationAdminEntity::find()
+        .filter(OrganizationAdminColumn::OrganizationId.eq(organization_id))
+        .order_by_asc(OrganizationAdminColumn::Created)
+        .all(database.as_ref())
+        .await?;
+    let users: Vec<TinyUser> = UserEntity::find()
+        .filter(UserColumn::Id.is_in(admins.iter().map(|admin| admin.user_id)))
+        .into_model()
+        .all(database.as_ref())
+        .await?;
+    let admins: Vec<OrganizationAdmin> = admins
+        .into_iter()
+        .filter_map(|admin| {
+            let user = users.iter().find(|user| user.id == admin.user_id)?;
+            Some(OrganizationAdmin {
+                user: user.clone(),
+                added: admin.created,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(admins))
+}
+
+#[utoipa::path(put,
+    impl_for=add_admin,
+    path = "/api/organizations/{organization}/admins/{user}",
+    responses(
+        (status = 204, description = "The user is an admin of the organization"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization or user not found"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[put("/organizations/{organization}/admins/{user}")]
+pub async fn add_admin(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, user) = path.into_inner();
+    let organization_id =
+        get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+    let Some(user_id) = user.get_id::<UserEntity>(database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if is_organization_admin(organization_id, user_id, database.as_ref()).await? {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+    OrganizationAdminActiveModel {
+        organization_id: ActiveValue::Set(organization_id),
+        user_id: ActiveValue::Set(user_id),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(delete,
+    impl_for=remove_admin,
+    path = "/api/organizations/{organization}/admins/{user}",
+    responses(
+        (status = 204, description = "Admin Removed"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or the user is not an admin"),
+        (status = 409, description = "The user is the last admin of the organization"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/organizations/{organization}/admins/{user}")]
+pub async fn remove_admin(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, user) = path.into_inner();
+    let organization_id =
+        get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+    let Some(user_id) = user.get_id::<UserEntity>(database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if !is_organization_admin(organization_id, user_id, database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if count_organization_admins(organization_id, database.as_ref()).await? <= 1 {
+        return Ok(HttpResponse::Conflict().body(
+            "An organization must have at least one admin. Delete the organization instead.",
+        ));
+    }
+    OrganizationAdminEntity::delete_many()
+        .filter(
+            OrganizationAdminColumn::OrganizationId
+                .eq(organization_id)
+                .and(OrganizationAdminColumn::UserId.eq(user_id)),
+        )
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=list_teams,
+    path = "/api/organizations/{organization}/teams",
+    responses(
+        (status = 200, description = "The teams of the organization", body = Vec<Team>),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}/teams")]
+pub async fn list_teams(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let teams: Vec<Team> = get_organization_teams(organization_id, database.as_ref())
+        .await?
+        .into_iter()
+        .map(Team::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(teams))
+}
+
+#[utoipa::path(put,
+    impl_for=add_team,
+    path = "/api/organizations/{organization}/teams/{team}",
+    responses(
+        (status = 204, description = "The team belongs to the organization"),
+        (status = 403, description = "You are not an admin of both the organization and the team"),
+        (status = 404, description = "Organization or team not found"),
+        (status = 409, description = "The team belongs to another organization"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[put("/organizations/{organization}/teams/{team}")]
+pub async fn add_team(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, team) = path.into_inner();
+    let organization_id =
+        get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+    let (team_id, _) =
+        get_team_with_role(team, auth.id(), TeamRole::Admin, database.as_ref()).await?;
+    let Some(team) = TeamEntity::find_by_id(team_id)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    match team.organization_id {
+        Some(current) if current == organization_id => {
+            return Ok(HttpResponse::NoContent().finish());
+        }
+        Some(_) => {
+            return Ok(HttpResponse::Conflict()
+                .body("The team must be removed from its current organization first."));
+        }
+        None => {}
+    }
+    let mut team = team.into_active_model();
+    team.organization_id = ActiveValue::Set(Some(organization_id));
+    team.update(database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(delete,
+    impl_for=remove_team,
+    path = "/api/organizations/{organization}/teams/{team}",
+    responses(
+        (status = 204, description = "The team no longer belongs to the organization"),
+        (status = 403, description = "You are not an admin of the organization or the team"),
+        (status = 404, description = "Organization or team not found or the team is not part of the organization"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/organizations/{organization}/teams/{team}")]
+pub async fn remove_team(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (organization, team) = path.into_inner();
+    let organization_id =
+        get_organization_as_member(organization, auth.id(), database.as_ref()).await?;
+    let Some(team_id) = team.get_id::<TeamEntity>(database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    // Either side can end the relationship
+    if !is_organization_admin(organization_id, auth.id(), database.as_ref()).await? {
+        get_team_with_role(
+            IdOrName::Id(team_id),
+            auth.id(),
+            TeamRole::Admin,
+            database.as_ref(),
+        )
+        .await?;
+    }
+    let Some(team) = TeamEntity::find_by_id(team_id)
+        .one(database.as_ref())
+        .await?
+        .filter(|team| team.organization_id == Some(organization_id))
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let mut team = team.into_active_model();
+    team.organization_id = ActiveValue::Set(None);
+    team.update(database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
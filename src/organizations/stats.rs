@@ -0,0 +1,104 @@
+//! Organization Dashboard
+//!
+//! Rolls up the time spent on the projects of every team in the organization.
+//! Only totals are shown. Per member breakdowns stay on the dashboards of the teams.
+use actix_web::{
+    get,
+    web::{self, Data, Query},
+    HttpResponse,
+};
+use common::{
+    stats::{OrganizationStats, StatsRange},
+    IdOrName,
+};
+use entities::{
+    heartbeats::{
+        get_time_by_day, get_time_by_language, get_time_by_project, get_time_by_team,
+        get_total_time,
+    },
+    HeartbeatColumn, ProjectColumn, ProjectEntity, TeamColumn, TeamEntity,
+};
+use sea_orm::{entity::prelude::*, sea_query::Query as SqlQuery, DatabaseConnection};
+
+use super::get_organization_as_admin;
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+    utils::time_utils,
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(organization_stats);
+}
+/// Builds the dashboard of an organization
+pub async fn build_organization_stats(
+    organization_id: i64,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    database: &DatabaseConnection,
+) -> Result<OrganizationStats, DbErr> {
+    let filter = || {
+        HeartbeatColumn::Project
+            .in_subquery(
+                SqlQuery::select()
+                    .column(ProjectColumn::Id)
+                    .from(ProjectEntity)
+                    .and_where(
+                        ProjectColumn::TeamId.in_subquery(
+                            SqlQuery::select()
+                                .column(TeamColumn::Id)
+                                .from(TeamEntity)
+                                .and_where(TeamColumn::OrganizationId.eq(organization_id))
+                                .to_owned(),
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .and(HeartbeatColumn::StartTime.gte(from))
+            .and(HeartbeatColumn::StartTime.lt(to))
+    };
+    Ok(OrganizationStats {
+        from,
+        to,
+        total_seconds: get_total_time(filter(), database).await?,
+        teams: get_time_by_team(filter(), database).await?,
+        projects: get_time_by_project(filter(), database).await?,
+        languages: get_time_by_language(filter(), database).await?,
+        days: get_time_by_day(filter(), database).await?,
+    })
+}
+
+#[utoipa::path(get,
+    impl_for=organization_stats,
+    path = "/api/organizations/{organization}/stats",
+    params(
+        ("from" = Option<DateTime<FixedOffset>>, Query, description = "The start of the range. Defaults to a week before `to`"),
+        ("to" = Option<DateTime<FixedOffset>>, Query, description = "The end of the range. Defaults to now"),
+    ),
+    responses(
+        (status = 200, description = "Time spent on the projects of the teams in the organization", body = OrganizationStats),
+        (status = 400, description = "Invalid Range"),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Organization not found or you are not a member of it"),
+    ),
+    security(
+        ("api_key" = ["ReadUsage"]),
+        ("session" = [])
+    )
+)]
+#[get("/organizations/{organization}/stats")]
+pub async fn organization_stats(
+    auth: Scoped<scopes::ReadUsage>,
+    path: web::Path<IdOrName>,
+    range: Query<StatsRange>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let organization_id =
+        get_organization_as_admin(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let (from, to) = match range.resolve(time_utils::get_current_time()) {
+        Ok(range) => range,
+        Err(error) => return Ok(HttpResponse::BadRequest().body(error.to_string())),
+    };
+    let stats = build_organization_stats(organization_id, from, to, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
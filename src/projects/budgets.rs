@@ -0,0 +1,251 @@
+//! Project Time Budgets
+//!
+//! Base Route /api/projects/{project}/budget
+use actix_web::{
+    delete, get, put,
+    web::{self, Data},
+    HttpResponse,
+};
+use common::budget::{are_thresholds_valid, BudgetEvent, BudgetPeriod, BudgetUsage, ProjectBudget};
+use entities::{
+    budgets::{get_budget_events, get_budget_for_project, get_budget_usage},
+    projects::{does_user_have_access_to_project, get_projects_user_has_access_to},
+    teams::get_team_member,
+    ProjectBudgetActiveModel, ProjectBudgetColumn, ProjectBudgetEntity, ProjectEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+    utils::time_utils,
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_budgets)
+        .service(get_budget)
+        .service(set_budget)
+        .service(delete_budget)
+        .service(list_budget_events);
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetBudget {
+    pub period: BudgetPeriod,
+    /// The budgeted time in seconds
+    pub seconds: i64,
+    /// Percentages of the budget that trigger a notification.
+    /// Defaults to 80 and 100
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<i32>,
+}
+fn default_thresholds() -> Vec<i32> {
+    vec![80, 100]
+}
+/// Checks if the user can change the budget of the project.
+///
+/// The owner of the project or a maintainer of the team that owns it.
+async fn can_manage_budget(
+    project_id: i64,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<bool, WebsiteError> {
+    let Some(project) = ProjectEntity::find_by_id(project_id).one(database).await? else {
+        return Ok(false);
+    };
+    match (project.user_id, project.team_id) {
+        (Some(owner), _) => Ok(owner == user_id),
+        (None, Some(team_id)) => Ok(get_team_member(team_id, user_id, database)
+            .await?
+            .is_some_and(|member| member.role.can_manage_projects())),
+        _ => Ok(false),
+    }
+}
+#[utoipa::path(get,
+    impl_for=list_budgets,
+    path = "/api/projects/budgets",
+    responses(
+        (status = 200, description = "The budget consumption of every project you have access to that has a budget", body = Vec<BudgetUsage>),
+    ),
+    security(
+        ("api_key" = ["ReadProjects"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/budgets")]
+pub async fn list_budgets(
+    auth: Scoped<scopes::ReadProjects>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let projects = get_projects_user_has_access_to(auth.id(), database.as_ref()).await?;
+    let budgets = ProjectBudgetEntity::find()
+        .filter(ProjectBudgetColumn::ProjectId.is_in(projects.iter().map(|project| project.id)))
+        .all(database.as_ref())
+        .await?;
+    let now = time_utils::get_current_time();
+    let mut usages = Vec::with_capacity(budgets.len());
+    for budget in budgets {
+        usages.push(get_budget_usage(ProjectBudget::from(budget), now, database.as_ref()).await?);
+    }
+    Ok(HttpResponse::Ok().json(usages))
+}
+
+#[utoipa::path(get,
+    impl_for=get_budget,
+    path = "/api/projects/{project}/budget",
+    responses(
+        (status = 200, description = "The budget consumption of the project", body = BudgetUsage),
+        (status = 404, description = "Project not found or it does not have a budget"),
+    ),
+    security(
+        ("api_key" = ["ReadProjects"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/{project}/budget")]
+pub async fn get_budget(
+    auth: Scoped<scopes::ReadProjects>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let project_id = path.into_inner();
+    if !does_user_have_access_to_project(auth.id(), project_id, database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let Some(budget) = get_budget_for_project(project_id, database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let usage = get_budget_usage(
+        ProjectBudget::from(budget),
+        time_utils::get_current_time(),
+        database.as_ref(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[utoipa::path(put,
+    impl_for=set_budget,
+    path = "/api/projects/{project}/budget",
+    request_body(content = SetBudget, description = "The budget of the project. Replaces the current budget", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Budget Set", body = BudgetUsage),
+        (status = 400, description = "Invalid seconds or thresholds"),
+        (status = 404, description = "Project not found or you can not manage its budget"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[put("/projects/{project}/budget")]
+pub async fn set_budget(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    budget: web::Json<SetBudget>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let project_id = path.into_inner();
+    if !can_manage_budget(project_id, auth.id(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let SetBudget {
+        period,
+        seconds,
+        mut thresholds,
+    } = budget.into_inner();
+    if seconds <= 0 {
+        return Ok(HttpResponse::BadRequest().body("Seconds must be greater than 0"));
+    }
+    if !are_thresholds_valid(&thresholds) {
+        return Ok(HttpResponse::BadRequest().body("Thresholds must be between 1 and 1000"));
+    }
+    thresholds.sort_unstable();
+    thresholds.dedup();
+    let budget = match get_budget_for_project(project_id, database.as_ref()).await? {
+        Some(budget) => {
+            let mut budget = budget.into_active_model();
+            budget.period = ActiveValue::Set(period);
+            budget.seconds = ActiveValue::Set(seconds);
+            budget.thresholds = ActiveValue::Set(thresholds);
+            budget.update(database.as_ref()).await?
+        }
+        None => {
+            ProjectBudgetActiveModel {
+                project_id: ActiveValue::Set(project_id),
+                period: ActiveValue::Set(period),
+                seconds: ActiveValue::Set(seconds),
+                thresholds: ActiveValue::Set(thresholds),
+                ..Default::default()
+            }
+            .insert(database.as_ref())
+            .await?
+        }
+    };
+    let usage = get_budget_usage(
+        ProjectBudget::from(budget),
+        time_utils::get_current_time(),
+        database.as_ref(),
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_budget,
+    path = "/api/projects/{project}/budget",
+    responses(
+        (status = 204, description = "Budget Removed"),
+        (status = 404, description = "Project not found, it does not have a budget or you can not manage its budget"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[delete("/projects/{project}/budget")]
+pub async fn delete_budget(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let project_id = path.into_inner();
+    if !can_manage_budget(project_id, auth.id(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let result = ProjectBudgetEntity::delete_many()
+        .filter(ProjectBudgetColumn::ProjectId.eq(project_id))
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=list_budget_events,
+    path = "/api/projects/{project}/budget/events",
+    responses(
+        (status = 200, description = "The thresholds crossed by the project. Newest first", body = Vec<BudgetEvent>),
+        (status = 404, description = "Project not found"),
+    ),
+    security(
+        ("api_key" = ["ReadProjects"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/{project}/budget/events")]
+pub async fn list_budget_events(
+    auth: Scoped<scopes::ReadProjects>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let project_id = path.into_inner();
+    if !does_user_have_access_to_project(auth.id(), project_id, database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let events = get_budget_events(project_id, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(events))
+}
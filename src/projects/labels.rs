@@ -0,0 +1,368 @@
+//! Project Labels
+//!
+//! Base Route /api/projects/labels
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Data, Query},
+    HttpResponse,
+};
+use common::{
+    label::{is_valid_color, Label},
+    stats::TimeBreakdown,
+    team::TeamRole,
+    IdOrName,
+};
+use entities::{
+    heartbeats::get_time_by_label,
+    labels::{self, detach_label, get_labels_available_to_user, get_labels_for_team},
+    projects::can_user_contribute_to_project,
+    teams::get_team_member,
+    HeartbeatColumn, LabelActiveModel, LabelColumn, LabelEntity, LabelModel, ProjectEntity,
+    TeamEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::WebsiteError,
+    teams::get_team_with_role,
+    user::scopes::{self, Scoped},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_labels)
+        .service(create_label)
+        .service(label_stats)
+        .service(update_label)
+        .service(delete_label)
+        .service(attach_label)
+        .service(remove_label);
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewLabel {
+    pub name: String,
+    /// A hex color such as `#ff0000`
+    pub color: String,
+    /// Creates the label for a team instead of yourself.
+    /// You must be a maintainer of the team.
+    pub team: Option<IdOrName>,
+}
+/// All fields are optional.
+/// If a field is not provided, it will not be updated.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateLabel {
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+pub struct LabelsQuery {
+    /// List the labels of a team instead of the labels available to you
+    pub team: Option<IdOrName>,
+}
+/// Checks if the owner already has a label with the name
+async fn does_label_exist(
+    name: &str,
+    user_id: Option<i64>,
+    team_id: Option<i64>,
+    database: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    let owner = match (user_id, team_id) {
+        (Some(user_id), _) => LabelColumn::UserId.eq(user_id),
+        (None, Some(team_id)) => LabelColumn::TeamId.eq(team_id),
+        (None, None) => return Ok(false),
+    };
+    let label = LabelEntity::find()
+        .filter(owner.and(LabelColumn::Name.eq(name)))
+        .one(database)
+        .await?;
+    Ok(label.is_some())
+}
+/// Gets a label that the user is allowed to modify.
+async fn get_label_for_modification(
+    label_id: i64,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<LabelModel, WebsiteError> {
+    let Some(label) = LabelEntity::find_by_id(label_id).one(database).await? else {
+        return Err(WebsiteError::NotFound);
+    };
+    match (label.user_id, label.team_id) {
+        (Some(owner), _) if owner == user_id => Ok(label),
+        (None, Some(team_id)) => {
+            get_team_with_role(
+                IdOrName::Id(team_id),
+                user_id,
+                TeamRole::Maintainer,
+                database,
+            )
+            .await?;
+            Ok(label)
+        }
+        _ => Err(WebsiteError::NotFound),
+    }
+}
+/// Checks that the user can attach or remove the label on the project.
+///
+/// Your own labels can be used on any project you contribute to.
+/// Team labels can be used by members of the team on the projects of the team. Viewers can not label projects.
+async fn can_label_project(
+    label_id: i64,
+    project_id: i64,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<bool, WebsiteError> {
+    let Some(label) = LabelEntity::find_by_id(label_id).one(database).await? else {
+        return Ok(false);
+    };
+    if !can_user_contribute_to_project(user_id, project_id, database).await? {
+        return Ok(false);
+    }
+    match (label.user_id, label.team_id) {
+        (Some(owner), _) => Ok(owner == user_id),
+        (None, Some(team_id)) => {
+            let project = ProjectEntity::find_by_id(project_id).one(database).await?;
+            Ok(project.and_then(|project| project.team_id) == Some(team_id))
+        }
+        _ => Ok(false),
+    }
+}
+#[utoipa::path(get,
+    impl_for=list_labels,
+    path = "/api/projects/labels",
+    params(
+        ("team" = Option<IdOrName>, Query, description = "List the labels of a team instead of the labels available to you"),
+    ),
+    responses(
+        (status = 200, description = "Your labels and the labels of your teams", body = Vec<Label>),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadProjects"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/labels")]
+pub async fn list_labels(
+    auth: Scoped<scopes::ReadProjects>,
+    query: Query<LabelsQuery>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let labels = if let Some(team) = query.into_inner().team {
+        let Some(team_id) = team.get_id::<TeamEntity>(database.as_ref()).await? else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        if get_team_member(team_id, auth.id(), database.as_ref())
+            .await?
+            .is_none()
+        {
+            return Ok(HttpResponse::NotFound().finish());
+        }
+        get_labels_for_team(team_id, database.as_ref()).await?
+    } else {
+        get_labels_available_to_user(auth.id(), database.as_ref()).await?
+    };
+    Ok(HttpResponse::Ok().json(labels))
+}
+
+#[utoipa::path(post,
+    impl_for=create_label,
+    path = "/api/projects/labels",
+    request_body(content = NewLabel, description = "The Label to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Label Created", body = Label),
+        (status = 400, description = "Invalid Color"),
+        (status = 403, description = "You are not a maintainer of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+        (status = 409, description = "A label with that name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[post("/projects/labels")]
+pub async fn create_label(
+    auth: Scoped<scopes::WriteProjects>,
+    label: web::Json<NewLabel>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let NewLabel { name, color, team } = label.into_inner();
+    if !is_valid_color(&color) {
+        return Ok(HttpResponse::BadRequest().body("Invalid Color. Expected #rrggbb"));
+    }
+    let (user_id, team_id) = if let Some(team) = team {
+        let (team_id, _) =
+            get_team_with_role(team, auth.id(), TeamRole::Maintainer, database.as_ref()).await?;
+        (None, Some(team_id))
+    } else {
+        (Some(auth.id()), None)
+    };
+    if does_label_exist(&name, user_id, team_id, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let label = LabelActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        team_id: ActiveValue::Set(team_id),
+        name: ActiveValue::Set(name),
+        color: ActiveValue::Set(color),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(Label::from(label)))
+}
+
+#[utoipa::path(put,
+    impl_for=update_label,
+    path = "/api/projects/labels/{id}",
+    request_body(content = UpdateLabel, description = "The fields to update", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Label Updated", body = Label),
+        (status = 400, description = "Invalid Color"),
+        (status = 403, description = "You are not a maintainer of the team"),
+        (status = 404, description = "Label not found"),
+        (status = 409, description = "A label with that name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[put("/projects/labels/{id}")]
+pub async fn update_label(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    updates: web::Json<UpdateLabel>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let label = get_label_for_modification(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let UpdateLabel { name, color } = updates.into_inner();
+    if let Some(color) = color.as_deref() {
+        if !is_valid_color(color) {
+            return Ok(HttpResponse::BadRequest().body("Invalid Color. Expected #rrggbb"));
+        }
+    }
+    if let Some(name) = name.as_deref() {
+        if name != label.name
+            && does_label_exist(name, label.user_id, label.team_id, database.as_ref()).await?
+        {
+            return Ok(HttpResponse::Conflict().finish());
+        }
+    }
+    let mut label = label.into_active_model();
+    if let Some(name) = name {
+        label.name = ActiveValue::Set(name);
+    }
+    if let Some(color) = color {
+        label.color = ActiveValue::Set(color);
+    }
+    let label = label.update(database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(Label::from(label)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_label,
+    path = "/api/projects/labels/{id}",
+    responses(
+        (status = 204, description = "Label Deleted. It is removed from all projects"),
+        (status = 403, description = "You are not a maintainer of the team"),
+        (status = 404, description = "Label not found"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[delete("/projects/labels/{id}")]
+pub async fn delete_label(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let label = get_label_for_modification(path.into_inner(), auth.id(), database.as_ref()).await?;
+    LabelEntity::delete_by_id(label.id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=label_stats,
+    path = "/api/projects/labels/stats",
+    responses(
+        (status = 200, description = "Time you have spent per label", body = Vec<TimeBreakdown>),
+    ),
+    security(
+        ("api_key" = ["ReadUsage"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/labels/stats")]
+pub async fn label_stats(
+    auth: Scoped<scopes::ReadUsage>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let labels =
+        get_time_by_label(HeartbeatColumn::UserId.eq(auth.id()), database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(labels))
+}
+
+#[utoipa::path(put,
+    impl_for=attach_label,
+    path = "/api/projects/{project}/labels/{label}",
+    responses(
+        (status = 201, description = "Label Attached"),
+        (status = 204, description = "The label was already attached"),
+        (status = 404, description = "Project or Label not found or you can not use the label on the project"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[put("/projects/{project}/labels/{label}")]
+pub async fn attach_label(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<(i64, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (project_id, label_id) = path.into_inner();
+    if !can_label_project(label_id, project_id, auth.id(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if labels::attach_label(project_id, label_id, database.as_ref()).await? {
+        Ok(HttpResponse::Created().finish())
+    } else {
+        Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+#[utoipa::path(delete,
+    impl_for=remove_label,
+    path = "/api/projects/{project}/labels/{label}",
+    responses(
+        (status = 204, description = "Label Removed"),
+        (status = 404, description = "The label is not attached to the project or you can not use the label on the project"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[delete("/projects/{project}/labels/{label}")]
+pub async fn remove_label(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<(i64, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (project_id, label_id) = path.into_inner();
+    if !can_label_project(label_id, project_id, auth.id(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if detach_label(project_id, label_id, database.as_ref()).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
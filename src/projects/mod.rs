@@ -3,38 +3,75 @@ use actix_web::{
     web::{self, Data, Query},
     HttpResponse,
 };
-use common::{PartialProjectQuery, Project, ProjectQuery};
+use common::{
+    Page, Pagination, PartialProjectQuery, Project, ProjectQuery, NEXT_CURSOR_HEADER,
+    TOTAL_COUNT_HEADER,
+};
+use entities::projects::ProjectQueryError;
 use sea_orm::DatabaseConnection;
+use serde::Serialize;
 
-use crate::{error::WebsiteError, user::Authentication};
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+};
+pub mod budgets;
+pub mod labels;
+pub mod public;
+pub mod rules;
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(projects_list);
+    cfg.service(projects_list)
+        .configure(rules::init)
+        .configure(labels::init)
+        .configure(budgets::init)
+        .configure(public::init);
+}
+/// Responds with the items of the page as the body and the total count and next cursor as headers
+pub fn page_response<T: Serialize>(page: Page<T>) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response.insert_header((TOTAL_COUNT_HEADER, page.total.to_string()));
+    if let Some(cursor) = page.next_cursor {
+        response.insert_header((NEXT_CURSOR_HEADER, cursor));
+    }
+    response.json(page.items)
 }
 
 #[utoipa::path(get,
     impl_for=projects_list,
     path = "/api/projects/list",
     responses(
-        (status = 200, description = "Projects you are have access to", body = Vec<Project>),
+        (status = 200, description = "Projects you are have access to", body = Vec<Project>,
+            headers(
+                ("X-Total-Count" = u64, description = "The number of projects matching the query"),
+                ("X-Next-Cursor" = String, description = "The cursor for the next page. Missing on the last page")
+            )
+        ),
+        (status = 400, description = "Invalid Cursor or the cursor is from a query with another sort"),
+    ),
+    params(
+        ("limit" = Option<u64>, Query, description = "The maximum number of projects to return. Defaults to 50. At most 500"),
+        ("offset" = Option<u64>, Query, description = "The number of projects to skip. Ignored if a cursor is provided"),
+        ("cursor" = Option<String>, Query, description = "The value of the X-Next-Cursor header from the previous page"),
     ),
     security(
-        ("api_key" = []),
+        ("api_key" = ["ReadProjects"]),
         ("session" = [])
     )
 )]
 #[get("/projects/list")]
 pub async fn projects_list(
-    auth: Authentication,
+    auth: Scoped<scopes::ReadProjects>,
     query: Query<PartialProjectQuery>,
+    pagination: Query<Pagination>,
     database: Data<DatabaseConnection>,
 ) -> Result<HttpResponse, WebsiteError> {
-    let query = query.into_inner();
-    let full_query = ProjectQuery::from((auth.id(), query));
-    let projects =
-        entities::projects::query_projects::<Project>(full_query, database.as_ref()).await?;
-    if let Some(projects) = projects {
-        Ok(HttpResponse::Ok().json(projects))
-    } else {
-        Ok(HttpResponse::NotFound().finish())
+    let full_query = ProjectQuery::from((auth.id(), query.into_inner(), pagination.into_inner()));
+    match entities::projects::query_projects(full_query, database.as_ref()).await {
+        Ok(Some(projects)) => Ok(page_response(projects)),
+        Ok(None) => Ok(HttpResponse::NotFound().finish()),
+        Err(ProjectQueryError::InvalidCursor(error)) => {
+            Ok(HttpResponse::BadRequest().body(error.to_string()))
+        }
+        Err(ProjectQueryError::Database(error)) => Err(error.into()),
     }
 }
@@ -0,0 +1,292 @@
+//! Public Project Pages
+//!
+//! These routes do not require authentication.
+//! Projects that are not public return 404 unless you have access to them.
+use actix_web::{
+    get,
+    web::{self, Data},
+    HttpResponse,
+};
+use ahash::{HashMap, HashMapExt};
+use common::{
+    label::Label, stats::TimeBreakdown, IdOrName, ProjectContributor, PublicProject,
+    PublicProjectPage, TinyUser,
+};
+use entities::{
+    heartbeats::{
+        get_time_by_label, get_time_by_language, get_time_by_user, get_time_by_user_and_language,
+        get_total_time,
+    },
+    labels::get_labels_for_project,
+    projects::{does_user_have_access_to_project, get_public_projects_for_user, get_user_project},
+    HeartbeatColumn, LabelColumn, ProjectEntity, ProjectModel, UserColumn, UserEntity,
+};
+use sea_orm::{entity::prelude::*, sea_query::SimpleExpr, DatabaseConnection};
+
+use crate::{
+    error::WebsiteError,
+    user::{
+        scopes::{self, Scoped},
+        Authentication,
+    },
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_public_project)
+        .service(get_user_public_projects)
+        .service(get_user_project_page)
+        .service(get_user_label_stats);
+}
+/// Gets the labels of the project that are owned by the owner of the project.
+///
+/// Empty if the owning user does not share their labels
+async fn get_public_labels(
+    project: &ProjectModel,
+    database: &DatabaseConnection,
+) -> Result<Vec<Label>, DbErr> {
+    let labels = get_labels_for_project(project.id, database).await?;
+    if let Some(user_id) = project.user_id {
+        let owner: Option<TinyUser> = UserEntity::find_by_id(user_id)
+            .into_model()
+            .one(database)
+            .await?;
+        if !owner.is_some_and(|owner| owner.preferences.share_labels) {
+            return Ok(Vec::new());
+        }
+        Ok(labels
+            .into_iter()
+            .filter(|label| label.user_id == Some(user_id))
+            .collect())
+    } else {
+        Ok(labels
+            .into_iter()
+            .filter(|label| label.team_id.is_some() && label.team_id == project.team_id)
+            .collect())
+    }
+}
+/// Gets the users who spent time on the heartbeats matching the filter. Ordered by the most time spent.
+///
+/// Returns the users that can be listed and the number of the others.
+/// Users that are banned or do not share their projects are only counted.
+/// Users that do not share their languages will not have their languages listed.
+pub(crate) async fn get_contributors<T>(
+    filter: impl Fn() -> SimpleExpr,
+    database: &DatabaseConnection,
+    contributor: impl Fn(TinyUser, i64, Option<Vec<TimeBreakdown>>) -> T,
+) -> Result<(Vec<T>, u64), DbErr> {
+    let time_by_user = get_time_by_user(filter(), database).await?;
+    let users: Vec<TinyUser> = UserEntity::find()
+        .filter(UserColumn::Id.is_in(time_by_user.iter().map(|(user_id, _)| *user_id)))
+        .into_model()
+        .all(database)
+        .await?;
+    let mut languages_by_user: HashMap<i64, Vec<TimeBreakdown>> = HashMap::new();
+    for (user_id, name, seconds) in get_time_by_user_and_language(filter(), database).await? {
+        languages_by_user
+            .entry(user_id)
+            .or_default()
+            .push(TimeBreakdown { name, seconds });
+    }
+
+    let mut contributors = Vec::with_capacity(time_by_user.len());
+    let mut anonymous = 0;
+    for (user_id, seconds) in time_by_user {
+        let Some(user) = users.iter().find(|user| user.id == user_id) else {
+            continue;
+        };
+        if user.banned || !user.preferences.share_projects {
+            anonymous += 1;
+            continue;
+        }
+        let languages = if user.preferences.share_languages {
+            Some(languages_by_user.remove(&user_id).unwrap_or_default())
+        } else {
+            None
+        };
+        contributors.push(contributor(user.clone(), seconds, languages));
+    }
+    Ok((contributors, anonymous))
+}
+/// Builds the page for a project.
+///
+/// See [get_contributors] for the contributors that are listed.
+pub async fn build_project_page(
+    project: ProjectModel,
+    database: &DatabaseConnection,
+) -> Result<PublicProjectPage, DbErr> {
+    let filter = || HeartbeatColumn::Project.eq(project.id);
+    let total_seconds = get_total_time(filter(), database).await?;
+    let languages = get_time_by_language(filter(), database).await?;
+    let labels = get_public_labels(&project, database).await?;
+    let (contributors, anonymous_contributors) =
+        get_contributors(filter, database, |user, seconds, languages| {
+            ProjectContributor {
+                user,
+                seconds,
+                languages,
+            }
+        })
+        .await?;
+    Ok(PublicProjectPage {
+        project: PublicProject::from(project),
+        total_seconds,
+        languages,
+        labels,
+        contributors,
+        anonymous_contributors,
+    })
+}
+/// Checks if the project can be viewed. The owner and team members can always view it.
+///
+/// Public projects can be viewed by everyone. Unless the owner is banned or does not share their projects.
+/// API keys without the ReadProjects scope are treated as anonymous.
+async fn can_view_project(
+    project: &ProjectModel,
+    auth: Option<&Authentication>,
+    database: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    if let Some(auth) = auth {
+        if does_user_have_access_to_project(auth.id(), project.id, database).await? {
+            return Ok(true);
+        }
+    }
+    if !project.public {
+        return Ok(false);
+    }
+    let Some(user_id) = project.user_id else {
+        return Ok(true);
+    };
+    let owner = UserEntity::find_by_id(user_id)
+        .into_model::<TinyUser>()
+        .one(database)
+        .await?;
+    Ok(owner.is_some_and(|owner| !owner.banned && owner.preferences.share_projects))
+}
+#[utoipa::path(get,
+    impl_for=get_public_project,
+    path = "/api/projects/public/{id}",
+    responses(
+        (status = 200, description = "The Project", body = PublicProjectPage),
+        (status = 404, description = "Project not found, is not public or the owner does not share their projects")
+    ),
+)]
+#[get("/projects/public/{id}")]
+pub async fn get_public_project(
+    auth: Option<Scoped<scopes::ReadProjects>>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let Some(project) = ProjectEntity::find_by_id(path.into_inner())
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if !can_view_project(&project, auth.as_deref(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let page = build_project_page(project, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+#[utoipa::path(get,
+    impl_for=get_user_public_projects,
+    path = "/api/user/{user}/projects",
+    responses(
+        (status = 200, description = "The public projects of the user. Empty if the user does not share their projects", body = Vec<PublicProject>),
+        (status = 404, description = "User not found")
+    ),
+)]
+#[get("/user/{user}/projects")]
+pub async fn get_user_public_projects(
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let Some(user) = UserEntity::find()
+        .filter(path.into_inner().query::<UserEntity>())
+        .into_model::<TinyUser>()
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if user.banned {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if !user.preferences.share_projects {
+        return Ok(HttpResponse::Ok().json(Vec::<PublicProject>::new()));
+    }
+    let projects = get_public_projects_for_user(user.id, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(projects))
+}
+#[utoipa::path(get,
+    impl_for=get_user_project_page,
+    path = "/api/user/{user}/projects/{project}",
+    responses(
+        (status = 200, description = "The Project", body = PublicProjectPage),
+        (status = 404, description = "User or Project not found, the project is not public or the user does not share their projects")
+    ),
+)]
+#[get("/user/{user}/projects/{project}")]
+pub async fn get_user_project_page(
+    auth: Option<Scoped<scopes::ReadProjects>>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (user, project) = path.into_inner();
+    let Some(user) = UserEntity::find()
+        .filter(user.query::<UserEntity>())
+        .into_model::<TinyUser>()
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    // Same as the project list of the user. Hidden projects are not found
+    if user.banned || !user.preferences.share_projects {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let Some(project) =
+        get_user_project::<ProjectModel>(user.id, project, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if !can_view_project(&project, auth.as_deref(), database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let page = build_project_page(project, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(page))
+}
+#[utoipa::path(get,
+    impl_for=get_user_label_stats,
+    path = "/api/user/{user}/stats/labels",
+    responses(
+        (status = 200, description = "Time spent per label. Empty if the user does not share their labels", body = Vec<TimeBreakdown>),
+        (status = 404, description = "User not found")
+    ),
+)]
+#[get("/user/{user}/stats/labels")]
+pub async fn get_user_label_stats(
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let Some(user) = UserEntity::find()
+        .filter(path.into_inner().query::<UserEntity>())
+        .into_model::<TinyUser>()
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if user.banned {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if !user.preferences.share_labels {
+        return Ok(HttpResponse::Ok().json(Vec::<TimeBreakdown>::new()));
+    }
+    // Only the labels owned by the user. Team labels are not shared on the user's profile
+    let filter = HeartbeatColumn::UserId
+        .eq(user.id)
+        .and(LabelColumn::UserId.eq(user.id));
+    let labels = get_time_by_label(filter, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(labels))
+}
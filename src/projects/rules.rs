@@ -0,0 +1,452 @@
+//! Project Mapping Rules
+//!
+//! Base Route /api/projects/rules
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Data, Query},
+    HttpResponse,
+};
+use common::{
+    project_rules::{
+        CompiledRule, HeartbeatTarget, ProjectRule, RuleAction, RuleField, RulePattern, RuleSet,
+    },
+    team::TeamRole,
+    IdOrName,
+};
+use entities::{
+    project_rules::{
+        get_rules_applied_to_user, get_rules_for_organization, get_rules_for_team,
+        get_rules_owned_by_user,
+    },
+    projects::can_user_contribute_to_project,
+    teams::get_team_member,
+    HeartbeatColumn, HeartbeatEntity, ProjectEntity, ProjectModel, ProjectRuleActiveModel,
+    ProjectRuleEntity, ProjectRuleModel, TeamEntity,
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    error::WebsiteError,
+    organizations::get_organization_as_admin,
+    teams::get_team_with_role,
+    user::scopes::{self, Scoped},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_rules)
+        .service(create_rule)
+        .service(test_rules)
+        .service(update_rule)
+        .service(delete_rule);
+}
+/// The part of a rule that decides what it matches and what it does
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RuleDefinition {
+    pub field: RuleField,
+    pub pattern: RulePattern,
+    pub action: RuleAction,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewProjectRule {
+    pub name: String,
+    #[serde(flatten)]
+    pub definition: RuleDefinition,
+    /// Higher priorities are checked first
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Creates the rule for a team instead of yourself.
+    /// You must be a maintainer of the team.
+    pub team: Option<IdOrName>,
+    /// Creates the rule for every team of an organization.
+    /// You must be an admin of the organization. Can not be combined with `team`
+    pub organization: Option<IdOrName>,
+}
+fn default_enabled() -> bool {
+    true
+}
+/// All fields are optional.
+/// If a field is not provided, it will not be updated.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateProjectRule {
+    pub name: Option<String>,
+    pub field: Option<RuleField>,
+    pub pattern: Option<RulePattern>,
+    pub action: Option<RuleAction>,
+    pub priority: Option<i32>,
+    pub enabled: Option<bool>,
+}
+#[derive(Debug, Deserialize)]
+pub struct RulesQuery {
+    /// List the rules of a team instead of your own
+    pub team: Option<IdOrName>,
+    /// List the rules of an organization instead of your own
+    pub organization: Option<IdOrName>,
+}
+/// Gets a rule that the user is allowed to modify.
+async fn get_rule_for_modification(
+    rule_id: i64,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<ProjectRuleModel, WebsiteError> {
+    let Some(rule) = ProjectRuleEntity::find_by_id(rule_id).one(database).await? else {
+        return Err(WebsiteError::NotFound);
+    };
+    match (rule.user_id, rule.team_id, rule.organization_id) {
+        (Some(owner), _, _) if owner == user_id => Ok(rule),
+        (None, Some(team_id), _) => {
+            get_team_with_role(
+                IdOrName::Id(team_id),
+                user_id,
+                TeamRole::Maintainer,
+                database,
+            )
+            .await?;
+            Ok(rule)
+        }
+        (None, None, Some(organization_id)) => {
+            get_organization_as_admin(IdOrName::Id(organization_id), user_id, database).await?;
+            Ok(rule)
+        }
+        _ => Err(WebsiteError::NotFound),
+    }
+}
+/// Ensures an [RuleAction::AssignProject] points to a project the owner of the rule can use.
+async fn is_action_valid(
+    action: &RuleAction,
+    user_id: Option<i64>,
+    team_id: Option<i64>,
+    organization_id: Option<i64>,
+    database: &DatabaseConnection,
+) -> Result<bool, WebsiteError> {
+    let RuleAction::AssignProject(project) = action else {
+        return Ok(true);
+    };
+    if let Some(organization_id) = organization_id {
+        let team = ProjectEntity::find_by_id(*project)
+            .find_also_related(TeamEntity)
+            .one(database)
+            .await?
+            .and_then(|(_, team)| team);
+        return Ok(team.and_then(|team| team.organization_id) == Some(organization_id));
+    }
+    if let Some(team_id) = team_id {
+        let project: Option<ProjectModel> =
+            ProjectEntity::find_by_id(*project).one(database).await?;
+        return Ok(project.and_then(|project| project.team_id) == Some(team_id));
+    }
+    if let Some(user_id) = user_id {
+        return Ok(can_user_contribute_to_project(user_id, *project, database).await?);
+    }
+    Ok(false)
+}
+#[utoipa::path(get,
+    impl_for=list_rules,
+    path = "/api/projects/rules",
+    params(
+        ("team" = Option<IdOrName>, Query, description = "List the rules of a team instead of your own"),
+        ("organization" = Option<IdOrName>, Query, description = "List the rules of an organization instead of your own"),
+    ),
+    responses(
+        (status = 200, description = "Project Rules", body = Vec<ProjectRule>),
+        (status = 403, description = "You are not an admin of the organization"),
+        (status = 404, description = "Team or organization not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadProjects"]),
+        ("session" = [])
+    )
+)]
+#[get("/projects/rules")]
+pub async fn list_rules(
+    auth: Scoped<scopes::ReadProjects>,
+    query: Query<RulesQuery>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let RulesQuery { team, organization } = query.into_inner();
+    let rules = if let Some(team) = team {
+        let Some(team_id) = team.get_id::<TeamEntity>(database.as_ref()).await? else {
+            return Ok(HttpResponse::NotFound().finish());
+        };
+        if get_team_member(team_id, auth.id(), database.as_ref())
+            .await?
+            .is_none()
+        {
+            return Ok(HttpResponse::NotFound().finish());
+        }
+        get_rules_for_team(team_id, database.as_ref()).await?
+    } else if let Some(organization) = organization {
+        let organization_id =
+            get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+        get_rules_for_organization(organization_id, database.as_ref()).await?
+    } else {
+        get_rules_owned_by_user(auth.id(), database.as_ref()).await?
+    };
+    Ok(HttpResponse::Ok().json(rules))
+}
+
+#[utoipa::path(post,
+    impl_for=create_rule,
+    path = "/api/projects/rules",
+    request_body(content = NewProjectRule, description = "The Rule to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Rule Created", body = ProjectRule),
+        (status = 400, description = "Invalid Pattern, the project can not be assigned or both a team and organization were provided"),
+        (status = 403, description = "You are not a maintainer of the team or an admin of the organization"),
+        (status = 404, description = "Team or organization not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[post("/projects/rules")]
+pub async fn create_rule(
+    auth: Scoped<scopes::WriteProjects>,
+    rule: web::Json<NewProjectRule>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let NewProjectRule {
+        name,
+        definition:
+            RuleDefinition {
+                field,
+                pattern,
+                action,
+            },
+        priority,
+        enabled,
+        team,
+        organization,
+    } = rule.into_inner();
+    if let Err(error) = pattern.compile() {
+        return Ok(HttpResponse::BadRequest().body(error.to_string()));
+    }
+    let (user_id, team_id, organization_id) = match (team, organization) {
+        (Some(_), Some(_)) => {
+            return Ok(HttpResponse::BadRequest()
+                .body("A rule can not belong to both a team and an organization."));
+        }
+        (Some(team), None) => {
+            let (team_id, _) =
+                get_team_with_role(team, auth.id(), TeamRole::Maintainer, database.as_ref())
+                    .await?;
+            (None, Some(team_id), None)
+        }
+        (None, Some(organization)) => {
+            let organization_id =
+                get_organization_as_admin(organization, auth.id(), database.as_ref()).await?;
+            (None, None, Some(organization_id))
+        }
+        (None, None) => (Some(auth.id()), None, None),
+    };
+    if !is_action_valid(
+        &action,
+        user_id,
+        team_id,
+        organization_id,
+        database.as_ref(),
+    )
+    .await?
+    {
+        return Ok(HttpResponse::BadRequest().body("Project can not be assigned by this rule."));
+    }
+    let rule = ProjectRuleActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        team_id: ActiveValue::Set(team_id),
+        organization_id: ActiveValue::Set(organization_id),
+        name: ActiveValue::Set(name),
+        field: ActiveValue::Set(field),
+        pattern: ActiveValue::Set(pattern),
+        action: ActiveValue::Set(action),
+        priority: ActiveValue::Set(priority),
+        enabled: ActiveValue::Set(enabled),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(ProjectRule::from(rule)))
+}
+
+#[utoipa::path(put,
+    impl_for=update_rule,
+    path = "/api/projects/rules/{id}",
+    request_body(content = UpdateProjectRule, description = "The fields to update", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Rule Updated", body = ProjectRule),
+        (status = 400, description = "Invalid Pattern or the project can not be assigned"),
+        (status = 403, description = "You are not a maintainer of the team or an admin of the organization"),
+        (status = 404, description = "Rule not found"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[put("/projects/rules/{id}")]
+pub async fn update_rule(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    updates: web::Json<UpdateProjectRule>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let rule = get_rule_for_modification(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let UpdateProjectRule {
+        name,
+        field,
+        pattern,
+        action,
+        priority,
+        enabled,
+    } = updates.into_inner();
+    if let Some(Err(error)) = pattern.as_ref().map(RulePattern::compile) {
+        return Ok(HttpResponse::BadRequest().body(error.to_string()));
+    }
+    if let Some(action) = action.as_ref() {
+        if !is_action_valid(
+            action,
+            rule.user_id,
+            rule.team_id,
+            rule.organization_id,
+            database.as_ref(),
+        )
+        .await?
+        {
+            return Ok(HttpResponse::BadRequest().body("Project can not be assigned by this rule."));
+        }
+    }
+    let mut rule = rule.into_active_model();
+    if let Some(name) = name {
+        rule.name = ActiveValue::Set(name);
+    }
+    if let Some(field) = field {
+        rule.field = ActiveValue::Set(field);
+    }
+    if let Some(pattern) = pattern {
+        rule.pattern = ActiveValue::Set(pattern);
+    }
+    if let Some(action) = action {
+        rule.action = ActiveValue::Set(action);
+    }
+    if let Some(priority) = priority {
+        rule.priority = ActiveValue::Set(priority);
+    }
+    if let Some(enabled) = enabled {
+        rule.enabled = ActiveValue::Set(enabled);
+    }
+    let rule = rule.update(database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(ProjectRule::from(rule)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_rule,
+    path = "/api/projects/rules/{id}",
+    responses(
+        (status = 204, description = "Rule Deleted"),
+        (status = 403, description = "You are not a maintainer of the team or an admin of the organization"),
+        (status = 404, description = "Rule not found"),
+    ),
+    security(
+        ("api_key" = ["WriteProjects"]),
+        ("session" = [])
+    )
+)]
+#[delete("/projects/rules/{id}")]
+pub async fn delete_rule(
+    auth: Scoped<scopes::WriteProjects>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let rule = get_rule_for_modification(path.into_inner(), auth.id(), database.as_ref()).await?;
+    ProjectRuleEntity::delete_by_id(rule.id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TestRulesRequest {
+    /// A rule to test instead of your saved rules
+    pub rule: Option<RuleDefinition>,
+    /// The number of recent heartbeats to test against.
+    /// Defaults to 100. At most 1000
+    pub limit: Option<u64>,
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuleTestResult {
+    pub heartbeat_id: i64,
+    pub entity: String,
+    pub project: Option<String>,
+    pub branch: Option<String>,
+    /// The saved rule that matched. None when testing an unsaved rule
+    pub rule_id: Option<i64>,
+    /// The action that would be taken. None if no rule matched
+    pub action: Option<RuleAction>,
+}
+#[utoipa::path(post,
+    impl_for=test_rules,
+    path = "/api/projects/rules/test",
+    request_body(content = TestRulesRequest, description = "The rule to test. If none is provided your saved rules are used", content_type = "application/json"),
+    responses(
+        (status = 200, description = "What the rules would do to your recent heartbeats", body = Vec<RuleTestResult>),
+        (status = 400, description = "Invalid Pattern"),
+    ),
+    security(
+        ("api_key" = ["ReadHeartbeat"]),
+        ("session" = [])
+    )
+)]
+#[post("/projects/rules/test")]
+pub async fn test_rules(
+    auth: Scoped<scopes::ReadHeartbeat>,
+    request: web::Json<TestRulesRequest>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let TestRulesRequest { rule, limit } = request.into_inner();
+    let (rules, is_saved) = if let Some(RuleDefinition {
+        field,
+        pattern,
+        action,
+    }) = rule
+    {
+        match CompiledRule::new(0, field, &pattern, action) {
+            Ok(rule) => (RuleSet { rules: vec![rule] }, false),
+            Err(error) => return Ok(HttpResponse::BadRequest().body(error.to_string())),
+        }
+    } else {
+        let rules = get_rules_applied_to_user(auth.id(), database.as_ref()).await?;
+        (RuleSet::new(rules), true)
+    };
+
+    let heartbeats = HeartbeatEntity::find()
+        .filter(HeartbeatColumn::UserId.eq(auth.id()))
+        .order_by_desc(HeartbeatColumn::StartTime)
+        .limit(limit.unwrap_or(100).min(1000))
+        .find_also_related(ProjectEntity)
+        .all(database.as_ref())
+        .await?;
+    let results: Vec<_> = heartbeats
+        .into_iter()
+        .map(|(heartbeat, project)| {
+            let project = project.map(|project| project.name);
+            let matched = rules.evaluate(&HeartbeatTarget {
+                entity: &heartbeat.entity,
+                project: project.as_deref(),
+                branch: heartbeat.branch.as_deref(),
+            });
+            RuleTestResult {
+                heartbeat_id: heartbeat.id,
+                rule_id: matched.filter(|_| is_saved).map(|rule| rule.id),
+                action: matched.map(|rule| rule.action.clone()),
+                entity: heartbeat.entity,
+                project,
+                branch: heartbeat.branch,
+            }
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(results))
+}
@@ -42,7 +42,7 @@ pub struct RecaptchaAccess {
     pub settings: Option<GoogleRecaptcha>,
     pub http_client: Client,
 }
-fn new_client() -> anyhow::Result<Client> {
+pub(crate) fn new_client() -> anyhow::Result<Client> {
     let client = Client::builder()
         .user_agent(concat!(
             env!("CARGO_PKG_NAME"),
@@ -118,6 +118,12 @@ impl RecaptchaAccess {
             .map(|s| s.public_config.require_on_login)
             .unwrap_or_default()
     }
+    pub fn require_on_password_reset(&self) -> bool {
+        self.settings
+            .as_ref()
+            .map(|s| s.public_config.require_on_password_reset)
+            .unwrap_or_default()
+    }
     #[instrument(skip(request, self))]
     pub async fn verify_response(
         &self,
@@ -20,6 +20,12 @@ pub struct State {
     /// URL to the home page
     pub home_url: Option<String>,
     pub https: bool,
+    /// True if passkeys can be used. Requires `home_url`
+    pub passkeys: bool,
+    /// False if users must log in through single sign on
+    pub password_login: bool,
+    /// True if users can link their GitHub account
+    pub github_connections: bool,
 }
 
 impl Default for State {
@@ -31,6 +37,9 @@ impl Default for State {
             recaptcha_config: None,
             home_url: None,
             https: false,
+            passkeys: false,
+            password_login: true,
+            github_connections: false,
         }
     }
 }
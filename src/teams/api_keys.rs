@@ -0,0 +1,174 @@
+//! Team API Keys
+//!
+//! API keys owned by a team for service accounts. Such as CI bots and shared build machines.
+//! Heartbeats sent with them are not attributed to a person and can only go to the projects of the team.
+use actix_web::{
+    delete, get, post,
+    web::{self, Data},
+    HttpResponse,
+};
+use chrono::{DateTime, FixedOffset};
+use common::{
+    team::{CreatedTeamAPIToken, TeamAPIToken, TeamRole},
+    APITokenPermissions, IdOrName,
+};
+use entities::{
+    teams::get_team_api_keys, TeamAPIKeyActiveModel, TeamAPIKeyColumn, TeamAPIKeyEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::get_team_with_role;
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+    utils::{time_utils, token::generate_token},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_api_keys)
+        .service(create_api_key)
+        .service(revoke_api_key);
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewTeamAPIKey {
+    /// Example: "GitHub Actions"
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub permissions: Vec<APITokenPermissions>,
+    /// The key never expires if not provided
+    pub expires_at: Option<DateTime<FixedOffset>>,
+}
+
+#[utoipa::path(get,
+    impl_for=list_api_keys,
+    path = "/api/teams/{team}/api-keys",
+    responses(
+        (status = 200, description = "The API keys of the team. Including revoked keys", body = Vec<TeamAPIToken>),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/{team}/api-keys")]
+pub async fn list_api_keys(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    let keys = get_team_api_keys(team_id, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(post,
+    impl_for=create_api_key,
+    path = "/api/teams/{team}/api-keys",
+    request_body(content = NewTeamAPIKey, description = "The API Key to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "API Key Created. The token is only returned once", body = CreatedTeamAPIToken),
+        (status = 400, description = "No permissions or the expiration is in the past"),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/{team}/api-keys")]
+pub async fn create_api_key(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    key: web::Json<NewTeamAPIKey>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    let NewTeamAPIKey {
+        name,
+        description,
+        permissions,
+        expires_at,
+    } = key.into_inner();
+    if permissions.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("At least one permission is required."));
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= time_utils::get_current_time()) {
+        return Ok(HttpResponse::BadRequest().body("The expiration must be in the future."));
+    }
+    let (token, token_hash) = generate_token();
+    let key = TeamAPIKeyActiveModel {
+        team_id: ActiveValue::Set(team_id),
+        created_by: ActiveValue::Set(auth.id()),
+        name: ActiveValue::Set(name),
+        description: ActiveValue::Set(description),
+        token: ActiveValue::Set(token_hash),
+        permissions: ActiveValue::Set(permissions),
+        expires_at: ActiveValue::Set(expires_at),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(CreatedTeamAPIToken {
+        key: TeamAPIToken::from(key),
+        token,
+    }))
+}
+
+#[utoipa::path(delete,
+    impl_for=revoke_api_key,
+    path = "/api/teams/{team}/api-keys/{id}",
+    responses(
+        (status = 204, description = "API Key Revoked. Heartbeats sent with it are kept"),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team or API Key not found or it is already revoked"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/teams/{team}/api-keys/{id}")]
+pub async fn revoke_api_key(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team, key_id) = path.into_inner();
+    let (team_id, _) =
+        get_team_with_role(team, auth.id(), TeamRole::Admin, database.as_ref()).await?;
+    let result = TeamAPIKeyEntity::update_many()
+        .filter(
+            TeamAPIKeyColumn::Id
+                .eq(key_id)
+                .and(TeamAPIKeyColumn::TeamId.eq(team_id))
+                .and(TeamAPIKeyColumn::Revoked.is_null()),
+        )
+        .col_expr(
+            TeamAPIKeyColumn::Revoked,
+            Expr::value(time_utils::get_current_time()),
+        )
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
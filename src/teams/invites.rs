@@ -0,0 +1,384 @@
+//! Team Invitations
+//!
+//! Admins invite users by username or email with the role they get when they join. The invitee has to accept the invite to join the team.
+//! Invites to emails that are not registered are claimed once an account verifies the email.
+//! The invite link is emailed to the invitee.
+use actix_web::{
+    delete, get, post,
+    web::{self, Data},
+    HttpResponse,
+};
+use chrono::Duration;
+use common::{
+    team::{CreatedTeamInvite, TeamInvite, TeamRole},
+    Email, IdOrName, Username,
+};
+use entities::{
+    teams::{
+        get_invite_by_token, get_pending_invites_for_team, get_pending_invites_for_user,
+        get_team_member, has_pending_invite,
+    },
+    TeamColumn, TeamEntity, TeamInviteActiveModel, TeamInviteColumn, TeamInviteEntity,
+    TeamInviteModel, TeamMemberActiveModel, UserColumn, UserEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, QuerySelect, TransactionTrait};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::get_team_with_role;
+use crate::{
+    email::{emails::TeamInvitation, EmailAccess, Recipient},
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+    utils::{sha256, time_utils, token::generate_token},
+};
+/// How long an invite can be accepted for
+pub fn invite_lifetime() -> Duration {
+    Duration::days(7)
+}
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(my_invites)
+        .service(accept_invite_by_token)
+        .service(accept_invite)
+        .service(decline_invite)
+        .service(create_invite)
+        .service(list_team_invites)
+        .service(revoke_invite);
+}
+/// Either a username or an email must be provided
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewTeamInvite {
+    pub username: Option<Username>,
+    pub email: Option<Email>,
+    /// Defaults to [TeamRole::Member]. Must be below your own role unless you are an owner
+    #[serde(default)]
+    pub role: TeamRole,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AcceptInviteByToken {
+    pub token: String,
+}
+/// Gets a pending invite that was sent to the user
+async fn get_invite_for_user(
+    invite_id: i64,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<TeamInviteModel, WebsiteError> {
+    let Some(invite) = TeamInviteEntity::find_by_id(invite_id)
+        .one(database)
+        .await?
+    else {
+        return Err(WebsiteError::NotFound);
+    };
+    if invite.user_id != Some(user_id) || invite.expires <= time_utils::get_current_time() {
+        return Err(WebsiteError::NotFound);
+    }
+    Ok(invite)
+}
+/// Adds the user to the team and deletes the invite
+async fn join_team(
+    invite: TeamInviteModel,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let transaction = database.begin().await?;
+    if get_team_member(invite.team_id, user_id, &transaction)
+        .await?
+        .is_none()
+    {
+        TeamMemberActiveModel {
+            team_id: ActiveValue::Set(invite.team_id),
+            user_id: ActiveValue::Set(user_id),
+            role: ActiveValue::Set(invite.role),
+            ..Default::default()
+        }
+        .insert(&transaction)
+        .await?;
+    }
+    TeamInviteEntity::delete_by_id(invite.id)
+        .exec(&transaction)
+        .await?;
+    transaction.commit().await
+}
+
+#[utoipa::path(get,
+    impl_for=my_invites,
+    path = "/api/teams/invites",
+    responses(
+        (status = 200, description = "The pending invites sent to you", body = Vec<TeamInvite>),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/invites")]
+pub async fn my_invites(
+    auth: Scoped<scopes::ReadTeams>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let invites =
+        get_pending_invites_for_user(auth.id(), time_utils::get_current_time(), database.as_ref())
+            .await?;
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+#[utoipa::path(post,
+    impl_for=accept_invite_by_token,
+    path = "/api/teams/invites/accept",
+    request_body(content = AcceptInviteByToken, description = "The token from the invite link", content_type = "application/json"),
+    responses(
+        (status = 204, description = "You joined the team"),
+        (status = 404, description = "Invite not found, expired, sent to someone else or your email is not verified"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/invites/accept")]
+pub async fn accept_invite_by_token(
+    auth: Scoped<scopes::WriteTeams>,
+    body: web::Json<AcceptInviteByToken>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let token_hash = sha256::encode_to_string(&body.into_inner().token);
+    let Some(invite) = get_invite_by_token(&token_hash, database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if invite.expires <= time_utils::get_current_time() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let user = auth.as_ref();
+    let is_recipient = match (invite.user_id, invite.email.as_ref()) {
+        (Some(user_id), _) => user_id == user.id,
+        // Anyone can set their email to the invited one. They must prove they own it
+        (None, Some(email)) => *email == user.email && user.email_verified_at.is_some(),
+        (None, None) => false,
+    };
+    if !is_recipient {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    join_team(invite, user.id, database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(post,
+    impl_for=accept_invite,
+    path = "/api/teams/invites/{id}/accept",
+    responses(
+        (status = 204, description = "You joined the team"),
+        (status = 404, description = "Invite not found or expired"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/invites/{id}/accept")]
+pub async fn accept_invite(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let invite = get_invite_for_user(path.into_inner(), auth.id(), database.as_ref()).await?;
+    join_team(invite, auth.id(), database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(post,
+    impl_for=decline_invite,
+    path = "/api/teams/invites/{id}/decline",
+    responses(
+        (status = 204, description = "Invite Declined"),
+        (status = 404, description = "Invite not found or expired"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/invites/{id}/decline")]
+pub async fn decline_invite(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let invite = get_invite_for_user(path.into_inner(), auth.id(), database.as_ref()).await?;
+    TeamInviteEntity::delete_by_id(invite.id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(post,
+    impl_for=create_invite,
+    path = "/api/teams/{team}/invites",
+    request_body(content = NewTeamInvite, description = "Who to invite", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Invite Created", body = CreatedTeamInvite),
+        (status = 400, description = "Neither a username or an email was provided"),
+        (status = 403, description = "You are not an admin of the team or you can not assign the role"),
+        (status = 404, description = "Team or user not found"),
+        (status = 409, description = "The user is already a member or has a pending invite"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/{team}/invites")]
+pub async fn create_invite(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    invite: web::Json<NewTeamInvite>,
+    database: Data<DatabaseConnection>,
+    email_access: Data<EmailAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, manager) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    let NewTeamInvite {
+        username,
+        email,
+        role,
+    } = invite.into_inner();
+    if !manager.role.can_assign(role) {
+        return Err(WebsiteError::Forbidden);
+    }
+    let filter = match (username, email.as_ref()) {
+        (Some(username), _) => UserColumn::Username.eq(username),
+        (None, Some(email)) => UserColumn::Email.eq(email.clone()),
+        (None, None) => {
+            return Ok(HttpResponse::BadRequest().body("A username or an email is required."))
+        }
+    };
+    let invitee = UserEntity::find()
+        .filter(filter)
+        .one(database.as_ref())
+        .await?;
+    let user_id = invitee.as_ref().map(|user| user.id);
+    let (recipient, email) = match (invitee, email) {
+        (Some(user), _) => (Recipient::new(user.name, user.email.as_ref()), None),
+        // Only emails can be invited before they register
+        (None, Some(email)) => (Recipient::new("", email.as_ref()), Some(email)),
+        (None, None) => return Ok(HttpResponse::NotFound().finish()),
+    };
+    if let Some(user_id) = user_id {
+        if get_team_member(team_id, user_id, database.as_ref())
+            .await?
+            .is_some()
+        {
+            return Ok(HttpResponse::Conflict().finish());
+        }
+    }
+    let now = time_utils::get_current_time();
+    if has_pending_invite(team_id, user_id, email.as_ref(), now, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let (token, token_hash) = generate_token();
+    let invite = TeamInviteActiveModel {
+        team_id: ActiveValue::Set(team_id),
+        invited_by: ActiveValue::Set(auth.id()),
+        user_id: ActiveValue::Set(user_id),
+        email: ActiveValue::Set(email),
+        role: ActiveValue::Set(role),
+        token: ActiveValue::Set(token_hash),
+        expires: ActiveValue::Set(now + invite_lifetime()),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    let team: Option<String> = TeamEntity::find_by_id(team_id)
+        .select_only()
+        .column(TeamColumn::Name)
+        .into_tuple()
+        .one(database.as_ref())
+        .await?;
+    let inviter = auth.as_ref();
+    let invitation = TeamInvitation {
+        name: recipient.name.clone(),
+        team: team.unwrap_or_default(),
+        invited_by: inviter.username.to_string(),
+        role: invite.role.to_string(),
+        token: token.clone(),
+        expires_in_days: invite_lifetime().num_days(),
+    };
+    email_access.send_or_log(recipient, invitation);
+    Ok(HttpResponse::Created().json(CreatedTeamInvite {
+        invite: TeamInvite::from(invite),
+        token,
+    }))
+}
+
+#[utoipa::path(get,
+    impl_for=list_team_invites,
+    path = "/api/teams/{team}/invites",
+    responses(
+        (status = 200, description = "The pending invites of the team", body = Vec<TeamInvite>),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/{team}/invites")]
+pub async fn list_team_invites(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    let invites =
+        get_pending_invites_for_team(team_id, time_utils::get_current_time(), database.as_ref())
+            .await?;
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+#[utoipa::path(delete,
+    impl_for=revoke_invite,
+    path = "/api/teams/{team}/invites/{id}",
+    responses(
+        (status = 204, description = "Invite Revoked"),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team or invite not found"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/teams/{team}/invites/{id}")]
+pub async fn revoke_invite(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, i64)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team, invite_id) = path.into_inner();
+    let (team_id, _) =
+        get_team_with_role(team, auth.id(), TeamRole::Admin, database.as_ref()).await?;
+    let result = TeamInviteEntity::delete_many()
+        .filter(
+            TeamInviteColumn::Id
+                .eq(invite_id)
+                .and(TeamInviteColumn::TeamId.eq(team_id)),
+        )
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,463 @@
+//! Team Management
+//!
+//! Base Route /api/teams
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Data},
+    HttpResponse,
+};
+use common::{
+    team::{to_id_style_name, Team, TeamMember, TeamMembership, TeamRole},
+    IdOrName, TinyUser,
+};
+use entities::{
+    teams::{
+        count_team_members_with_role, does_id_style_name_exist, get_team_member, get_team_members,
+        get_teams_for_user,
+    },
+    TeamActiveModel, TeamEntity, TeamMemberActiveModel, TeamMemberEntity, TeamMemberModel,
+    UserColumn, UserEntity,
+};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel, TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::{
+    error::WebsiteError,
+    user::scopes::{self, Scoped},
+};
+pub mod api_keys;
+pub mod invites;
+pub mod languages;
+pub mod stats;
+pub fn init(cfg: &mut web::ServiceConfig) {
+    // Registered first so `/teams/invites` is not matched as a team
+    cfg.configure(invites::init)
+        .service(my_teams)
+        .service(create_team)
+        .service(get_team)
+        .service(rename_team)
+        .service(delete_team)
+        .service(list_members)
+        .service(set_member_role)
+        .service(remove_member)
+        .service(leave_team)
+        .configure(api_keys::init)
+        .configure(languages::init)
+        .configure(stats::init);
+}
+/// Resolves the team and checks that the user is a member of it
+pub(crate) async fn get_team_as_member(
+    team: IdOrName,
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<(i64, TeamMemberModel), WebsiteError> {
+    let Some(team_id) = team.get_id::<TeamEntity>(database).await? else {
+        return Err(WebsiteError::NotFound);
+    };
+    match get_team_member(team_id, user_id, database).await? {
+        Some(member) => Ok((team_id, member)),
+        None => Err(WebsiteError::NotFound),
+    }
+}
+/// Resolves the team and checks that the user has at least the role in it
+pub(crate) async fn get_team_with_role(
+    team: IdOrName,
+    user_id: i64,
+    role: TeamRole,
+    database: &DatabaseConnection,
+) -> Result<(i64, TeamMemberModel), WebsiteError> {
+    let (team_id, member) = get_team_as_member(team, user_id, database).await?;
+    if !member.role.is_at_least(role) {
+        return Err(WebsiteError::Forbidden);
+    }
+    Ok((team_id, member))
+}
+/// Checks if the member is the only one left that can manage the team
+async fn is_last_manager(
+    member: &TeamMemberModel,
+    database: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    if !member.role.can_manage_team() {
+        return Ok(false);
+    }
+    let managers = count_team_members_with_role(
+        member.team_id,
+        &[TeamRole::Owner, TeamRole::Admin],
+        database,
+    )
+    .await?;
+    Ok(managers <= 1)
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewTeam {
+    /// Example: "Codi Time Developers". The id style name is generated from it
+    pub name: String,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameTeam {
+    /// The id style name is regenerated from the new name
+    pub name: String,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetMemberRole {
+    pub role: TeamRole,
+}
+
+#[utoipa::path(get,
+    impl_for=my_teams,
+    path = "/api/teams",
+    responses(
+        (status = 200, description = "The teams you are a member of", body = Vec<TeamMembership>),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams")]
+pub async fn my_teams(
+    auth: Scoped<scopes::ReadTeams>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let teams: Vec<TeamMembership> = get_teams_for_user(auth.id(), database.as_ref())
+        .await?
+        .into_iter()
+        .map(|(member, team)| TeamMembership {
+            team: team.into(),
+            role: member.role,
+            joined: member.created,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(teams))
+}
+
+#[utoipa::path(post,
+    impl_for=create_team,
+    path = "/api/teams",
+    request_body(content = NewTeam, description = "The Team to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Team Created. You are its owner", body = Team),
+        (status = 400, description = "Invalid Name"),
+        (status = 409, description = "A team with that id style name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams")]
+pub async fn create_team(
+    auth: Scoped<scopes::WriteTeams>,
+    team: web::Json<NewTeam>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let name = team.into_inner().name.trim().to_owned();
+    let Some(id_style_name) = to_id_style_name(&name) else {
+        return Ok(HttpResponse::BadRequest()
+            .body("Team name must contain at least one non-numeric character."));
+    };
+    if does_id_style_name_exist(&id_style_name, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let transaction = database.begin().await?;
+    let team = TeamActiveModel {
+        name: ActiveValue::Set(name),
+        id_style_name: ActiveValue::Set(id_style_name),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+    TeamMemberActiveModel {
+        team_id: ActiveValue::Set(team.id),
+        user_id: ActiveValue::Set(auth.id()),
+        role: ActiveValue::Set(TeamRole::Owner),
+        ..Default::default()
+    }
+    .insert(&transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(HttpResponse::Created().json(Team::from(team)))
+}
+
+#[utoipa::path(get,
+    impl_for=get_team,
+    path = "/api/teams/{team}",
+    responses(
+        (status = 200, description = "The Team", body = Team),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/{team}")]
+pub async fn get_team(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let Some(team) = TeamEntity::find_by_id(team_id)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    Ok(HttpResponse::Ok().json(Team::from(team)))
+}
+
+#[utoipa::path(put,
+    impl_for=rename_team,
+    path = "/api/teams/{team}",
+    request_body(content = RenameTeam, description = "The new name", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Team Renamed", body = Team),
+        (status = 400, description = "Invalid Name"),
+        (status = 403, description = "You are not an admin of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+        (status = 409, description = "A team with that id style name already exists"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[put("/teams/{team}")]
+pub async fn rename_team(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    rename: web::Json<RenameTeam>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    let Some(team) = TeamEntity::find_by_id(team_id)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let name = rename.into_inner().name.trim().to_owned();
+    let Some(id_style_name) = to_id_style_name(&name) else {
+        return Ok(HttpResponse::BadRequest()
+            .body("Team name must contain at least one non-numeric character."));
+    };
+    if id_style_name != team.id_style_name
+        && does_id_style_name_exist(&id_style_name, database.as_ref()).await?
+    {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let mut team = team.into_active_model();
+    team.name = ActiveValue::Set(name);
+    team.id_style_name = ActiveValue::Set(id_style_name);
+    let team = team.update(database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(Team::from(team)))
+}
+
+#[utoipa::path(delete,
+    impl_for=delete_team,
+    path = "/api/teams/{team}",
+    responses(
+        (status = 204, description = "Team Deleted. Along with its projects"),
+        (status = 403, description = "You are not an owner of the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/teams/{team}")]
+pub async fn delete_team(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, member) = get_team_with_role(
+        path.into_inner(),
+        auth.id(),
+        TeamRole::Admin,
+        database.as_ref(),
+    )
+    .await?;
+    // Teams from before roles existed only have admins. They act as the owners
+    if member.role != TeamRole::Owner
+        && count_team_members_with_role(team_id, &[TeamRole::Owner], database.as_ref()).await? > 0
+    {
+        return Err(WebsiteError::Forbidden);
+    }
+    TeamEntity::delete_by_id(team_id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(get,
+    impl_for=list_members,
+    path = "/api/teams/{team}/members",
+    responses(
+        (status = 200, description = "The members of the team", body = Vec<TeamMember>),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadTeams"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/{team}/members")]
+pub async fn list_members(
+    auth: Scoped<scopes::ReadTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (team_id, _) = get_team_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let members = get_team_members(team_id, database.as_ref()).await?;
+    let users: Vec<TinyUser> = UserEntity::find()
+        .filter(UserColumn::Id.is_in(members.iter().map(|member| member.user_id)))
+        .into_model()
+        .all(database.as_ref())
+        .await?;
+    let members: Vec<TeamMember> = members
+        .into_iter()
+        .filter_map(|member| {
+            let user = users.iter().find(|user| user.id == member.user_id)?;
+            Some(TeamMember {
+                user: user.clone(),
+                role: member.role,
+                joined: member.created,
+            })
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(members))
+}
+/// Resolves the team and the member that a manager of the team is acting on.
+///
+/// Returns the membership of the manager and the member
+async fn get_member_as_manager(
+    path: (IdOrName, IdOrName),
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<(TeamMemberModel, TeamMemberModel), WebsiteError> {
+    let (team, member) = path;
+    let (team_id, manager) = get_team_with_role(team, user_id, TeamRole::Admin, database).await?;
+    let Some(member_id) = member.get_id::<UserEntity>(database).await? else {
+        return Err(WebsiteError::NotFound);
+    };
+    let member = get_team_member(team_id, member_id, database)
+        .await?
+        .ok_or(WebsiteError::NotFound)?;
+    Ok((manager, member))
+}
+
+#[utoipa::path(put,
+    impl_for=set_member_role,
+    path = "/api/teams/{team}/members/{user}/role",
+    request_body(content = SetMemberRole, description = "The new role of the member", content_type = "application/json"),
+    responses(
+        (status = 204, description = "Role Changed"),
+        (status = 403, description = "You are not an admin of the team or you can not assign the role"),
+        (status = 404, description = "Team or member not found"),
+        (status = 409, description = "The member is the last owner or admin of the team"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[put("/teams/{team}/members/{user}/role")]
+pub async fn set_member_role(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    body: web::Json<SetMemberRole>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (manager, member) =
+        get_member_as_manager(path.into_inner(), auth.id(), database.as_ref()).await?;
+    let role = body.into_inner().role;
+    if member.role == role {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+    if !manager.role.can_assign(member.role) || !manager.role.can_assign(role) {
+        return Err(WebsiteError::Forbidden);
+    }
+    if !role.can_manage_team() && is_last_manager(&member, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().body("A team must have at least one owner or admin."));
+    }
+    let mut member = member.into_active_model();
+    member.role = ActiveValue::Set(role);
+    member.update(database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(delete,
+    impl_for=remove_member,
+    path = "/api/teams/{team}/members/{user}",
+    responses(
+        (status = 204, description = "Member Removed"),
+        (status = 403, description = "You are not an admin of the team or the member's role is not below yours"),
+        (status = 404, description = "Team or member not found"),
+        (status = 409, description = "The member is the last owner or admin of the team"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[delete("/teams/{team}/members/{user}")]
+pub async fn remove_member(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<(IdOrName, IdOrName)>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (manager, member) =
+        get_member_as_manager(path.into_inner(), auth.id(), database.as_ref()).await?;
+    if !manager.role.can_assign(member.role) {
+        return Err(WebsiteError::Forbidden);
+    }
+    if is_last_manager(&member, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict().body("A team must have at least one owner or admin."));
+    }
+    TeamMemberEntity::delete_by_id(member.id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(post,
+    impl_for=leave_team,
+    path = "/api/teams/{team}/leave",
+    responses(
+        (status = 204, description = "You left the team"),
+        (status = 404, description = "Team not found or you are not a member"),
+        (status = 409, description = "You are the last owner or admin. Promote another member or delete the team"),
+    ),
+    security(
+        ("api_key" = ["WriteTeams"]),
+        ("session" = [])
+    )
+)]
+#[post("/teams/{team}/leave")]
+pub async fn leave_team(
+    auth: Scoped<scopes::WriteTeams>,
+    path: web::Path<IdOrName>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (_, member) = get_team_as_member(path.into_inner(), auth.id(), database.as_ref()).await?;
+    if is_last_manager(&member, database.as_ref()).await? {
+        return Ok(HttpResponse::Conflict()
+            .body("You are the last owner or admin. Promote another member or delete the team."));
+    }
+    TeamMemberEntity::delete_by_id(member.id)
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
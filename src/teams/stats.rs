@@ -0,0 +1,138 @@
+//! Team Dashboard
+//!
+//! Aggregates the time spent by every member on the projects of the team.
+//! Service accounts of the team can read it with the ReadUsage scope.
+use actix_web::{
+    get,
+    web::{self, Data, Query},
+    HttpResponse,
+};
+use common::{
+    stats::{StatsRange, TeamMemberTime, TeamStats},
+    IdOrName,
+};
+use entities::{
+    heartbeats::{
+        get_time_by_day, get_time_by_language, get_time_by_project, get_time_by_team_api_key,
+        get_total_time,
+    },
+    HeartbeatColumn, ProjectColumn, ProjectEntity, TeamEntity,
+};
+use sea_orm::{entity::prelude::*, sea_query::Query as SqlQuery, DatabaseConnection};
+
+use super::get_team_as_member;
+use crate::{
+    error::WebsiteError,
+    projects::public::get_contributors,
+    user::{
+        scopes::{self, Scoped},
+        AnyAuthentication,
+    },
+    utils::time_utils,
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(team_stats);
+}
+/// Builds the dashboard of a team.
+///
+/// See [get_contributors] for the members that are listed.
+pub async fn build_team_stats(
+    team_id: i64,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    database: &DatabaseConnection,
+) -> Result<TeamStats, DbErr> {
+    let filter = || {
+        HeartbeatColumn::Project
+            .in_subquery(
+                SqlQuery::select()
+                    .column(ProjectColumn::Id)
+                    .from(ProjectEntity)
+                    .and_where(ProjectColumn::TeamId.eq(team_id))
+                    .to_owned(),
+            )
+            .and(HeartbeatColumn::StartTime.gte(from))
+            .and(HeartbeatColumn::StartTime.lt(to))
+    };
+    let total_seconds = get_total_time(filter(), database).await?;
+    let projects = get_time_by_project(filter(), database).await?;
+    let languages = get_time_by_language(filter(), database).await?;
+    let days = get_time_by_day(filter(), database).await?;
+    let service_accounts = get_time_by_team_api_key(filter(), database).await?;
+
+    let (members, anonymous_members) =
+        get_contributors(filter, database, |user, seconds, languages| {
+            TeamMemberTime {
+                user,
+                seconds,
+                languages,
+            }
+        })
+        .await?;
+    Ok(TeamStats {
+        from,
+        to,
+        total_seconds,
+        members,
+        anonymous_members,
+        service_accounts,
+        projects,
+        languages,
+        days,
+    })
+}
+
+#[utoipa::path(get,
+    impl_for=team_stats,
+    path = "/api/teams/{team}/stats",
+    params(
+        ("from" = Option<DateTime<FixedOffset>>, Query, description = "The start of the range. Defaults to a week before `to`"),
+        ("to" = Option<DateTime<FixedOffset>>, Query, description = "The end of the range. Defaults to now"),
+    ),
+    responses(
+        (status = 200, description = "Time spent on the projects of the team", body = TeamStats),
+        (status = 400, description = "Invalid Range"),
+        (status = 403, description = "The API key does not have the ReadUsage scope"),
+        (status = 404, description = "Team not found or you are not a member"),
+    ),
+    security(
+        ("api_key" = ["ReadUsage"]),
+        ("session" = [])
+    )
+)]
+#[get("/teams/{team}/stats")]
+pub async fn team_stats(
+    auth: Scoped<scopes::ReadUsage, AnyAuthentication>,
+    path: web::Path<IdOrName>,
+    range: Query<StatsRange>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let team_id = match auth.into_inner() {
+        // Every role can view the dashboard. Including viewers
+        AnyAuthentication::User(auth) => {
+            get_team_as_member(path.into_inner(), auth.id(), database.as_ref())
+                .await?
+                .0
+        }
+        AnyAuthentication::ServiceAccount(service) => {
+            let Some(team_id) = path
+                .into_inner()
+                .get_id::<TeamEntity>(database.as_ref())
+                .await?
+            else {
+                return Ok(HttpResponse::NotFound().finish());
+            };
+            if team_id != service.key.team_id {
+                return Ok(HttpResponse::NotFound().finish());
+            }
+            team_id
+        }
+    };
+    let (from, to) = match range.resolve(time_utils::get_current_time()) {
+        Ok(range) => range,
+        Err(error) => return Ok(HttpResponse::BadRequest().body(error.to_string())),
+    };
+    let stats = build_team_stats(team_id, from, to, database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(stats))
+}
@@ -0,0 +1,69 @@
+//! Setup shared by the tests that need a database
+//!
+//! Set `CODI_TIME_TEST_DATABASE` to the URL of a Postgres database to run them. They are skipped otherwise.
+use actix_web::web::Data;
+use common::user_types::{Email, Username};
+use entities::{UserActiveModel, UserModel};
+use migration::{Migrator, MigratorTrait};
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{ActiveModelTrait, ActiveValue, Database, DatabaseConnection};
+use tokio::sync::OnceCell;
+
+use crate::{
+    config::SessionConfig,
+    user::session::{memory::MemorySessionManager, DynSessionManager, SessionManager},
+    utils::{password, time_utils},
+};
+
+pub const DATABASE_ENV: &str = "CODI_TIME_TEST_DATABASE";
+pub const PASSWORD: &str = "password";
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+/// Connects to the test database and runs the migrations once.
+///
+/// None if `CODI_TIME_TEST_DATABASE` is not set
+pub async fn database() -> Option<DatabaseConnection> {
+    let Ok(url) = std::env::var(DATABASE_ENV) else {
+        eprintln!("{} is not set. Skipping", DATABASE_ENV);
+        return None;
+    };
+    let database = Database::connect(url)
+        .await
+        .expect("Failed to connect to the test database");
+    MIGRATED
+        .get_or_init(|| async {
+            Migrator::up(&database, None)
+                .await
+                .expect("Failed to migrate the test database")
+        })
+        .await;
+    Some(database)
+}
+/// A random valid username. Tests share the database so every name must be unique
+pub fn unique_name() -> String {
+    let suffix: String = rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    format!("t{}", suffix)
+}
+/// Creates a user with a verified email and the password [PASSWORD]
+pub async fn create_user(database: &DatabaseConnection) -> UserModel {
+    let name = unique_name();
+    UserActiveModel {
+        name: ActiveValue::Set(name.clone()),
+        username: ActiveValue::Set(Username::new(&name).unwrap()),
+        email: ActiveValue::Set(Email::new(format!("{}@example.com", name)).unwrap()),
+        email_verified_at: ActiveValue::Set(Some(time_utils::get_current_time())),
+        password: ActiveValue::Set(password::encrypt_password(PASSWORD).unwrap()),
+        ..Default::default()
+    }
+    .insert(database)
+    .await
+    .expect("Failed to create the test user")
+}
+pub fn session_manager() -> Data<DynSessionManager> {
+    let manager = MemorySessionManager::new(16, SessionConfig::default()).unwrap();
+    Data::new(DynSessionManager::Memory(manager))
+}
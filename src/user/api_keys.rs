@@ -1,9 +1,263 @@
-use actix_web::{get, post, web, web::Data, HttpRequest, HttpResponse};
-use common::user_types::api_token::FromCLI;
-use serde::{Deserialize, Serialize};
-use tracing::warn;
+//! API Key Management
+//!
+//! Keys created here are used by the editor plugins and scripts.
+//! Creating and revoking keys requires a session. So a leaked key can not create more keys.
+//! Changing the IP allowlist of a key also requires a session. So a leaked key can not widen it.
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Data, Query},
+    HttpResponse,
+};
+use chrono::{DateTime, Duration, FixedOffset};
+use common::{
+    ip_range::IpRange,
+    user_types::api_token::{
+        APIToken, APITokenListing, CreatedAPIToken, DEFAULT_STALE_API_KEY_DAYS,
+        MAX_STALE_API_KEY_DAYS,
+    },
+    APITokenPermissions,
+};
+use entities::{
+    api_keys::{get_api_keys_for_user, get_recent_requests_for_user},
+    APIKeyActiveModel, APIKeyColumn, APIKeyEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, IntoActiveModel};
+use serde::Deserialize;
+use utoipa::ToSchema;
 
-use super::routes::Login;
-use crate::{cli_access::CLIAccess, error::WebsiteError, state::State};
+use super::{
+    scopes::{self, Scoped},
+    SessionAuthentication,
+};
+use crate::{
+    error::WebsiteError,
+    utils::{time_utils, token::generate_token},
+};
 
-pub fn init(cfg: &mut web::ServiceConfig) {}
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_api_keys)
+        .service(create_api_key)
+        .service(update_api_key)
+        .service(revoke_api_key);
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewAPIKey {
+    /// Example: "Work Laptop"
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub permissions: Vec<APITokenPermissions>,
+    /// The key never expires if not provided
+    pub expires_at: Option<DateTime<FixedOffset>>,
+    /// IPs or CIDR ranges the key can be used from. Empty allows any address
+    #[serde(default)]
+    pub allowed_ips: Vec<IpRange>,
+}
+#[derive(Debug, Deserialize)]
+pub struct APIKeysQuery {
+    /// Keys unused for this many days are flagged as stale. Recent requests are counted over the same days
+    pub stale_after_days: Option<i64>,
+}
+/// All fields are optional.
+/// If a field is not provided, it will not be updated.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAPIKey {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Replaces the allowlist. Requires a session
+    pub allowed_ips: Option<Vec<IpRange>>,
+}
+
+#[utoipa::path(get,
+    impl_for=list_api_keys,
+    path = "/api/me/api-keys",
+    params(
+        ("stale_after_days" = Option<i64>, Query, description = "Keys unused for this many days are flagged as stale. Recent requests are counted over the same days. Defaults to 30. At most 365"),
+    ),
+    responses(
+        (status = 200, description = "Your API keys. Including revoked keys", body = Vec<APITokenListing>),
+        (status = 400, description = "stale_after_days is not between 1 and 365"),
+    ),
+    security(
+        ("api_key" = ["ReadProfile"]),
+        ("session" = [])
+    )
+)]
+#[get("/me/api-keys")]
+pub async fn list_api_keys(
+    auth: Scoped<scopes::ReadProfile>,
+    query: Query<APIKeysQuery>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let stale_after_days = query.stale_after_days.unwrap_or(DEFAULT_STALE_API_KEY_DAYS);
+    if !(1..=MAX_STALE_API_KEY_DAYS).contains(&stale_after_days) {
+        return Ok(HttpResponse::BadRequest().body(format!(
+            "stale_after_days must be between 1 and {}",
+            MAX_STALE_API_KEY_DAYS
+        )));
+    }
+    let now = time_utils::get_current_time();
+    let since = (now - Duration::days(stale_after_days)).naive_utc().date();
+    let recent_requests = get_recent_requests_for_user(auth.id(), since, database.as_ref()).await?;
+    let keys: Vec<APITokenListing> = get_api_keys_for_user(auth.id(), database.as_ref())
+        .await?
+        .into_iter()
+        .map(|key| APITokenListing {
+            stale: key.is_stale(now, stale_after_days),
+            recent_requests: recent_requests.get(&key.id).copied().unwrap_or_default(),
+            key,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(post,
+    impl_for=create_api_key,
+    path = "/api/me/api-keys",
+    request_body(content = NewAPIKey, description = "The API Key to create", content_type = "application/json"),
+    responses(
+        (status = 201, description = "API Key Created. The token is only returned once", body = CreatedAPIToken),
+        (status = 400, description = "No name, no permissions, an invalid allowed IP or the expiration is in the past"),
+        (status = 403, description = "You are not logged in with a session"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/api-keys")]
+pub async fn create_api_key(
+    auth: SessionAuthentication,
+    key: web::Json<NewAPIKey>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let NewAPIKey {
+        name,
+        description,
+        permissions,
+        expires_at,
+        allowed_ips,
+    } = key.into_inner();
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("A name is required."));
+    }
+    if permissions.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("At least one permission is required."));
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= time_utils::get_current_time()) {
+        return Ok(HttpResponse::BadRequest().body("The expiration must be in the future."));
+    }
+    let (token, token_hash) = generate_token();
+    let key = APIKeyActiveModel {
+        user_id: ActiveValue::Set(auth.user.id),
+        name: ActiveValue::Set(name),
+        description: ActiveValue::Set(description),
+        token: ActiveValue::Set(token_hash),
+        permissions: ActiveValue::Set(permissions),
+        from_cli: ActiveValue::Set(None),
+        expires_at: ActiveValue::Set(expires_at),
+        allowed_ips: ActiveValue::Set(to_strings(allowed_ips)),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    Ok(HttpResponse::Created().json(CreatedAPIToken {
+        key: APIToken::from(key),
+        token,
+    }))
+}
+
+#[utoipa::path(put,
+    impl_for=update_api_key,
+    path = "/api/me/api-keys/{id}",
+    request_body(content = UpdateAPIKey, description = "The fields to update", content_type = "application/json"),
+    responses(
+        (status = 200, description = "API Key Updated", body = APIToken),
+        (status = 400, description = "The name is empty or an allowed IP is invalid"),
+        (status = 403, description = "Changing the allowed IPs requires a session"),
+        (status = 404, description = "API Key not found"),
+    ),
+    security(
+        ("api_key" = ["WriteProfile"]),
+        ("session" = [])
+    )
+)]
+#[put("/me/api-keys/{id}")]
+pub async fn update_api_key(
+    auth: Scoped<scopes::WriteProfile>,
+    path: web::Path<i64>,
+    updates: web::Json<UpdateAPIKey>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let Some(key) = APIKeyEntity::find_by_id(path.into_inner())
+        .filter(APIKeyColumn::UserId.eq(auth.id()))
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let UpdateAPIKey {
+        name,
+        description,
+        allowed_ips,
+    } = updates.into_inner();
+    if allowed_ips.is_some() && !auth.is_session() {
+        return Ok(HttpResponse::Forbidden().body("Changing the allowed IPs requires a session."));
+    }
+    let mut key = key.into_active_model();
+    if let Some(name) = name {
+        let name = name.trim().to_owned();
+        if name.is_empty() {
+            return Ok(HttpResponse::BadRequest().body("A name is required."));
+        }
+        key.name = ActiveValue::Set(name);
+    }
+    if let Some(description) = description {
+        key.description = ActiveValue::Set(description);
+    }
+    if let Some(allowed_ips) = allowed_ips {
+        key.allowed_ips = ActiveValue::Set(to_strings(allowed_ips));
+    }
+    let key = key.update(database.as_ref()).await?;
+    Ok(HttpResponse::Ok().json(APIToken::from(key)))
+}
+
+#[utoipa::path(delete,
+    impl_for=revoke_api_key,
+    path = "/api/me/api-keys/{id}",
+    responses(
+        (status = 204, description = "API Key Revoked"),
+        (status = 403, description = "You are not logged in with a session"),
+        (status = 404, description = "API Key not found or it is already revoked"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[delete("/me/api-keys/{id}")]
+pub async fn revoke_api_key(
+    auth: SessionAuthentication,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let result = APIKeyEntity::update_many()
+        .filter(
+            APIKeyColumn::Id
+                .eq(path.into_inner())
+                .and(APIKeyColumn::UserId.eq(auth.user.id))
+                .and(APIKeyColumn::Revoked.is_null()),
+        )
+        .col_expr(
+            APIKeyColumn::Revoked,
+            Expr::value(time_utils::get_current_time()),
+        )
+        .exec(database.as_ref())
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+fn to_strings(allowed_ips: Vec<IpRange>) -> Vec<String> {
+    allowed_ips.into_iter().map(String::from).collect()
+}
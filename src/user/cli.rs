@@ -1,27 +1,69 @@
+//! CLI Device Login
+//!
+//! 1. The CLI calls [init_session] and opens `/login-cli/{key}` in the browser.
+//! 2. The browser shows the details from [pending_access] and the user approves or denies with [complete_access].
+//! 3. The CLI polls [retrieve_result] until the token is handed back. The token is only handed back once.
 use actix_web::{get, post, web, web::Data, HttpRequest, HttpResponse};
-use common::user_types::api_token::FromCLI;
+use chrono::{DateTime, Local};
+use common::{
+    user_types::api_token::{CreatedAPIToken, FromCLI},
+    APIToken, APITokenPermissions,
+};
+use entities::{APIKeyActiveModel, APIKeyColumn, APIKeyEntity};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
+use utoipa::ToSchema;
 
-use super::routes::Login;
-use crate::{cli_access::CLIAccess, error::WebsiteError, state::State};
+use super::SessionAuthentication;
+use crate::{
+    cli_access::{
+        CLIAccess, CLIAccessError, CLIAccessStorage, FinishedRequest,
+        NewCLIRequest as PendingRequest, RequestStatus,
+    },
+    client_ip::TrustedProxies,
+    error::WebsiteError,
+    state::State,
+    utils::{time_utils, token::generate_token},
+};
 
+/// The permissions the editor plugins need
+const CLI_PERMISSIONS: [APITokenPermissions; 3] = [
+    APITokenPermissions::WriteHeartbeat,
+    APITokenPermissions::ReadHeartbeat,
+    APITokenPermissions::ReadUsage,
+];
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(init_session)
         .service(retrieve_result)
+        .service(pending_access)
         .service(complete_access);
 }
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct NewCLIRequest {
+    /// The user the CLI expects to be logged in as
     pub username: Option<String>,
     #[serde(flatten)]
     pub from_cli: FromCLI,
 }
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct InitSessionResponse {
     pub token: String,
     pub absolute_url: Option<String>,
 }
+/// The machine asking for access. Shown to the user before they approve it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingCLIAccess {
+    pub username: Option<String>,
+    pub from_cli: FromCLI,
+    pub ip_address: String,
+    pub created_at: DateTime<Local>,
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CLIAccessDecision {
+    /// False denies the request
+    pub approve: bool,
+}
 #[utoipa::path(post,
     impl_for=init_session,
     path = "/api/cli/init-session",
@@ -29,10 +71,11 @@ pub struct InitSessionResponse {
     responses(
         (status = 200, description = "CLI Access was Initiated", body = InitSessionResponse),
         (status = 401, description = "IP Address was blacklisted"),
-        (status = 400, description = "NO IP Address was provided")
+        (status = 400, description = "NO IP Address was provided"),
+        (status = 429, description = "Too many pending requests from your IP Address"),
     )
 )]
-#[post("/init-session")]
+#[post("/cli/init-session")]
 pub async fn init_session(
     body: web::Json<NewCLIRequest>,
     state: Data<State>,
@@ -41,12 +84,20 @@ pub async fn init_session(
 ) -> Result<HttpResponse, WebsiteError> {
     let body = body.into_inner();
 
-    let ip_address = if let Some(ip) = request.connection_info().realip_remote_addr() {
-        ip.to_owned()
-    } else {
+    let Some(ip_address) = TrustedProxies::client_ip_of(&request).map(|ip| ip.to_string()) else {
         return Ok(HttpResponse::BadRequest().finish());
     };
-    let key = cli_access.create_new_pending_access(body.from_cli, body.username, ip_address);
+    let key = match cli_access
+        .create_new_pending_access(body.from_cli, body.username, ip_address)
+        .await
+    {
+        Ok(key) => key,
+        Err(error @ CLIAccessError::TooManyPendingRequests(_)) => {
+            warn!("{}", error);
+            return Ok(HttpResponse::TooManyRequests().finish());
+        }
+        Err(error) => return Err(error.into()),
+    };
     let absolute_url = if let Some(state) = state.home_url.as_ref() {
         Some(format!("{}/login-cli/{}", state, key))
     } else {
@@ -61,47 +112,179 @@ pub async fn init_session(
 
 #[utoipa::path(get,
     impl_for=retrieve_result,
-    path = "/api/retrieve-result/{key}",
+    path = "/api/cli/retrieve-result/{key}",
     responses(
-        (status = 200, description = "CLI Access was Initiated", body = APIToken),
-        (status = 401, description = "IP Address was blacklisted"),
+        (status = 200, description = "The request was approved", body = CreatedAPIToken),
+        (status = 102, description = "The request is still waiting for the user"),
+        (status = 401, description = "The request was made from another IP Address. The API key is revoked"),
+        (status = 403, description = "The user denied the request"),
+        (status = 404, description = "The request was already retrieved or does not exist"),
+        (status = 410, description = "The request expired before it was answered or retrieved"),
     )
 )]
-#[get("/retrieve-result/{key}")]
+#[get("/cli/retrieve-result/{key}")]
 pub async fn retrieve_result(
     path: web::Path<String>,
     cli_access: Data<CLIAccess>,
+    database: Data<DatabaseConnection>,
     request: HttpRequest,
 ) -> Result<HttpResponse, WebsiteError> {
-    let ip_address = if let Some(ip) = request.connection_info().realip_remote_addr() {
-        ip.to_owned()
-    } else {
+    let Some(ip_address) = TrustedProxies::client_ip_of(&request).map(|ip| ip.to_string()) else {
         return Ok(HttpResponse::BadRequest().finish());
     };
     let key = path.into_inner();
 
-    let Some(cli_access) = cli_access.get_unclaimed_access(&key) else {
-        return Ok(HttpResponse::Processing().finish());
+    let Some(completed) = cli_access.get_unclaimed_access(&key).await? else {
+        let response = match cli_access.status(&key).await? {
+            RequestStatus::Pending => HttpResponse::Processing().finish(),
+            RequestStatus::Finished(FinishedRequest::Denied) => {
+                HttpResponse::Forbidden().body("The request was denied.")
+            }
+            RequestStatus::Finished(FinishedRequest::Expired) => {
+                HttpResponse::Gone().body("The request expired.")
+            }
+            RequestStatus::Unknown => HttpResponse::NotFound().finish(),
+        };
+        return Ok(response);
     };
-    if ip_address != cli_access.ip_address {
+    if ip_address != completed.ip_address {
         warn!(
-            "IP Address Mismatch: {} != {}",
-            ip_address, cli_access.ip_address
+            "IP Address Mismatch: {} != {}. Revoking API key {}",
+            ip_address, completed.ip_address, completed.api_token.id
         );
+        // The request was already taken. Nobody else can retrieve the token so the key is useless
+        APIKeyEntity::update_many()
+            .filter(
+                APIKeyColumn::Id
+                    .eq(completed.api_token.id)
+                    .and(APIKeyColumn::Revoked.is_null()),
+            )
+            .col_expr(
+                APIKeyColumn::Revoked,
+                Expr::value(time_utils::get_current_time()),
+            )
+            .exec(database.as_ref())
+            .await?;
         return Ok(HttpResponse::Unauthorized().finish());
     }
 
-    let api_token = cli_access.api_token;
+    Ok(HttpResponse::Ok().json(CreatedAPIToken {
+        key: completed.api_token,
+        token: completed.token,
+    }))
+}
+/// Returns the pending request if the user is allowed to answer it
+async fn get_pending_for_user(
+    cli_access: &CLIAccess,
+    key: &str,
+    auth: &SessionAuthentication,
+) -> Result<Option<PendingRequest>, WebsiteError> {
+    let Some(pending) = cli_access.get_pending_access(key).await? else {
+        return Ok(None);
+    };
+    if let Some(username) = pending.username.as_deref() {
+        if !username.eq_ignore_ascii_case(auth.user.username.as_ref()) {
+            return Err(WebsiteError::Forbidden);
+        }
+    }
+    Ok(Some(pending))
+}
 
-    Ok(HttpResponse::Ok().json(api_token))
+#[utoipa::path(get,
+    impl_for=pending_access,
+    path = "/api/cli/pending/{key}",
+    responses(
+        (status = 200, description = "The machine asking for access", body = PendingCLIAccess),
+        (status = 403, description = "The CLI asked for a different user"),
+        (status = 404, description = "The request does not exist or was already answered"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[get("/cli/pending/{key}")]
+pub async fn pending_access(
+    auth: SessionAuthentication,
+    path: web::Path<String>,
+    cli_access: Data<CLIAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let Some(pending) = get_pending_for_user(&cli_access, &path.into_inner(), &auth).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    Ok(HttpResponse::Ok().json(PendingCLIAccess {
+        username: pending.username,
+        from_cli: pending.from_cli,
+        ip_address: pending.ip_address,
+        created_at: pending.created_at,
+    }))
 }
 
-#[post("/complete-access/{key}")]
+#[utoipa::path(post,
+    impl_for=complete_access,
+    path = "/api/cli/complete-access/{key}",
+    request_body(content = CLIAccessDecision, description = "Approve or deny the request", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Approved. The CLI can now retrieve the token", body = APIToken),
+        (status = 204, description = "Denied"),
+        (status = 403, description = "The CLI asked for a different user"),
+        (status = 404, description = "The request does not exist or was already answered"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/cli/complete-access/{key}")]
 pub async fn complete_access(
-    _path: web::Path<String>,
-    _login: web::Json<Login>,
-    _cli_access: Data<CLIAccess>,
-    _request: HttpRequest,
+    auth: SessionAuthentication,
+    path: web::Path<String>,
+    decision: web::Json<CLIAccessDecision>,
+    cli_access: Data<CLIAccess>,
+    database: Data<DatabaseConnection>,
 ) -> Result<HttpResponse, WebsiteError> {
-    todo!();
+    let key = path.into_inner();
+    if get_pending_for_user(&cli_access, &key, &auth)
+        .await?
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    // Removing it first stops the request from being answered twice
+    let Some(pending) = cli_access.remove_pending_access(&key).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if !decision.approve {
+        if !cli_access.deny_access(&key).await? {
+            return Ok(HttpResponse::NotFound().finish());
+        }
+        return Ok(HttpResponse::NoContent().finish());
+    }
+    let (token, token_hash) = generate_token();
+    let name = if pending.from_cli.machine_hostname.is_empty() {
+        "CLI".to_owned()
+    } else {
+        format!("CLI on {}", pending.from_cli.machine_hostname)
+    };
+    let api_key = APIKeyActiveModel {
+        user_id: ActiveValue::Set(auth.user.id),
+        name: ActiveValue::Set(name),
+        token: ActiveValue::Set(token_hash),
+        permissions: ActiveValue::Set(CLI_PERMISSIONS.to_vec()),
+        from_cli: ActiveValue::Set(Some(pending.from_cli.clone())),
+        expires_at: ActiveValue::Set(None),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    let api_token = APIToken::from(api_key);
+    if !cli_access
+        .complete_access(&key, pending, api_token.clone(), token)
+        .await?
+    {
+        // Nobody can retrieve the token
+        APIKeyEntity::delete_by_id(api_token.id)
+            .exec(database.as_ref())
+            .await?;
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::Ok().json(api_token))
 }
@@ -0,0 +1,268 @@
+//! Email Verification
+//!
+//! Users can not log in until their email is verified. Admins are exempt.
+//! A token is emailed at registration and whenever the email changes.
+//!
+//! If emails are disabled. The emails of new accounts are verified immediately since they could never be verified otherwise.
+//! Changed emails are not. An admin has to mark them as verified.
+//!
+//! Team invites sent to an email are only claimed once the user proved they own it or an admin verified it.
+use actix_web::{
+    post,
+    web::{self, Data},
+    HttpResponse,
+};
+use chrono::Duration;
+use common::{Email, Group};
+use entities::{
+    teams::claim_invites_for_email, EmailVerificationActiveModel, EmailVerificationColumn,
+    EmailVerificationEntity, UserColumn, UserEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, QueryOrder};
+use serde::Deserialize;
+use tracing::{debug, info};
+use utoipa::ToSchema;
+
+use super::SessionAuthentication;
+use crate::{
+    email::{emails::VerifyEmail, EmailAccess, Recipient},
+    error::WebsiteError,
+    utils::{sha256, time_utils, token::generate_token},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(verify_email).service(resend_verification);
+}
+pub fn init_admin(cfg: &mut web::ServiceConfig) {
+    cfg.service(mark_verified);
+}
+/// How long a verification token can be used for
+pub fn verification_lifetime() -> Duration {
+    Duration::hours(24)
+}
+/// The time a user has to wait before requesting another verification email
+pub fn resend_cooldown() -> Duration {
+    Duration::minutes(5)
+}
+/// Creates a verification token for the email of a new account and sends it.
+///
+/// If emails are disabled. The email is marked as verified instead. Without claiming invites
+pub async fn send_verification(
+    user_id: i64,
+    name: &str,
+    email: Email,
+    email_access: &EmailAccess,
+    database: &DatabaseConnection,
+) -> Result<(), WebsiteError> {
+    if !email_access.is_enabled() {
+        info!(
+            "Emails are disabled. Marking the email of user {} as verified",
+            user_id
+        );
+        set_verified(user_id, database).await?;
+        return Ok(());
+    }
+    create_and_send(user_id, name, email, email_access, database).await
+}
+/// Sends a token for a changed email or another token for an unverified one.
+///
+/// Never marks the email as verified. If emails are disabled it stays unverified until an admin marks it as verified
+pub async fn send_verification_token(
+    user_id: i64,
+    name: &str,
+    email: Email,
+    email_access: &EmailAccess,
+    database: &DatabaseConnection,
+) -> Result<(), WebsiteError> {
+    if !email_access.is_enabled() {
+        info!(
+            "Emails are disabled. The new email of user {} has to be verified by an admin",
+            user_id
+        );
+        return Ok(());
+    }
+    create_and_send(user_id, name, email, email_access, database).await
+}
+async fn create_and_send(
+    user_id: i64,
+    name: &str,
+    email: Email,
+    email_access: &EmailAccess,
+    database: &DatabaseConnection,
+) -> Result<(), WebsiteError> {
+    let (token, token_hash) = generate_token();
+    let now = time_utils::get_current_time();
+    EmailVerificationActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        email: ActiveValue::Set(email.clone()),
+        token: ActiveValue::Set(token_hash),
+        expires: ActiveValue::Set(now + verification_lifetime()),
+        created: ActiveValue::Set(now),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    email_access.send_or_log(
+        Recipient::new(name, email.as_ref()),
+        VerifyEmail {
+            name: name.to_owned(),
+            token,
+            expires_in_hours: verification_lifetime().num_hours(),
+        },
+    );
+    Ok(())
+}
+/// Sets `email_verified_at` and removes any outstanding tokens
+async fn set_verified(user_id: i64, database: &DatabaseConnection) -> Result<(), DbErr> {
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user_id))
+        .col_expr(
+            UserColumn::EmailVerifiedAt,
+            Expr::value(time_utils::get_current_time()),
+        )
+        .exec(database)
+        .await?;
+    EmailVerificationEntity::delete_many()
+        .filter(EmailVerificationColumn::UserId.eq(user_id))
+        .exec(database)
+        .await?;
+    Ok(())
+}
+/// Claims the team invites sent to the email. Only once the user proved they own it or an admin verified it
+async fn claim_invites(
+    user_id: i64,
+    email: Email,
+    database: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let claimed = claim_invites_for_email(user_id, email, database).await?;
+    if claimed > 0 {
+        debug!("User {} claimed {} team invites", user_id, claimed);
+    }
+    Ok(())
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+#[utoipa::path(post,
+    impl_for=verify_email,
+    path = "/api/verify-email",
+    request_body(content = VerifyEmailRequest, description = "The token from the email", content_type = "application/json"),
+    responses(
+        (status = 204, description = "Email Verified. You can now log in"),
+        (status = 404, description = "The token is invalid, expired or for an email you no longer use"),
+    )
+)]
+#[post("/verify-email")]
+pub async fn verify_email(
+    body: web::Json<VerifyEmailRequest>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let token_hash = sha256::encode_to_string(&body.into_inner().token);
+    let Some((verification, Some(user))) = EmailVerificationEntity::find()
+        .filter(
+            EmailVerificationColumn::Token
+                .eq(token_hash)
+                .and(EmailVerificationColumn::Expires.gt(time_utils::get_current_time())),
+        )
+        .find_also_related(UserEntity)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if verification.email != user.email {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    set_verified(user.id, database.as_ref()).await?;
+    claim_invites(user.id, user.email, database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResendVerification {
+    pub email: Email,
+}
+#[utoipa::path(post,
+    impl_for=resend_verification,
+    path = "/api/verify-email/resend",
+    request_body(content = ResendVerification, description = "The email to verify", content_type = "application/json"),
+    responses(
+        (status = 204, description = "If the email belongs to an unverified account. A new token was sent. At most once every 5 minutes"),
+    )
+)]
+#[post("/verify-email/resend")]
+pub async fn resend_verification(
+    body: web::Json<ResendVerification>,
+    database: Data<DatabaseConnection>,
+    email_access: Data<EmailAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    // Always responds the same. So it can not be used to find registered emails
+    let email = body.into_inner().email;
+    let Some(user) = UserEntity::find()
+        .filter(
+            UserColumn::Email
+                .eq(email)
+                .and(UserColumn::EmailVerifiedAt.is_null()),
+        )
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+    let last_sent = EmailVerificationEntity::find()
+        .filter(EmailVerificationColumn::UserId.eq(user.id))
+        .order_by_desc(EmailVerificationColumn::Created)
+        .one(database.as_ref())
+        .await?;
+    if last_sent.is_some_and(|last_sent| {
+        last_sent.created + resend_cooldown() > time_utils::get_current_time()
+    }) {
+        debug!("User {} requested a verification email too soon", user.id);
+        return Ok(HttpResponse::NoContent().finish());
+    }
+    send_verification_token(
+        user.id,
+        &user.name,
+        user.email,
+        &email_access,
+        database.as_ref(),
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(post,
+    impl_for=mark_verified,
+    path = "/api/admin/users/{user}/verify-email",
+    responses(
+        (status = 204, description = "The email of the user is now verified"),
+        (status = 403, description = "You are not an admin"),
+        (status = 404, description = "User not found"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/users/{user}/verify-email")]
+pub async fn mark_verified(
+    auth: SessionAuthentication,
+    path: web::Path<i64>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    if auth.user.group != Group::Admin {
+        return Err(WebsiteError::Forbidden);
+    }
+    let Some(user) = UserEntity::find_by_id(path.into_inner())
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    info!(
+        "Admin {} marked the email of user {} as verified",
+        auth.user.id, user.id
+    );
+    set_verified(user.id, database.as_ref()).await?;
+    claim_invites(user.id, user.email, database.as_ref()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,12 +1,18 @@
+pub mod api_keys;
 pub mod cli;
+pub mod email_verification;
 pub mod middleware;
+pub mod passkeys;
+pub mod password_reset;
 pub mod routes;
+pub mod scopes;
 pub mod session;
+pub mod two_factor;
 pub mod update_routes;
-use std::fmt::Debug;
+use std::{fmt::Debug, net::IpAddr};
 
 use actix_web::{dev::Payload, web::Data, FromRequest, HttpMessage, HttpRequest};
-use common::{APIToken, User};
+use common::{team::TeamAPIToken, APIToken, APITokenPermissions, User};
 use derive_more::{AsRef, From, Into};
 use digestible::Digestible;
 use either::Either;
@@ -20,7 +26,13 @@ use thiserror::Error;
 use tracing::{instrument, warn};
 use utoipa::ToSchema;
 
-use crate::{error::WebsiteError, user::session::Session, utils};
+use crate::{
+    api_key_usage::{APIKeyUsage, RequestDetails},
+    client_ip::TrustedProxies,
+    error::WebsiteError,
+    user::session::Session,
+    utils,
+};
 
 #[derive(Serialize, Digestible, Debug, ToSchema)]
 pub struct LoginResponse {
@@ -42,6 +54,15 @@ pub enum AuthenticationError {
     #[status_code(FORBIDDEN)]
     #[error("Must be a session")]
     MustBeSession,
+    #[status_code(FORBIDDEN)]
+    #[error("Service accounts can not use this route")]
+    ServiceAccountNotAllowed,
+    #[status_code(FORBIDDEN)]
+    #[error("API key is missing the {0} scope")]
+    MissingScope(APITokenPermissions),
+    #[status_code(FORBIDDEN)]
+    #[error("API key can not be used from this address")]
+    IPNotAllowed,
     #[error("Database Error")]
     #[status_code(INTERNAL_SERVER_ERROR)]
     DatabaseError(Either<DbErr, sqlx::Error>),
@@ -67,6 +88,8 @@ pub enum AuthenticationRaw {
 
 /// The authorized user.
 /// Containing the user model and any additional data to the authentication method.
+///
+/// Team API keys are rejected. Use [AnyAuthentication] on routes that service accounts can use.
 
 #[derive(Debug, Clone, EnumIs)]
 pub enum Authentication {
@@ -90,10 +113,12 @@ impl AsRef<User> for Authentication {
     }
 }
 impl Authentication {
+    /// `client_ip` is checked against the allowlist of the API key
     #[instrument(skip(database, raw))]
     pub async fn new(
         database: Data<DatabaseConnection>,
         raw: AuthenticationRaw,
+        client_ip: Option<IpAddr>,
     ) -> Result<Authentication, AuthenticationError> {
         let result = match raw {
             AuthenticationRaw::Session(session) => {
@@ -104,10 +129,26 @@ impl Authentication {
             }
             AuthenticationRaw::APIToken(token) => {
                 let as_sha256 = utils::sha256::encode_to_string(&token);
-                entities::api_keys::get_user_and_token(&as_sha256, database.as_ref())
-                    .await?
-                    .map(|(token, user)| Authentication::APIToken { user, token })
-                    .ok_or(AuthenticationError::InvalidAPIKey)
+                match entities::api_keys::get_user_and_token(&as_sha256, database.as_ref()).await? {
+                    Some((token, _)) if !token.allows_ip(client_ip) => {
+                        warn!(
+                            "API key {} was used from {:?} which is not in its allowlist",
+                            token.id, client_ip
+                        );
+                        Err(AuthenticationError::IPNotAllowed)
+                    }
+                    Some((token, user)) => Ok(Authentication::APIToken { user, token }),
+                    None => {
+                        if entities::teams::get_team_api_key_by_token(&as_sha256, database.as_ref())
+                            .await?
+                            .is_some()
+                        {
+                            Err(AuthenticationError::ServiceAccountNotAllowed)
+                        } else {
+                            Err(AuthenticationError::InvalidAPIKey)
+                        }
+                    }
+                }
             }
         };
         result
@@ -120,6 +161,78 @@ impl Authentication {
         }
     }
 }
+/// A service account. Such as a CI bot authenticated with a team API key
+#[derive(Debug, Clone)]
+pub struct ServiceAccountAuthentication {
+    pub key: TeamAPIToken,
+}
+/// Either a user or a service account.
+///
+/// Used by routes that service accounts can use. Such as sending heartbeats
+#[derive(Debug, Clone, EnumIs)]
+pub enum AnyAuthentication {
+    User(Authentication),
+    ServiceAccount(ServiceAccountAuthentication),
+}
+impl AnyAuthentication {
+    #[instrument(skip(database, raw))]
+    pub async fn new(
+        database: Data<DatabaseConnection>,
+        raw: AuthenticationRaw,
+        client_ip: Option<IpAddr>,
+    ) -> Result<AnyAuthentication, AuthenticationError> {
+        if let AuthenticationRaw::APIToken(token) = &raw {
+            let as_sha256 = utils::sha256::encode_to_string(token);
+            if let Some(key) =
+                entities::teams::get_team_api_key_by_token(&as_sha256, database.as_ref()).await?
+            {
+                return Ok(AnyAuthentication::ServiceAccount(
+                    ServiceAccountAuthentication { key },
+                ));
+            }
+        }
+        Authentication::new(database, raw, client_ip)
+            .await
+            .map(AnyAuthentication::User)
+    }
+    /// The user. None for service accounts
+    pub fn user(&self) -> Option<&Authentication> {
+        match self {
+            AnyAuthentication::User(auth) => Some(auth),
+            AnyAuthentication::ServiceAccount(_) => None,
+        }
+    }
+    /// The team API key. None for users
+    pub fn service_account(&self) -> Option<&TeamAPIToken> {
+        match self {
+            AnyAuthentication::User(_) => None,
+            AnyAuthentication::ServiceAccount(service) => Some(&service.key),
+        }
+    }
+}
+/// Records the usage of the API key once the request is authenticated
+struct UsageRecorder {
+    usage: Data<APIKeyUsage>,
+    details: RequestDetails,
+}
+impl UsageRecorder {
+    /// None if the request is not using an API key
+    fn new(req: &HttpRequest, raw: &AuthenticationRaw, client_ip: Option<IpAddr>) -> Option<Self> {
+        if !raw.is_api_token() {
+            return None;
+        }
+        let usage = req.app_data::<Data<APIKeyUsage>>()?.clone();
+        Some(Self {
+            usage,
+            details: RequestDetails::new(req, client_ip),
+        })
+    }
+    fn record(self, auth: &Authentication) {
+        if let Authentication::APIToken { token, .. } = auth {
+            self.usage.record(token.id, self.details);
+        }
+    }
+}
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct NoAuthenticationAllowed;
 
@@ -150,7 +263,41 @@ impl FromRequest for Authentication {
             .app_data::<Data<DatabaseConnection>>()
             .expect("Unable to get Database Ref")
             .clone();
-        return Box::pin(async move { Authentication::new(database, raw_auth).await });
+        let client_ip = TrustedProxies::client_ip_of(req);
+        let usage = UsageRecorder::new(req, &raw_auth, client_ip);
+        return Box::pin(async move {
+            let auth = Authentication::new(database, raw_auth, client_ip).await?;
+            if let Some(usage) = usage {
+                usage.record(&auth);
+            }
+            Ok(auth)
+        });
+    }
+}
+impl FromRequest for AnyAuthentication {
+    type Error = AuthenticationError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    /// Extracts the authentication data from the request.
+    #[instrument(skip(req))]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let raw_auth = req.extensions_mut().get::<AuthenticationRaw>().cloned();
+        let Some(raw_auth) = raw_auth else {
+            return Box::pin(async move { Err(AuthenticationError::NoAuthenticationProvided) });
+        };
+        let database = req
+            .app_data::<Data<DatabaseConnection>>()
+            .expect("Unable to get Database Ref")
+            .clone();
+        let client_ip = TrustedProxies::client_ip_of(req);
+        let usage = UsageRecorder::new(req, &raw_auth, client_ip);
+        return Box::pin(async move {
+            let auth = AnyAuthentication::new(database, raw_auth, client_ip).await?;
+            if let (Some(usage), Some(user)) = (usage, auth.user()) {
+                usage.record(user);
+            }
+            Ok(auth)
+        });
     }
 }
 #[derive(Debug, Clone, AsRef, Into, From)]
@@ -0,0 +1,725 @@
+//! Passkeys
+//!
+//! Every ceremony has a start and a finish route.
+//! Start returns the options for `navigator.credentials` and a ceremony key. Finish takes the key and the credential.
+//!
+//! A passkey can be used for a passwordless login or to answer a two factor challenge.
+use actix_web::{
+    delete, get, post,
+    web::{self, Data},
+    HttpResponse,
+};
+use chrono::{DateTime, FixedOffset};
+use entities::{PasskeyActiveModel, PasskeyColumn, PasskeyEntity, PasskeyModel, UserEntity};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use webauthn_rs::prelude::{
+    AuthenticationResult, CreationChallengeResponse, DiscoverableKey, Passkey, PublicKeyCredential,
+    RegisterPublicKeyCredential, RequestChallengeResponse,
+};
+
+use super::{
+    routes::{email_verified, start_session},
+    session::DynSessionManager,
+    two_factor, SessionAuthentication,
+};
+use crate::{
+    error::WebsiteError,
+    utils::time_utils,
+    webauthn::{encode_credential_id, user_handle, CeremonyState, PasskeyError, WebAuthnAccess},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_passkeys)
+        .service(start_registration)
+        .service(finish_registration)
+        .service(delete_passkey)
+        .service(start_login)
+        .service(finish_login)
+        .service(start_second_factor)
+        .service(finish_second_factor);
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyInfo {
+    pub id: i64,
+    pub name: String,
+    pub last_used: Option<DateTime<FixedOffset>>,
+    pub created: DateTime<FixedOffset>,
+}
+impl From<PasskeyModel> for PasskeyInfo {
+    fn from(model: PasskeyModel) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            last_used: model.last_used,
+            created: model.created,
+        }
+    }
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyRegistrationStart {
+    /// Send this back with the credential
+    pub ceremony: String,
+    /// Pass to `navigator.credentials.create`
+    #[schema(value_type = Object)]
+    pub options: CreationChallengeResponse,
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PasskeyAuthenticationStart {
+    /// Send this back with the credential
+    pub ceremony: String,
+    /// Pass to `navigator.credentials.get`
+    #[schema(value_type = Object)]
+    pub options: RequestChallengeResponse,
+}
+fn parse_passkey(model: &PasskeyModel) -> Result<Passkey, PasskeyError> {
+    Ok(serde_json::from_value(model.passkey.clone())?)
+}
+/// Updates the signature counter and the last used time
+async fn record_use(
+    model: PasskeyModel,
+    mut passkey: Passkey,
+    result: &AuthenticationResult,
+    database: &DatabaseConnection,
+) -> Result<(), WebsiteError> {
+    let mut active: PasskeyActiveModel = model.into();
+    if passkey.update_credential(result).unwrap_or_default() {
+        active.passkey =
+            ActiveValue::Set(serde_json::to_value(&passkey).map_err(PasskeyError::from)?);
+    }
+    active.last_used = ActiveValue::Set(Some(time_utils::get_current_time()));
+    active.update(database).await?;
+    Ok(())
+}
+#[utoipa::path(get,
+    impl_for=list_passkeys,
+    path = "/api/me/passkeys",
+    responses(
+        (status = 200, description = "Your passkeys", body = [PasskeyInfo]),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[get("/me/passkeys")]
+pub async fn list_passkeys(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let passkeys: Vec<PasskeyInfo> = PasskeyEntity::find()
+        .filter(PasskeyColumn::UserId.eq(auth.user.id))
+        .all(database.as_ref())
+        .await?
+        .into_iter()
+        .map(PasskeyInfo::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(passkeys))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartPasskeyRegistration {
+    /// So you can tell your passkeys apart
+    pub name: String,
+}
+#[utoipa::path(post,
+    impl_for=start_registration,
+    path = "/api/me/passkeys/register/start",
+    request_body(content = StartPasskeyRegistration, description = "The name of the new passkey", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Create the credential then finish the registration", body = PasskeyRegistrationStart),
+        (status = 404, description = "Passkeys are disabled"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/passkeys/register/start")]
+pub async fn start_registration(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+    body: web::Json<StartPasskeyRegistration>,
+) -> Result<HttpResponse, WebsiteError> {
+    let webauthn = webauthn.get()?;
+    let existing = PasskeyEntity::find()
+        .filter(PasskeyColumn::UserId.eq(auth.user.id))
+        .all(database.as_ref())
+        .await?
+        .iter()
+        .map(|model| parse_passkey(model).map(|passkey| passkey.cred_id().clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let display_name = if auth.user.name.is_empty() {
+        auth.user.username.as_ref()
+    } else {
+        auth.user.name.as_str()
+    };
+    let (options, state) = webauthn
+        .start_passkey_registration(
+            user_handle(auth.user.id),
+            &auth.user.username,
+            display_name,
+            Some(existing),
+        )
+        .map_err(PasskeyError::from)?;
+    let ceremony = CeremonyState::Registration {
+        name: body.into_inner().name,
+        state,
+    }
+    .save(Some(auth.user.id), database.as_ref())
+    .await?;
+    Ok(HttpResponse::Ok().json(PasskeyRegistrationStart { ceremony, options }))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FinishPasskeyRegistration {
+    pub ceremony: String,
+    /// The result of `navigator.credentials.create`
+    #[schema(value_type = Object)]
+    pub credential: RegisterPublicKeyCredential,
+}
+#[utoipa::path(post,
+    impl_for=finish_registration,
+    path = "/api/me/passkeys/register/finish",
+    request_body(content = FinishPasskeyRegistration, description = "The ceremony key and the new credential", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The passkey was added", body = PasskeyInfo),
+        (status = 400, description = "The credential was rejected"),
+        (status = 404, description = "The ceremony is invalid or expired"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/passkeys/register/finish")]
+pub async fn finish_registration(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+    body: web::Json<FinishPasskeyRegistration>,
+) -> Result<HttpResponse, WebsiteError> {
+    let webauthn = webauthn.get()?;
+    let FinishPasskeyRegistration {
+        ceremony,
+        credential,
+    } = body.into_inner();
+    let Some((Some(user_id), CeremonyState::Registration { name, state })) =
+        CeremonyState::take(&ceremony, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if user_id != auth.user.id {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let passkey = webauthn
+        .finish_passkey_registration(&credential, &state)
+        .map_err(PasskeyError::from)?;
+    let passkey = PasskeyActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        name: ActiveValue::Set(name),
+        credential_id: ActiveValue::Set(encode_credential_id(&passkey.cred_id().0)),
+        passkey: ActiveValue::Set(serde_json::to_value(&passkey).map_err(PasskeyError::from)?),
+        last_used: ActiveValue::Set(None),
+        created: ActiveValue::Set(time_utils::get_current_time()),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    info!("User {} added passkey {}", user_id, passkey.id);
+    Ok(HttpResponse::Ok().json(PasskeyInfo::from(passkey)))
+}
+#[utoipa::path(delete,
+    impl_for=delete_passkey,
+    path = "/api/me/passkeys/{id}",
+    responses(
+        (status = 204, description = "The passkey was removed"),
+        (status = 404, description = "Passkey not found"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[delete("/me/passkeys/{id}")]
+pub async fn delete_passkey(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    id: web::Path<i64>,
+) -> Result<HttpResponse, WebsiteError> {
+    let deleted = PasskeyEntity::delete_many()
+        .filter(
+            PasskeyColumn::Id
+                .eq(id.into_inner())
+                .and(PasskeyColumn::UserId.eq(auth.user.id)),
+        )
+        .exec(database.as_ref())
+        .await?;
+    if deleted.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+#[utoipa::path(post,
+    impl_for=start_login,
+    path = "/api/login/passkey/start",
+    responses(
+        (status = 200, description = "Get the credential then finish the login", body = PasskeyAuthenticationStart),
+        (status = 404, description = "Passkeys are disabled"),
+    )
+)]
+#[post("/login/passkey/start")]
+pub async fn start_login(
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let (options, state) = webauthn
+        .get()?
+        .start_discoverable_authentication()
+        .map_err(PasskeyError::from)?;
+    let ceremony = CeremonyState::Login { state }
+        .save(None, database.as_ref())
+        .await?;
+    Ok(HttpResponse::Ok().json(PasskeyAuthenticationStart { ceremony, options }))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FinishPasskeyLogin {
+    pub ceremony: String,
+    /// The result of `navigator.credentials.get`
+    #[schema(value_type = Object)]
+    pub credential: PublicKeyCredential,
+}
+#[utoipa::path(post,
+    impl_for=finish_login,
+    path = "/api/login/passkey/finish",
+    request_body(content = FinishPasskeyLogin, description = "The ceremony key and the credential", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Logged In", body = super::LoginResponse),
+        (status = 400, description = "The credential was rejected"),
+        (status = 401, description = "Unknown passkey or the email is not verified"),
+        (status = 404, description = "The ceremony is invalid or expired"),
+    )
+)]
+#[post("/login/passkey/finish")]
+pub async fn finish_login(
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+    session: Data<DynSessionManager>,
+    body: web::Json<FinishPasskeyLogin>,
+) -> Result<HttpResponse, WebsiteError> {
+    let webauthn = webauthn.get()?;
+    let FinishPasskeyLogin {
+        ceremony,
+        credential,
+    } = body.into_inner();
+    let Some((_, CeremonyState::Login { state })) =
+        CeremonyState::take(&ceremony, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let (handle, credential_id) = webauthn
+        .identify_discoverable_authentication(&credential)
+        .map_err(PasskeyError::from)?;
+    let Some((model, Some(user))) = PasskeyEntity::find()
+        .filter(PasskeyColumn::CredentialId.eq(encode_credential_id(credential_id)))
+        .find_also_related(UserEntity)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    if user_handle(user.id) != handle {
+        warn!("Passkey {} was used with the wrong user handle", model.id);
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let passkey = parse_passkey(&model)?;
+    let result = webauthn
+        .finish_discoverable_authentication(&credential, state, &[DiscoverableKey::from(&passkey)])
+        .map_err(PasskeyError::from)?;
+    record_use(model, passkey, &result, database.as_ref()).await?;
+    if !email_verified(&user) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    start_session(&session, user)
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartPasskeySecondFactor {
+    /// The challenge from login
+    pub challenge: String,
+}
+#[utoipa::path(post,
+    impl_for=start_second_factor,
+    path = "/api/login/two-factor/passkey/start",
+    request_body(content = StartPasskeySecondFactor, description = "The challenge from login", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Get the credential then finish the login", body = PasskeyAuthenticationStart),
+        (status = 400, description = "You have no passkeys"),
+        (status = 404, description = "The challenge is invalid or expired. Or passkeys are disabled"),
+    )
+)]
+#[post("/login/two-factor/passkey/start")]
+pub async fn start_second_factor(
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+    body: web::Json<StartPasskeySecondFactor>,
+) -> Result<HttpResponse, WebsiteError> {
+    let webauthn = webauthn.get()?;
+    let Some((challenge, user)) =
+        two_factor::find_challenge(&body.challenge, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let passkeys = PasskeyEntity::find()
+        .filter(PasskeyColumn::UserId.eq(user.id))
+        .all(database.as_ref())
+        .await?
+        .iter()
+        .map(parse_passkey)
+        .collect::<Result<Vec<_>, _>>()?;
+    if passkeys.is_empty() {
+        return Ok(HttpResponse::BadRequest().body("You have no passkeys."));
+    }
+    let (options, state) = webauthn
+        .start_passkey_authentication(&passkeys)
+        .map_err(PasskeyError::from)?;
+    let ceremony = CeremonyState::SecondFactor {
+        challenge_id: challenge.id,
+        state,
+    }
+    .save(Some(user.id), database.as_ref())
+    .await?;
+    Ok(HttpResponse::Ok().json(PasskeyAuthenticationStart { ceremony, options }))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FinishPasskeySecondFactor {
+    /// The challenge from login
+    pub challenge: String,
+    pub ceremony: String,
+    /// The result of `navigator.credentials.get`
+    #[schema(value_type = Object)]
+    pub credential: PublicKeyCredential,
+}
+#[utoipa::path(post,
+    impl_for=finish_second_factor,
+    path = "/api/login/two-factor/passkey/finish",
+    request_body(content = FinishPasskeySecondFactor, description = "The challenge, the ceremony key and the credential", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Logged In", body = super::LoginResponse),
+        (status = 401, description = "The credential was rejected. The challenge stops working after 5 failures"),
+        (status = 404, description = "The challenge or ceremony is invalid or expired"),
+    )
+)]
+#[post("/login/two-factor/passkey/finish")]
+pub async fn finish_second_factor(
+    database: Data<DatabaseConnection>,
+    webauthn: Data<WebAuthnAccess>,
+    session: Data<DynSessionManager>,
+    body: web::Json<FinishPasskeySecondFactor>,
+) -> Result<HttpResponse, WebsiteError> {
+    let webauthn = webauthn.get()?;
+    let FinishPasskeySecondFactor {
+        challenge,
+        ceremony,
+        credential,
+    } = body.into_inner();
+    let Some((challenge, user)) = two_factor::find_challenge(&challenge, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let Some((
+        _,
+        CeremonyState::SecondFactor {
+            challenge_id,
+            state,
+        },
+    )) = CeremonyState::take(&ceremony, database.as_ref()).await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if challenge_id != challenge.id {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let result = match webauthn.finish_passkey_authentication(&credential, &state) {
+        Ok(result) => result,
+        Err(err) => {
+            warn!("Passkey rejected for user {}: {}", user.id, err);
+            two_factor::fail_challenge(challenge.id, database.as_ref()).await?;
+            return Ok(HttpResponse::Unauthorized().finish());
+        }
+    };
+    let Some(model) = PasskeyEntity::find()
+        .filter(
+            PasskeyColumn::UserId
+                .eq(user.id)
+                .and(PasskeyColumn::CredentialId.eq(encode_credential_id(&result.cred_id().0))),
+        )
+        .one(database.as_ref())
+        .await?
+    else {
+        // Removed while the ceremony was in progress
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    let passkey = parse_passkey(&model)?;
+    record_use(model, passkey, &result, database.as_ref()).await?;
+    if !two_factor::use_challenge(challenge.id, database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    start_session(&session, user)
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        cookie::Cookie,
+        dev::{Service, ServiceResponse},
+        http::StatusCode,
+        test::{call_service, init_service, read_body_json, TestRequest},
+        App, Scope,
+    };
+    use serde_json::{json, Value};
+    use webauthn_authenticator_rs::{softpasskey::SoftPasskey, WebauthnAuthenticator};
+    use webauthn_rs::prelude::Url;
+
+    use super::*;
+    use crate::{
+        test_utils,
+        user::{middleware::HandleSession, session::SessionManager},
+    };
+
+    const ORIGIN: &str = "https://codi.example";
+
+    async fn app(
+        database: &DatabaseConnection,
+        sessions: Data<DynSessionManager>,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>
+    {
+        init_service(
+            App::new()
+                .app_data(Data::new(database.clone()))
+                .app_data(Data::new(WebAuthnAccess::new(Some(ORIGIN)).unwrap()))
+                .app_data(sessions.clone())
+                .service(
+                    Scope::new("/api")
+                        .wrap(HandleSession {
+                            session_manager: sessions.into_inner(),
+                        })
+                        .configure(init),
+                ),
+        )
+        .await
+    }
+    fn authenticator() -> WebauthnAuthenticator<SoftPasskey> {
+        WebauthnAuthenticator::new(SoftPasskey::new(true))
+    }
+    fn origin() -> Url {
+        Url::parse(ORIGIN).unwrap()
+    }
+    /// Registers a passkey with the authenticator. Returns its credential id
+    async fn register(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+        authenticator: &mut WebauthnAuthenticator<SoftPasskey>,
+        session_id: &str,
+    ) -> Value {
+        let start: Value = read_body_json(
+            call_service(
+                app,
+                TestRequest::post()
+                    .uri("/api/me/passkeys/register/start")
+                    .cookie(Cookie::new("session", session_id.to_owned()))
+                    .set_json(json!({ "name": "Laptop" }))
+                    .to_request(),
+            )
+            .await,
+        )
+        .await;
+        let options = serde_json::from_value(start["options"].clone()).unwrap();
+        let credential = authenticator.do_registration(origin(), options).unwrap();
+        let response = call_service(
+            app,
+            TestRequest::post()
+                .uri("/api/me/passkeys/register/finish")
+                .cookie(Cookie::new("session", session_id.to_owned()))
+                .set_json(json!({ "ceremony": start["ceremony"], "credential": credential }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        serde_json::to_value(&credential.raw_id).unwrap()
+    }
+    /// Answers the options like an authenticator that stores the passkey.
+    ///
+    /// The soft passkey can not be discovered and does not return the user handle. Neither is signed
+    fn authenticate_as(
+        authenticator: &mut WebauthnAuthenticator<SoftPasskey>,
+        options: &Value,
+        credential_id: &Value,
+        user_id: i64,
+    ) -> PublicKeyCredential {
+        let mut options = options.clone();
+        let allowed = &mut options["publicKey"]["allowCredentials"];
+        if allowed.as_array().map_or(true, Vec::is_empty) {
+            *allowed = json!([{ "type": "public-key", "id": credential_id }]);
+        }
+        let options: RequestChallengeResponse = serde_json::from_value(options).unwrap();
+        let mut credential = authenticator.do_authentication(origin(), options).unwrap();
+        credential.response.user_handle = Some(user_handle(user_id).as_bytes().to_vec().into());
+        credential
+    }
+
+    #[actix_web::test]
+    async fn register_and_log_in_without_a_password() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let sessions = test_utils::session_manager();
+        let session = sessions.create_session(user.id).unwrap();
+        let app = app(&database, sessions).await;
+        let mut authenticator = authenticator();
+        let credential_id = register(&app, &mut authenticator, &session.session_id).await;
+
+        let listed: Vec<Value> = read_body_json(
+            call_service(
+                &app,
+                TestRequest::get()
+                    .uri("/api/me/passkeys")
+                    .cookie(Cookie::new("session", session.session_id.clone()))
+                    .to_request(),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["name"], "Laptop");
+
+        let start: Value = read_body_json(
+            call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/api/login/passkey/start")
+                    .to_request(),
+            )
+            .await,
+        )
+        .await;
+        let credential = authenticate_as(
+            &mut authenticator,
+            &start["options"],
+            &credential_id,
+            user.id,
+        );
+        let finish = || {
+            TestRequest::post()
+                .uri("/api/login/passkey/finish")
+                .set_json(json!({ "ceremony": start["ceremony"], "credential": credential }))
+                .to_request()
+        };
+        let response = call_service(&app, finish()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .response()
+            .cookies()
+            .any(|cookie| cookie.name() == "session"));
+        let replayed = call_service(&app, finish()).await;
+        assert_eq!(replayed.status(), StatusCode::NOT_FOUND);
+
+        let passkey = PasskeyEntity::find()
+            .filter(PasskeyColumn::UserId.eq(user.id))
+            .one(&database)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(passkey.last_used.is_some());
+    }
+
+    #[actix_web::test]
+    async fn a_passkey_answers_the_two_factor_challenge() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let sessions = test_utils::session_manager();
+        let session = sessions.create_session(user.id).unwrap();
+        let app = app(&database, sessions).await;
+        let mut authenticator = authenticator();
+        let credential_id = register(&app, &mut authenticator, &session.session_id).await;
+
+        let challenge = two_factor::create_challenge(user.id, &database)
+            .await
+            .unwrap()
+            .challenge;
+        let start: Value = read_body_json(
+            call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/api/login/two-factor/passkey/start")
+                    .set_json(json!({ "challenge": challenge }))
+                    .to_request(),
+            )
+            .await,
+        )
+        .await;
+        let credential = authenticate_as(
+            &mut authenticator,
+            &start["options"],
+            &credential_id,
+            user.id,
+        );
+        let response = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/login/two-factor/passkey/finish")
+                .set_json(json!({
+                    "challenge": challenge,
+                    "ceremony": start["ceremony"],
+                    "credential": credential,
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            two_factor::find_challenge(&challenge, &database)
+                .await
+                .unwrap()
+                .is_none(),
+            "The challenge can be answered again"
+        );
+    }
+
+    #[actix_web::test]
+    async fn a_passkey_of_another_user_can_not_log_in() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let other = test_utils::create_user(&database).await;
+        let sessions = test_utils::session_manager();
+        let session = sessions.create_session(user.id).unwrap();
+        let app = app(&database, sessions).await;
+        let mut authenticator = authenticator();
+        let credential_id = register(&app, &mut authenticator, &session.session_id).await;
+
+        let start: Value = read_body_json(
+            call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/api/login/passkey/start")
+                    .to_request(),
+            )
+            .await,
+        )
+        .await;
+        let credential = authenticate_as(
+            &mut authenticator,
+            &start["options"],
+            &credential_id,
+            other.id,
+        );
+        let response = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/login/passkey/finish")
+                .set_json(json!({ "ceremony": start["ceremony"], "credential": credential }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
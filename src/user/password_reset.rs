@@ -0,0 +1,226 @@
+//! Password Reset
+//!
+//! A single use token is emailed to the user. Using it sets the new password and logs the user out everywhere.
+//!
+//! Neither route reveals if an account exists for an email.
+use actix_web::{
+    http::StatusCode,
+    post,
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use chrono::Duration;
+use common::Email;
+use entities::{
+    PasswordResetActiveModel, PasswordResetColumn, PasswordResetEntity, UserColumn, UserEntity,
+};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection, QueryOrder};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    email::{
+        emails::{PasswordChanged, PasswordReset},
+        EmailAccess, Recipient,
+    },
+    error::WebsiteError,
+    recaptcha::RecaptchaAccess,
+    user::session::{DynSessionManager, SessionManager},
+    utils::{password, sha256, time_utils, token::generate_token},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(forgot_password).service(reset_password);
+}
+/// How long a reset token can be used for
+pub fn reset_lifetime() -> Duration {
+    Duration::hours(1)
+}
+/// The time before another reset email will be sent to the same account
+pub fn request_cooldown() -> Duration {
+    Duration::minutes(5)
+}
+/// Returns `Some(response)` if the recaptcha is required and failed
+async fn check_recaptcha(
+    recaptcha: &RecaptchaAccess,
+    response: Option<&str>,
+    request: &HttpRequest,
+) -> Result<Option<HttpResponse>, WebsiteError> {
+    if !recaptcha.require_on_password_reset() {
+        return Ok(None);
+    }
+    let Some(response) = response else {
+        return Ok(Some(HttpResponse::BadRequest().finish()));
+    };
+    let passed = recaptcha
+        .verify_response(response, Some(request))
+        .await
+        .map_err(|e| {
+            warn!("Failed to verify recaptcha: {}", e);
+            WebsiteError::RecaptchaError
+        })?;
+    if !passed {
+        return Ok(Some(HttpResponse::new(StatusCode::BAD_REQUEST)));
+    }
+    Ok(None)
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ForgotPassword {
+    pub email: Email,
+    #[serde(rename = "g-recaptcha-response")]
+    pub recaptcha: Option<String>,
+}
+#[utoipa::path(post,
+    impl_for=forgot_password,
+    path = "/api/password-reset/request",
+    request_body(content = ForgotPassword, description = "The email of the account", content_type = "application/json"),
+    responses(
+        (status = 204, description = "If an account uses the email. A reset token was sent to it"),
+        (status = 400, description = "Recaptcha is required and failed"),
+    )
+)]
+#[post("/password-reset/request")]
+pub async fn forgot_password(
+    request: HttpRequest,
+    body: web::Json<ForgotPassword>,
+    database: Data<DatabaseConnection>,
+    email_access: Data<EmailAccess>,
+    recaptcha: Data<RecaptchaAccess>,
+) -> Result<HttpResponse, WebsiteError> {
+    let ForgotPassword {
+        email,
+        recaptcha: recaptcha_response,
+    } = body.into_inner();
+    if let Some(response) =
+        check_recaptcha(&recaptcha, recaptcha_response.as_deref(), &request).await?
+    {
+        return Ok(response);
+    }
+    let Some(user) = UserEntity::find()
+        .filter(UserColumn::Email.eq(email))
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+    let last_requested = PasswordResetEntity::find()
+        .filter(PasswordResetColumn::UserId.eq(user.id))
+        .order_by_desc(PasswordResetColumn::Created)
+        .one(database.as_ref())
+        .await?;
+    if last_requested.is_some_and(|last_requested| {
+        last_requested.created + request_cooldown() > time_utils::get_current_time()
+    }) {
+        debug!("User {} requested a password reset too soon", user.id);
+        return Ok(HttpResponse::NoContent().finish());
+    }
+    let (token, token_hash) = generate_token();
+    let now = time_utils::get_current_time();
+    PasswordResetActiveModel {
+        user_id: ActiveValue::Set(user.id),
+        token: ActiveValue::Set(token_hash),
+        expires: ActiveValue::Set(now + reset_lifetime()),
+        created: ActiveValue::Set(now),
+        ..Default::default()
+    }
+    .insert(database.as_ref())
+    .await?;
+    email_access.send_or_log(
+        Recipient::new(user.name.clone(), user.email.as_ref()),
+        PasswordReset {
+            name: user.name,
+            username: user.username.to_string(),
+            token,
+            expires_in_minutes: reset_lifetime().num_minutes(),
+        },
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResetPassword {
+    pub token: String,
+    pub password: String,
+    #[serde(rename = "g-recaptcha-response")]
+    pub recaptcha: Option<String>,
+}
+#[utoipa::path(post,
+    impl_for=reset_password,
+    path = "/api/password-reset",
+    request_body(content = ResetPassword, description = "The token from the email and the new password", content_type = "application/json"),
+    responses(
+        (status = 204, description = "Password changed. All sessions were logged out"),
+        (status = 400, description = "Recaptcha is required and failed"),
+        (status = 404, description = "The token is invalid, expired or already used"),
+    )
+)]
+#[post("/password-reset")]
+pub async fn reset_password(
+    request: HttpRequest,
+    body: web::Json<ResetPassword>,
+    database: Data<DatabaseConnection>,
+    email_access: Data<EmailAccess>,
+    recaptcha: Data<RecaptchaAccess>,
+    session: Data<DynSessionManager>,
+) -> Result<HttpResponse, WebsiteError> {
+    let ResetPassword {
+        token,
+        password,
+        recaptcha: recaptcha_response,
+    } = body.into_inner();
+    if let Some(response) =
+        check_recaptcha(&recaptcha, recaptcha_response.as_deref(), &request).await?
+    {
+        return Ok(response);
+    }
+    let token_hash = sha256::encode_to_string(&token);
+    let Some((reset, Some(user))) = PasswordResetEntity::find()
+        .filter(
+            PasswordResetColumn::Token
+                .eq(token_hash)
+                .and(PasswordResetColumn::Expires.gt(time_utils::get_current_time())),
+        )
+        .find_also_related(UserEntity)
+        .one(database.as_ref())
+        .await?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    // Deleting the token first means two requests can not both use it
+    let deleted = PasswordResetEntity::delete_by_id(reset.id)
+        .exec(database.as_ref())
+        .await?;
+    if deleted.rows_affected == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let changed_at = time_utils::get_current_time();
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user.id))
+        .col_expr(
+            UserColumn::Password,
+            Expr::value(password::encrypt_password(&password)?),
+        )
+        .col_expr(UserColumn::RequirePasswordChange, Expr::value(false))
+        .col_expr(UserColumn::PasswordChangedAt, Expr::value(changed_at))
+        .exec(database.as_ref())
+        .await?;
+    // Any other outstanding tokens were requested before the new password
+    PasswordResetEntity::delete_many()
+        .filter(PasswordResetColumn::UserId.eq(user.id))
+        .exec(database.as_ref())
+        .await?;
+    let removed_sessions = session.delete_user_sessions(user.id)?;
+    info!(
+        "User {} reset their password. Removed {} sessions",
+        user.id, removed_sessions
+    );
+    email_access.send_or_log(
+        Recipient::new(user.name.clone(), user.email.as_ref()),
+        PasswordChanged {
+            name: user.name,
+            username: user.username.to_string(),
+            changed_at: changed_at.format("%Y-%m-%d %H:%M %:z").to_string(),
+        },
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
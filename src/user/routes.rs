@@ -1,9 +1,19 @@
 use actix_web::{
-    cookie::CookieBuilder, get, http::StatusCode, post, web, web::Data, HttpResponse, Responder,
+    cookie::{Cookie, CookieBuilder},
+    get,
+    http::StatusCode,
+    post, web,
+    web::Data,
+    HttpResponse, Responder,
 };
-use common::{user_types::Location, Email, Group, IdOrName, PublicUser, User, Username};
-use entities::users::{
-    does_email_exist, does_username_exist, UserActiveModel, UserModel, UserType,
+use common::{
+    language::{Category, Language},
+    user_types::Location,
+    Email, Group, IdOrName, PublicUser, User, Username,
+};
+use entities::{
+    custom_languages::{get_language_categories_for_user, get_languages_for_user},
+    users::{does_email_exist, does_username_exist, UserActiveModel, UserModel, UserType},
 };
 use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
 use serde::Deserialize;
@@ -11,14 +21,22 @@ use tracing::warn;
 
 use super::session::DynSessionManager;
 use crate::{
+    email::EmailAccess,
     error::WebsiteError,
     recaptcha::RecaptchaAccess,
-    user::{session::SessionManager, Authentication, LoginResponse, NoAuthenticationAllowed},
+    user::{
+        email_verification::send_verification,
+        scopes::{self, Scoped},
+        session::{Session, SessionManager},
+        two_factor, LoginResponse, NoAuthenticationAllowed,
+    },
     utils::password,
 };
 
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(me)
+        .service(my_languages)
+        .service(my_language_categories)
         .service(get_user)
         .service(login)
         .service(register);
@@ -31,12 +49,61 @@ pub fn init(cfg: &mut web::ServiceConfig) {
         (status = 401, description = "You are not logged in")
     ),
     security(
-        ("api_key" = [])
+        ("api_key" = ["ReadProfile"])
     )
 )]
 #[get("/me")]
-pub async fn me(auth: Authentication) -> impl Responder {
-    HttpResponse::Ok().json(Into::<User>::into(auth))
+pub async fn me(auth: Scoped<scopes::ReadProfile>) -> impl Responder {
+    HttpResponse::Ok().json(Into::<User>::into(auth.into_inner()))
+}
+
+#[utoipa::path(get,
+    impl_for=my_languages,
+    path = "/api/me/languages",
+    responses(
+        (status = 200, description = "The languages available to you. Your own definitions take precedence over your teams', organizations' and the default ones", body = Vec<Language>),
+        (status = 401, description = "You are not logged in")
+    ),
+    security(
+        ("api_key" = ["ReadProfile"]),
+        ("session" = [])
+    )
+)]
+#[get("/me/languages")]
+pub async fn my_languages(
+    auth: Scoped<scopes::ReadProfile>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let languages: Vec<Language> = get_languages_for_user(auth.id(), database.as_ref())
+        .await?
+        .into_iter()
+        .map(Language::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(languages))
+}
+#[utoipa::path(get,
+    impl_for=my_language_categories,
+    path = "/api/me/languages/categories",
+    responses(
+        (status = 200, description = "The language categories available to you. Resolved like the languages", body = Vec<Category>),
+        (status = 401, description = "You are not logged in")
+    ),
+    security(
+        ("api_key" = ["ReadProfile"]),
+        ("session" = [])
+    )
+)]
+#[get("/me/languages/categories")]
+pub async fn my_language_categories(
+    auth: Scoped<scopes::ReadProfile>,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let categories: Vec<Category> = get_language_categories_for_user(auth.id(), database.as_ref())
+        .await?
+        .into_iter()
+        .map(Category::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(categories))
 }
 
 #[utoipa::path(get,
@@ -75,6 +142,7 @@ pub async fn login(
     login: web::Json<Login>,
     session: Data<DynSessionManager>,
     recaptcha: Data<RecaptchaAccess>,
+    state: Data<crate::State>,
 ) -> Result<HttpResponse, WebsiteError> {
     let login = login.into_inner();
 
@@ -102,22 +170,46 @@ pub async fn login(
     if !password::check_password(&login.password, &user.password)? {
         return Ok(HttpResponse::Unauthorized().finish());
     }
-    // Admins get a pass on email verification
-    if user.email_verified_at.is_none() && user.group != Group::Admin {
+    // Admins can always use their password. So a broken single sign on provider can not lock everyone out
+    if !state.password_login && user.group != Group::Admin {
+        return Ok(
+            HttpResponse::Forbidden().body("Password login is disabled. Use single sign on.")
+        );
+    }
+    if !email_verified(&user) {
         return Ok(HttpResponse::Unauthorized().finish());
     }
+    if user.totp_enabled_at.is_some() {
+        let challenge = two_factor::create_challenge(user.id, database.as_ref()).await?;
+        return Ok(HttpResponse::Accepted().json(challenge));
+    }
+    start_session(&session, user)
+}
+/// Users must verify their email before logging in
+pub(crate) fn email_verified(user: &UserModel) -> bool {
+    // Admins get a pass on email verification
+    user.email_verified_at.is_some() || user.group == Group::Admin
+}
+/// Creates a session for the user and sets the session cookie
+pub(crate) fn start_session(
+    session: &DynSessionManager,
+    user: UserModel,
+) -> Result<HttpResponse, WebsiteError> {
     let session = session.create_session(user.id)?;
-    let cookie = CookieBuilder::new("session", session.session_id.clone())
+    Ok(HttpResponse::Ok()
+        .cookie(session_cookie(&session))
+        .json(LoginResponse {
+            user: User::from(user),
+            session: Some(session),
+        }))
+}
+pub(crate) fn session_cookie(session: &Session) -> Cookie<'static> {
+    CookieBuilder::new("session", session.session_id.clone())
         .path("/")
         .http_only(true)
         .secure(true)
         .same_site(actix_web::cookie::SameSite::Strict)
-        .finish();
-
-    Ok(HttpResponse::Ok().cookie(cookie).json(LoginResponse {
-        user: User::from(user),
-        session: Some(session),
-    }))
+        .finish()
 }
 #[derive(Debug, Deserialize)]
 pub struct RegisterRequest {
@@ -153,19 +245,28 @@ pub async fn register(
     signup: web::Json<RegisterRequest>,
     state: Data<crate::State>,
     recaptcha: Data<RecaptchaAccess>,
+    email_access: Data<EmailAccess>,
     _: NoAuthenticationAllowed,
 ) -> Result<HttpResponse, WebsiteError> {
     let register = signup.into_inner();
     if state.is_first_user() {
         let mut user = register.new_user()?;
         user.group = ActiveValue::Set(Group::Admin);
-        let _user = user.save(database.as_ref()).await?;
+        let user = user.insert(database.as_ref()).await?;
         state.created_first_user();
-        // TODO: Send Email Verification
+        send_verification(
+            user.id,
+            &user.name,
+            user.email,
+            &email_access,
+            database.as_ref(),
+        )
+        .await?;
         return Ok(HttpResponse::NoContent().finish());
     }
 
-    if !state.public_registration {
+    // Without password login the account could never be used
+    if !state.public_registration || !state.password_login {
         return Ok(HttpResponse::Forbidden().finish());
     }
 
@@ -190,9 +291,17 @@ pub async fn register(
     if does_username_exist(register.username.clone(), database.as_ref()).await? {
         return Ok(HttpResponse::Conflict().finish());
     }
+    // Invites sent to the email are claimed once it is verified
     let user = register.new_user()?;
-    let _user = user.save(database.as_ref()).await?;
-    // TODO: Send Email Verification
+    let user = user.insert(database.as_ref()).await?;
+    send_verification(
+        user.id,
+        &user.name,
+        user.email,
+        &email_access,
+        database.as_ref(),
+    )
+    .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -0,0 +1,103 @@
+//! API Key Scopes
+//!
+//! Routes declare the [APITokenPermissions] an API key needs by extracting [Scoped].
+//! Sessions have every scope.
+use std::{marker::PhantomData, ops::Deref};
+
+use actix_web::{dev::Payload, FromRequest, HttpRequest};
+use common::APITokenPermissions;
+use futures_util::future::LocalBoxFuture;
+
+use super::{AnyAuthentication, Authentication, AuthenticationError};
+
+/// A scope that a route requires
+pub trait RequiredScope {
+    const SCOPE: APITokenPermissions;
+}
+macro_rules! scopes {
+    ($($scope:ident),*) => {
+        $(
+            #[derive(Debug, Clone, Copy)]
+            pub struct $scope;
+            impl RequiredScope for $scope {
+                const SCOPE: APITokenPermissions = APITokenPermissions::$scope;
+            }
+        )*
+    };
+}
+scopes!(
+    WriteHeartbeat,
+    ReadHeartbeat,
+    ReadUsage,
+    ReadProfile,
+    WriteProfile,
+    ReadProjects,
+    WriteProjects,
+    ReadTeams,
+    WriteTeams
+);
+/// An authentication that might be limited to a set of scopes
+pub trait HasScope {
+    fn has_scope(&self, scope: APITokenPermissions) -> bool;
+}
+impl HasScope for Authentication {
+    fn has_scope(&self, scope: APITokenPermissions) -> bool {
+        match self {
+            Authentication::Session { .. } => true,
+            Authentication::APIToken { token, .. } => token.has_permission(scope),
+        }
+    }
+}
+impl HasScope for AnyAuthentication {
+    fn has_scope(&self, scope: APITokenPermissions) -> bool {
+        match self {
+            AnyAuthentication::User(auth) => auth.has_scope(scope),
+            AnyAuthentication::ServiceAccount(service) => service.key.has_permission(scope),
+        }
+    }
+}
+/// An authentication that has the scope `S`.
+///
+/// Responds with 403 and the missing scope if the API key does not have it.
+/// ```ignore
+/// pub async fn list(auth: Scoped<scopes::ReadProjects>) -> HttpResponse
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scoped<S: RequiredScope, A = Authentication> {
+    auth: A,
+    scope: PhantomData<S>,
+}
+impl<S: RequiredScope, A> Scoped<S, A> {
+    pub fn into_inner(self) -> A {
+        self.auth
+    }
+}
+impl<S: RequiredScope, A> Deref for Scoped<S, A> {
+    type Target = A;
+    fn deref(&self) -> &Self::Target {
+        &self.auth
+    }
+}
+impl<S, A> FromRequest for Scoped<S, A>
+where
+    S: RequiredScope + 'static,
+    A: HasScope + FromRequest<Error = AuthenticationError> + 'static,
+    A::Future: 'static,
+{
+    type Error = AuthenticationError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth = A::from_request(req, payload);
+        Box::pin(async move {
+            let auth = auth.await?;
+            if !auth.has_scope(S::SCOPE) {
+                return Err(AuthenticationError::MissingScope(S::SCOPE));
+            }
+            Ok(Scoped {
+                auth,
+                scope: PhantomData,
+            })
+        })
+    }
+}
@@ -96,6 +96,17 @@ impl SessionManager for DynSessionManager {
         }
     }
     #[inline]
+    fn delete_user_sessions(&self, user_id: i64) -> Result<usize, SessionError> {
+        match self {
+            DynSessionManager::Memory(session) => session
+                .delete_user_sessions(user_id)
+                .map_err(|_| SessionError::Infallible),
+            DynSessionManager::Redb(session) => session
+                .delete_user_sessions(user_id)
+                .map_err(|x| SessionError::RedbError(x)),
+        }
+    }
+    #[inline]
     fn get_session_config(&self) -> Arc<SessionConfig> {
         match self {
             DynSessionManager::Memory(session) => session.get_session_config(),
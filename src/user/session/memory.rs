@@ -59,6 +59,13 @@ impl SessionManager for MemorySessionManager {
         let mut sessions = self.sessions.write();
         Ok(sessions.remove(session_id))
     }
+    #[instrument]
+    fn delete_user_sessions(&self, user_id: i64) -> Result<usize, Infallible> {
+        let mut sessions = self.sessions.write();
+        let before = sessions.len();
+        sessions.retain(|_, session| session.user_id != user_id);
+        Ok(before - sessions.len())
+    }
 
     fn get_session_config(&self) -> Arc<SessionConfig> {
         self.config.clone()
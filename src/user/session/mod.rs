@@ -96,6 +96,8 @@ pub trait SessionManager: Debug {
     fn get_session(&self, session_id: &str) -> Result<Option<Session>, Self::Error>;
 
     fn delete_session(&self, session_id: &str) -> Result<Option<Session>, Self::Error>;
+    /// Logs the user out everywhere. Returns the number of sessions removed
+    fn delete_user_sessions(&self, user_id: i64) -> Result<usize, Self::Error>;
     fn get_session_config(&self) -> Arc<SessionConfig>;
     fn get_session_config_ref(&self) -> &SessionConfig;
 }
@@ -126,6 +128,10 @@ impl<T: SessionManager> SessionManager for Data<T> {
         self.get_ref().delete_session(session_id)
     }
 
+    fn delete_user_sessions(&self, user_id: i64) -> Result<usize, Self::Error> {
+        self.get_ref().delete_user_sessions(user_id)
+    }
+
     fn get_session_config(&self) -> Arc<SessionConfig> {
         self.get_ref().get_session_config()
     }
@@ -70,6 +70,24 @@ impl SessionManager for RedbSessionManager {
         sessions.commit()?;
         Ok(session)
     }
+    #[instrument]
+    fn delete_user_sessions(&self, user_id: i64) -> Result<usize, Self::Error> {
+        let sessions = self.sessions.begin_write()?;
+        let mut table = sessions.open_table(TABLE)?;
+        let mut to_remove = Vec::new();
+        for entry in table.iter()? {
+            let (session_id, session) = entry?;
+            if session.value().0 == user_id {
+                to_remove.push(session_id.value().to_owned());
+            }
+        }
+        for session_id in &to_remove {
+            table.remove(session_id.as_str())?;
+        }
+        drop(table);
+        sessions.commit()?;
+        Ok(to_remove.len())
+    }
 
     fn get_session_config(&self) -> Arc<SessionConfig> {
         self.config.clone()
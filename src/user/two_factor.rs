@@ -0,0 +1,654 @@
+//! TOTP Two Factor Authentication
+//!
+//! Enrolling stores a secret. It is only enforced once a code from the authenticator app is confirmed.
+//! Confirming returns one time recovery codes. Only their hashes are stored.
+//!
+//! Once enabled `login` returns a [TwoFactorChallenge] instead of a session.
+//! The challenge is exchanged for a session at `/login/two-factor` or with a passkey.
+//! API keys are not affected.
+//!
+//! A TOTP code is only accepted once. Wrong codes count against the user across challenges.
+//! After five wrong codes the user has to wait before the next code is checked. The wait doubles with every wrong code.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+    delete,
+    http::header::RETRY_AFTER,
+    post,
+    web::{self, Data},
+    HttpResponse,
+};
+use chrono::{DateTime, Duration, FixedOffset};
+use entities::{
+    LoginChallengeActiveModel, LoginChallengeColumn, LoginChallengeEntity, LoginChallengeModel,
+    RecoveryCodeActiveModel, RecoveryCodeColumn, RecoveryCodeEntity, UserColumn, UserEntity,
+    UserModel,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sea_orm::{
+    entity::prelude::*, ActiveValue, DatabaseConnection, DatabaseTransaction, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use this_actix_error::ActixError;
+use thiserror::Error;
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use super::{routes::start_session, session::DynSessionManager, SessionAuthentication};
+use crate::{
+    error::WebsiteError,
+    utils::{password, sha256, time_utils, token::generate_token},
+};
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(enroll)
+        .service(confirm)
+        .service(regenerate_recovery_codes)
+        .service(disable)
+        .service(login_two_factor);
+}
+/// Shown as the account provider in authenticator apps
+const ISSUER: &str = "Codi Time";
+const RECOVERY_CODE_COUNT: usize = 10;
+/// A challenge stops working after this many wrong codes
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+/// Seconds each TOTP code is valid for
+const TOTP_STEP: u64 = 30;
+/// Codes from this many steps before or after the current one are accepted. For clock drift
+const TOTP_SKEW: u64 = 1;
+/// Wrong codes the user can enter before they are locked out of two factor logins
+const FREE_FAILED_ATTEMPTS: i32 = 5;
+/// The lockout after the first wrong code past [FREE_FAILED_ATTEMPTS]. It doubles with every wrong code after that
+fn initial_lockout() -> Duration {
+    Duration::seconds(30)
+}
+fn max_lockout() -> Duration {
+    Duration::hours(1)
+}
+/// How long two factor logins are locked after the wrong code with the number
+fn lockout_after(failed_attempts: i32) -> Option<Duration> {
+    if failed_attempts < FREE_FAILED_ATTEMPTS {
+        return None;
+    }
+    let doublings = (failed_attempts - FREE_FAILED_ATTEMPTS).min(16) as u32;
+    Some((initial_lockout() * 2i32.pow(doublings)).min(max_lockout()))
+}
+/// How long the user has to enter their code after entering their password
+pub fn challenge_lifetime() -> Duration {
+    Duration::minutes(5)
+}
+#[derive(Debug, Error, ActixError)]
+pub enum TwoFactorError {
+    #[error("Stored TOTP secret is invalid: {0}")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    InvalidSecret(#[from] totp_rs::SecretParseError),
+    #[error("Failed to create the TOTP: {0}")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    TotpError(#[from] totp_rs::TotpUrlError),
+    #[error("System clock is before the unix epoch")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    SystemTime(#[from] std::time::SystemTimeError),
+}
+fn totp(secret: &str, account_name: &str) -> Result<TOTP, TwoFactorError> {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes()?;
+    // The skew is handled by [matching_step]
+    let totp = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        TOTP_STEP,
+        secret,
+        Some(ISSUER.to_owned()),
+        account_name.to_owned(),
+    )?;
+    Ok(totp)
+}
+/// Finds the time step the code belongs to. None if the code is wrong
+fn matching_step(totp: &TOTP, code: &str) -> Result<Option<i64>, TwoFactorError> {
+    let current = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() / TOTP_STEP;
+    Ok((current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+        .find(|step| totp.check(code, step * TOTP_STEP))
+        .map(|step| step as i64))
+}
+/// Marks the time step as used.
+///
+/// Returns false if a code of this or a later step was already accepted. Which makes every code single use
+async fn use_totp_step(
+    user_id: i64,
+    step: i64,
+    database: &DatabaseConnection,
+) -> Result<bool, DbErr> {
+    let updated = UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user_id))
+        .filter(
+            UserColumn::TotpLastStep
+                .is_null()
+                .or(UserColumn::TotpLastStep.lt(step)),
+        )
+        .col_expr(UserColumn::TotpLastStep, Expr::value(step))
+        .exec(database)
+        .await?;
+    Ok(updated.rows_affected == 1)
+}
+/// Counts a wrong code against the user. Locks two factor logins once there were too many
+async fn record_failed_attempt(
+    user: &UserModel,
+    database: &DatabaseConnection,
+) -> Result<(), DbErr> {
+    let locked_until = lockout_after(user.two_factor_failed_attempts + 1)
+        .map(|lockout| time_utils::get_current_time() + lockout);
+    if locked_until.is_some() {
+        warn!("Two factor logins of user {} are locked", user.id);
+    }
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user.id))
+        .col_expr(
+            UserColumn::TwoFactorFailedAttempts,
+            Expr::col(UserColumn::TwoFactorFailedAttempts).add(1),
+        )
+        .col_expr(UserColumn::TwoFactorLockedUntil, Expr::value(locked_until))
+        .exec(database)
+        .await?;
+    Ok(())
+}
+async fn reset_failed_attempts(user_id: i64, database: &DatabaseConnection) -> Result<(), DbErr> {
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user_id))
+        .col_expr(UserColumn::TwoFactorFailedAttempts, Expr::value(0))
+        .col_expr(
+            UserColumn::TwoFactorLockedUntil,
+            Expr::value(None::<DateTimeWithTimeZone>),
+        )
+        .exec(database)
+        .await?;
+    Ok(())
+}
+/// Recovery codes are compared without dashes, whitespace or case
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+/// Replaces all the recovery codes of the user. Returns the new codes
+async fn replace_recovery_codes(
+    user_id: i64,
+    database: &DatabaseTransaction,
+) -> Result<Vec<String>, DbErr> {
+    RecoveryCodeEntity::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user_id))
+        .exec(database)
+        .await?;
+    let mut rng = StdRng::from_entropy();
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let code: String = (0..10)
+            .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let code = format!("{}-{}", &code[..5], &code[5..]);
+        RecoveryCodeActiveModel {
+            user_id: ActiveValue::Set(user_id),
+            code: ActiveValue::Set(sha256::encode_to_string(&normalize_recovery_code(&code))),
+            created: ActiveValue::Set(time_utils::get_current_time()),
+            ..Default::default()
+        }
+        .insert(database)
+        .await?;
+        codes.push(code);
+    }
+    Ok(codes)
+}
+/// Checks a TOTP code or uses up a recovery code
+async fn check_code(
+    user: &UserModel,
+    code: &str,
+    database: &DatabaseConnection,
+) -> Result<bool, WebsiteError> {
+    let Some(secret) = user.totp_secret.as_deref() else {
+        return Ok(false);
+    };
+    let code = code.trim();
+    if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+        let totp = totp(secret, &user.username)?;
+        let Some(step) = matching_step(&totp, code)? else {
+            return Ok(false);
+        };
+        if !use_totp_step(user.id, step, database).await? {
+            warn!("User {} reused a TOTP code", user.id);
+            return Ok(false);
+        }
+        return Ok(true);
+    }
+    let used = RecoveryCodeEntity::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user.id).and(
+            RecoveryCodeColumn::Code.eq(sha256::encode_to_string(&normalize_recovery_code(code))),
+        ))
+        .exec(database)
+        .await?;
+    if used.rows_affected > 0 {
+        info!("User {} used a recovery code", user.id);
+    }
+    Ok(used.rows_affected > 0)
+}
+async fn find_user(user_id: i64, database: &DatabaseConnection) -> Result<UserModel, WebsiteError> {
+    UserEntity::find_by_id(user_id)
+        .one(database)
+        .await?
+        .ok_or(WebsiteError::NotFound)
+}
+/// Returned by login when the user has two factor authentication enabled
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TwoFactorChallenge {
+    pub challenge: String,
+    pub expires: DateTime<FixedOffset>,
+}
+pub async fn create_challenge(
+    user_id: i64,
+    database: &DatabaseConnection,
+) -> Result<TwoFactorChallenge, DbErr> {
+    let now = time_utils::get_current_time();
+    // Challenges are only looked up by token. So clean up the old ones here
+    LoginChallengeEntity::delete_many()
+        .filter(
+            LoginChallengeColumn::UserId
+                .eq(user_id)
+                .and(LoginChallengeColumn::Expires.lte(now)),
+        )
+        .exec(database)
+        .await?;
+    let (challenge, challenge_hash) = generate_token();
+    let expires = now + challenge_lifetime();
+    LoginChallengeActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        token: ActiveValue::Set(challenge_hash),
+        failed_attempts: ActiveValue::Set(0),
+        expires: ActiveValue::Set(expires),
+        created: ActiveValue::Set(now),
+        ..Default::default()
+    }
+    .insert(database)
+    .await?;
+    Ok(TwoFactorChallenge { challenge, expires })
+}
+/// Finds a challenge that can still be answered
+pub(crate) async fn find_challenge(
+    challenge: &str,
+    database: &DatabaseConnection,
+) -> Result<Option<(LoginChallengeModel, UserModel)>, DbErr> {
+    let challenge = LoginChallengeEntity::find()
+        .filter(
+            LoginChallengeColumn::Token
+                .eq(sha256::encode_to_string(challenge))
+                .and(LoginChallengeColumn::Expires.gt(time_utils::get_current_time()))
+                .and(LoginChallengeColumn::FailedAttempts.lt(MAX_FAILED_ATTEMPTS)),
+        )
+        .find_also_related(UserEntity)
+        .one(database)
+        .await?;
+    Ok(challenge.and_then(|(challenge, user)| user.map(|user| (challenge, user))))
+}
+pub(crate) async fn fail_challenge(id: i64, database: &DatabaseConnection) -> Result<(), DbErr> {
+    LoginChallengeEntity::update_many()
+        .filter(LoginChallengeColumn::Id.eq(id))
+        .col_expr(
+            LoginChallengeColumn::FailedAttempts,
+            Expr::col(LoginChallengeColumn::FailedAttempts).add(1),
+        )
+        .exec(database)
+        .await?;
+    Ok(())
+}
+/// Removes the challenge once it was answered.
+///
+/// Returns false if another request used it first
+pub(crate) async fn use_challenge(id: i64, database: &DatabaseConnection) -> Result<bool, DbErr> {
+    let deleted = LoginChallengeEntity::delete_by_id(id)
+        .exec(database)
+        .await?;
+    Ok(deleted.rows_affected == 1)
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TwoFactorLogin {
+    pub challenge: String,
+    /// A code from the authenticator app or a recovery code
+    pub code: String,
+}
+#[utoipa::path(post,
+    impl_for=login_two_factor,
+    path = "/api/login/two-factor",
+    request_body(content = TwoFactorLogin, description = "The challenge from login and a code", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Logged In", body = super::LoginResponse),
+        (status = 401, description = "Wrong or already used code. The challenge stops working after 5 wrong codes"),
+        (status = 404, description = "The challenge is invalid or expired. Log in again"),
+        (status = 429, description = "Too many wrong codes. Retry after the time in the Retry-After header"),
+    )
+)]
+#[post("/login/two-factor")]
+pub async fn login_two_factor(
+    body: web::Json<TwoFactorLogin>,
+    database: Data<DatabaseConnection>,
+    session: Data<DynSessionManager>,
+) -> Result<HttpResponse, WebsiteError> {
+    let TwoFactorLogin { challenge, code } = body.into_inner();
+    let Some((challenge, user)) = find_challenge(&challenge, database.as_ref()).await? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let now = time_utils::get_current_time();
+    if let Some(locked_until) = user.two_factor_locked_until.filter(|until| *until > now) {
+        let retry_after = (locked_until - now).num_seconds().max(1);
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, retry_after.to_string()))
+            .finish());
+    }
+    if !check_code(&user, &code, database.as_ref()).await? {
+        warn!("Wrong two factor code for user {}", user.id);
+        fail_challenge(challenge.id, database.as_ref()).await?;
+        record_failed_attempt(&user, database.as_ref()).await?;
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    if !use_challenge(challenge.id, database.as_ref()).await? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if user.two_factor_failed_attempts > 0 {
+        reset_failed_attempts(user.id, database.as_ref()).await?;
+    }
+    start_session(&session, user)
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TotpEnrollment {
+    /// Base32 secret. For authenticator apps that can not scan a QR code
+    pub secret: String,
+    /// The `otpauth://` URI to show as a QR code
+    pub provisioning_uri: String,
+}
+#[utoipa::path(post,
+    impl_for=enroll,
+    path = "/api/me/two-factor/enroll",
+    responses(
+        (status = 200, description = "Add the secret to an authenticator app then confirm a code", body = TotpEnrollment),
+        (status = 409, description = "Two factor authentication is already enabled"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/two-factor/enroll")]
+pub async fn enroll(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    if auth.user.totp_enabled_at.is_some() {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let secret = Secret::generate_secret().to_encoded().to_string();
+    let totp = totp(&secret, &auth.user.username)?;
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(auth.user.id))
+        .col_expr(UserColumn::TotpSecret, Expr::value(secret.clone()))
+        .exec(database.as_ref())
+        .await?;
+    Ok(HttpResponse::Ok().json(TotpEnrollment {
+        secret,
+        provisioning_uri: totp.get_url(),
+    }))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConfirmTotp {
+    pub code: String,
+}
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecoveryCodes {
+    /// Each can be used once instead of a TOTP code. They will not be shown again
+    pub codes: Vec<String>,
+}
+#[utoipa::path(post,
+    impl_for=confirm,
+    path = "/api/me/two-factor/confirm",
+    request_body(content = ConfirmTotp, description = "A code from the authenticator app", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Two factor authentication is enabled", body = RecoveryCodes),
+        (status = 400, description = "Enrollment was not started or the code is wrong"),
+        (status = 409, description = "Two factor authentication is already enabled"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/two-factor/confirm")]
+pub async fn confirm(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    body: web::Json<ConfirmTotp>,
+) -> Result<HttpResponse, WebsiteError> {
+    if auth.user.totp_enabled_at.is_some() {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let user = find_user(auth.user.id, database.as_ref()).await?;
+    let Some(secret) = user.totp_secret.as_deref() else {
+        return Ok(HttpResponse::BadRequest().body("Enrollment was not started."));
+    };
+    let totp = totp(secret, &user.username)?;
+    let Some(step) = matching_step(&totp, body.code.trim())? else {
+        return Ok(HttpResponse::BadRequest().body("Invalid code."));
+    };
+    // The confirmation code can not be used to log in
+    if !use_totp_step(user.id, step, database.as_ref()).await? {
+        return Ok(HttpResponse::BadRequest().body("Invalid code."));
+    }
+    let transaction = database.begin().await?;
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user.id))
+        .col_expr(
+            UserColumn::TotpEnabledAt,
+            Expr::value(time_utils::get_current_time()),
+        )
+        .exec(&transaction)
+        .await?;
+    let codes = replace_recovery_codes(user.id, &transaction).await?;
+    transaction.commit().await?;
+    info!("User {} enabled two factor authentication", user.id);
+    Ok(HttpResponse::Ok().json(RecoveryCodes { codes }))
+}
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PasswordConfirmation {
+    pub password: String,
+}
+#[utoipa::path(post,
+    impl_for=regenerate_recovery_codes,
+    path = "/api/me/two-factor/recovery-codes",
+    request_body(content = PasswordConfirmation, description = "Your password", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The old recovery codes no longer work", body = RecoveryCodes),
+        (status = 400, description = "Two factor authentication is not enabled"),
+        (status = 403, description = "Password is incorrect"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[post("/me/two-factor/recovery-codes")]
+pub async fn regenerate_recovery_codes(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    body: web::Json<PasswordConfirmation>,
+) -> Result<HttpResponse, WebsiteError> {
+    let user = find_user(auth.user.id, database.as_ref()).await?;
+    if user.totp_enabled_at.is_none() {
+        return Ok(HttpResponse::BadRequest().body("Two factor authentication is not enabled."));
+    }
+    if !password::check_password(&body.password, &user.password)? {
+        return Ok(HttpResponse::Forbidden().body("Password is incorrect."));
+    }
+    let transaction = database.begin().await?;
+    let codes = replace_recovery_codes(user.id, &transaction).await?;
+    transaction.commit().await?;
+    Ok(HttpResponse::Ok().json(RecoveryCodes { codes }))
+}
+#[utoipa::path(delete,
+    impl_for=disable,
+    path = "/api/me/two-factor",
+    request_body(content = PasswordConfirmation, description = "Your password", content_type = "application/json"),
+    responses(
+        (status = 204, description = "Two factor authentication is disabled"),
+        (status = 403, description = "Password is incorrect"),
+    ),
+    security(
+        ("session" = [])
+    )
+)]
+#[delete("/me/two-factor")]
+pub async fn disable(
+    auth: SessionAuthentication,
+    database: Data<DatabaseConnection>,
+    body: web::Json<PasswordConfirmation>,
+) -> Result<HttpResponse, WebsiteError> {
+    let user = find_user(auth.user.id, database.as_ref()).await?;
+    if !password::check_password(&body.password, &user.password)? {
+        return Ok(HttpResponse::Forbidden().body("Password is incorrect."));
+    }
+    let transaction = database.begin().await?;
+    UserEntity::update_many()
+        .filter(UserColumn::Id.eq(user.id))
+        .col_expr(UserColumn::TotpSecret, Expr::value(None::<String>))
+        .col_expr(
+            UserColumn::TotpEnabledAt,
+            Expr::value(None::<DateTimeWithTimeZone>),
+        )
+        .col_expr(UserColumn::TwoFactorFailedAttempts, Expr::value(0))
+        .col_expr(
+            UserColumn::TwoFactorLockedUntil,
+            Expr::value(None::<DateTimeWithTimeZone>),
+        )
+        .exec(&transaction)
+        .await?;
+    RecoveryCodeEntity::delete_many()
+        .filter(RecoveryCodeColumn::UserId.eq(user.id))
+        .exec(&transaction)
+        .await?;
+    LoginChallengeEntity::delete_many()
+        .filter(LoginChallengeColumn::UserId.eq(user.id))
+        .exec(&transaction)
+        .await?;
+    transaction.commit().await?;
+    info!("User {} disabled two factor authentication", user.id);
+    Ok(HttpResponse::NoContent().finish())
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn lockout_doubles_up_to_the_maximum() {
+        assert_eq!(lockout_after(FREE_FAILED_ATTEMPTS - 1), None);
+        assert_eq!(lockout_after(FREE_FAILED_ATTEMPTS), Some(initial_lockout()));
+        assert_eq!(
+            lockout_after(FREE_FAILED_ATTEMPTS + 2),
+            Some(initial_lockout() * 4)
+        );
+        assert_eq!(lockout_after(i32::MAX), Some(max_lockout()));
+    }
+
+    #[test]
+    fn codes_match_their_step() {
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = totp(&secret, "user").unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let step = (now / TOTP_STEP) as i64;
+        assert_eq!(
+            matching_step(&totp, &totp.generate(now)).unwrap(),
+            Some(step)
+        );
+        assert_eq!(
+            matching_step(&totp, &totp.generate(now - TOTP_STEP)).unwrap(),
+            Some(step - 1)
+        );
+        let expired = totp.generate(now - TOTP_STEP * 3);
+        if expired != totp.generate(now) && expired != totp.generate(now - TOTP_STEP) {
+            assert_eq!(matching_step(&totp, &expired).unwrap(), None);
+        }
+    }
+
+    #[actix_web::test]
+    async fn codes_are_single_use_and_wrong_codes_lock_the_user() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        UserEntity::update_many()
+            .filter(UserColumn::Id.eq(user.id))
+            .col_expr(UserColumn::TotpSecret, Expr::value(secret.clone()))
+            .col_expr(
+                UserColumn::TotpEnabledAt,
+                Expr::value(time_utils::get_current_time()),
+            )
+            .exec(&database)
+            .await
+            .unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(database.clone()))
+                .app_data(test_utils::session_manager())
+                .service(login_two_factor),
+        )
+        .await;
+        let login = |challenge: String, code: String| {
+            TestRequest::post()
+                .uri("/login/two-factor")
+                .set_json(json!({ "challenge": challenge, "code": code }))
+                .to_request()
+        };
+        let code = totp(&secret, &user.username)
+            .unwrap()
+            .generate_current()
+            .unwrap();
+
+        let challenge = create_challenge(user.id, &database).await.unwrap();
+        let response = call_service(&app, login(challenge.challenge, code.clone())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let challenge = create_challenge(user.id, &database).await.unwrap();
+        let response = call_service(&app, login(challenge.challenge, code.clone())).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNAUTHORIZED,
+            "A used code was accepted again"
+        );
+
+        // The reused code was the first wrong one. The lockout counts across challenges
+        let challenge = create_challenge(user.id, &database).await.unwrap();
+        for _ in 2..=FREE_FAILED_ATTEMPTS {
+            let response = call_service(
+                &app,
+                login(challenge.challenge.clone(), "000000".to_owned()),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let challenge = create_challenge(user.id, &database).await.unwrap();
+        let response = call_service(&app, login(challenge.challenge, code)).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(RETRY_AFTER));
+        let user = UserEntity::find_by_id(user.id)
+            .one(&database)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.two_factor_failed_attempts, FREE_FAILED_ATTEMPTS);
+        assert!(user.two_factor_locked_until.is_some());
+    }
+}
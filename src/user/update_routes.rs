@@ -13,8 +13,17 @@ use tracing::{error, info};
 use utoipa::ToSchema;
 
 use crate::{
+    email::{
+        emails::{EmailChanged, PasswordChanged},
+        EmailAccess,
+    },
     error::WebsiteError,
-    user::{Authentication, SessionAuthentication},
+    user::{
+        email_verification::send_verification_token,
+        scopes::{self, Scoped},
+        session::{DynSessionManager, SessionManager},
+        SessionAuthentication,
+    },
     utils::{password, time_utils},
 };
 
@@ -58,7 +67,7 @@ impl UpdateCore {
     impl_for=update_core,
     path = "/api/me/update/core",
     responses(
-        (status = 204, description = "Account Successfully Updated"),
+        (status = 204, description = "Account Successfully Updated. A changed email has to be verified again. If emails are disabled an admin has to verify it"),
         (status = 400, description = "No fields to update or Invalid fields"),
         (status = 401, description = "You are not logged in."),
         (status = 403, description = "You are logged in with a session."),
@@ -73,6 +82,7 @@ impl UpdateCore {
 pub async fn update_core(
     auth: SessionAuthentication,
     connection: Data<DatabaseConnection>,
+    email_access: Data<EmailAccess>,
     updates: web::Json<UpdateCore>,
 ) -> Result<HttpResponse, WebsiteError> {
     if updates.is_empty() {
@@ -89,25 +99,26 @@ pub async fn update_core(
     } = updates.into_inner();
 
     let mut update_query = UserEntity::update_many().filter(UserColumn::Id.eq(user.id()));
-    let is_email_update = email.is_some();
     if let Some(username) = username {
-        if does_username_exist(username.clone(), connection.as_ref()).await? {
-            update_query = update_query.col_expr(UserColumn::Username, Expr::value(username));
-        } else {
+        if username != user.username
+            && does_username_exist(username.clone(), connection.as_ref()).await?
+        {
             return Ok(HttpResponse::Conflict().body("Username already exists."));
         }
+        update_query = update_query.col_expr(UserColumn::Username, Expr::value(username));
     }
-    if let Some(email) = email {
+    // Only a different email needs to be verified again
+    let new_email = email.filter(|email| *email != user.email);
+    if let Some(email) = new_email.clone() {
         if does_email_exist(email.clone(), connection.as_ref()).await? {
-            update_query = update_query
-                .col_expr(UserColumn::Email, Expr::value(email))
-                .col_expr(
-                    UserColumn::EmailVerifiedAt,
-                    Expr::value(None::<DateTimeWithTimeZone>),
-                )
-        } else {
             return Ok(HttpResponse::Conflict().body("Email already exists."));
         }
+        update_query = update_query
+            .col_expr(UserColumn::Email, Expr::value(email))
+            .col_expr(
+                UserColumn::EmailVerifiedAt,
+                Expr::value(None::<DateTimeWithTimeZone>),
+            );
     }
     if let Some(name) = name {
         update_query = update_query.col_expr(UserColumn::Name, Expr::value(name));
@@ -130,8 +141,23 @@ pub async fn update_core(
     }
     update_query.exec(connection.as_ref()).await?;
 
-    if is_email_update {
-        // TODO: Send email to new email address to confirm. And send email to old email address to notify of change.
+    if let Some(new_email) = new_email {
+        email_access.send_or_log(
+            &user,
+            EmailChanged {
+                name: user.name.clone(),
+                username: user.username.to_string(),
+                new_email: new_email.to_string(),
+            },
+        );
+        send_verification_token(
+            user.id,
+            &user.name,
+            new_email,
+            &email_access,
+            connection.as_ref(),
+        )
+        .await?;
     }
 
     Ok(HttpResponse::NoContent().finish())
@@ -169,6 +195,8 @@ pub struct UpdatePassword {
 pub async fn update_password(
     auth: SessionAuthentication,
     connection: Data<DatabaseConnection>,
+    session: Data<DynSessionManager>,
+    email_access: Data<EmailAccess>,
     updates: web::Json<UpdatePassword>,
 ) -> Result<HttpResponse, WebsiteError> {
     let user: User = auth.into();
@@ -188,22 +216,34 @@ pub async fn update_password(
     if !password::check_password(&old_password, &password_in_db)? {
         return Ok(HttpResponse::Forbidden().body("Old Password is incorrect."));
     }
+    let changed_at = time_utils::get_current_time();
     UserEntity::update_many()
         .filter(UserColumn::Id.eq(user.id()))
-        .col_expr(UserColumn::Password, Expr::value(password))
-        .col_expr(UserColumn::RequirePasswordChange, Expr::value(false))
         .col_expr(
-            UserColumn::PasswordChangedAt,
-            Expr::value(time_utils::get_current_time()),
+            UserColumn::Password,
+            Expr::value(password::encrypt_password(&password)?),
         )
+        .col_expr(UserColumn::RequirePasswordChange, Expr::value(false))
+        .col_expr(UserColumn::PasswordChangedAt, Expr::value(changed_at))
         .exec(connection.as_ref())
         .await?;
-    // TODO Send Email to notify of password change.
+    email_access.send_or_log(
+        &user,
+        PasswordChanged {
+            name: user.name.clone(),
+            username: user.username.to_string(),
+            changed_at: changed_at.format("%Y-%m-%d %H:%M %:z").to_string(),
+        },
+    );
 
     let mut response = UpdatePasswordResponse::default();
     if force_logout {
-        // TODO: Remove all sessions for user. Change below to true
-        response.removed_sessions = false;
+        let removed_sessions = session.delete_user_sessions(user.id)?;
+        info!(
+            "User {} changed their password. Removed {} sessions",
+            user.id, removed_sessions
+        );
+        response.removed_sessions = true;
     }
     if remove_api_keys {
         let UpdateResult { rows_affected } = APIKeyEntity::update_many()
@@ -330,17 +370,17 @@ pub async fn update_bio(
     ),
     request_body(content = Vec<ReportIntervals>, description = "The new account information to update to.", content_type = "application/json"),
     security(
-        ("api_key" = []),
+        ("api_key" = ["WriteProfile"]),
         ("session" = [])
     )
 )]
 #[put("/me/update/report-intervals")]
 pub async fn update_report_intervals(
-    auth: Authentication,
+    auth: Scoped<scopes::WriteProfile>,
     connection: Data<DatabaseConnection>,
     updates: web::Json<Vec<ReportIntervals>>,
 ) -> Result<HttpResponse, WebsiteError> {
-    let user: User = auth.into();
+    let user: User = auth.into_inner().into();
     let updates = updates.into_inner();
     UserEntity::update_many()
         .filter(UserColumn::Id.eq(user.id()))
@@ -404,16 +444,16 @@ impl UpdatePreferences {
     ),
     request_body(content = UpdatePreferences, description = "The new account information to update to.", content_type = "application/json"),
     security(
-        ("api_key" = [])
+        ("api_key" = ["WriteProfile"])
     )
 )]
 #[put("/me/update/preferences")]
 pub async fn update_preferences(
-    auth: Authentication,
+    auth: Scoped<scopes::WriteProfile>,
     connection: Data<DatabaseConnection>,
     updates: web::Json<UpdatePreferences>,
 ) -> Result<HttpResponse, WebsiteError> {
-    let mut user: User = auth.into();
+    let mut user: User = auth.into_inner().into();
     let updates = updates.into_inner();
     if !updates.update_preferences(&mut user.preferences) {
         return Ok(HttpResponse::BadRequest().body("No fields to update."));
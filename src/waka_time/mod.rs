@@ -1,24 +1,275 @@
 //! Routes to Build Compatibility with the WakaTime API
 //!  In the Future, We will have our own API, but for now, we will use the WakaTime API
 
-use actix_web::{post, web, HttpRequest, HttpResponse};
+use std::str::FromStr;
+
+use actix_web::{
+    http::header::USER_AGENT,
+    post,
+    web::{self, Data},
+    HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use common::heartbeat::{CodeChanges, HeartbeatCategory, HeartbeatType};
+use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use crate::user::Authentication;
+use crate::{
+    error::WebsiteError,
+    heartbeats::{
+        ingest_heartbeat, ingest_service_heartbeat, load_rules, load_team_rules, IngestResult,
+        NewHeartbeat,
+    },
+    user::{
+        scopes::{self, Scoped},
+        AnyAuthentication,
+    },
+};
+/// The header wakatime-cli puts the hostname in
+const MACHINE_NAME_HEADER: &str = "X-Machine-Name";
 /// Base Route /api/waka-time
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(heartbeat);
 }
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct WakaTimeHeartbeat {
+    /// The file, app or domain
     pub entity: String,
-    pub category: String,
-    pub created_at: i64,
-    pub editor: String,
-    pub language: String,
+    /// `file`, `app` or `domain`. Defaults to `file`
+    #[serde(rename = "type", default)]
+    pub type_: Option<String>,
+    /// Defaults to `coding`
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Unix timestamp in seconds
+    pub time: f64,
+    pub project: Option<String>,
+    pub branch: Option<String>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub is_write: bool,
+    pub line_additions: Option<u32>,
+    pub line_deletions: Option<u32>,
+    /// Overrides the editor read from the user agent
+    pub editor: Option<String>,
+}
+impl WakaTimeHeartbeat {
+    fn into_new_heartbeat(self, user_agent: String, machine_name_id: String) -> NewHeartbeat {
+        let (editor, operating_system) = parse_user_agent(&user_agent);
+        let code_change = match (self.line_additions, self.line_deletions) {
+            (None, None) => None,
+            (lines_added, lines_removed) => Some(CodeChanges {
+                lines_added: lines_added.unwrap_or_default(),
+                lines_removed: lines_removed.unwrap_or_default(),
+            }),
+        };
+        let start_time = NaiveDateTime::from_timestamp_millis((self.time * 1000.0) as i64)
+            .map(|time| DateTime::<Utc>::from_naive_utc_and_offset(time, Utc).into());
+        NewHeartbeat {
+            entity: self.entity,
+            type_: self
+                .type_
+                .and_then(|type_| HeartbeatType::from_str(&type_).ok())
+                .unwrap_or(HeartbeatType::File),
+            category: self
+                .category
+                .and_then(|category| HeartbeatCategory::from_str(&category).ok())
+                .unwrap_or(HeartbeatCategory::Coding),
+            code_change,
+            project: self.project,
+            branch: self.branch,
+            language: self.language,
+            is_write: self.is_write,
+            editor: self.editor.or(editor),
+            operating_system,
+            machine_name_id,
+            user_agent,
+            start_time,
+        }
+    }
+}
+/// Reads the editor and the operating system from a wakatime-cli user agent.
+///
+/// `wakatime/v1.86.1 (linux-6.5.0-x86_64) go1.21.5 vscode/1.85.1 vscode-wakatime/24.4.0`
+fn parse_user_agent(user_agent: &str) -> (Option<String>, Option<String>) {
+    let operating_system = user_agent
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split(|c| c == '-' || c == ')').next())
+        .filter(|os| !os.is_empty())
+        .map(str::to_owned);
+    let editor = user_agent
+        .split_once(')')
+        .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+        .and_then(|editor| editor.split('/').next())
+        .filter(|editor| !editor.is_empty())
+        .map(str::to_owned);
+    (editor, operating_system)
 }
-#[post("/api/waka-time/heartbeat")]
-pub async fn heartbeat(_auth: Authentication, _request: HttpRequest) -> HttpResponse {
-    todo!()
+#[utoipa::path(post,
+    impl_for=heartbeat,
+    path = "/api/waka-time/heartbeat",
+    request_body = WakaTimeHeartbeat,
+    responses(
+        (status = 201, description = "The heartbeat was stored"),
+        (status = 202, description = "A project rule ignored the heartbeat"),
+        (status = 403, description = "The API key does not have the WriteHeartbeat scope"),
+    ),
+    security(
+        ("api_key" = ["WriteHeartbeat"]),
+        ("session" = [])
+    )
+)]
+#[post("/waka-time/heartbeat")]
+pub async fn heartbeat(
+    auth: Scoped<scopes::WriteHeartbeat, AnyAuthentication>,
+    heartbeat: web::Json<WakaTimeHeartbeat>,
+    request: HttpRequest,
+    database: Data<DatabaseConnection>,
+) -> Result<HttpResponse, WebsiteError> {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    };
+    let user_agent = header(USER_AGENT.as_str()).unwrap_or_default();
+    let machine_name_id = header(MACHINE_NAME_HEADER).unwrap_or_else(|| "unknown".to_owned());
+    let heartbeat = heartbeat
+        .into_inner()
+        .into_new_heartbeat(user_agent, machine_name_id);
+    let result = match auth.into_inner() {
+        AnyAuthentication::User(auth) => {
+            let rules = load_rules(auth.id(), database.as_ref()).await?;
+            ingest_heartbeat(auth.id(), heartbeat, &rules, database.as_ref()).await?
+        }
+        AnyAuthentication::ServiceAccount(service) => {
+            let rules = load_team_rules(service.key.team_id, database.as_ref()).await?;
+            ingest_service_heartbeat(&service.key, heartbeat, &rules, database.as_ref()).await?
+        }
+    };
+    match result {
+        IngestResult::Ignored { .. } => Ok(HttpResponse::Accepted().finish()),
+        IngestResult::Stored { .. } => Ok(HttpResponse::Created().finish()),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App, Scope,
+    };
+    use common::project_rules::{RuleAction, RuleField, RulePattern};
+    use entities::{
+        projects::get_user_project_by_name, HeartbeatColumn, HeartbeatEntity, ProjectModel,
+        ProjectRuleActiveModel,
+    };
+    use sea_orm::{entity::prelude::*, ActiveValue};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        test_utils,
+        user::{middleware::HandleSession, session::SessionManager},
+    };
+
+    #[test]
+    fn reads_the_user_agent() {
+        let (editor, operating_system) = parse_user_agent(
+            "wakatime/v1.86.1 (linux-6.5.0-x86_64) go1.21.5 vscode/1.85.1 vscode-wakatime/24.4.0",
+        );
+        assert_eq!(editor.as_deref(), Some("vscode"));
+        assert_eq!(operating_system.as_deref(), Some("linux"));
+        assert_eq!(parse_user_agent("curl/8.0"), (None, None));
+    }
+
+    #[actix_web::test]
+    async fn rules_apply_to_posted_heartbeats() {
+        let Some(database) = test_utils::database().await else {
+            return;
+        };
+        let user = test_utils::create_user(&database).await;
+        for (name, field, pattern, action) in [
+            (
+                "Scratch files",
+                RuleField::Entity,
+                RulePattern::Glob("/tmp/**".to_owned()),
+                RuleAction::Ignore,
+            ),
+            (
+                "Old name",
+                RuleField::ProjectName,
+                RulePattern::Regex("^old-name$".to_owned()),
+                RuleAction::Rename("new-name".to_owned()),
+            ),
+        ] {
+            ProjectRuleActiveModel {
+                user_id: ActiveValue::Set(Some(user.id)),
+                name: ActiveValue::Set(name.to_owned()),
+                field: ActiveValue::Set(field),
+                pattern: ActiveValue::Set(pattern),
+                action: ActiveValue::Set(action),
+                ..Default::default()
+            }
+            .insert(&database)
+            .await
+            .unwrap();
+        }
+        let sessions = test_utils::session_manager();
+        let session = sessions.create_session(user.id).unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(database.clone()))
+                .app_data(sessions.clone())
+                .service(
+                    Scope::new("/api")
+                        .wrap(HandleSession {
+                            session_manager: sessions.clone().into_inner(),
+                        })
+                        .configure(init),
+                ),
+        )
+        .await;
+        let post = |entity: &str, project: &str| {
+            TestRequest::post()
+                .uri("/api/waka-time/heartbeat")
+                .cookie(actix_web::cookie::Cookie::new(
+                    "session",
+                    session.session_id.clone(),
+                ))
+                .set_json(json!({
+                    "entity": entity,
+                    "time": 1700000000.5,
+                    "project": project,
+                    "is_write": true,
+                }))
+                .to_request()
+        };
+
+        let ignored = call_service(&app, post("/tmp/scratch.rs", "scratch")).await;
+        assert_eq!(ignored.status(), StatusCode::ACCEPTED);
+        let renamed = call_service(&app, post("/home/me/src/main.rs", "old-name")).await;
+        assert_eq!(renamed.status(), StatusCode::CREATED);
+
+        let heartbeats = HeartbeatEntity::find()
+            .filter(HeartbeatColumn::UserId.eq(user.id))
+            .all(&database)
+            .await
+            .unwrap();
+        assert_eq!(heartbeats.len(), 1, "The ignored heartbeat was stored");
+        let project = get_user_project_by_name::<ProjectModel>(user.id, "new-name", &database)
+            .await
+            .unwrap()
+            .expect("The renamed project was not created");
+        assert_eq!(heartbeats[0].project, Some(project.id));
+        assert_eq!(heartbeats[0].entity, "/home/me/src/main.rs");
+        assert!(
+            get_user_project_by_name::<ProjectModel>(user.id, "old-name", &database)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,140 @@
+//! WebAuthn
+//!
+//! The relying party is the host of `home_url`. Without a `home_url` passkeys are disabled.
+//!
+//! Ceremony state is kept in the `webauthn_ceremonies` table between the start and finish requests.
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Duration;
+use entities::{WebAuthnCeremonyActiveModel, WebAuthnCeremonyColumn, WebAuthnCeremonyEntity};
+use sea_orm::{entity::prelude::*, ActiveValue, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+use this_actix_error::ActixError;
+use thiserror::Error;
+use tracing::{info, warn};
+use webauthn_rs::prelude::{
+    DiscoverableAuthentication, PasskeyAuthentication, PasskeyRegistration, Url, Uuid, Webauthn,
+    WebauthnBuilder, WebauthnError,
+};
+
+use crate::{
+    error::WebsiteError,
+    utils::{time_utils, token::generate_token},
+};
+
+/// How long the client has to finish a ceremony
+pub fn ceremony_lifetime() -> Duration {
+    Duration::minutes(5)
+}
+#[derive(Debug, Error, ActixError)]
+pub enum PasskeyError {
+    #[error("Passkeys are disabled. Set home_url to enable them")]
+    #[status_code(NOT_FOUND)]
+    Disabled,
+    #[error("WebAuthn Error: {0}")]
+    #[status_code(BAD_REQUEST)]
+    WebauthnError(#[from] WebauthnError),
+    #[error("Failed to (de)serialize passkey state: {0}")]
+    #[status_code(INTERNAL_SERVER_ERROR)]
+    SerdeError(#[from] serde_json::Error),
+}
+#[derive(Debug)]
+pub struct WebAuthnAccess {
+    webauthn: Option<Webauthn>,
+}
+impl WebAuthnAccess {
+    pub fn new(home_url: Option<&str>) -> anyhow::Result<Self> {
+        let Some(home_url) = home_url else {
+            info!("No home_url provided. Passkeys are disabled");
+            return Ok(Self { webauthn: None });
+        };
+        let origin = Url::parse(home_url)?;
+        let Some(rp_id) = origin.host_str() else {
+            warn!("home_url {} has no host. Passkeys are disabled", home_url);
+            return Ok(Self { webauthn: None });
+        };
+        let webauthn = WebauthnBuilder::new(rp_id, &origin)?
+            .rp_name("Codi Time")
+            .build()?;
+        Ok(Self {
+            webauthn: Some(webauthn),
+        })
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.webauthn.is_some()
+    }
+    pub fn get(&self) -> Result<&Webauthn, PasskeyError> {
+        self.webauthn.as_ref().ok_or(PasskeyError::Disabled)
+    }
+}
+/// The user handle given to authenticators. Users only have numeric ids
+pub fn user_handle(user_id: i64) -> Uuid {
+    Uuid::from_u64_pair(0, user_id as u64)
+}
+/// Passkeys are stored and looked up by the base64 URL safe credential id
+pub fn encode_credential_id(credential_id: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(credential_id)
+}
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CeremonyState {
+    Registration {
+        name: String,
+        state: PasskeyRegistration,
+    },
+    /// A passwordless login
+    Login { state: DiscoverableAuthentication },
+    /// Answering a two factor challenge
+    SecondFactor {
+        challenge_id: i64,
+        state: PasskeyAuthentication,
+    },
+}
+impl CeremonyState {
+    /// Stores the state. Returns the key to finish it with
+    pub async fn save(
+        &self,
+        user_id: Option<i64>,
+        database: &DatabaseConnection,
+    ) -> Result<String, WebsiteError> {
+        let now = time_utils::get_current_time();
+        // Abandoned ceremonies are cleaned up here
+        WebAuthnCeremonyEntity::delete_many()
+            .filter(WebAuthnCeremonyColumn::Expires.lte(now))
+            .exec(database)
+            .await?;
+        let (key, _) = generate_token();
+        WebAuthnCeremonyActiveModel {
+            key: ActiveValue::Set(key.clone()),
+            user_id: ActiveValue::Set(user_id),
+            state: ActiveValue::Set(serde_json::to_value(self).map_err(PasskeyError::from)?),
+            expires: ActiveValue::Set(now + ceremony_lifetime()),
+            created: ActiveValue::Set(now),
+        }
+        .insert(database)
+        .await?;
+        Ok(key)
+    }
+    /// Removes the ceremony so it can only be finished once
+    ///
+    /// Returns the user the ceremony was started for and its state
+    pub async fn take(
+        key: &str,
+        database: &DatabaseConnection,
+    ) -> Result<Option<(Option<i64>, Self)>, WebsiteError> {
+        let Some(ceremony) = WebAuthnCeremonyEntity::find_by_id(key.to_owned())
+            .filter(WebAuthnCeremonyColumn::Expires.gt(time_utils::get_current_time()))
+            .one(database)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let deleted = WebAuthnCeremonyEntity::delete_by_id(key.to_owned())
+            .exec(database)
+            .await?;
+        if deleted.rows_affected == 0 {
+            return Ok(None);
+        }
+        let state = serde_json::from_value(ceremony.state).map_err(PasskeyError::from)?;
+        Ok(Some((ceremony.user_id, state)))
+    }
+}